@@ -0,0 +1,132 @@
+use glam::Vec3;
+use lofter::{
+    Lofter,
+    analysis::{self, DraftClass},
+};
+
+const DRAFT_INSUFFICIENT_COLOR: Vec3 = Vec3::new(0.9, 0.15, 0.1);
+/// Faces at the minimum draft, blending to the positive or negative colors
+/// as their draft increases.
+const DRAFT_MIN_COLOR: Vec3 = Vec3::new(0.95, 0.85, 0.2);
+const DRAFT_POSITIVE_COLOR: Vec3 = Vec3::new(0.2, 0.75, 0.3);
+const DRAFT_NEGATIVE_COLOR: Vec3 = Vec3::new(0.2, 0.45, 0.9);
+
+/// Faces without overhang, blending to the maximum overhang color as their
+/// overhang approaches the maximum.
+const SUPPORTED_COLOR: Vec3 = Vec3::new(0.2, 0.75, 0.3);
+const MAX_OVERHANG_COLOR: Vec3 = Vec3::new(0.95, 0.85, 0.2);
+const OVERHANG_COLOR: Vec3 = Vec3::new(0.9, 0.15, 0.1);
+
+/// The pull directions for draft analysis, and build directions for overhang
+/// analysis, with their labels.
+pub const DIRECTIONS: [(Vec3, &str); 6] = [
+    (Vec3::X, "+X"),
+    (Vec3::NEG_X, "-X"),
+    (Vec3::Y, "+Y"),
+    (Vec3::NEG_Y, "-Y"),
+    (Vec3::Z, "+Z"),
+    (Vec3::NEG_Z, "-Z"),
+];
+
+/// An analysis of the loft's surface, shown as a heatmap in place of the
+/// sections' colors.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SurfaceAnalysis {
+    #[default]
+    None,
+    /// Colors faces by their draft along a pull direction, with faces drafted
+    /// by less than `min_angle` degrees red.
+    Draft {
+        pull_direction: Vec3,
+        min_angle: f32,
+    },
+    /// Colors faces by their overhang when printed along a build direction,
+    /// with faces overhanging by more than `max_angle` degrees red.
+    Overhang {
+        build_direction: Vec3,
+        max_angle: f32,
+    },
+}
+
+/// A surface analysis's colors, with a summary for the UI.
+pub struct AnalysisResult {
+    /// In the order of `Lofter::vertex_buffer`.
+    pub triangle_colors: Vec<Vec3>,
+    pub summary: String,
+}
+
+impl SurfaceAnalysis {
+    pub const DEFAULT_DRAFT: SurfaceAnalysis = SurfaceAnalysis::Draft {
+        pull_direction: Vec3::Z,
+        min_angle: 1.,
+    };
+    pub const DEFAULT_OVERHANG: SurfaceAnalysis = SurfaceAnalysis::Overhang {
+        build_direction: Vec3::Z,
+        max_angle: 45.,
+    };
+
+    /// Analyzes the loft, returning `None` without an analysis.
+    pub fn evaluate(self, lofter: &Lofter) -> Option<AnalysisResult> {
+        match self {
+            SurfaceAnalysis::None => None,
+            SurfaceAnalysis::Draft {
+                pull_direction,
+                min_angle,
+            } => {
+                let drafts = analysis::draft_angles(lofter, pull_direction, min_angle);
+                let insufficient = drafts
+                    .iter()
+                    .filter(|draft| draft.class == DraftClass::Insufficient)
+                    .count() as f32
+                    / drafts.len().max(1) as f32;
+
+                let triangle_colors = drafts
+                    .into_iter()
+                    .map(|draft| {
+                        let t = ((draft.angle.abs() - min_angle) / (90. - min_angle)).clamp(0., 1.);
+
+                        match draft.class {
+                            DraftClass::Insufficient => DRAFT_INSUFFICIENT_COLOR,
+                            DraftClass::Positive => DRAFT_MIN_COLOR.lerp(DRAFT_POSITIVE_COLOR, t),
+                            DraftClass::Negative => DRAFT_MIN_COLOR.lerp(DRAFT_NEGATIVE_COLOR, t),
+                        }
+                    })
+                    .collect();
+
+                Some(AnalysisResult {
+                    triangle_colors,
+                    summary: format!(
+                        "{:.1}% of faces have insufficient draft",
+                        insufficient * 100.
+                    ),
+                })
+            }
+            SurfaceAnalysis::Overhang {
+                build_direction,
+                max_angle,
+            } => {
+                let report = analysis::overhangs(lofter, build_direction, max_angle);
+
+                let triangle_colors = report
+                    .angles
+                    .iter()
+                    .map(|&angle| {
+                        if angle > max_angle {
+                            OVERHANG_COLOR
+                        } else {
+                            SUPPORTED_COLOR.lerp(MAX_OVERHANG_COLOR, angle / max_angle)
+                        }
+                    })
+                    .collect();
+
+                Some(AnalysisResult {
+                    triangle_colors,
+                    summary: format!(
+                        "{:.1}% of the surface needs support",
+                        report.overhanging_fraction * 100.
+                    ),
+                })
+            }
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use glam::{Mat4, Vec2, Vec3};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Projection {
+    Perspective,
+    Orthographic,
+}
+
+/// The direction from the camera's target to the camera, before rotation.
+const EYE_DIRECTION: Vec3 = Vec3::new(5., 0., 2.5);
+
+/// Extra space left around the loft when fitting the camera to it.
+const FIT_MARGIN: f32 = 1.1;
+
+/// A camera orbiting a target about the z axis.
+#[derive(Clone, Copy)]
+pub struct Camera {
+    pub target: Vec3,
+    /// Distance from the target to the camera.
+    pub distance: f32,
+    /// Rotation about the z axis, in radians.
+    pub rotation: f32,
+    pub projection: Projection,
+    /// Vertical field of view of the perspective projection, in degrees.
+    pub fov_y: f32,
+    /// Height of the view volume of the orthographic projection.
+    pub orthographic_scale: f32,
+    pub near: f32,
+    pub far: f32,
+    /// When enabled, the near and far planes are fit to the loft's bounds
+    /// whenever the loft changes.
+    pub auto_clip: bool,
+    /// Whether the camera has been framed on a loft yet.
+    has_framed: bool,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            target: Vec3::new(0., 0., 1.5),
+            distance: EYE_DIRECTION.length(),
+            rotation: 0.,
+            projection: Projection::Perspective,
+            fov_y: 45.,
+            orthographic_scale: 5.,
+            near: 1.,
+            far: 20.,
+            auto_clip: true,
+            has_framed: false,
+        }
+    }
+}
+
+impl Camera {
+    pub fn eye(&self) -> Vec3 {
+        self.target + EYE_DIRECTION.normalize().rotate_z(self.rotation) * self.distance
+    }
+
+    /// Returns the combined projection and view matrix.
+    pub fn proj_view(&self, aspect_ratio: f32) -> Mat4 {
+        let up = Vec3::Z;
+
+        let view = Mat4::look_at_rh(self.eye(), self.target, up);
+
+        let proj = match self.projection {
+            Projection::Perspective => {
+                Mat4::perspective_rh(self.fov_y.to_radians(), aspect_ratio, self.near, self.far)
+            }
+            Projection::Orthographic => {
+                let half_height = self.orthographic_scale / 2.;
+                let half_width = half_height * aspect_ratio;
+
+                Mat4::orthographic_rh(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    self.near,
+                    self.far,
+                )
+            }
+        };
+
+        proj * view
+    }
+
+    /// Updates the camera for a new or changed loft mesh, with the given
+    /// bounding sphere. The camera is framed on the first loft it sees, and
+    /// the clipping planes are refit on every change if `auto_clip` is
+    /// enabled.
+    pub fn fit_to_bounds(&mut self, center: Vec3, radius: f32) {
+        if !self.has_framed {
+            self.has_framed = true;
+            self.frame(center, radius);
+        }
+
+        if self.auto_clip {
+            let distance = self.eye().distance(center);
+            let radius = radius * FIT_MARGIN;
+
+            self.far = (distance + radius).max(f32::EPSILON);
+            // The near plane can't be behind the camera, or at it.
+            self.near = (distance - radius).max(self.far * 1e-4);
+        }
+    }
+
+    /// Moves the target in the view plane. The offset is in units of the
+    /// camera's distance, so panning feels the same at any zoom level.
+    pub fn pan(&mut self, offset: Vec2) {
+        let forward = (self.target - self.eye()).normalize();
+        let right = forward.cross(Vec3::Z).normalize();
+        let up = right.cross(forward);
+
+        self.target += (right * offset.x + up * offset.y) * self.distance;
+    }
+
+    /// Scales the distance to the target, and the orthographic view volume,
+    /// by a factor.
+    pub fn zoom(&mut self, factor: f32) {
+        self.distance *= factor;
+        self.orthographic_scale *= factor;
+    }
+
+    /// Moves the camera so that the whole bounding sphere is in view.
+    pub fn frame(&mut self, center: Vec3, radius: f32) {
+        let radius = radius.max(f32::EPSILON) * FIT_MARGIN;
+
+        self.target = center;
+        self.distance = radius / (self.fov_y.to_radians() / 2.).sin();
+        self.orthographic_scale = radius * 2.;
+    }
+}
+
+/// Returns the center and radius of a sphere enclosing all vertices in a
+/// vertex buffer, or `None` if it's empty.
+pub fn bounding_sphere(vertex_buffer: &[[[Vec3; 2]; 3]]) -> Option<(Vec3, f32)> {
+    let positions = || {
+        vertex_buffer
+            .iter()
+            .flatten()
+            .map(|[position, _]| *position)
+    };
+
+    let (min, max) = positions().fold(None, |bounds, position| match bounds {
+        None => Some((position, position)),
+        Some((min, max)) => Some((position.min(min), position.max(max))),
+    })?;
+
+    let center = (min + max) / 2.;
+    let radius = positions()
+        .map(|position| position.distance(center))
+        .fold(0., f32::max);
+
+    Some((center, radius))
+}
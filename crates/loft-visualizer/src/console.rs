@@ -0,0 +1,262 @@
+use std::{
+    cell::{Cell, RefCell},
+    f32::consts::TAU,
+    rc::Rc,
+};
+
+use glam::Vec3;
+use lofter::{LoftError, LoftOptions, Lofter, SketchDescriptor};
+use rhai::{Array, Dynamic, Engine, EvalAltResult, FLOAT, INT};
+
+/// The most lines of output kept in the console.
+const MAX_OUTPUT_LINES: usize = 200;
+
+const DEFAULT_SCRIPT: &str = "\
+// 20 elliptical stations, tapering and twisting up a curve.
+clear_sketches();
+
+for i in 0..20 {
+    let t = i / 19.0;
+    let offset = if i == 0 { [0.0, 0.0, 0.0] } else { [0.1 * t, 0.0, 0.3] };
+
+    push_sketch(ellipse(1.5 - t, 0.8 - 0.5 * t, 24), offset, 30.0 * t);
+}
+
+loft(30.0);
+print(`${sketch_count()} sketches`);
+";
+
+/// A console running Rhai scripts against the lofter, for procedural lofts.
+///
+/// Scripts can call:
+///
+/// - `push_sketch(points, offset)` and `push_sketch(points, offset, rotation)`,
+///   where `points` is an array of `[x, y]` or `[x, y, z]` arrays, `offset` is
+///   the position relative to the previous sketch, and `rotation` is about the
+///   z axis, in degrees
+/// - `insert_sketch(index, points, offset)`, `remove_sketch(index)` and
+///   `clear_sketches()`
+/// - `infer_sketch_at(z)`, inserting the lofted cross-section at height `z`
+/// - `sketch_count()`
+/// - `set_rotation(index, degrees)` and `set_offset(index, offset)`
+/// - `loft()` and `loft(max_angle)`, which is called with the default options
+///   after scripts which don't loft
+/// - `ellipse(a, b, count)`, returning the points of an ellipse with the given
+///   semi-axes
+pub struct ScriptConsole {
+    pub script: String,
+    pub output: Vec<String>,
+    /// Set by the UI to run the script.
+    pub run_requested: bool,
+}
+
+impl Default for ScriptConsole {
+    fn default() -> Self {
+        Self {
+            script: DEFAULT_SCRIPT.into(),
+            output: Vec::new(),
+            run_requested: false,
+        }
+    }
+}
+
+impl ScriptConsole {
+    /// Runs the script on a copy of the lofter, which replaces the lofter only
+    /// if the script succeeds.
+    pub fn run(&mut self, lofter: &mut Lofter) {
+        let output = Rc::new(RefCell::new(Vec::new()));
+
+        let result = run_script(&self.script, lofter.clone(), output.clone());
+
+        self.output.append(&mut output.borrow_mut());
+
+        match result {
+            Ok(scripted) => *lofter = scripted,
+            Err(err) => self.output.push(format!("Error: {err}")),
+        }
+
+        let excess = self.output.len().saturating_sub(MAX_OUTPUT_LINES);
+        self.output.drain(..excess);
+    }
+}
+
+fn run_script(
+    script: &str,
+    lofter: Lofter,
+    output: Rc<RefCell<Vec<String>>>,
+) -> Result<Lofter, Box<EvalAltResult>> {
+    let lofter = Rc::new(RefCell::new(lofter));
+    let lofted = Rc::new(Cell::new(false));
+
+    let mut engine = Engine::new();
+
+    engine.on_print(move |text| output.borrow_mut().push(text.into()));
+
+    let push_sketch = {
+        let lofter = lofter.clone();
+        move |points: Array, offset: Array, rotation: FLOAT| {
+            let sketch = sketch_descriptor(points, offset, rotation)?;
+            lofter
+                .borrow_mut()
+                .push_sketch(&sketch)
+                .map(|_| ())
+                .map_err(script_error)
+        }
+    };
+    engine.register_fn("push_sketch", push_sketch.clone());
+    engine.register_fn("push_sketch", move |points: Array, offset: Array| {
+        push_sketch(points, offset, 0.)
+    });
+
+    let l = lofter.clone();
+    engine.register_fn(
+        "insert_sketch",
+        move |index: INT, points: Array, offset: Array| {
+            let index = sketch_index(index, l.borrow().sketch_count() + 1)?;
+            let sketch = sketch_descriptor(points, offset, 0.)?;
+            l.borrow_mut()
+                .insert_sketch(index, &sketch)
+                .map(|_| ())
+                .map_err(script_error)
+        },
+    );
+
+    let l = lofter.clone();
+    engine.register_fn("infer_sketch_at", move |z: FLOAT| {
+        l.borrow_mut()
+            .infer_sketch_at(z as f32)
+            .map(|index| index as INT)
+            .map_err(script_error)
+    });
+
+    let l = lofter.clone();
+    engine.register_fn("remove_sketch", move |index: INT| {
+        let index = sketch_index(index, l.borrow().sketch_count())?;
+        l.borrow_mut().remove_sketch(index).map_err(script_error)
+    });
+
+    let l = lofter.clone();
+    engine.register_fn("clear_sketches", move || {
+        let mut lofter = l.borrow_mut();
+        for sketch_index in (0..lofter.sketch_count()).rev() {
+            let _ = lofter.remove_sketch(sketch_index);
+        }
+    });
+
+    let l = lofter.clone();
+    engine.register_fn("sketch_count", move || l.borrow().sketch_count() as INT);
+
+    let l = lofter.clone();
+    engine.register_fn("set_rotation", move |index: INT, degrees: FLOAT| {
+        let index = sketch_index(index, l.borrow().sketch_count())?;
+        l.borrow_mut()
+            .set_sketch_rotation(index, &Vec3::new(0., 0., degrees as f32))
+            .map_err(script_error)
+    });
+
+    let l = lofter.clone();
+    engine.register_fn("set_offset", move |index: INT, offset: Array| {
+        let index = sketch_index(index, l.borrow().sketch_count())?;
+        let offset = to_vec3(Dynamic::from_array(offset))?;
+        l.borrow_mut()
+            .set_sketch_relative_position(index, &offset)
+            .map_err(script_error)
+    });
+
+    let loft = {
+        let lofter = lofter.clone();
+        let lofted = lofted.clone();
+        move |max_angle: FLOAT| {
+            lofter.borrow_mut().loft(&LoftOptions {
+                max_radial_edge_angle: max_angle as f32,
+                ..Default::default()
+            });
+            lofted.set(true);
+        }
+    };
+    engine.register_fn("loft", loft.clone());
+    engine.register_fn("loft", move || {
+        loft(LoftOptions::default().max_radial_edge_angle as FLOAT)
+    });
+
+    engine.register_fn("ellipse", ellipse);
+
+    engine.run(script)?;
+
+    // The engine holds references to the lofter.
+    drop(engine);
+    let mut lofter = Rc::into_inner(lofter).unwrap().into_inner();
+
+    // The sketch editors edit the bottom two sketches.
+    if lofter.sketch_count() < 2 {
+        return Err("scripts must leave at least two sketches".into());
+    }
+
+    // Loft maps aren't valid for new sketches until relofting.
+    if !lofted.get() {
+        lofter.loft(&Default::default());
+    }
+
+    Ok(lofter)
+}
+
+fn script_error(err: LoftError) -> Box<EvalAltResult> {
+    err.to_string().into()
+}
+
+/// Returns the points of an ellipse with semi-axes `a` and `b`, in CCW order.
+fn ellipse(a: FLOAT, b: FLOAT, count: INT) -> Array {
+    (0..count.max(3))
+        .map(|i| {
+            let angle = TAU * i as f32 / count.max(3) as f32;
+            let point: Array = vec![
+                Dynamic::from_float(a * angle.cos() as FLOAT),
+                Dynamic::from_float(b * angle.sin() as FLOAT),
+            ];
+
+            Dynamic::from_array(point)
+        })
+        .collect()
+}
+
+fn sketch_descriptor(
+    points: Array,
+    offset: Array,
+    rotation: FLOAT,
+) -> Result<SketchDescriptor, Box<EvalAltResult>> {
+    Ok(SketchDescriptor {
+        vertices: points.into_iter().map(to_vec3).collect::<Result<_, _>>()?,
+        relative_position: to_vec3(Dynamic::from_array(offset))?,
+        rotation: Vec3::new(0., 0., rotation as f32),
+    })
+}
+
+/// Checks a script's sketch index is less than `count`.
+fn sketch_index(index: INT, count: usize) -> Result<usize, Box<EvalAltResult>> {
+    usize::try_from(index)
+        .ok()
+        .filter(|&index| index < count)
+        .ok_or_else(|| format!("no sketch at index {index}").into())
+}
+
+/// Converts an array of two or three numbers to a vector.
+fn to_vec3(value: Dynamic) -> Result<Vec3, Box<EvalAltResult>> {
+    let components = value
+        .into_array()
+        .map_err(|type_name| format!("expected an array of numbers, found {type_name}"))?;
+
+    if !(2..=3).contains(&components.len()) {
+        return Err(format!("expected 2 or 3 coordinates, found {}", components.len()).into());
+    }
+
+    let mut vector = Vec3::ZERO;
+    for (axis, component) in components.into_iter().enumerate() {
+        vector[axis] = component
+            .as_float()
+            .or_else(|_| component.as_int().map(|int| int as FLOAT))
+            .map_err(|type_name| format!("expected a number, found {type_name}"))?
+            as f32;
+    }
+
+    Ok(vector)
+}
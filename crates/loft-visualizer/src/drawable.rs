@@ -0,0 +1,104 @@
+use lofter::OutputLayout;
+
+use crate::render::PipelineKind;
+
+/// The layout of every drawable's vertices: an interleaved position and color,
+/// as in `Lofter::vertex_buffer`.
+pub const VERTEX_LAYOUT: OutputLayout = OutputLayout::PositionColor;
+
+/// Bytes per vertex.
+pub const VERTEX_SIZE: u64 = VERTEX_LAYOUT.stride() as u64;
+
+/// The smallest vertex buffer allocation, in bytes.
+const MIN_VERTEX_BUFFER_SIZE: u64 = 4096;
+
+/// A vertex buffer which is reused for new contents when they fit, rather than
+/// being reallocated.
+#[derive(Default)]
+pub struct VertexBuffer {
+    buffer: Option<wgpu::Buffer>,
+    vertex_count: u32,
+}
+
+impl VertexBuffer {
+    /// Writes vertices to the buffer, reallocating it if they don't fit.
+    pub fn write(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, contents: &[u8]) {
+        let size = contents.len() as u64;
+
+        let fits = self.buffer.as_ref().is_some_and(|buffer| {
+            buffer.usage().contains(wgpu::BufferUsages::COPY_DST) && buffer.size() >= size
+        });
+
+        if !fits {
+            // Grow to a power of two, so a growing mesh isn't reallocated every
+            // time it changes.
+            let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: size.next_power_of_two().max(MIN_VERTEX_BUFFER_SIZE),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+
+            self.replace(buffer, 0);
+        }
+
+        if let Some(buffer) = &self.buffer
+            && size > 0
+        {
+            queue.write_buffer(buffer, 0, contents);
+        }
+
+        self.vertex_count = (size / VERTEX_SIZE) as u32;
+    }
+
+    /// Replaces the buffer with one written elsewhere, destroying the old
+    /// buffer.
+    pub fn replace(&mut self, buffer: wgpu::Buffer, vertex_count: u32) {
+        if let Some(old_buffer) = self.buffer.replace(buffer) {
+            old_buffer.destroy();
+        }
+
+        self.vertex_count = vertex_count;
+    }
+
+    /// Stops drawing the buffer's contents. The buffer is kept for reuse.
+    pub fn clear(&mut self) {
+        self.vertex_count = 0;
+    }
+
+    /// Draws the buffer's contents with the render pass's current pipeline.
+    pub fn draw(&self, rpass: &mut wgpu::RenderPass) {
+        let Some(buffer) = &self.buffer else {
+            return;
+        };
+
+        if self.vertex_count == 0 {
+            return;
+        }
+
+        rpass.set_vertex_buffer(0, buffer.slice(..));
+        rpass.draw(0..self.vertex_count, 0..1);
+    }
+}
+
+/// A handle to a drawable added to the renderer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DrawableId(pub usize);
+
+/// A vertex buffer the renderer draws with one of its pipelines.
+pub struct Drawable {
+    pub vertex_buffer: VertexBuffer,
+    pub pipeline: PipelineKind,
+    /// Hidden drawables keep their vertices, but aren't drawn.
+    pub visible: bool,
+}
+
+impl Drawable {
+    pub fn new(pipeline: PipelineKind) -> Self {
+        Self {
+            vertex_buffer: Default::default(),
+            pipeline,
+            visible: true,
+        }
+    }
+}
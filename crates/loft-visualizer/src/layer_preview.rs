@@ -0,0 +1,90 @@
+use glam::Vec3;
+use lofter::{Lofter, SliceLayer};
+
+const LAYER_COLOR: Vec3 = Vec3::new(0.9, 0.55, 0.2);
+const TOP_LAYER_COLOR: Vec3 = Vec3::new(1., 1., 1.);
+
+/// Previews the loft sliced into layers for printing, drawn in place of the
+/// loft as the layers' contours, up to the highest layer shown, which is
+/// highlighted.
+pub struct LayerPreview {
+    pub enabled: bool,
+    /// In world units.
+    pub layer_height: f32,
+    /// The number of layers shown, from the bottom.
+    pub shown_layers: usize,
+    layers: Vec<SliceLayer>,
+    /// The lofter revision and layer height the layers were sliced at.
+    sliced: Option<(u64, f32)>,
+}
+
+impl Default for LayerPreview {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            layer_height: 0.05,
+            shown_layers: usize::MAX,
+            layers: Vec::new(),
+            sliced: None,
+        }
+    }
+}
+
+impl LayerPreview {
+    /// Slices the loft again if it or the layer height has changed since it
+    /// was last sliced. If every layer was shown, every new layer is too.
+    pub fn update(&mut self, lofter: &Lofter) {
+        if !self.enabled {
+            self.layers.clear();
+            self.sliced = None;
+            return;
+        }
+
+        let key = (lofter.revision(), self.layer_height);
+        if self.sliced == Some(key) {
+            return;
+        }
+
+        let showing_all = self.shown_layers >= self.layers.len();
+        self.layers = lofter.slice_layers(self.layer_height);
+        self.sliced = Some(key);
+
+        if showing_all {
+            self.shown_layers = self.layers.len();
+        }
+        self.shown_layers = self.shown_layers.min(self.layers.len());
+    }
+
+    pub fn layer_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// The height of the highest layer shown.
+    pub fn shown_height(&self) -> Option<f32> {
+        let layer = self.layers[..self.shown_layers].last()?;
+        Some(layer.z)
+    }
+
+    /// Returns line segments of the shown layers' contours.
+    pub fn lines(&self) -> Vec<[[Vec3; 2]; 2]> {
+        let shown = &self.layers[..self.shown_layers];
+        let mut lines = Vec::new();
+
+        for (i, layer) in shown.iter().enumerate() {
+            let color = if i + 1 == shown.len() {
+                TOP_LAYER_COLOR
+            } else {
+                LAYER_COLOR
+            };
+
+            for contour in &layer.contours {
+                for (j, &point) in contour.iter().enumerate() {
+                    let next = contour[(j + 1) % contour.len()];
+                    lines.push([[point, color], [next, color]]);
+                }
+            }
+        }
+
+        lines
+    }
+}
@@ -0,0 +1,170 @@
+use std::{
+    env,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter},
+    path::PathBuf,
+};
+
+use glam::Vec3;
+use lofter::{LoftOptions, LoftResult, Lofter, SketchDescriptor};
+
+/// A library of named profiles, saved as JSON files in a directory, which can
+/// be inserted into any project as new sketches.
+pub struct ProfileLibrary {
+    pub directory: String,
+    /// The name the sketch is saved under.
+    pub name: String,
+    /// The sketch saved to the library, and the index profiles are inserted
+    /// at.
+    pub sketch_index: usize,
+    /// The position of inserted profiles, relative to the previous sketch.
+    pub offset: [f32; 3],
+    /// The names of the profiles in the directory, refreshed by requests.
+    pub profiles: Option<Vec<String>>,
+    /// Set by the UI.
+    pub request: Option<LibraryRequest>,
+    /// A message describing the result of the last request.
+    pub status: Option<String>,
+}
+
+#[derive(Clone)]
+pub enum LibraryRequest {
+    /// Lists the profiles in the directory.
+    Refresh,
+    /// Saves the sketch at `sketch_index` as a profile named `name`.
+    Save,
+    /// Inserts the named profile as a new sketch at `sketch_index`.
+    Insert(String),
+}
+
+impl Default for ProfileLibrary {
+    fn default() -> Self {
+        Self {
+            directory: default_directory(),
+            name: String::new(),
+            sketch_index: 0,
+            offset: [0., 0., 1.],
+            profiles: None,
+            request: Some(LibraryRequest::Refresh),
+            status: None,
+        }
+    }
+}
+
+impl ProfileLibrary {
+    /// Handles a request from the UI, if there is one. Inserted profiles are
+    /// lofted right away, returning the result.
+    pub fn handle_request(
+        &mut self,
+        lofter: &mut Lofter,
+        options: &LoftOptions,
+    ) -> Option<LoftResult> {
+        let request = self.request.take()?;
+        let mut loft_result = None;
+
+        let result = match request {
+            LibraryRequest::Refresh => self.refresh().map(|count| format!("{count} profiles")),
+            LibraryRequest::Save => self.save(lofter).and_then(|()| {
+                self.refresh()?;
+                Ok(format!("Saved {}", self.name))
+            }),
+            LibraryRequest::Insert(name) => self.load(&name).and_then(|mut profile| {
+                profile.relative_position = Vec3::from_array(self.offset);
+
+                lofter
+                    .insert_sketch(self.sketch_index, &profile)
+                    .map_err(io::Error::other)?;
+                loft_result = Some(lofter.loft(options));
+
+                Ok(format!("Inserted {name} at sketch {}", self.sketch_index))
+            }),
+        };
+
+        self.status = Some(result.unwrap_or_else(|err| format!("Library failed: {err}")));
+
+        loft_result
+    }
+
+    /// Lists the profiles in the directory, returning how many there are. A
+    /// missing directory is an empty library.
+    fn refresh(&mut self) -> io::Result<usize> {
+        let mut profiles = Vec::new();
+
+        match fs::read_dir(&self.directory) {
+            Ok(entries) => {
+                for entry in entries {
+                    let path = entry?.path();
+
+                    if path
+                        .extension()
+                        .is_some_and(|extension| extension == "json")
+                        && let Some(name) = path.file_stem().and_then(|stem| stem.to_str())
+                    {
+                        profiles.push(name.to_owned());
+                    }
+                }
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+
+        profiles.sort();
+        let count = profiles.len();
+        self.profiles = Some(profiles);
+
+        Ok(count)
+    }
+
+    /// Saves the selected sketch's vertices, without its transform, so the
+    /// profile can be inserted anywhere.
+    fn save(&self, lofter: &Lofter) -> io::Result<()> {
+        if self.name.is_empty() || self.name.contains(['/', '\\']) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "profiles need a name, without slashes",
+            ));
+        }
+
+        let profile = lofter.sketch_descriptor(self.sketch_index).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no sketch at index {}", self.sketch_index),
+            )
+        })?;
+        let profile = SketchDescriptor {
+            relative_position: Vec3::ZERO,
+            rotation: Vec3::ZERO,
+            ..profile
+        };
+
+        fs::create_dir_all(&self.directory)?;
+        let writer = BufWriter::new(File::create(self.profile_path(&self.name))?);
+        serde_json::to_writer_pretty(writer, &profile)?;
+
+        Ok(())
+    }
+
+    fn load(&self, name: &str) -> io::Result<SketchDescriptor> {
+        let reader = BufReader::new(File::open(self.profile_path(name))?);
+
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    fn profile_path(&self, name: &str) -> PathBuf {
+        PathBuf::from(&self.directory).join(format!("{name}.json"))
+    }
+}
+
+/// The user's library directory, shared between projects.
+fn default_directory() -> String {
+    let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"));
+
+    match home {
+        Some(home) => PathBuf::from(home)
+            .join(".loft-visualizer")
+            .join("profiles")
+            .to_string_lossy()
+            .into_owned(),
+        None => "profiles".into(),
+    }
+}
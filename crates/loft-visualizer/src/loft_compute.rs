@@ -0,0 +1,195 @@
+use bytemuck::{Pod, Zeroable};
+use glam::Vec4;
+use lofter::LocalLoftVertex;
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+
+use crate::drawable::VERTEX_SIZE;
+
+/// Threads per workgroup of the compute shader.
+const WORKGROUP_SIZE: u32 = 64;
+
+/// Matches `LocalVertex` in the compute shader.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct GpuLocalVertex {
+    position: [f32; 3],
+    sketch_index: u32,
+    color: [f32; 3],
+    _padding: u32,
+}
+
+/// Evaluates the loft mesh on the GPU from its vertices in sketch-local space,
+/// so changing sketch transforms only uploads one transform per sketch rather
+/// than the whole mesh.
+///
+/// Meshes are limited to `65535 * WORKGROUP_SIZE` vertices, the maximum
+/// dispatch size.
+pub struct LoftCompute {
+    pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    buffers: Option<LoftComputeBuffers>,
+}
+
+struct LoftComputeBuffers {
+    sketch_transform_buffer: wgpu::Buffer,
+    vertex_count: u32,
+    bind_group: wgpu::BindGroup,
+}
+
+impl LoftCompute {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let storage_entry = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                storage_entry(0, true),
+                storage_entry(1, true),
+                storage_entry(2, false),
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("loft_compute.wgsl"));
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: None,
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        Self {
+            pipeline,
+            bind_group_layout,
+            buffers: None,
+        }
+    }
+
+    /// Uploads the loft's vertices in sketch-local space, for a loft with the
+    /// given number of sketches. Returns the vertex buffer the evaluated mesh
+    /// will be written to, and its vertex count, or `None` if the loft is
+    /// empty.
+    pub fn set_local_vertices(
+        &mut self,
+        device: &wgpu::Device,
+        local_vertex_buffer: &[[LocalLoftVertex; 3]],
+        sketch_count: usize,
+    ) -> Option<(wgpu::Buffer, u32)> {
+        self.buffers = None;
+
+        if local_vertex_buffer.is_empty() || sketch_count == 0 {
+            return None;
+        }
+
+        let local_vertices: Vec<_> = local_vertex_buffer
+            .iter()
+            .flatten()
+            .map(|vertex| GpuLocalVertex {
+                position: vertex.position.to_array(),
+                sketch_index: vertex.sketch_index as u32,
+                color: vertex.color.to_array(),
+                _padding: 0,
+            })
+            .collect();
+
+        let vertex_count = local_vertices.len() as u32;
+
+        let local_vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&local_vertices),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+
+        let sketch_transform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (sketch_count * size_of::<Vec4>()) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: vertex_count as u64 * VERTEX_SIZE,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: local_vertex_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: sketch_transform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: vertex_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        self.buffers = Some(LoftComputeBuffers {
+            sketch_transform_buffer,
+            vertex_count,
+            bind_group,
+        });
+
+        Some((vertex_buffer, vertex_count))
+    }
+
+    /// Evaluates the mesh for new sketch transforms. Each transform is the
+    /// sketch's world position, with its rotation about the z axis, in
+    /// radians, as w.
+    pub fn evaluate(&self, device: &wgpu::Device, queue: &wgpu::Queue, sketch_transforms: &[Vec4]) {
+        let Some(buffers) = &self.buffers else {
+            return;
+        };
+
+        // The transform buffer is sized for the sketches the local vertices
+        // were uploaded for.
+        let size = buffers.sketch_transform_buffer.size() as usize / size_of::<Vec4>();
+        let Some(sketch_transforms) = sketch_transforms.get(..size) else {
+            return;
+        };
+
+        queue.write_buffer(
+            &buffers.sketch_transform_buffer,
+            0,
+            bytemuck::cast_slice(sketch_transforms),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut cpass = encoder.begin_compute_pass(&Default::default());
+            cpass.set_pipeline(&self.pipeline);
+            cpass.set_bind_group(0, &buffers.bind_group, &[]);
+            cpass.dispatch_workgroups(buffers.vertex_count.div_ceil(WORKGROUP_SIZE), 1, 1);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
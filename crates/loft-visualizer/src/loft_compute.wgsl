@@ -0,0 +1,46 @@
+struct LocalVertex {
+    position: vec3<f32>,
+    sketch_index: u32,
+    color: vec3<f32>,
+}
+
+struct SketchTransform {
+    origin: vec3<f32>,
+    /// Rotation about the z axis, in radians.
+    rotation: f32,
+}
+
+@group(0) @binding(0)
+var<storage, read> local_vertices: array<LocalVertex>;
+@group(0) @binding(1)
+var<storage, read> sketch_transforms: array<SketchTransform>;
+/// Interleaved positions and colors, matching the render pipeline's vertex
+/// layout.
+@group(0) @binding(2)
+var<storage, read_write> vertices: array<f32>;
+
+@compute
+@workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+    if index >= arrayLength(&local_vertices) {
+        return;
+    }
+
+    let vertex = local_vertices[index];
+    let transform = sketch_transforms[vertex.sketch_index];
+
+    let c = cos(transform.rotation);
+    let s = sin(transform.rotation);
+    let local = vertex.position;
+    let position = vec3<f32>(local.x * c - local.y * s, local.x * s + local.y * c, local.z)
+        + transform.origin;
+
+    let offset = index * 6u;
+    vertices[offset] = position.x;
+    vertices[offset + 1u] = position.y;
+    vertices[offset + 2u] = position.z;
+    vertices[offset + 3u] = vertex.color.x;
+    vertices[offset + 4u] = vertex.color.y;
+    vertices[offset + 5u] = vertex.color.z;
+}
@@ -1,18 +1,69 @@
-use glam::Vec3;
-use lofter::{LoftOptions, Lofter};
-use std::sync::Arc;
+use glam::{Vec3, Vec4};
+use lofter::{Lofter, StationChange};
+use std::{
+    iter::zip,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use winit::{
     application::ApplicationHandler,
-    dpi::LogicalSize,
-    event::{Event, WindowEvent},
+    dpi::{LogicalSize, PhysicalPosition},
+    event::{ElementState, Event, MouseButton, TouchPhase, WindowEvent},
     event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
     window::Window,
 };
 
-use crate::{render::Renderer, ui::ImguiState};
+use crate::{
+    analysis::SurfaceAnalysis,
+    camera::{Camera, bounding_sphere},
+    navigation::Navigation,
+    render::Renderer,
+    ui::ImguiState,
+};
 
+mod analysis;
+mod camera;
+#[cfg(feature = "scripting")]
+mod console;
+mod drawable;
+mod layer_preview;
+mod library;
+mod loft_compute;
+mod navigation;
+mod picking;
+mod playback;
 mod render;
+mod session;
+mod sketch_editor;
+#[cfg(feature = "sync")]
+mod sync;
+mod turntable;
 mod ui;
+#[cfg(feature = "sync")]
+mod websocket;
+
+/// The number of frames drawn after each change in redraw-on-change mode, as
+/// imgui needs a few frames to settle, such as for hover highlights.
+const REDRAW_FRAMES: u32 = 3;
+
+/// How often connected gamepads are polled in redraw-on-change mode, as they
+/// don't wake the event loop.
+const GAMEPAD_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How often other visualizers' edits are checked for in redraw-on-change
+/// mode, as they don't wake the event loop.
+#[cfg(feature = "sync")]
+const SYNC_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The longest time step used for animation, in seconds. After idling in
+/// redraw-on-change mode, the time since the last frame is meaningless.
+const MAX_DELTA_TIME: f32 = 0.1;
+
+/// Line colors for the sketch outlines and ruling edges drawn over the loft.
+const SKETCH_OUTLINE_COLOR: Vec3 = Vec3::new(1., 1., 1.);
+const RULING_EDGE_COLOR: Vec3 = Vec3::new(0.2, 0.2, 0.2);
+/// The color of ruling edges exceeding the station change limits.
+const STATION_CHANGE_COLOR: Vec3 = Vec3::new(1., 0.6, 0.1);
 
 fn main() {
     let event_loop = EventLoop::new().unwrap();
@@ -24,16 +75,34 @@ struct AppWindow {
     renderer: Renderer,
     window: Arc<Window>,
     hidpi_factor: f32,
-    camera_rotation: f32,
+    camera: Camera,
+    navigation: Navigation,
+    loft_mesh: LoftMesh,
+    last_frame: Instant,
+    /// Frames left to draw before the event loop waits for another change, in
+    /// redraw-on-change mode.
+    redraw_frames: u32,
+    /// The cursor position in physical pixels, if it's over the window.
+    cursor_position: Option<PhysicalPosition<f64>>,
+    /// Whether a sketch was being edited last frame.
+    editing: bool,
     imgui: Option<ImguiState>,
 }
 
-#[derive(Default)]
 struct App {
     lofter: Lofter,
     app_window: Option<AppWindow>,
 }
 
+impl Default for App {
+    fn default() -> Self {
+        Self {
+            lofter: Lofter::demo(),
+            app_window: None,
+        }
+    }
+}
+
 impl AppWindow {
     fn setup_gpu(event_loop: &ActiveEventLoop) -> Self {
         let window = {
@@ -52,7 +121,13 @@ impl AppWindow {
             renderer,
             window,
             hidpi_factor,
-            camera_rotation: 0.,
+            camera: Default::default(),
+            navigation: Default::default(),
+            loft_mesh: Default::default(),
+            last_frame: Instant::now(),
+            redraw_frames: REDRAW_FRAMES,
+            cursor_position: None,
+            editing: false,
             imgui: None,
         }
     }
@@ -61,15 +136,206 @@ impl AppWindow {
         let mut app_window = Self::setup_gpu(event_loop);
         app_window.imgui = ImguiState::new(&app_window.renderer, app_window.hidpi_factor).into();
 
-        let vb = lofter.vertex_buffer();
-
-        app_window.renderer.set_loft_vertex_buffer(&vb);
-        app_window.renderer.set_camera_rotation(0.);
+        app_window.loft_mesh.update(
+            lofter,
+            &[],
+            &mut app_window.renderer,
+            false,
+            0.,
+            SurfaceAnalysis::None,
+        );
+        app_window.renderer.set_camera(&app_window.camera);
 
         app_window
     }
 }
 
+/// The loft's mesh, which is only rebuilt when the lofter changes.
+#[derive(Default)]
+struct LoftMesh {
+    /// The lofter revision the mesh was built from.
+    revision: Option<u64>,
+    /// The lofter shape revision the mesh was built from.
+    shape_revision: Option<u64>,
+    /// Whether the mesh was built for evaluation on the GPU.
+    gpu_evaluated: bool,
+    /// How far the mesh's sections were exploded, as a fraction of the loft's
+    /// bounding radius.
+    explode: f32,
+    /// The analysis coloring the mesh.
+    analysis: SurfaceAnalysis,
+    /// The summary of the analysis, for the UI.
+    analysis_summary: Option<String>,
+    bounds: Option<(Vec3, f32)>,
+}
+
+impl LoftMesh {
+    /// Rebuilds the mesh if the lofter has changed since it was last built,
+    /// highlighting the ruling edges of `station_changes`. Returns true if it
+    /// was rebuilt.
+    ///
+    /// With GPU evaluation, only the sketch transforms are uploaded unless the
+    /// loft's shape changed. Exploded and analyzed meshes are always built on
+    /// the CPU.
+    fn update(
+        &mut self,
+        lofter: &Lofter,
+        station_changes: &[StationChange],
+        renderer: &mut Renderer,
+        gpu_evaluation: bool,
+        explode: f32,
+        analysis: SurfaceAnalysis,
+    ) -> bool {
+        let gpu_evaluation = gpu_evaluation && explode == 0. && analysis == SurfaceAnalysis::None;
+
+        let mode_changed = gpu_evaluation != self.gpu_evaluated
+            || explode != self.explode
+            || analysis != self.analysis;
+        if !mode_changed && self.revision == Some(lofter.revision()) {
+            return false;
+        }
+
+        let shape_changed = mode_changed || self.shape_revision != Some(lofter.shape_revision());
+
+        self.revision = Some(lofter.revision());
+        self.shape_revision = Some(lofter.shape_revision());
+        self.gpu_evaluated = gpu_evaluation;
+        self.explode = explode;
+        self.analysis = analysis;
+        self.analysis_summary = None;
+
+        renderer.set_drawable_vertices(renderer.sketch_outlines, &sketch_outlines(lofter));
+
+        let ruling_edges = lofter.ruling_edges();
+        let lines: Vec<_> = ruling_edges
+            .iter()
+            .map(|edge| edge.map(|position| [position, RULING_EDGE_COLOR]))
+            .collect();
+        renderer.set_drawable_vertices(renderer.ruling_edges, &lines);
+
+        let lines: Vec<_> = station_changes
+            .iter()
+            .filter_map(|change| ruling_edges.get(change.ruling_edge))
+            .map(|edge| edge.map(|position| [position, STATION_CHANGE_COLOR]))
+            .collect();
+        renderer.set_drawable_vertices(renderer.station_changes, &lines);
+
+        if !gpu_evaluation {
+            let mut vertex_buffer = lofter.vertex_buffer();
+            self.bounds = bounding_sphere(&vertex_buffer);
+
+            if let Some(result) = analysis.evaluate(lofter) {
+                for (triangle, color) in zip(&mut vertex_buffer, result.triangle_colors) {
+                    for vertex in triangle {
+                        vertex[1] = color;
+                    }
+                }
+                self.analysis_summary = Some(result.summary);
+            }
+
+            if let Some((center, radius)) = self.bounds
+                && explode > 0.
+            {
+                let sections = lofter.triangle_sections();
+                explode_sections(&mut vertex_buffer, &sections, center, explode * radius);
+            }
+
+            renderer.set_loft_vertex_buffer(&vertex_buffer);
+
+            return true;
+        }
+
+        // The bounds aren't updated for transform changes, since that would
+        // need the mesh on the CPU.
+        if shape_changed {
+            self.bounds = bounding_sphere(&lofter.vertex_buffer());
+            renderer
+                .set_loft_local_vertex_buffer(&lofter.local_vertex_buffer(), lofter.sketch_count());
+        }
+
+        renderer.evaluate_loft(&sketch_transforms(lofter));
+
+        true
+    }
+}
+
+/// Moves each section of the mesh by `distance` along its average normal,
+/// oriented away from `center`. `sections` holds the section of each triangle.
+fn explode_sections(
+    vertex_buffer: &mut [[[Vec3; 2]; 3]],
+    sections: &[usize],
+    center: Vec3,
+    distance: f32,
+) {
+    let section_count = sections.iter().max().map_or(0, |max| max + 1);
+
+    // Summing unnormalized face normals weights them by area.
+    let mut normals = vec![Vec3::ZERO; section_count];
+    let mut centroids = vec![Vec3::ZERO; section_count];
+
+    for (triangle, &section) in zip(&*vertex_buffer, sections) {
+        let [a, b, c] = triangle.map(|[position, _]| position);
+        normals[section] += (b - a).cross(c - a);
+        centroids[section] += a + b + c;
+    }
+
+    let offsets: Vec<_> = zip(normals, centroids)
+        .map(|(normal, centroid)| {
+            let normal = normal.normalize_or_zero();
+            let outward = if normal.dot(centroid - center) < 0. {
+                -normal
+            } else {
+                normal
+            };
+
+            outward * distance
+        })
+        .collect();
+
+    for (triangle, &section) in zip(vertex_buffer, sections) {
+        for [position, _] in triangle {
+            *position += offsets[section];
+        }
+    }
+}
+
+/// Returns each sketch's outline as world space line segments, with a position
+/// and color for each end.
+fn sketch_outlines(lofter: &Lofter) -> Vec<[[Vec3; 2]; 2]> {
+    let mut lines = Vec::new();
+
+    for sketch_index in 0..lofter.sketch_count() {
+        let positions: Vec<_> = lofter
+            .vertices(sketch_index)
+            .unwrap()
+            .map(|(id, _)| lofter.vertex_world_position(sketch_index, id).unwrap())
+            .collect();
+
+        for (i, position) in positions.iter().enumerate() {
+            let next = positions[(i + 1) % positions.len()];
+            lines.push([
+                [*position, SKETCH_OUTLINE_COLOR],
+                [next, SKETCH_OUTLINE_COLOR],
+            ]);
+        }
+    }
+
+    lines
+}
+
+/// Returns each sketch's world position, with its rotation about the z axis,
+/// in radians, as w.
+fn sketch_transforms(lofter: &Lofter) -> Vec<Vec4> {
+    (0..lofter.sketch_count())
+        .map(|sketch_index| {
+            let origin = lofter.sketch_world_position(sketch_index).unwrap();
+            let rotation = lofter.sketch_rotation(sketch_index).unwrap().z;
+
+            origin.extend(rotation.to_radians())
+        })
+        .collect()
+}
+
 impl ApplicationHandler for App {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         self.app_window = Some(AppWindow::new(&self.lofter, event_loop));
@@ -82,12 +348,22 @@ impl ApplicationHandler for App {
         event: WindowEvent,
     ) {
         let app_window = self.app_window.as_mut().unwrap();
+
+        // Any input may change what's drawn.
+        if !matches!(event, WindowEvent::RedrawRequested) {
+            app_window.redraw_frames = REDRAW_FRAMES;
+        }
+
         let imgui = app_window.imgui.as_mut().unwrap();
 
         match &event {
             WindowEvent::Resized(size) => {
                 app_window.renderer.resize(size.width, size.height);
             }
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                app_window.hidpi_factor = *scale_factor as f32;
+                imgui.set_hidpi_factor(&app_window.renderer, app_window.hidpi_factor);
+            }
             WindowEvent::MouseWheel { delta, .. } => {
                 let delta = match delta {
                     winit::event::MouseScrollDelta::LineDelta(_, y) => *y,
@@ -96,10 +372,46 @@ impl ApplicationHandler for App {
                     }
                 };
 
-                app_window.camera_rotation += delta * 0.01;
+                app_window.camera.rotation += delta * 0.01;
+            }
+            // Touches over the UI are left to it, but all touches are tracked
+            // until they end.
+            WindowEvent::Touch(touch)
+                if touch.phase != TouchPhase::Started || !imgui.wants_pointer() =>
+            {
+                let viewport_height = app_window.window.inner_size().height as f32;
                 app_window
-                    .renderer
-                    .set_camera_rotation(app_window.camera_rotation);
+                    .navigation
+                    .handle_touch(touch, &mut app_window.camera, viewport_height);
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                app_window.cursor_position = Some(*position);
+            }
+            WindowEvent::CursorLeft { .. } => {
+                app_window.cursor_position = None;
+            }
+            // Right-clicking the loft picks the triangle under the cursor.
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Right,
+                ..
+            } if !imgui.wants_pointer() => {
+                imgui.picked_triangle = app_window.cursor_position.and_then(|position| {
+                    let index = app_window
+                        .renderer
+                        .pick_loft_triangle(position.x as u32, position.y as u32)?;
+
+                    let triangle = *self.lofter.vertex_buffer().get(index as usize)?;
+                    let centroid =
+                        triangle.iter().map(|[position, _]| *position).sum::<Vec3>() / 3.;
+
+                    Some((index, centroid))
+                });
+            }
+            WindowEvent::PinchGesture { delta, .. } => {
+                app_window
+                    .navigation
+                    .handle_pinch(*delta, &mut app_window.camera);
             }
             WindowEvent::CloseRequested => event_loop.exit(),
             WindowEvent::RedrawRequested => {
@@ -111,25 +423,176 @@ impl ApplicationHandler for App {
                     .texture
                     .create_view(&wgpu::TextureViewDescriptor::default());
 
+                app_window.redraw_frames = app_window.redraw_frames.saturating_sub(1);
+
                 app_window.renderer.draw(&view);
-                imgui.draw(&app_window.renderer, &view, &mut self.lofter);
+                imgui.draw(
+                    &app_window.renderer,
+                    &view,
+                    &mut self.lofter,
+                    &mut app_window.camera,
+                );
 
                 // Check UI changes.
 
+                if imgui.playback.record_requested {
+                    imgui.playback.record_requested = false;
+
+                    let (steps, result) = self.lofter.explain_loft(&imgui.loft_state.options());
+                    imgui.playback.start(steps, &self.lofter);
+                    imgui.loft_state.result = Some(result);
+                }
+
+                if let Some(result) = imgui
+                    .library
+                    .handle_request(&mut self.lofter, &imgui.loft_state.options())
+                {
+                    imgui.loft_state.result = Some(result);
+                }
+
                 if imgui.loft_state.reloft {
                     imgui.loft_state.reloft = false;
 
-                    self.lofter.loft(&LoftOptions {
-                        max_radial_edge_angle: imgui.loft_state.max_angle,
-                    });
+                    let result = self.lofter.loft(&imgui.loft_state.options());
+                    imgui.loft_state.result = Some(result);
+                }
+
+                #[cfg(feature = "scripting")]
+                if imgui.console.run_requested {
+                    imgui.console.run_requested = false;
+                    imgui.console.run(&mut self.lofter);
+                }
+
+                imgui.session.handle_request(&mut self.lofter);
+                imgui.session.update(&mut self.lofter);
+
+                #[cfg(feature = "sync")]
+                {
+                    imgui.sync.handle_request(&mut self.lofter);
+                    imgui.sync.update(&mut self.lofter);
+                }
+
+                let station_changes = imgui
+                    .loft_state
+                    .result
+                    .as_ref()
+                    .map_or(&[][..], |result| &result.station_changes);
+                if app_window.loft_mesh.update(
+                    &self.lofter,
+                    station_changes,
+                    &mut app_window.renderer,
+                    imgui.gpu_loft_evaluation,
+                    imgui.explode_sections,
+                    imgui.surface_analysis,
+                ) {
+                    app_window.redraw_frames = REDRAW_FRAMES;
+                    // Triangle indices change when the loft is rebuilt.
+                    imgui.picked_triangle = None;
+                    imgui
+                        .analysis_summary
+                        .clone_from(&app_window.loft_mesh.analysis_summary);
+                }
+
+                let renderer = &mut app_window.renderer;
+                renderer.set_shading(imgui.shading);
+                renderer.set_drawable_visible(renderer.sketch_outlines, imgui.show_sketch_outlines);
+                renderer.set_drawable_visible(renderer.ruling_edges, imgui.show_ruling_edges);
+
+                let bounds = app_window.loft_mesh.bounds;
+                if let Some((center, radius)) = bounds {
+                    app_window.camera.fit_to_bounds(center, radius);
+                }
+
+                let highlight = imgui
+                    .highlighted_vertex
+                    .and_then(|(sketch_index, id)| {
+                        self.lofter.vertex_world_position(sketch_index, id)
+                    })
+                    .or(imgui.picked_triangle.map(|(_, centroid)| centroid));
+                let highlight_radius = bounds.map_or(0.05, |(_, radius)| radius * 0.03);
+                app_window
+                    .renderer
+                    .set_highlight(highlight, highlight_radius);
+
+                if let Some(present_mode) = imgui.present_mode_request.take() {
+                    app_window.renderer.set_present_mode(present_mode);
+                }
+
+                if imgui.frame_requested {
+                    imgui.frame_requested = false;
+
+                    if let Some((center, radius)) = bounds {
+                        app_window.camera.frame(center, radius);
+                    }
+                }
+
+                // While a sketch is being edited, show the loft from before
+                // the edit started as a ghost.
+                let editing = imgui.is_editing();
+                if editing != app_window.editing {
+                    app_window.editing = editing;
+                    let ghost_vertex_buffer = editing.then(|| self.lofter.vertex_buffer());
+                    app_window
+                        .renderer
+                        .set_ghost_vertex_buffer(ghost_vertex_buffer.as_deref());
+                }
+
+                let now = Instant::now();
+                let delta_time = (now - app_window.last_frame)
+                    .as_secs_f32()
+                    .min(MAX_DELTA_TIME);
+                app_window.last_frame = now;
+
+                if app_window
+                    .navigation
+                    .update(&mut app_window.camera, delta_time)
+                {
+                    app_window.redraw_frames = REDRAW_FRAMES;
                 }
 
-                self.lofter
-                    .set_sketch_rotation(1, &Vec3::new(0., 0., imgui.loft_state.rotation));
+                // Playback and the layer preview are drawn in place of the
+                // loft.
+                let playback = &mut imgui.playback;
+                playback.update(&self.lofter, delta_time);
+                let layer_preview = &mut imgui.layer_preview;
+                layer_preview.update(&self.lofter);
 
-                // Todo: don't do this every frame.
-                let vertex_buffer = self.lofter.vertex_buffer();
-                app_window.renderer.set_loft_vertex_buffer(&vertex_buffer);
+                let renderer = &mut app_window.renderer;
+                renderer.set_loft_visible(!playback.is_active() && !layer_preview.enabled);
+                if playback.is_active() {
+                    renderer.set_drawable_vertices(
+                        renderer.construction,
+                        &playback.lines(&self.lofter),
+                    );
+                } else {
+                    renderer.clear_drawable(renderer.construction);
+                }
+                if layer_preview.enabled {
+                    renderer.set_drawable_vertices(renderer.slice_layers, &layer_preview.lines());
+                } else {
+                    renderer.clear_drawable(renderer.slice_layers);
+                }
+
+                if imgui.turntable.enabled {
+                    app_window.camera.rotation += imgui.turntable.speed.to_radians() * delta_time;
+                }
+
+                app_window.renderer.set_camera(&app_window.camera);
+
+                let export = &mut imgui.turntable.export;
+                if export.requested {
+                    export.requested = false;
+
+                    export.status = Some(
+                        match export.export(&app_window.renderer, &app_window.camera) {
+                            Ok(path) => format!("Exported to {}", path.display()),
+                            Err(err) => format!("Export failed: {err}"),
+                        },
+                    );
+
+                    // Exporting moves the camera.
+                    app_window.renderer.set_camera(&app_window.camera);
+                }
 
                 surface.present();
             }
@@ -155,6 +618,10 @@ impl ApplicationHandler for App {
         event: winit::event::DeviceEvent,
     ) {
         let app_window = self.app_window.as_mut().unwrap();
+        if app_window.navigation.handle_device_event(device_id, &event) {
+            app_window.redraw_frames = REDRAW_FRAMES;
+        }
+
         let imgui = app_window.imgui.as_mut().unwrap();
         imgui.handle_event(
             &app_window.renderer,
@@ -162,10 +629,33 @@ impl ApplicationHandler for App {
         );
     }
 
-    fn about_to_wait(&mut self, _event_loop: &ActiveEventLoop) {
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
         let app_window = self.app_window.as_mut().unwrap();
         let imgui = app_window.imgui.as_mut().unwrap();
-        app_window.window.request_redraw();
+
+        if app_window.navigation.poll_gamepads() {
+            app_window.redraw_frames = REDRAW_FRAMES;
+        }
+
+        // In redraw-on-change mode, wait for events unless something changed
+        // recently or is animating.
+        if !imgui.redraw_on_change
+            || imgui.turntable.enabled
+            || imgui.playback.playing
+            || imgui.session.is_replaying()
+            || app_window.redraw_frames > 0
+        {
+            event_loop.set_control_flow(ControlFlow::Poll);
+            app_window.window.request_redraw();
+        } else if app_window.navigation.wants_polling() {
+            event_loop.set_control_flow(ControlFlow::wait_duration(GAMEPAD_POLL_INTERVAL));
+        } else if cfg!(feature = "sync") && imgui.is_syncing() {
+            #[cfg(feature = "sync")]
+            event_loop.set_control_flow(ControlFlow::wait_duration(SYNC_POLL_INTERVAL));
+            app_window.window.request_redraw();
+        } else {
+            event_loop.set_control_flow(ControlFlow::Wait);
+        }
         imgui.handle_event(&app_window.renderer, &Event::AboutToWait);
     }
 }
@@ -0,0 +1,239 @@
+use std::collections::{HashMap, HashSet};
+
+use glam::Vec2;
+use winit::event::{DeviceEvent, DeviceId, Touch, TouchPhase};
+
+use crate::camera::Camera;
+
+/// The lowest axis index which only 6-DoF devices report. Regular mice report
+/// motion on axes 0 and 1, plus 2 and 3 for scrolling on some platforms.
+const SIX_DOF_MIN_AXIS: u32 = 4;
+
+/// Scale applied to raw 3D mouse motion, which is reported in device units of
+/// roughly ±350 at full deflection.
+const SPACE_MOUSE_SCALE: f32 = 1e-5;
+
+/// Orbit speed when dragging a single finger, in radians per pixel.
+const TOUCH_ORBIT_SPEED: f32 = 0.01;
+
+/// Stick deflection below which gamepad input is ignored.
+#[cfg(feature = "gamepad")]
+const GAMEPAD_DEADZONE: f32 = 0.15;
+
+/// Orbit speed at full stick deflection, in radians per second.
+#[cfg(feature = "gamepad")]
+const GAMEPAD_ORBIT_SPEED: f32 = 2.;
+
+/// Pan speed at full stick deflection, in camera distances per second.
+#[cfg(feature = "gamepad")]
+const GAMEPAD_PAN_SPEED: f32 = 0.5;
+
+/// Zoom speed at full trigger pressure, as the log of the zoom factor per
+/// second.
+#[cfg(feature = "gamepad")]
+const GAMEPAD_ZOOM_SPEED: f32 = 1.;
+
+/// Camera navigation with 3D mice (such as SpaceMice) and gamepads.
+///
+/// 3D mice are read from raw device motion: twisting the cap orbits, pushing
+/// and pulling it zooms, and sliding it pans. Gamepads require the `gamepad`
+/// feature: the left stick orbits and the right stick pans, while the triggers
+/// zoom.
+///
+/// On touchscreens, dragging one finger orbits, and dragging two fingers pans,
+/// while pinching zooms.
+pub struct Navigation {
+    /// Devices which have reported motion on a 6-DoF axis.
+    six_dof_devices: HashSet<DeviceId>,
+    /// 3D mouse motion accumulated since the last update, per axis: x, y and z
+    /// translation, then x, y and z rotation.
+    motion: [f32; 6],
+    /// The last known positions of the touches on the screen, by ID.
+    touches: HashMap<u64, Vec2>,
+    /// `None` if gamepad support failed to initialize.
+    #[cfg(feature = "gamepad")]
+    gilrs: Option<gilrs::Gilrs>,
+}
+
+impl Default for Navigation {
+    fn default() -> Self {
+        Self {
+            six_dof_devices: HashSet::new(),
+            motion: [0.; 6],
+            touches: HashMap::new(),
+            #[cfg(feature = "gamepad")]
+            gilrs: gilrs::Gilrs::new().ok(),
+        }
+    }
+}
+
+impl Navigation {
+    /// Records 3D mouse motion, returning true if the event came from a 3D
+    /// mouse.
+    pub fn handle_device_event(&mut self, device_id: DeviceId, event: &DeviceEvent) -> bool {
+        let DeviceEvent::Motion { axis, value } = *event else {
+            return false;
+        };
+
+        if axis >= SIX_DOF_MIN_AXIS {
+            self.six_dof_devices.insert(device_id);
+        }
+
+        if self.six_dof_devices.contains(&device_id)
+            && let Some(motion) = self.motion.get_mut(axis as usize)
+        {
+            *motion += value as f32;
+            return true;
+        }
+
+        false
+    }
+
+    /// Moves the camera for a touch event. `viewport_height` is in physical
+    /// pixels, like the touch's location.
+    pub fn handle_touch(&mut self, touch: &Touch, camera: &mut Camera, viewport_height: f32) {
+        let location = Vec2::new(touch.location.x as f32, touch.location.y as f32);
+
+        match touch.phase {
+            TouchPhase::Started => {
+                self.touches.insert(touch.id, location);
+                return;
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                self.touches.remove(&touch.id);
+                return;
+            }
+            TouchPhase::Moved => (),
+        }
+
+        // Touches that started over the UI aren't tracked.
+        if !self.touches.contains_key(&touch.id) {
+            return;
+        }
+
+        let (previous_centroid, previous_spread) = touch_gesture(&self.touches);
+        self.touches.insert(touch.id, location);
+        let (centroid, spread) = touch_gesture(&self.touches);
+
+        let delta = centroid - previous_centroid;
+
+        if self.touches.len() == 1 {
+            camera.rotation -= delta.x * TOUCH_ORBIT_SPEED;
+        } else {
+            // Move the target opposite to the fingers, so the loft follows
+            // them.
+            camera.pan(Vec2::new(-delta.x, delta.y) / viewport_height.max(1.));
+
+            if spread > 0. {
+                camera.zoom(previous_spread / spread);
+            }
+        }
+    }
+
+    /// Zooms the camera for a trackpad pinch gesture, where a positive delta
+    /// zooms in.
+    pub fn handle_pinch(&mut self, delta: f64, camera: &mut Camera) {
+        camera.zoom((-delta as f32).exp());
+    }
+
+    /// Processes pending gamepad events, which updates the gamepads' state.
+    /// Returns true if there were any.
+    pub fn poll_gamepads(&mut self) -> bool {
+        #[cfg(feature = "gamepad")]
+        if let Some(gilrs) = &mut self.gilrs {
+            let mut any_events = false;
+            while gilrs.next_event().is_some() {
+                any_events = true;
+            }
+
+            return any_events;
+        }
+
+        false
+    }
+
+    /// Returns true if gamepads are connected, so `poll_gamepads` should be
+    /// called regularly even when nothing else is happening.
+    pub fn wants_polling(&self) -> bool {
+        #[cfg(feature = "gamepad")]
+        if let Some(gilrs) = &self.gilrs {
+            return gilrs.gamepads().next().is_some();
+        }
+
+        false
+    }
+
+    /// Moves the camera by the input received since the last update. Returns
+    /// true if the camera moved.
+    pub fn update(&mut self, camera: &mut Camera, delta_time: f32) -> bool {
+        let motion = std::mem::take(&mut self.motion);
+        let moved = motion != [0.; 6];
+
+        let [tx, ty, tz, _, _, rz] = motion.map(|m| m * SPACE_MOUSE_SCALE);
+
+        // Device y and z point towards the user and down, respectively.
+        camera.rotation += rz;
+        camera.zoom((-tz).exp());
+        camera.pan(Vec2::new(tx, -ty));
+
+        #[cfg(feature = "gamepad")]
+        let moved = self.update_gamepads(camera, delta_time) || moved;
+        #[cfg(not(feature = "gamepad"))]
+        let _ = delta_time;
+
+        moved
+    }
+
+    /// Returns true if any gamepad moved the camera.
+    #[cfg(feature = "gamepad")]
+    fn update_gamepads(&self, camera: &mut Camera, delta_time: f32) -> bool {
+        use gilrs::{Axis, Button};
+
+        let Some(gilrs) = &self.gilrs else {
+            return false;
+        };
+
+        let mut moved = false;
+
+        let deadzone = |value: f32| {
+            if value.abs() < GAMEPAD_DEADZONE {
+                0.
+            } else {
+                value
+            }
+        };
+
+        for (_, gamepad) in gilrs.gamepads() {
+            let trigger = |button| gamepad.button_data(button).map_or(0., |data| data.value());
+
+            let orbit = deadzone(gamepad.value(Axis::LeftStickX));
+            let pan = Vec2::new(
+                deadzone(gamepad.value(Axis::RightStickX)),
+                deadzone(gamepad.value(Axis::RightStickY)),
+            );
+            let zoom = trigger(Button::LeftTrigger2) - trigger(Button::RightTrigger2);
+
+            camera.rotation += orbit * GAMEPAD_ORBIT_SPEED * delta_time;
+            camera.pan(pan * GAMEPAD_PAN_SPEED * delta_time);
+            camera.zoom((zoom * GAMEPAD_ZOOM_SPEED * delta_time).exp());
+
+            moved |= orbit != 0. || pan != Vec2::ZERO || zoom != 0.;
+        }
+
+        moved
+    }
+}
+
+/// Returns the centroid of the touches, and their mean distance from it.
+fn touch_gesture(touches: &HashMap<u64, Vec2>) -> (Vec2, f32) {
+    let count = touches.len().max(1) as f32;
+
+    let centroid = touches.values().sum::<Vec2>() / count;
+    let spread = touches
+        .values()
+        .map(|location| location.distance(centroid))
+        .sum::<f32>()
+        / count;
+
+    (centroid, spread)
+}
@@ -0,0 +1,25 @@
+struct PickOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) @interpolate(flat) id: u32,
+};
+
+@group(0)
+@binding(0)
+var<uniform> proj_view: mat4x4<f32>;
+
+// IDs are offset by one, so zero means nothing was drawn.
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+    @location(0) position: vec3<f32>,
+) -> PickOutput {
+    var result: PickOutput;
+    result.position = proj_view * vec4<f32>(position, 1.0);
+    result.id = vertex_index / 3u + 1u;
+    return result;
+}
+
+@fragment
+fn fs_main(vertex: PickOutput) -> @location(0) u32 {
+    return vertex.id;
+}
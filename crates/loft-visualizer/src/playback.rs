@@ -0,0 +1,167 @@
+use glam::Vec3;
+use lofter::{LoftStep, Lofter};
+
+const ACCEPTED_COLOR: Vec3 = Vec3::new(0.3, 0.9, 0.3);
+const REJECTED_COLOR: Vec3 = Vec3::new(0.9, 0.2, 0.2);
+const BUILT_COLOR: Vec3 = Vec3::new(0.6, 0.6, 0.6);
+
+/// Replays the matcher's decisions, recorded with `Lofter::explain_loft`, one
+/// step at a time. Accepted edge candidates stay drawn, in green, while a
+/// rejected candidate is only drawn, in red, on its own step. Built sections
+/// replace the candidates of their sketch pair with their ruling edges.
+pub struct ConstructionPlayback {
+    /// Empty unless a trace has been recorded.
+    pub steps: Vec<LoftStep>,
+    pub playing: bool,
+    /// In steps per second.
+    pub speed: f32,
+    /// The number of steps shown, which is fractional while playing.
+    pub position: f32,
+    /// Set by the UI to record a new trace and start playing it.
+    pub record_requested: bool,
+    /// The lofter revision the trace was recorded at. The trace is discarded
+    /// once the lofter changes, since its vertex IDs are no longer valid.
+    revision: Option<u64>,
+}
+
+impl Default for ConstructionPlayback {
+    fn default() -> Self {
+        Self {
+            steps: Vec::new(),
+            playing: false,
+            speed: 20.,
+            position: 0.,
+            record_requested: false,
+            revision: None,
+        }
+    }
+}
+
+impl ConstructionPlayback {
+    /// Starts playing a trace from its first step.
+    pub fn start(&mut self, steps: Vec<LoftStep>, lofter: &Lofter) {
+        self.steps = steps;
+        self.position = 0.;
+        self.playing = true;
+        self.revision = Some(lofter.revision());
+    }
+
+    /// Returns true while a trace is shown in place of the loft.
+    pub fn is_active(&self) -> bool {
+        !self.steps.is_empty()
+    }
+
+    pub fn stop(&mut self) {
+        self.steps.clear();
+        self.playing = false;
+        self.revision = None;
+    }
+
+    /// Advances playback. Stops if the lofter has changed since the trace was
+    /// recorded.
+    pub fn update(&mut self, lofter: &Lofter, delta_time: f32) {
+        if self.is_active() && self.revision != Some(lofter.revision()) {
+            self.stop();
+        }
+
+        if !self.playing {
+            return;
+        }
+
+        self.position = (self.position + self.speed * delta_time).min(self.steps.len() as f32);
+        if self.shown_step_count() == self.steps.len() {
+            self.playing = false;
+        }
+    }
+
+    pub fn shown_step_count(&self) -> usize {
+        self.position as usize
+    }
+
+    /// Returns line segments showing the loft's construction up to the current
+    /// step.
+    pub fn lines(&self, lofter: &Lofter) -> Vec<[[Vec3; 2]; 2]> {
+        let shown_steps = &self.steps[..self.shown_step_count()];
+
+        let edge = |sketch_pair: usize, (lower, upper), color| {
+            let lower = lofter.vertex_world_position(sketch_pair, lower)?;
+            let upper = lofter.vertex_world_position(sketch_pair + 1, upper)?;
+
+            Some([[lower, color], [upper, color]])
+        };
+
+        // Sketch pairs with a built section have finished matching.
+        let built = |sketch_pair: usize| {
+            shown_steps.iter().any(|step| match step {
+                LoftStep::SectionBuilt {
+                    sketch_pair: built, ..
+                } => *built == sketch_pair,
+                _ => false,
+            })
+        };
+
+        let mut lines = Vec::new();
+
+        for (i, step) in shown_steps.iter().enumerate() {
+            let is_current = i + 1 == shown_steps.len();
+
+            match step {
+                LoftStep::CandidateAccepted {
+                    sketch_pair,
+                    vertices,
+                    ..
+                } if !built(*sketch_pair) => {
+                    lines.extend(edge(*sketch_pair, *vertices, ACCEPTED_COLOR));
+                }
+                LoftStep::CandidateRejected {
+                    sketch_pair,
+                    vertices,
+                    ..
+                } if is_current => {
+                    lines.extend(edge(*sketch_pair, *vertices, REJECTED_COLOR));
+                }
+                LoftStep::SectionBuilt { ruling_edges, .. } => {
+                    lines.extend(
+                        ruling_edges
+                            .iter()
+                            .map(|edge| edge.map(|position| [position, BUILT_COLOR])),
+                    );
+                }
+                _ => (),
+            }
+        }
+
+        lines
+    }
+
+    /// Describes the current step, for the UI.
+    pub fn current_step_description(&self) -> Option<String> {
+        let step = self.steps[..self.shown_step_count()].last()?;
+
+        Some(match step {
+            LoftStep::CandidateAccepted {
+                vertices,
+                radial_error,
+                ..
+            } => format!(
+                "Accepted {} - {} ({:.1} deg)",
+                vertices.0,
+                vertices.1,
+                radial_error.to_degrees()
+            ),
+            LoftStep::CandidateRejected {
+                vertices,
+                radial_error,
+                ..
+            } => format!(
+                "Rejected {} - {} ({:.1} deg), spans two sections",
+                vertices.0,
+                vertices.1,
+                radial_error.to_degrees()
+            ),
+            LoftStep::SectionBuilt { ruling_edges, .. } => {
+                format!("Built section with {} edges", ruling_edges.len())
+            }
+        })
+    }
+}
@@ -1,23 +1,80 @@
-use std::sync::Arc;
+use std::{iter::zip, sync::Arc};
 
-use glam::{Mat4, Vec3};
+use glam::{Mat4, Vec3, Vec4};
+use lofter::LocalLoftVertex;
 use pollster::block_on;
-use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::util::DeviceExt;
 use winit::window::Window;
 
+use crate::{
+    camera::Camera,
+    drawable::{Drawable, DrawableId, VERTEX_LAYOUT, VERTEX_SIZE},
+    loft_compute::LoftCompute,
+    picking::IdBuffer,
+};
+
+/// Matches `Uniforms` in the shader.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Uniforms {
+    proj_view: [f32; 16],
+    shading: u32,
+    _padding: [u32; 3],
+}
+
+/// How the loft's surface is shaded.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum Shading {
+    /// Each triangle's color, unlit.
+    #[default]
+    Unlit,
+    /// Lit from a sky above and the ground below, so concavities read.
+    Hemisphere,
+}
+
+/// A frame read back from the GPU, as 8-bit sRGB RGBA pixels.
+pub struct CapturedFrame {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
 pub struct Renderer {
     pub window: Arc<Window>,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
     pub surface_config: wgpu::SurfaceConfiguration,
+    pub adapter_info: wgpu::AdapterInfo,
+    /// The present modes supported by the surface.
+    pub present_modes: Vec<wgpu::PresentMode>,
     aspect_ratio: f32,
     depth_texture: wgpu::Texture,
     uniform_buffer: wgpu::Buffer,
     surface: wgpu::Surface<'static>,
-    pipeline: wgpu::RenderPipeline,
+    /// One pipeline for each `PipelineKind`, in order.
+    pipelines: Vec<wgpu::RenderPipeline>,
     bind_group: wgpu::BindGroup,
-    vertex_buffer: Option<wgpu::Buffer>,
-    vertex_count: u32,
+    loft_compute: LoftCompute,
+    id_buffer: IdBuffer,
+    drawables: Vec<Drawable>,
+    loft: DrawableId,
+    /// A translucent copy of the loft, drawn over the loft while it is being
+    /// edited.
+    ghost: DrawableId,
+    /// A marker highlighting a vertex, drawn over everything else.
+    highlight: DrawableId,
+    /// The outline of each sketch, drawn as lines over the loft.
+    pub sketch_outlines: DrawableId,
+    /// The loft's edges between adjacent sketches, drawn as lines over the
+    /// loft.
+    pub ruling_edges: DrawableId,
+    /// The ruling edges exceeding the station change limits, drawn even when
+    /// the other ruling edges aren't.
+    pub station_changes: DrawableId,
+    /// The loft's construction, as replayed from a trace of the matcher.
+    pub construction: DrawableId,
+    /// The contours of the loft's print layers, from a layer preview.
+    pub slice_layers: DrawableId,
 }
 
 impl Renderer {
@@ -37,7 +94,10 @@ impl Renderer {
 
         let (device, queue) = block_on(adapter.request_device(&Default::default())).unwrap();
 
-        let surface_config = surface_configuration(size.width, size.height);
+        let adapter_info = adapter.get_info();
+        let capabilities = surface.get_capabilities(&adapter);
+
+        let surface_config = surface_configuration(&capabilities, size.width, size.height);
         surface.configure(&device, &surface_config);
 
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -48,7 +108,7 @@ impl Renderer {
                 ty: wgpu::BindingType::Buffer {
                     ty: wgpu::BufferBindingType::Uniform,
                     has_dynamic_offset: false,
-                    min_binding_size: wgpu::BufferSize::new(64),
+                    min_binding_size: wgpu::BufferSize::new(size_of::<Uniforms>() as u64),
                 },
                 count: None,
             }],
@@ -62,54 +122,26 @@ impl Renderer {
 
         let shader = device.create_shader_module(wgpu::include_wgsl!("shader.wgsl"));
 
-        let vertex_buffers = &[wgpu::VertexBufferLayout {
-            array_stride: 24,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &[
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x3,
-                    offset: 0,
-                    shader_location: 0,
-                },
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x3,
-                    offset: 12,
-                    shader_location: 1,
-                },
-            ],
-        }];
-
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: None,
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: None,
-                compilation_options: Default::default(),
-                buffers: vertex_buffers,
-            },
-            primitive: Default::default(),
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: wgpu::TextureFormat::Depth32Float,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
-            }),
-            multisample: Default::default(),
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: None,
-                compilation_options: Default::default(),
-                targets: &[Some(wgpu::TextureFormat::Bgra8UnormSrgb.into())],
-            }),
-            multiview: None,
-            cache: None,
-        });
+        let pipelines = PipelineKind::ALL
+            .into_iter()
+            .map(|kind| {
+                create_pipeline(
+                    &device,
+                    &pipeline_layout,
+                    &shader,
+                    surface_config.format,
+                    kind,
+                )
+            })
+            .collect();
 
         let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: None,
-            contents: bytemuck::cast_slice(Mat4::IDENTITY.as_ref()),
+            contents: bytemuck::bytes_of(&Uniforms {
+                proj_view: Mat4::IDENTITY.to_cols_array(),
+                shading: Shading::default() as u32,
+                _padding: [0; 3],
+            }),
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
@@ -124,37 +156,96 @@ impl Renderer {
 
         let depth_texture = create_depth_texture(&device, &surface_config);
 
-        Self {
+        let loft_compute = LoftCompute::new(&device);
+        let id_buffer = IdBuffer::new(&device, &pipeline_layout, size.width, size.height);
+
+        let mut renderer = Self {
             window,
             device,
             queue,
             surface_config,
+            adapter_info,
+            present_modes: capabilities.present_modes,
             surface,
             aspect_ratio,
             depth_texture,
             uniform_buffer,
-            vertex_buffer: None,
-            pipeline,
+            pipelines,
             bind_group,
-            vertex_count: 0,
-        }
+            loft_compute,
+            id_buffer,
+            drawables: Vec::new(),
+            loft: DrawableId(0),
+            ghost: DrawableId(0),
+            highlight: DrawableId(0),
+            sketch_outlines: DrawableId(0),
+            ruling_edges: DrawableId(0),
+            station_changes: DrawableId(0),
+            construction: DrawableId(0),
+            slice_layers: DrawableId(0),
+        };
+
+        renderer.loft = renderer.add_drawable(PipelineKind::Solid);
+        renderer.ghost = renderer.add_drawable(PipelineKind::Ghost);
+        renderer.highlight = renderer.add_drawable(PipelineKind::Overlay);
+        renderer.sketch_outlines = renderer.add_drawable(PipelineKind::Lines);
+        renderer.ruling_edges = renderer.add_drawable(PipelineKind::Lines);
+        renderer.station_changes = renderer.add_drawable(PipelineKind::Lines);
+        renderer.construction = renderer.add_drawable(PipelineKind::Lines);
+        renderer.slice_layers = renderer.add_drawable(PipelineKind::Lines);
+
+        renderer
+    }
+
+    /// Adds an empty drawable, which is drawn with the given pipeline once its
+    /// vertices are set.
+    pub fn add_drawable(&mut self, pipeline: PipelineKind) -> DrawableId {
+        self.drawables.push(Drawable::new(pipeline));
+
+        DrawableId(self.drawables.len() - 1)
+    }
+
+    /// Sets a drawable's vertices, which are interleaved positions and colors,
+    /// e.g. `[[Vec3; 2]; 3]` triangles, or `[[Vec3; 2]; 2]` line segments.
+    pub fn set_drawable_vertices<T: bytemuck::Pod>(&mut self, id: DrawableId, vertices: &[T]) {
+        self.drawables[id.0].vertex_buffer.write(
+            &self.device,
+            &self.queue,
+            bytemuck::cast_slice(vertices),
+        );
+    }
+
+    /// Shows or hides a drawable, keeping its vertices.
+    pub fn set_drawable_visible(&mut self, id: DrawableId, visible: bool) {
+        self.drawables[id.0].visible = visible;
+    }
+
+    /// Stops drawing a drawable until its vertices are set again.
+    pub fn clear_drawable(&mut self, id: DrawableId) {
+        self.drawables[id.0].vertex_buffer.clear();
     }
 
     pub fn resize(&mut self, width: u32, height: u32) {
-        self.surface_config = surface_configuration(width, height);
+        self.surface_config.width = width;
+        self.surface_config.height = height;
         self.surface.configure(&self.device, &self.surface_config);
         self.aspect_ratio = width as f32 / height as f32;
         self.depth_texture = create_depth_texture(&self.device, &self.surface_config);
+        self.id_buffer.resize(&self.device, width, height);
     }
 
-    pub fn set_camera_rotation(&self, rotation: f32) {
-        let eye = Vec3::new(5., 0., 4.).rotate_z(rotation);
-        let center = Vec3::new(0., 0., 1.5);
-        let up = Vec3::Z;
+    /// Reconfigures the surface with a new present mode, if it's supported.
+    pub fn set_present_mode(&mut self, present_mode: wgpu::PresentMode) {
+        if !self.present_modes.contains(&present_mode) {
+            return;
+        }
+
+        self.surface_config.present_mode = present_mode;
+        self.surface.configure(&self.device, &self.surface_config);
+    }
 
-        let view = Mat4::look_at_rh(eye, center, up);
-        let proj = Mat4::perspective_rh(std::f32::consts::FRAC_PI_4, self.aspect_ratio, 1.0, 20.0);
-        let proj_view = proj * view;
+    pub fn set_camera(&self, camera: &Camera) {
+        let proj_view = camera.proj_view(self.aspect_ratio);
 
         self.queue.write_buffer(
             &self.uniform_buffer,
@@ -163,15 +254,96 @@ impl Renderer {
         );
     }
 
+    pub fn set_shading(&self, shading: Shading) {
+        self.queue.write_buffer(
+            &self.uniform_buffer,
+            std::mem::offset_of!(Uniforms, shading) as u64,
+            bytemuck::bytes_of(&(shading as u32)),
+        );
+    }
+
     pub fn set_loft_vertex_buffer(&mut self, vertex_buffer: &[[[Vec3; 2]; 3]]) {
-        let buffer = self.device.create_buffer_init(&BufferInitDescriptor {
-            label: None,
-            contents: bytemuck::cast_slice(vertex_buffer),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
+        self.set_drawable_vertices(self.loft, vertex_buffer);
+    }
+
+    /// Uploads the loft in sketch-local space, to be evaluated on the GPU by
+    /// `evaluate_loft`. This replaces the loft vertex buffer.
+    pub fn set_loft_local_vertex_buffer(
+        &mut self,
+        local_vertex_buffer: &[[LocalLoftVertex; 3]],
+        sketch_count: usize,
+    ) {
+        let buffer =
+            self.loft_compute
+                .set_local_vertices(&self.device, local_vertex_buffer, sketch_count);
+
+        let vertex_buffer = &mut self.drawables[self.loft.0].vertex_buffer;
+
+        match buffer {
+            Some((buffer, vertex_count)) => vertex_buffer.replace(buffer, vertex_count),
+            None => vertex_buffer.clear(),
+        }
+    }
 
-        self.vertex_buffer = Some(buffer);
-        self.vertex_count = vertex_buffer.len() as u32 * 3;
+    /// Evaluates the loft uploaded with `set_loft_local_vertex_buffer` on the
+    /// GPU, for new sketch transforms. Each transform is a sketch's world
+    /// position, with its rotation about the z axis, in radians, as w.
+    pub fn evaluate_loft(&self, sketch_transforms: &[Vec4]) {
+        self.loft_compute
+            .evaluate(&self.device, &self.queue, sketch_transforms);
+    }
+
+    pub fn set_loft_visible(&mut self, visible: bool) {
+        self.set_drawable_visible(self.loft, visible);
+    }
+
+    /// Returns the index of the loft triangle drawn at a pixel, if any, in the
+    /// order of the loft vertex buffer.
+    pub fn pick_loft_triangle(&self, x: u32, y: u32) -> Option<u32> {
+        self.id_buffer.pick(
+            &self.device,
+            &self.queue,
+            &self.bind_group,
+            &self.drawables[self.loft.0].vertex_buffer,
+            x,
+            y,
+        )
+    }
+
+    /// Sets (or clears) the ghost loft, which is drawn translucently over the
+    /// loft.
+    pub fn set_ghost_vertex_buffer(&mut self, vertex_buffer: Option<&[[[Vec3; 2]; 3]]>) {
+        match vertex_buffer {
+            Some(vertex_buffer) => self.set_drawable_vertices(self.ghost, vertex_buffer),
+            None => self.clear_drawable(self.ghost),
+        }
+    }
+
+    /// Sets (or clears) the highlighted position, which is marked with a small
+    /// octahedron of the given radius.
+    pub fn set_highlight(&mut self, position: Option<Vec3>, radius: f32) {
+        let Some(position) = position else {
+            self.clear_drawable(self.highlight);
+            return;
+        };
+
+        let color = Vec3::new(1., 1., 0.);
+        let axes = [Vec3::X, Vec3::Y, Vec3::Z].map(|axis| axis * radius);
+
+        let mut triangles = Vec::with_capacity(8);
+        for x in [-1., 1.] {
+            for y in [-1., 1.] {
+                for z in [-1., 1.] {
+                    triangles.push([
+                        [position + axes[0] * x, color],
+                        [position + axes[1] * y, color],
+                        [position + axes[2] * z, color],
+                    ]);
+                }
+            }
+        }
+
+        self.set_drawable_vertices(self.highlight, &triangles);
     }
 
     pub fn frame_surface_texture(&self) -> Option<wgpu::SurfaceTexture> {
@@ -185,6 +357,111 @@ impl Renderer {
 
         let depth_texture_view = self.depth_texture.create_view(&Default::default());
 
+        self.encode_draw(&mut encoder, view, &depth_texture_view);
+
+        self.queue.submit(Some(encoder.finish()));
+    }
+
+    /// Renders a frame offscreen, at the size and format of the window surface,
+    /// and reads it back from the GPU. The surface format must have 8-bit
+    /// channels.
+    pub fn capture_frame(&self) -> CapturedFrame {
+        let format = self.surface_config.format;
+        let width = self.surface_config.width.max(1);
+        let height = self.surface_config.height.max(1);
+
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&Default::default());
+        let depth_texture_view = self.depth_texture.create_view(&Default::default());
+
+        // Rows must be padded to the copy alignment.
+        let unpadded_bytes_per_row = width * 4;
+        let bytes_per_row = unpadded_bytes_per_row.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        let readback_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: bytes_per_row as u64 * height as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder: wgpu::CommandEncoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        self.encode_draw(&mut encoder, &view, &depth_texture_view);
+
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &readback_buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            texture.size(),
+        );
+
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = readback_buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |result| result.unwrap());
+        self.device.poll(wgpu::PollType::Wait).unwrap();
+
+        let is_bgra = matches!(
+            format,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        );
+
+        let mut rgba = Vec::with_capacity((unpadded_bytes_per_row * height) as usize);
+
+        for row in slice
+            .get_mapped_range()
+            .chunks_exact(bytes_per_row as usize)
+        {
+            let row = &row[..unpadded_bytes_per_row as usize];
+
+            if is_bgra {
+                for pixel in row.chunks_exact(4) {
+                    rgba.extend_from_slice(&[pixel[2], pixel[1], pixel[0], pixel[3]]);
+                }
+            } else {
+                rgba.extend_from_slice(row);
+            }
+        }
+
+        readback_buffer.unmap();
+
+        CapturedFrame {
+            width,
+            height,
+            rgba,
+        }
+    }
+
+    fn encode_draw(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        depth_texture_view: &wgpu::TextureView,
+    ) {
         let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: None,
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
@@ -196,7 +473,7 @@ impl Renderer {
                 },
             })],
             depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                view: &depth_texture_view,
+                view: depth_texture_view,
                 depth_ops: Some(wgpu::Operations {
                     load: wgpu::LoadOp::Clear(1.0),
                     store: wgpu::StoreOp::Store,
@@ -207,29 +484,156 @@ impl Renderer {
             occlusion_query_set: None,
         });
 
-        if let Some(vertex_buffer) = &self.vertex_buffer {
-            rpass.set_pipeline(&self.pipeline);
-            rpass.set_bind_group(0, &self.bind_group, &[]);
-            rpass.set_vertex_buffer(0, vertex_buffer.slice(..));
-            rpass.draw(0..self.vertex_count, 0..1);
-        }
+        rpass.set_bind_group(0, &self.bind_group, &[]);
 
-        drop(rpass);
+        // Drawables are drawn grouped by pipeline, in the order of
+        // `PipelineKind`, so blended drawables are drawn over opaque ones.
+        for (kind, pipeline) in zip(PipelineKind::ALL, &self.pipelines) {
+            rpass.set_pipeline(pipeline);
 
-        self.queue.submit(Some(encoder.finish()));
+            for drawable in &self.drawables {
+                if drawable.pipeline == kind && drawable.visible {
+                    drawable.vertex_buffer.draw(&mut rpass);
+                }
+            }
+        }
     }
 }
 
-fn surface_configuration(width: u32, height: u32) -> wgpu::SurfaceConfiguration {
+#[derive(Clone, Copy, PartialEq)]
+pub enum PipelineKind {
+    /// Opaque, depth-writing triangles, shaded according to `Shading`. These
+    /// are pushed back slightly in depth, so lines on their surface aren't
+    /// hidden by them.
+    Solid,
+    /// Opaque, depth-tested line segments.
+    Lines,
+    /// Alpha-blended triangles which are depth tested, but don't write depth.
+    Ghost,
+    /// Opaque triangles drawn over everything, ignoring depth.
+    Overlay,
+}
+
+impl PipelineKind {
+    /// All pipeline kinds, in draw order.
+    const ALL: [Self; 4] = [Self::Solid, Self::Lines, Self::Ghost, Self::Overlay];
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+    kind: PipelineKind,
+) -> wgpu::RenderPipeline {
+    let vertex_buffers = &[wgpu::VertexBufferLayout {
+        array_stride: VERTEX_SIZE,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &[
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x3,
+                offset: VERTEX_LAYOUT.position_offset() as u64,
+                shader_location: 0,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x3,
+                offset: VERTEX_LAYOUT.color_offset().unwrap() as u64,
+                shader_location: 1,
+            },
+        ],
+    }];
+
+    let (fragment_entry_point, blend) = match kind {
+        PipelineKind::Solid => ("fs_shaded", None),
+        PipelineKind::Lines => ("fs_main", None),
+        PipelineKind::Ghost => ("fs_ghost", Some(wgpu::BlendState::ALPHA_BLENDING)),
+        PipelineKind::Overlay => ("fs_main", None),
+    };
+
+    let depth_compare = match kind {
+        PipelineKind::Solid | PipelineKind::Ghost => wgpu::CompareFunction::Less,
+        PipelineKind::Lines => wgpu::CompareFunction::LessEqual,
+        PipelineKind::Overlay => wgpu::CompareFunction::Always,
+    };
+
+    let topology = match kind {
+        PipelineKind::Lines => wgpu::PrimitiveTopology::LineList,
+        _ => wgpu::PrimitiveTopology::TriangleList,
+    };
+
+    // Depth bias isn't applied to lines on every backend, so the surface is
+    // offset away from the camera instead (like `glPolygonOffset`), which
+    // keeps lines lying on it from z-fighting with it.
+    let bias = match kind {
+        PipelineKind::Solid => wgpu::DepthBiasState {
+            constant: 2,
+            slope_scale: 1.,
+            clamp: 0.,
+        },
+        _ => wgpu::DepthBiasState::default(),
+    };
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: None,
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: None,
+            compilation_options: Default::default(),
+            buffers: vertex_buffers,
+        },
+        primitive: wgpu::PrimitiveState {
+            topology,
+            ..Default::default()
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: wgpu::TextureFormat::Depth32Float,
+            depth_write_enabled: matches!(kind, PipelineKind::Solid | PipelineKind::Lines),
+            depth_compare,
+            stencil: wgpu::StencilState::default(),
+            bias,
+        }),
+        multisample: Default::default(),
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some(fragment_entry_point),
+            compilation_options: Default::default(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        multiview: None,
+        cache: None,
+    })
+}
+
+/// Returns a surface configuration supported by the surface, preferring 8-bit
+/// sRGB formats and vsync.
+fn surface_configuration(
+    capabilities: &wgpu::SurfaceCapabilities,
+    width: u32,
+    height: u32,
+) -> wgpu::SurfaceConfiguration {
+    let format = [
+        wgpu::TextureFormat::Bgra8UnormSrgb,
+        wgpu::TextureFormat::Rgba8UnormSrgb,
+    ]
+    .into_iter()
+    .find(|format| capabilities.formats.contains(format))
+    .unwrap_or(capabilities.formats[0]);
+
     wgpu::SurfaceConfiguration {
         usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-        format: wgpu::TextureFormat::Bgra8UnormSrgb,
+        format,
         width,
         height,
+        // Fifo is supported everywhere.
         present_mode: wgpu::PresentMode::Fifo,
         desired_maximum_frame_latency: 2,
-        alpha_mode: wgpu::CompositeAlphaMode::Auto,
-        view_formats: vec![wgpu::TextureFormat::Bgra8Unorm],
+        alpha_mode: capabilities.alpha_modes[0],
+        view_formats: vec![format.remove_srgb_suffix()],
     }
 }
 
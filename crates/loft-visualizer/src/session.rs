@@ -0,0 +1,128 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter},
+    time::Instant,
+};
+
+use lofter::{Lofter, Session};
+
+/// Records edit sessions to a JSON file, and replays them, either in real time
+/// or all at once.
+pub struct SessionControls {
+    /// The file sessions are saved to and loaded from.
+    pub path: String,
+    /// Set by the UI.
+    pub request: Option<SessionRequest>,
+    /// A message describing the result of the last request.
+    pub status: Option<String>,
+    replay: Option<Replay>,
+}
+
+#[derive(Clone, Copy)]
+pub enum SessionRequest {
+    StartRecording,
+    /// Stops recording, and saves the session.
+    StopRecording,
+    /// Loads the session, and replays it at the speed it was recorded.
+    Replay,
+    /// Loads the session, and replays it all at once.
+    ReplayInstantly,
+}
+
+struct Replay {
+    session: Session,
+    started: Instant,
+    next_command: usize,
+}
+
+impl Default for SessionControls {
+    fn default() -> Self {
+        Self {
+            path: "session.json".into(),
+            request: None,
+            status: None,
+            replay: None,
+        }
+    }
+}
+
+impl SessionControls {
+    pub fn is_replaying(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    /// Handles a request from the UI, if there is one. Replaying replaces the
+    /// lofter with the session's initial lofter.
+    pub fn handle_request(&mut self, lofter: &mut Lofter) {
+        let Some(request) = self.request.take() else {
+            return;
+        };
+
+        let result = match request {
+            SessionRequest::StartRecording => {
+                lofter.start_recording();
+                Ok("Recording".into())
+            }
+            SessionRequest::StopRecording => match lofter.stop_recording() {
+                Some(session) => self.save(&session).map(|()| {
+                    format!("Saved {} commands to {}", session.commands.len(), self.path)
+                }),
+                None => Ok("Not recording".into()),
+            },
+            SessionRequest::Replay => self.load().map(|session| {
+                *lofter = session.initial_lofter();
+                self.replay = Some(Replay {
+                    session,
+                    started: Instant::now(),
+                    next_command: 0,
+                });
+
+                "Replaying".into()
+            }),
+            SessionRequest::ReplayInstantly => self.load().map(|session| {
+                *lofter = session.replay();
+                self.replay = None;
+
+                format!("Replayed {} commands", session.commands.len())
+            }),
+        };
+
+        self.status = Some(result.unwrap_or_else(|err| format!("Session failed: {err}")));
+    }
+
+    /// Applies the replayed commands which are due.
+    pub fn update(&mut self, lofter: &mut Lofter) {
+        let Some(replay) = &mut self.replay else {
+            return;
+        };
+
+        let elapsed = replay.started.elapsed();
+        let commands = &replay.session.commands[replay.next_command..];
+        let due = commands.partition_point(|command| command.time <= elapsed);
+
+        for timed_command in &commands[..due] {
+            // Commands which failed when recorded fail again, without modifying
+            // the lofter.
+            let _ = lofter.apply(&timed_command.command);
+        }
+        replay.next_command += due;
+
+        if replay.next_command == replay.session.commands.len() {
+            self.replay = None;
+            self.status = Some("Replay finished".into());
+        }
+    }
+
+    fn save(&self, session: &Session) -> io::Result<()> {
+        let writer = BufWriter::new(File::create(&self.path)?);
+        serde_json::to_writer(writer, session)?;
+
+        Ok(())
+    }
+
+    fn load(&self) -> io::Result<Session> {
+        let reader = BufReader::new(File::open(&self.path)?);
+
+        Ok(serde_json::from_reader(reader)?)
+    }
+}
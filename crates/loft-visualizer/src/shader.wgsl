@@ -1,11 +1,23 @@
 struct VertexOutput {
     @builtin(position) position: vec4<f32>,
     @location(0) color: vec3<f32>,
+    @location(1) world_position: vec3<f32>,
+};
+
+struct Uniforms {
+    proj_view: mat4x4<f32>,
+    // Matches `Shading` in the renderer.
+    shading: u32,
 };
 
 @group(0)
 @binding(0)
-var<uniform> proj_view: mat4x4<f32>;
+var<uniform> uniforms: Uniforms;
+
+const SHADING_HEMISPHERE: u32 = 1u;
+
+const SKY_COLOR: vec3<f32> = vec3<f32>(1.0, 0.98, 0.92);
+const GROUND_COLOR: vec3<f32> = vec3<f32>(0.18, 0.2, 0.26);
 
 @vertex
 fn vs_main(
@@ -13,8 +25,9 @@ fn vs_main(
     @location(1) color: vec3<f32>,
 ) -> VertexOutput {
     var result: VertexOutput;
-    result.position = proj_view * vec4<f32>(position, 1.0);
+    result.position = uniforms.proj_view * vec4<f32>(position, 1.0);
     result.color = color;
+    result.world_position = position;
     return result;
 }
 
@@ -22,3 +35,28 @@ fn vs_main(
 fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
     return vec4<f32>(vertex.color, 1.0);
 }
+
+// Optionally lights the surface from a sky above and the ground below, so its
+// shape reads through the per-triangle colors.
+@fragment
+fn fs_shaded(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    if uniforms.shading != SHADING_HEMISPHERE {
+        return vec4<f32>(vertex.color, 1.0);
+    }
+
+    // The mesh has no normals, so the face normal is taken from the screen
+    // space derivatives of the position, facing the camera.
+    let normal = normalize(cross(dpdy(vertex.world_position), dpdx(vertex.world_position)));
+
+    let light = mix(GROUND_COLOR, SKY_COLOR, normal.z * 0.5 + 0.5);
+
+    // Mostly neutral, since the random colors would hide the shading.
+    let albedo = mix(vec3<f32>(0.8), vertex.color, 0.2);
+
+    return vec4<f32>(albedo * light, 1.0);
+}
+
+@fragment
+fn fs_ghost(vertex: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(vertex.color, 0.25);
+}
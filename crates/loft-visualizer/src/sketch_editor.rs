@@ -0,0 +1,689 @@
+use std::collections::HashSet;
+
+use glam::{Vec2, Vec3, Vec3Swizzles};
+use imgui::{DrawListMut, Key, MouseButton, Ui};
+use lofter::{Lofter, MirrorAxis, VertexId};
+
+/// Pixels per sketch unit.
+const VIEW_SCALE: f32 = 50.;
+
+/// Half-size, in pixels, of the square drawn for each vertex.
+const VERTEX_HANDLE_SIZE: f32 = 5.;
+
+/// How a drag of the selected vertices transforms them.
+#[derive(Clone, Copy, PartialEq)]
+pub enum TransformMode {
+    Translate,
+    Rotate,
+    Scale,
+    /// Slide vertices along their adjacent sketch edges.
+    Slide,
+}
+
+/// How strongly unselected vertices follow a proportional edit, by distance
+/// from the selection.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Falloff {
+    Smooth,
+    Linear,
+    Sharp,
+    Root,
+    Sphere,
+    Constant,
+}
+
+impl Falloff {
+    pub const ALL: [Falloff; 6] = [
+        Falloff::Smooth,
+        Falloff::Linear,
+        Falloff::Sharp,
+        Falloff::Root,
+        Falloff::Sphere,
+        Falloff::Constant,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Falloff::Smooth => "Smooth",
+            Falloff::Linear => "Linear",
+            Falloff::Sharp => "Sharp",
+            Falloff::Root => "Root",
+            Falloff::Sphere => "Sphere",
+            Falloff::Constant => "Constant",
+        }
+    }
+
+    /// Returns the weight of an edit at a distance from the selection, where
+    /// `distance` is normalized to the proportional editing radius.
+    fn weight(self, distance: f32) -> f32 {
+        let t = (1. - distance).clamp(0., 1.);
+
+        match self {
+            Falloff::Smooth => t * t * (3. - 2. * t),
+            Falloff::Linear => t,
+            Falloff::Sharp => t * t,
+            Falloff::Root => t.sqrt(),
+            Falloff::Sphere => (t * (2. - t)).sqrt(),
+            Falloff::Constant => 1.,
+        }
+    }
+}
+
+/// Settings shared by all sketch editors.
+pub struct EditSettings {
+    pub transform_mode: TransformMode,
+    /// When enabled, unselected vertices within `proportional_radius` of the
+    /// selection follow edits, weighted by `falloff`.
+    pub proportional_editing: bool,
+    /// In sketch units.
+    pub proportional_radius: f32,
+    pub falloff: Falloff,
+    /// When enabled, edits to a vertex are mirrored to its tagged mirror
+    /// partner.
+    pub mirror_editing: bool,
+    pub mirror_axis: MirrorAxis,
+    /// The fillet radius or chamfer distance of cut corners, in sketch units.
+    pub corner_size: f32,
+    pub fillet_segments: usize,
+}
+
+impl Default for EditSettings {
+    fn default() -> Self {
+        Self {
+            transform_mode: TransformMode::Translate,
+            proportional_editing: false,
+            proportional_radius: 0.5,
+            falloff: Falloff::Smooth,
+            mirror_editing: false,
+            mirror_axis: MirrorAxis::Y,
+            corner_size: 0.1,
+            fillet_segments: 4,
+        }
+    }
+}
+
+/// An interactive 2D editor for a single sketch.
+pub struct SketchEditor {
+    pub sketch_index: usize,
+    selection: HashSet<VertexId>,
+    interaction: Option<Interaction>,
+}
+
+enum Interaction {
+    /// Rubber-band selection, started at a screen position.
+    Select { start: Vec2 },
+    /// A drag transforming the selected vertices. The original vertex
+    /// positions are kept so that the transform is always applied relative to
+    /// where the drag started.
+    Transform {
+        start: Vec2,
+        pivot: Vec2,
+        /// The vertex that was clicked to start the drag.
+        grabbed: WeightedVertex,
+        original_positions: Vec<WeightedVertex>,
+        /// Text typed while dragging. When this parses to a valid value, it is
+        /// used for the transform instead of the mouse position.
+        numeric_input: String,
+    },
+}
+
+/// A vertex affected by a transform. Selected vertices have a weight of 1,
+/// while proportionally edited vertices have a weight in range [0, 1).
+#[derive(Clone, Copy)]
+struct WeightedVertex {
+    id: VertexId,
+    position: Vec3,
+    weight: f32,
+    /// The original positions of the previous and next vertices in the
+    /// sketch, used when sliding along sketch edges.
+    neighbors: (Vec2, Vec2),
+}
+
+impl SketchEditor {
+    pub fn new(sketch_index: usize) -> Self {
+        Self {
+            sketch_index,
+            selection: HashSet::new(),
+            interaction: None,
+        }
+    }
+
+    pub fn selection(&self) -> &HashSet<VertexId> {
+        &self.selection
+    }
+
+    /// Returns true while vertices are being dragged.
+    pub fn is_editing(&self) -> bool {
+        matches!(self.interaction, Some(Interaction::Transform { .. }))
+    }
+
+    /// Draws the editor into the current window, and handles mouse input.
+    pub fn draw(&mut self, ui: &Ui, lofter: &mut Lofter, settings: &EditSettings) {
+        let window_pos = Vec2::from_array(ui.window_pos());
+        let window_size = Vec2::from_array(ui.window_size());
+        let window_center = window_pos + window_size / 2.;
+
+        let to_screen = |pos: &Vec3| window_center + pos.xy() * VIEW_SCALE;
+        let to_sketch = |pos: Vec2| (pos - window_center) / VIEW_SCALE;
+
+        // Capture mouse input over the whole editor, so that dragging doesn't
+        // move the parent window.
+        ui.invisible_button("canvas", ui.content_region_avail());
+        let hovered = ui.is_item_hovered();
+
+        let mouse = Vec2::from_array(ui.io().mouse_pos);
+        let locked = lofter.sketch_locked(self.sketch_index) == Some(true);
+
+        let vertices: Vec<_> = lofter
+            .vertices(self.sketch_index)
+            .unwrap()
+            .map(|(id, pos)| (id, *pos))
+            .collect();
+
+        if hovered && ui.is_mouse_clicked(MouseButton::Left) {
+            let hit = vertices
+                .iter()
+                .map(|(id, pos)| (*id, to_screen(pos).distance(mouse)))
+                .filter(|(_, distance)| *distance <= VERTEX_HANDLE_SIZE * 1.5)
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(id, _)| id);
+
+            let shift = ui.io().key_shift;
+
+            match hit {
+                Some(id) => {
+                    if shift {
+                        if !self.selection.remove(&id) {
+                            self.selection.insert(id);
+                        }
+                    } else if !self.selection.contains(&id) {
+                        self.selection = HashSet::from([id]);
+                    }
+
+                    if !locked && self.selection.contains(&id) {
+                        self.interaction = Some(self.begin_transform(
+                            lofter,
+                            &vertices,
+                            id,
+                            to_sketch(mouse),
+                            settings,
+                        ));
+                    }
+                }
+                None => {
+                    if !shift {
+                        self.selection.clear();
+                    }
+
+                    self.interaction = Some(Interaction::Select { start: mouse });
+                }
+            }
+        }
+
+        match &mut self.interaction {
+            Some(Interaction::Transform {
+                start,
+                pivot,
+                grabbed,
+                original_positions,
+                numeric_input,
+            }) if ui.is_mouse_down(MouseButton::Left) => {
+                if ui.is_key_pressed(Key::Escape) {
+                    // Cancel the transform, restoring the original positions.
+                    let positions: Vec<_> = original_positions
+                        .iter()
+                        .map(|vertex| (vertex.id, vertex.position))
+                        .collect();
+
+                    lofter
+                        .set_vertex_positions(self.sketch_index, &positions)
+                        .unwrap();
+
+                    self.interaction = None;
+                } else {
+                    numeric_input.extend(
+                        ui.io()
+                            .input_queue_characters()
+                            .filter(|c| c.is_ascii_digit() || matches!(c, '.' | '-' | ',' | '=')),
+                    );
+
+                    if ui.is_key_pressed(Key::Backspace) {
+                        numeric_input.pop();
+                    }
+
+                    let transform =
+                        Transform::parse(numeric_input, settings.transform_mode, *pivot)
+                            .unwrap_or_else(|| {
+                                Transform::from_drag(
+                                    settings.transform_mode,
+                                    *pivot,
+                                    grabbed,
+                                    *start,
+                                    to_sketch(mouse),
+                                )
+                            });
+
+                    let mut positions = transform.apply(original_positions, *pivot);
+
+                    if settings.mirror_editing {
+                        positions = mirror_to_partners(
+                            lofter,
+                            self.sketch_index,
+                            positions,
+                            settings.mirror_axis,
+                        );
+                    }
+
+                    // The selection can't contain stale vertices, since
+                    // vertices aren't removed while dragging.
+                    lofter
+                        .set_vertex_positions(self.sketch_index, &positions)
+                        .unwrap();
+
+                    // Enter commits the typed transform without waiting for
+                    // the mouse to be released.
+                    if ui.is_key_pressed(Key::Enter) || ui.is_key_pressed(Key::KeypadEnter) {
+                        self.interaction = None;
+                    }
+                }
+            }
+            Some(Interaction::Select { start }) if ui.is_mouse_released(MouseButton::Left) => {
+                let min = start.min(mouse);
+                let max = start.max(mouse);
+
+                self.selection.extend(
+                    vertices
+                        .iter()
+                        .filter(|(_, pos)| {
+                            let pos = to_screen(pos);
+                            pos.cmpge(min).all() && pos.cmple(max).all()
+                        })
+                        .map(|(id, _)| *id),
+                );
+            }
+            _ => (),
+        }
+
+        if !ui.is_mouse_down(MouseButton::Left) {
+            self.interaction = None;
+        }
+
+        // Drawing.
+
+        let draw_list = ui.get_window_draw_list();
+
+        let points: Vec<_> = lofter
+            .vertices(self.sketch_index)
+            .unwrap()
+            .map(|(id, pos)| (id, to_screen(pos)))
+            .collect();
+
+        draw_list
+            .add_polyline(
+                points.iter().map(|(_, pos)| pos.to_array()).collect(),
+                [1., 0., 0.],
+            )
+            .filled(true)
+            .build();
+
+        for (id, point) in &points {
+            let excluded = lofter.vertex_excluded(self.sketch_index, *id) == Some(true);
+
+            let rect = draw_list.add_rect(
+                (*point - Vec2::splat(VERTEX_HANDLE_SIZE)).to_array(),
+                (*point + Vec2::splat(VERTEX_HANDLE_SIZE)).to_array(),
+                if self.selection.contains(id) {
+                    [1., 1., 0.]
+                } else if excluded {
+                    // Decorative vertices are dimmed.
+                    [0.5, 0.5, 0.5]
+                } else {
+                    [1., 1., 1.]
+                },
+            );
+
+            rect.filled(self.selection.contains(id)).build();
+        }
+
+        if let Some(Interaction::Select { start }) = &self.interaction {
+            draw_list
+                .add_rect(start.to_array(), mouse.to_array(), [0.4, 0.6, 1.])
+                .build();
+        }
+
+        if settings.mirror_editing {
+            let (start, end) = match settings.mirror_axis {
+                MirrorAxis::X => (
+                    Vec2::new(window_pos.x, window_center.y),
+                    Vec2::new(window_pos.x + window_size.x, window_center.y),
+                ),
+                MirrorAxis::Y => (
+                    Vec2::new(window_center.x, window_pos.y),
+                    Vec2::new(window_center.x, window_pos.y + window_size.y),
+                ),
+            };
+
+            draw_list
+                .add_line(start.to_array(), end.to_array(), [0.3, 0.8, 0.3])
+                .build();
+        }
+
+        if let Some(Interaction::Transform { numeric_input, .. }) = &self.interaction {
+            let hint = match settings.transform_mode {
+                TransformMode::Translate => "dx, dy (or =x, y)",
+                TransformMode::Rotate => "degrees",
+                TransformMode::Scale => "factor",
+                TransformMode::Slide => "factor, -1 to 1",
+            };
+
+            let text = if numeric_input.is_empty() {
+                format!("Type {hint}")
+            } else {
+                format!("{numeric_input}_")
+            };
+
+            draw_list.add_text((mouse + Vec2::new(12., 12.)).to_array(), [1., 1., 1.], text);
+        }
+
+        if let Some(Interaction::Transform { pivot, .. }) = &self.interaction
+            && settings.proportional_editing
+        {
+            draw_list
+                .add_circle(
+                    (window_center + *pivot * VIEW_SCALE).to_array(),
+                    settings.proportional_radius * VIEW_SCALE,
+                    [0.6, 0.6, 0.6],
+                )
+                .build();
+        }
+
+        if locked {
+            draw_lock_badge(&draw_list, window_pos + Vec2::splat(8.));
+        }
+    }
+
+    fn begin_transform(
+        &self,
+        lofter: &Lofter,
+        vertices: &[(VertexId, Vec3)],
+        grabbed: VertexId,
+        start: Vec2,
+        settings: &EditSettings,
+    ) -> Interaction {
+        let neighbors = |id| {
+            let index = vertices.iter().position(|(other, _)| *other == id).unwrap();
+            let count = vertices.len();
+
+            (
+                vertices[(index + count - 1) % count].1.xy(),
+                vertices[(index + 1) % count].1.xy(),
+            )
+        };
+
+        let selected: Vec<_> = vertices
+            .iter()
+            .filter(|(id, _)| self.selection.contains(id))
+            .map(|(_, pos)| pos.xy())
+            .collect();
+
+        // Rotate and scale about the centroid of the selection.
+        let pivot = selected.iter().sum::<Vec2>() / selected.len() as f32;
+
+        let mut original_positions: Vec<_> = vertices
+            .iter()
+            .filter_map(|&(id, position)| {
+                let weight = if self.selection.contains(&id) {
+                    1.
+                } else if settings.proportional_editing {
+                    // Weight by the distance to the nearest selected vertex.
+                    let distance = selected
+                        .iter()
+                        .map(|pos| pos.distance(position.xy()))
+                        .fold(f32::INFINITY, f32::min);
+
+                    if distance >= settings.proportional_radius {
+                        return None;
+                    }
+
+                    settings
+                        .falloff
+                        .weight(distance / settings.proportional_radius)
+                } else {
+                    return None;
+                };
+
+                Some(WeightedVertex {
+                    id,
+                    position,
+                    weight,
+                    neighbors: neighbors(id),
+                })
+            })
+            .collect();
+
+        if settings.mirror_editing {
+            // Mirror partners are moved by the transform too, so their original
+            // positions are needed to cancel it. Their weight is 0, since their
+            // positions are mirrored rather than transformed.
+            let partners: Vec<_> = original_positions
+                .iter()
+                .filter_map(|vertex| lofter.mirror_partner(self.sketch_index, vertex.id))
+                .filter(|partner| {
+                    !original_positions
+                        .iter()
+                        .any(|vertex| vertex.id == *partner)
+                })
+                .collect();
+
+            original_positions.extend(partners.into_iter().map(|id| WeightedVertex {
+                id,
+                position: *lofter.get_vertex(self.sketch_index, id).unwrap(),
+                weight: 0.,
+                neighbors: neighbors(id),
+            }));
+        }
+
+        // Sort by decreasing weight, so that when both a vertex and its mirror
+        // partner are edited, the more strongly edited vertex is mirrored.
+        original_positions.sort_by(|a, b| b.weight.total_cmp(&a.weight));
+
+        let grabbed = *original_positions
+            .iter()
+            .find(|vertex| vertex.id == grabbed)
+            .unwrap();
+
+        Interaction::Transform {
+            start,
+            pivot,
+            grabbed,
+            original_positions,
+            numeric_input: String::new(),
+        }
+    }
+}
+
+/// A transform of the selected vertices, either from dragging the mouse or
+/// typed in.
+#[derive(Clone, Copy)]
+enum Transform {
+    Translate(Vec2),
+    /// In radians.
+    Rotate(f32),
+    Scale(f32),
+    /// A factor in range [-1, 1]. Positive values slide vertices towards the
+    /// next vertex in the sketch, and negative values towards the previous
+    /// vertex.
+    Slide(f32),
+}
+
+impl Transform {
+    /// Returns the transform for a drag from `start` to `current` (in sketch
+    /// coordinates).
+    fn from_drag(
+        mode: TransformMode,
+        pivot: Vec2,
+        grabbed: &WeightedVertex,
+        start: Vec2,
+        current: Vec2,
+    ) -> Self {
+        match mode {
+            TransformMode::Translate => Transform::Translate(current - start),
+            TransformMode::Rotate => Transform::Rotate((start - pivot).angle_to(current - pivot)),
+            TransformMode::Scale => {
+                let start_distance = start.distance(pivot);
+                if start_distance <= f32::EPSILON {
+                    return Transform::Scale(1.);
+                }
+
+                Transform::Scale(current.distance(pivot) / start_distance)
+            }
+            TransformMode::Slide => {
+                // Project the drag onto both edges adjacent to the grabbed
+                // vertex, and slide along whichever edge the drag follows
+                // more closely.
+                let position = grabbed.position.xy();
+                let drag = current - start;
+
+                let project = |neighbor: Vec2| {
+                    let edge = neighbor - position;
+                    if edge.length_squared() <= f32::EPSILON {
+                        return (0., f32::INFINITY);
+                    }
+
+                    let factor = (drag.dot(edge) / edge.length_squared()).clamp(0., 1.);
+                    let error = (edge * factor).distance(drag);
+
+                    (factor, error)
+                };
+
+                let (previous_factor, previous_error) = project(grabbed.neighbors.0);
+                let (next_factor, next_error) = project(grabbed.neighbors.1);
+
+                if next_error <= previous_error {
+                    Transform::Slide(next_factor)
+                } else {
+                    Transform::Slide(-previous_factor)
+                }
+            }
+        }
+    }
+
+    /// Parses typed numeric input. Translations are typed as a delta `dx` or
+    /// `dx, dy`, or as an absolute coordinate for the pivot `=x, y`.
+    /// Rotations are typed in degrees, and scales as a factor.
+    fn parse(input: &str, mode: TransformMode, pivot: Vec2) -> Option<Self> {
+        match mode {
+            TransformMode::Translate => {
+                let (absolute, input) = match input.strip_prefix('=') {
+                    Some(input) => (true, input),
+                    None => (false, input),
+                };
+
+                let mut values = input.split(',').map(|value| value.trim().parse::<f32>());
+                let x = values.next()?.ok()?;
+                let y = match values.next() {
+                    Some(y) => y.ok()?,
+                    None if absolute => return None,
+                    None => 0.,
+                };
+
+                let value = Vec2::new(x, y);
+
+                Some(Transform::Translate(if absolute {
+                    value - pivot
+                } else {
+                    value
+                }))
+            }
+            TransformMode::Rotate => {
+                Some(Transform::Rotate(input.parse::<f32>().ok()?.to_radians()))
+            }
+            TransformMode::Scale => Some(Transform::Scale(input.parse().ok()?)),
+            TransformMode::Slide => {
+                Some(Transform::Slide(input.parse::<f32>().ok()?.clamp(-1., 1.)))
+            }
+        }
+    }
+
+    /// Returns the transformed positions of the vertices.
+    fn apply(self, original_positions: &[WeightedVertex], pivot: Vec2) -> Vec<(VertexId, Vec3)> {
+        let transform = |vertex: &WeightedVertex| {
+            let pos = vertex.position.xy();
+
+            match self {
+                Transform::Translate(delta) => pos + delta,
+                Transform::Rotate(angle) => pivot + Vec2::from_angle(angle).rotate(pos - pivot),
+                Transform::Scale(factor) => pivot + (pos - pivot) * factor,
+                Transform::Slide(factor) if factor >= 0. => pos.lerp(vertex.neighbors.1, factor),
+                Transform::Slide(factor) => pos.lerp(vertex.neighbors.0, -factor),
+            }
+        };
+
+        original_positions
+            .iter()
+            .map(|vertex| {
+                let original = vertex.position.xy();
+                let transformed = original.lerp(transform(vertex), vertex.weight);
+
+                (vertex.id, transformed.extend(vertex.position.z))
+            })
+            .collect()
+    }
+}
+
+/// Mirrors each edited position to the vertex's mirror partner. If both a
+/// vertex and its partner were edited, the edit that comes first is kept.
+/// Vertices which are their own partner stay on the mirror axis.
+fn mirror_to_partners(
+    lofter: &Lofter,
+    sketch_index: usize,
+    positions: Vec<(VertexId, Vec3)>,
+    axis: MirrorAxis,
+) -> Vec<(VertexId, Vec3)> {
+    let mut edited = HashSet::new();
+    let mut mirrored = Vec::with_capacity(positions.len() * 2);
+
+    for (id, position) in positions {
+        if !edited.insert(id) {
+            continue;
+        }
+
+        match lofter.mirror_partner(sketch_index, id) {
+            Some(partner) if partner == id => {
+                mirrored.push((id, (position + axis.mirror(position)) / 2.));
+            }
+            Some(partner) => {
+                mirrored.push((id, position));
+
+                if edited.insert(partner) {
+                    mirrored.push((partner, axis.mirror(position)));
+                }
+            }
+            None => mirrored.push((id, position)),
+        }
+    }
+
+    mirrored
+}
+
+/// Draws a small padlock in the corner of a locked sketch's editor.
+fn draw_lock_badge(draw_list: &DrawListMut, corner: Vec2) {
+    let color = [1., 0.8, 0.2];
+
+    // Shackle.
+    draw_list
+        .add_circle((corner + Vec2::new(6., 6.)).to_array(), 4., color)
+        .thickness(2.)
+        .build();
+
+    // Body, covering the lower half of the shackle.
+    draw_list
+        .add_rect(
+            (corner + Vec2::new(0., 6.)).to_array(),
+            (corner + Vec2::new(12., 16.)).to_array(),
+            color,
+        )
+        .filled(true)
+        .rounding(2.)
+        .build();
+}
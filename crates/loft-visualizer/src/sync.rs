@@ -0,0 +1,426 @@
+use std::{
+    io,
+    net::TcpListener,
+    time::{Duration, Instant},
+};
+
+use lofter::{LoftDocument, Lofter, Op, OpId};
+use serde_json::{Value, json};
+
+use crate::websocket::{Handshake, WebSocket};
+
+/// Syncs the loft live with other visualizers over WebSockets, so one user
+/// can edit the sketches while another reviews the result.
+///
+/// One visualizer hosts, keeping the `LoftDocument` whose log decides the
+/// order of everyone's edits. The others join it, apply their own edits right
+/// away, and send them to the host, rebasing them if the host's log has other
+/// edits first. Replacing the whole lofter, such as by loading a template,
+/// replaces the document for everyone.
+pub struct SyncControls {
+    /// The address hosted on, or joined, such as "127.0.0.1:9001".
+    pub address: String,
+    /// Set by the UI.
+    pub request: Option<SyncRequest>,
+    /// A message describing the connection, or why it failed.
+    pub status: Option<String>,
+    connection: Option<Connection>,
+}
+
+#[derive(Clone, Copy)]
+pub enum SyncRequest {
+    Host,
+    Join,
+    Disconnect,
+}
+
+enum Connection {
+    Host {
+        listener: TcpListener,
+        document: LoftDocument,
+        /// Clients which have connected, but not finished their handshakes.
+        handshakes: Vec<Handshake>,
+        /// Each joined client, and the ID its edits are logged under.
+        clients: Vec<(u64, WebSocket)>,
+        next_client: u64,
+    },
+    Client {
+        host: WebSocket,
+        /// `None` until the host sends its document.
+        document: Option<LoftDocument>,
+        /// The host's log, as far as it has been received.
+        log: Vec<Op>,
+        /// When edits were last dropped, for showing a message.
+        conflicted: Option<Instant>,
+    },
+}
+
+/// The ID the host's own edits are logged under.
+const HOST_CLIENT: u64 = 0;
+
+/// How long a message about dropped edits is shown.
+const CONFLICT_MESSAGE_DURATION: Duration = Duration::from_secs(3);
+
+impl Default for SyncControls {
+    fn default() -> Self {
+        Self {
+            address: "127.0.0.1:9001".into(),
+            request: None,
+            status: None,
+            connection: None,
+        }
+    }
+}
+
+impl SyncControls {
+    pub fn is_connected(&self) -> bool {
+        self.connection.is_some()
+    }
+
+    /// Handles a request from the UI, if there is one. Joining replaces the
+    /// lofter with the host's, once it's received.
+    pub fn handle_request(&mut self, lofter: &mut Lofter) {
+        let Some(request) = self.request.take() else {
+            return;
+        };
+
+        self.disconnect(lofter);
+
+        let result = match request {
+            SyncRequest::Host => self
+                .host(lofter)
+                .map(|()| format!("Hosting on {}", self.address)),
+            SyncRequest::Join => self.join().map(|()| format!("Joining {}", self.address)),
+            SyncRequest::Disconnect => Ok("Disconnected".into()),
+        };
+
+        self.status = Some(result.unwrap_or_else(|err| format!("Sync failed: {err}")));
+    }
+
+    /// Sends the lofter's edits, and applies those received.
+    pub fn update(&mut self, lofter: &mut Lofter) {
+        let Some(connection) = &mut self.connection else {
+            return;
+        };
+
+        let result = match connection {
+            Connection::Host {
+                listener,
+                document,
+                handshakes,
+                clients,
+                next_client,
+            } => update_host(lofter, listener, document, handshakes, clients, next_client),
+            Connection::Client {
+                host,
+                document,
+                log,
+                conflicted,
+            } => update_client(lofter, host, document, log, conflicted),
+        };
+
+        match result {
+            Ok(status) => self.status = Some(status),
+            Err(err) => {
+                self.disconnect(lofter);
+                self.status = Some(format!("Sync failed: {err}"));
+            }
+        }
+    }
+
+    fn host(&mut self, lofter: &mut Lofter) -> io::Result<()> {
+        let listener = TcpListener::bind(&self.address)?;
+        listener.set_nonblocking(true)?;
+
+        let document = LoftDocument::new(lofter.clone(), HOST_CLIENT);
+        lofter.start_capturing();
+
+        self.connection = Some(Connection::Host {
+            listener,
+            document,
+            handshakes: Vec::new(),
+            clients: Vec::new(),
+            next_client: HOST_CLIENT + 1,
+        });
+
+        Ok(())
+    }
+
+    fn join(&mut self) -> io::Result<()> {
+        let host = WebSocket::connect(&self.address)?;
+
+        self.connection = Some(Connection::Client {
+            host,
+            document: None,
+            log: Vec::new(),
+            conflicted: None,
+        });
+
+        Ok(())
+    }
+
+    fn disconnect(&mut self, lofter: &mut Lofter) {
+        if self.connection.take().is_some() {
+            lofter.stop_capturing();
+        }
+    }
+}
+
+fn update_host(
+    lofter: &mut Lofter,
+    listener: &TcpListener,
+    document: &mut LoftDocument,
+    handshakes: &mut Vec<Handshake>,
+    clients: &mut Vec<(u64, WebSocket)>,
+    next_client: &mut u64,
+) -> io::Result<String> {
+    loop {
+        match listener.accept() {
+            Ok((stream, _)) => handshakes.extend(Handshake::new(stream).ok()),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+            Err(err) => return Err(err),
+        }
+    }
+
+    // A failed handshake only loses that client.
+    for mut handshake in std::mem::take(handshakes) {
+        match handshake.poll() {
+            Ok(true) => {
+                if let Ok(mut client) = handshake.accept() {
+                    client.send(&document_message(document, *next_client));
+                    clients.push((*next_client, client));
+                    *next_client += 1;
+                }
+            }
+            Ok(false) => handshakes.push(handshake),
+            Err(_) => {}
+        }
+    }
+
+    // The host's own edits.
+    if lofter.is_capturing() {
+        let ops = apply_captured(lofter, document);
+        broadcast(clients, &ops_message(&ops));
+    } else {
+        *document = LoftDocument::new(lofter.clone(), HOST_CLIENT);
+        lofter.start_capturing();
+        broadcast_document(clients, document);
+    }
+
+    for index in 0..clients.len() {
+        let messages = clients[index].1.poll().unwrap_or_default();
+
+        for message in messages {
+            // A client sending something malformed only loses that client.
+            let Ok(message) = parse_message(&message) else {
+                clients[index].1.close();
+                break;
+            };
+
+            match message {
+                Message::Ops(ops) => {
+                    let applied = document.ops().len();
+                    let dropped = document.append(&ops);
+
+                    let new_ops = document.ops()[applied..].to_vec();
+                    apply_remote(lofter, &new_ops);
+                    broadcast(clients, &ops_message(&new_ops));
+
+                    if !dropped.is_empty() {
+                        let ids: Vec<OpId> = dropped.iter().map(|op| op.id).collect();
+                        clients[index]
+                            .1
+                            .send(&json!({ "type": "rejected", "ids": ids }).to_string());
+                    }
+                }
+                // The client replaced its lofter, which replaces everyone's.
+                Message::Document { base, ops, .. } => {
+                    *document = LoftDocument::new(*base, HOST_CLIENT);
+                    document.merge(&ops);
+                    replace_lofter(lofter, document);
+                    broadcast_document(clients, document);
+                }
+                Message::Rejected(_) => {}
+            }
+        }
+    }
+
+    clients.retain(|(_, client)| !client.is_closed());
+
+    Ok(match clients.len() {
+        1 => "Hosting, with 1 client".into(),
+        count => format!("Hosting, with {count} clients"),
+    })
+}
+
+fn update_client(
+    lofter: &mut Lofter,
+    host: &mut WebSocket,
+    document: &mut Option<LoftDocument>,
+    log: &mut Vec<Op>,
+    conflicted: &mut Option<Instant>,
+) -> io::Result<String> {
+    // Edits before the host's document arrives are replaced by it.
+    if let Some(document) = document {
+        if lofter.is_capturing() {
+            let ops = apply_captured(lofter, document);
+            if !ops.is_empty() {
+                host.send(&ops_message(&ops));
+            }
+        } else {
+            *document = LoftDocument::new(lofter.clone(), document.client());
+            lofter.start_capturing();
+            host.send(&document_message(document, document.client()));
+        }
+    }
+
+    let mut dropped = Vec::new();
+    for message in host.poll()? {
+        match parse_message(&message)? {
+            Message::Document { client, base, ops } => {
+                let mut new_document = LoftDocument::new(*base, client);
+                dropped.extend(new_document.merge(&ops));
+                replace_lofter(lofter, &new_document);
+
+                *document = Some(new_document);
+                *log = ops;
+            }
+            Message::Ops(ops) => {
+                let Some(document) = document else {
+                    continue;
+                };
+                log.extend(ops);
+
+                // Without edits of its own waiting for the host, the new ops
+                // are applied as they are, rather than replaying the log.
+                if log.starts_with(document.ops()) {
+                    let new_ops = log[document.ops().len()..].to_vec();
+                    dropped.extend(document.merge(log));
+                    apply_remote(lofter, &new_ops);
+                } else {
+                    dropped.extend(document.merge(log));
+                    replace_lofter(lofter, document);
+                }
+            }
+            Message::Rejected(ids) => {
+                let Some(document) = document else {
+                    continue;
+                };
+                dropped.extend(document.discard(&ids));
+                replace_lofter(lofter, document);
+                *conflicted = Some(Instant::now());
+            }
+        }
+    }
+
+    if !dropped.is_empty() {
+        *conflicted = Some(Instant::now());
+    }
+
+    if host.is_closed() {
+        return Err(io::Error::new(
+            io::ErrorKind::ConnectionAborted,
+            "the host closed the connection",
+        ));
+    }
+
+    Ok(match document {
+        None => "Waiting for the host's loft".into(),
+        Some(_) if conflicted.is_some_and(|time| time.elapsed() < CONFLICT_MESSAGE_DURATION) => {
+            "Synced, dropping edits which conflicted".into()
+        }
+        Some(document) => format!("Synced as client {}", document.client()),
+    })
+}
+
+/// Logs the commands applied to the lofter since last time as new ops of the
+/// document, returning the ops. Commands which failed aren't logged.
+fn apply_captured(lofter: &mut Lofter, document: &mut LoftDocument) -> Vec<Op> {
+    lofter
+        .take_captured()
+        .into_iter()
+        .filter_map(|command| {
+            document.apply(command).ok()?;
+
+            document.ops().last().cloned()
+        })
+        .collect()
+}
+
+/// Applies other visualizers' ops to the lofter, without capturing them as
+/// its own edits.
+fn apply_remote(lofter: &mut Lofter, ops: &[Op]) {
+    for op in ops {
+        let _ = lofter.apply(&op.command);
+    }
+    lofter.take_captured();
+}
+
+/// Replaces the lofter with the document's, capturing its edits.
+fn replace_lofter(lofter: &mut Lofter, document: &LoftDocument) {
+    *lofter = document.lofter().clone();
+    lofter.start_capturing();
+}
+
+fn broadcast(clients: &mut [(u64, WebSocket)], message: &str) {
+    for (_, client) in clients {
+        client.send(message);
+    }
+}
+
+fn broadcast_document(clients: &mut [(u64, WebSocket)], document: &LoftDocument) {
+    for (id, client) in clients {
+        client.send(&document_message(document, *id));
+    }
+}
+
+enum Message {
+    /// A whole document, sent to clients when they join and whenever it's
+    /// replaced. `client` is the receiving client's ID.
+    Document {
+        client: u64,
+        base: Box<Lofter>,
+        ops: Vec<Op>,
+    },
+    /// Ops sent to the host, or appended to its log.
+    Ops(Vec<Op>),
+    /// Ops of the receiving client which the host dropped.
+    Rejected(Vec<OpId>),
+}
+
+fn document_message(document: &LoftDocument, client: u64) -> String {
+    json!({
+        "type": "document",
+        "client": client,
+        "base": document.base(),
+        "ops": document.ops(),
+    })
+    .to_string()
+}
+
+fn ops_message(ops: &[Op]) -> String {
+    json!({ "type": "ops", "ops": ops }).to_string()
+}
+
+fn parse_message(message: &str) -> io::Result<Message> {
+    let mut value: Value = serde_json::from_str(message)?;
+    let mut field = |name: &str| value[name].take();
+
+    let message = match field("type").as_str() {
+        Some("document") => Message::Document {
+            client: serde_json::from_value(field("client"))?,
+            base: serde_json::from_value(field("base"))?,
+            ops: serde_json::from_value(field("ops"))?,
+        },
+        Some("ops") => Message::Ops(serde_json::from_value(field("ops"))?),
+        Some("rejected") => Message::Rejected(serde_json::from_value(field("ids"))?),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unknown sync message",
+            ));
+        }
+    };
+
+    Ok(message)
+}
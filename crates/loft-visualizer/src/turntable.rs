@@ -0,0 +1,157 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    camera::Camera,
+    render::{CapturedFrame, Renderer},
+};
+
+/// Frame rate of exported turntables.
+#[cfg(feature = "mp4")]
+const EXPORT_FRAMES_PER_SECOND: u32 = 30;
+
+/// A turntable rotates the camera around the loft at a constant speed.
+pub struct Turntable {
+    pub enabled: bool,
+    /// In degrees per second.
+    pub speed: f32,
+    pub export: TurntableExport,
+}
+
+impl Default for Turntable {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            speed: 30.,
+            export: Default::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    /// One PNG file per frame.
+    PngSequence,
+    #[cfg(feature = "mp4")]
+    Mp4,
+}
+
+/// Options for exporting a single, full rotation of the turntable.
+pub struct TurntableExport {
+    /// The number of frames in a full rotation.
+    pub frame_count: u32,
+    pub directory: String,
+    pub format: ExportFormat,
+    /// Set by the UI to request an export.
+    pub requested: bool,
+    /// A message describing the result of the last export.
+    pub status: Option<String>,
+}
+
+impl Default for TurntableExport {
+    fn default() -> Self {
+        Self {
+            frame_count: 120,
+            directory: "turntable".into(),
+            format: ExportFormat::PngSequence,
+            requested: false,
+            status: None,
+        }
+    }
+}
+
+impl TurntableExport {
+    /// Renders a full rotation of the camera, starting at its current
+    /// rotation, and writes it to the export directory. Returns the path of the
+    /// written file or directory.
+    pub fn export(&self, renderer: &Renderer, camera: &Camera) -> io::Result<PathBuf> {
+        let directory = Path::new(&self.directory);
+        fs::create_dir_all(directory)?;
+
+        let frames = (0..self.frame_count).map(|i| {
+            let mut camera = *camera;
+            camera.rotation += std::f32::consts::TAU * i as f32 / self.frame_count as f32;
+
+            renderer.set_camera(&camera);
+            renderer.capture_frame()
+        });
+
+        match self.format {
+            ExportFormat::PngSequence => {
+                for (i, frame) in frames.enumerate() {
+                    write_png(&directory.join(format!("frame_{i:04}.png")), &frame)?;
+                }
+
+                Ok(directory.to_owned())
+            }
+            #[cfg(feature = "mp4")]
+            ExportFormat::Mp4 => {
+                let path = directory.join("turntable.mp4");
+                write_mp4(&path, frames)?;
+
+                Ok(path)
+            }
+        }
+    }
+}
+
+fn write_png(path: &Path, frame: &CapturedFrame) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(File::create(path)?),
+        frame.width,
+        frame.height,
+    );
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&frame.rgba))
+        .map_err(io::Error::other)
+}
+
+/// Encodes frames as an MP4 video, by piping raw frames into `ffmpeg`.
+#[cfg(feature = "mp4")]
+fn write_mp4(path: &Path, frames: impl Iterator<Item = CapturedFrame>) -> io::Result<()> {
+    use std::{
+        io::Write,
+        process::{Command, Stdio},
+    };
+
+    let mut frames = frames.peekable();
+    let Some(first_frame) = frames.peek() else {
+        return Ok(());
+    };
+
+    let mut ffmpeg = Command::new("ffmpeg")
+        .args(["-y", "-loglevel", "error", "-f", "rawvideo"])
+        .args(["-pixel_format", "rgba"])
+        .arg("-video_size")
+        .arg(format!("{}x{}", first_frame.width, first_frame.height))
+        .arg("-framerate")
+        .arg(EXPORT_FRAMES_PER_SECOND.to_string())
+        .args(["-i", "-"])
+        // H.264 requires even dimensions.
+        .args(["-vf", "scale=trunc(iw/2)*2:trunc(ih/2)*2"])
+        .args(["-pix_fmt", "yuv420p"])
+        .arg(path)
+        .stdin(Stdio::piped())
+        .spawn()?;
+
+    let mut stdin = ffmpeg.stdin.take().unwrap();
+    for frame in frames {
+        stdin.write_all(&frame.rgba)?;
+    }
+    drop(stdin);
+
+    let status = ffmpeg.wait()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("ffmpeg exited with {status}")));
+    }
+
+    Ok(())
+}
@@ -1,13 +1,41 @@
-use std::time::Instant;
+use std::{mem::discriminant, time::Instant};
 
-use glam::{Vec2, Vec3Swizzles};
-use imgui::{Condition, FontSource, MouseCursor};
+use glam::Vec3;
+use imgui::{Condition, FontSource, MouseCursor, SliderFlags, TreeNodeFlags};
 use imgui_wgpu::RendererConfig;
 use imgui_winit_support::WinitPlatform;
-use lofter::Lofter;
+use lofter::{
+    LoftOptions, LoftResult, Lofter, Matching, MirrorAxis, StationChange, Template, VertexId,
+};
 use winit::event::Event;
 
-use crate::render::Renderer;
+#[cfg(feature = "scripting")]
+use crate::console::ScriptConsole;
+#[cfg(feature = "sync")]
+use crate::sync::{SyncControls, SyncRequest};
+#[cfg(feature = "mp4")]
+use crate::turntable::ExportFormat;
+use crate::{
+    analysis::{DIRECTIONS, SurfaceAnalysis},
+    camera::{Camera, Projection},
+    layer_preview::LayerPreview,
+    library::{LibraryRequest, ProfileLibrary},
+    playback::ConstructionPlayback,
+    render::{Renderer, Shading},
+    session::{SessionControls, SessionRequest},
+    sketch_editor::{EditSettings, Falloff, SketchEditor, TransformMode},
+    turntable::Turntable,
+};
+
+/// Maximum distance, in sketch units, between a vertex's mirrored position and
+/// its partner when tagging mirror pairs.
+const MIRROR_TAG_TOLERANCE: f32 = 1e-3;
+
+/// The printf-style formats of coordinate fields, indexed by their decimal
+/// places. imgui only accepts static formats.
+const COORDINATE_FORMATS: [&str; 9] = [
+    "%.0f", "%.1f", "%.2f", "%.3f", "%.4f", "%.5f", "%.6f", "%.7f", "%.8f",
+];
 
 pub struct ImguiState {
     context: imgui::Context,
@@ -15,13 +43,68 @@ pub struct ImguiState {
     renderer: imgui_wgpu::Renderer,
     last_frame: Instant,
     last_cursor: Option<MouseCursor>,
+    sketch_editors: [SketchEditor; 2],
+    edit_settings: EditSettings,
+    /// Whether the Vertices window shows world coordinates, rather than
+    /// sketch-local coordinates.
+    vertex_world_space: bool,
+    /// Only vertices with IDs containing this text are listed in the Vertices
+    /// window.
+    vertex_filter: String,
+    /// The digits shown after the decimal point by coordinate fields.
+    decimal_places: u32,
+    /// A vertex highlighted in the 3D view, as `(sketch_index, vertex_id)`.
+    pub highlighted_vertex: Option<(usize, VertexId)>,
     pub loft_state: LoftState,
+    pub turntable: Turntable,
+    pub playback: ConstructionPlayback,
+    pub layer_preview: LayerPreview,
+    pub session: SessionControls,
+    pub library: ProfileLibrary,
+    #[cfg(feature = "scripting")]
+    pub console: ScriptConsole,
+    #[cfg(feature = "sync")]
+    pub sync: SyncControls,
+    /// Set when the camera should be moved to frame the whole loft.
+    pub frame_requested: bool,
+    /// Whether frames are only drawn after input or other changes, rather than
+    /// continuously.
+    pub redraw_on_change: bool,
+    /// Whether the loft mesh is evaluated on the GPU from sketch-local
+    /// vertices, so transforming sketches doesn't re-upload the mesh.
+    pub gpu_loft_evaluation: bool,
+    pub shading: Shading,
+    /// How far each loft section is moved out along its average normal, as a
+    /// fraction of the loft's bounding radius.
+    pub explode_sections: f32,
+    pub surface_analysis: SurfaceAnalysis,
+    /// The summary of the surface analysis the loft was last drawn with.
+    pub analysis_summary: Option<String>,
+    /// Whether each sketch's outline is drawn over the loft.
+    pub show_sketch_outlines: bool,
+    /// Whether the loft's edges between sketches are drawn over the loft.
+    pub show_ruling_edges: bool,
+    /// The loft triangle picked in the 3D view, as `(index, centroid)`.
+    pub picked_triangle: Option<(u32, Vec3)>,
+    /// Set when the surface should be reconfigured with a new present mode.
+    pub present_mode_request: Option<wgpu::PresentMode>,
 }
 
 pub struct LoftState {
     pub reloft: bool,
     pub max_angle: f32,
-    pub rotation: f32,
+    pub matching: Matching,
+    /// Whether features, such as corners, are matched first.
+    pub align_features: bool,
+    /// In degrees.
+    pub feature_angle: f32,
+    /// Whether ruling edges exceeding the station change limits are reported.
+    pub check_station_changes: bool,
+    pub max_station_change_distance: f32,
+    /// In degrees.
+    pub max_station_change_angle: f32,
+    /// The result of the last loft from the UI.
+    pub result: Option<LoftResult>,
 }
 
 impl Default for LoftState {
@@ -29,7 +112,30 @@ impl Default for LoftState {
         Self {
             reloft: false,
             max_angle: 30.,
-            rotation: 0.,
+            matching: Matching::Polar,
+            align_features: false,
+            feature_angle: 60.,
+            check_station_changes: false,
+            max_station_change_distance: 0.5,
+            max_station_change_angle: 30.,
+            result: None,
+        }
+    }
+}
+
+impl LoftState {
+    pub fn options(&self) -> LoftOptions {
+        LoftOptions {
+            max_radial_edge_angle: self.max_angle,
+            matching: self.matching,
+            feature_angle: self.align_features.then_some(self.feature_angle),
+            max_station_change_distance: self
+                .check_station_changes
+                .then_some(self.max_station_change_distance),
+            max_station_change_angle: self
+                .check_station_changes
+                .then_some(self.max_station_change_angle),
+            ..Default::default()
         }
     }
 }
@@ -45,17 +151,7 @@ impl ImguiState {
         );
         context.set_ini_filename(None);
 
-        let font_size = 13.0 * hidpi_factor;
-        context.io_mut().font_global_scale = 1.0 / hidpi_factor;
-
-        context.fonts().add_font(&[FontSource::DefaultFontData {
-            config: Some(imgui::FontConfig {
-                oversample_h: 1,
-                pixel_snap_h: true,
-                size_pixels: font_size,
-                ..Default::default()
-            }),
-        }]);
+        add_fonts(&mut context, hidpi_factor);
 
         let renderer_config = RendererConfig {
             texture_format: renderer.surface_config.format,
@@ -78,16 +174,80 @@ impl ImguiState {
             renderer,
             last_frame,
             last_cursor,
+            sketch_editors: [SketchEditor::new(1), SketchEditor::new(0)],
+            edit_settings: Default::default(),
+            vertex_world_space: false,
+            vertex_filter: String::new(),
+            decimal_places: 3,
+            highlighted_vertex: None,
             loft_state: Default::default(),
+            turntable: Default::default(),
+            playback: Default::default(),
+            layer_preview: Default::default(),
+            session: Default::default(),
+            library: Default::default(),
+            #[cfg(feature = "scripting")]
+            console: Default::default(),
+            #[cfg(feature = "sync")]
+            sync: Default::default(),
+            frame_requested: false,
+            redraw_on_change: false,
+            gpu_loft_evaluation: false,
+            shading: Default::default(),
+            explode_sections: 0.,
+            surface_analysis: Default::default(),
+            analysis_summary: None,
+            show_sketch_outlines: true,
+            show_ruling_edges: false,
+            picked_triangle: None,
+            present_mode_request: None,
         }
     }
 
+    /// Returns true while a sketch is being edited in one of the sketch
+    /// editors.
+    pub fn is_editing(&self) -> bool {
+        self.sketch_editors.iter().any(SketchEditor::is_editing)
+    }
+
+    /// Returns true while the loft is synced with other visualizers, so frames
+    /// are drawn to poll for their edits.
+    pub fn is_syncing(&self) -> bool {
+        #[cfg(feature = "sync")]
+        return self.sync.is_connected();
+
+        #[cfg(not(feature = "sync"))]
+        false
+    }
+
+    /// Rebuilds the fonts for a new scale factor, such as when the window moves
+    /// to another monitor, so the UI stays sharp and correctly sized.
+    pub fn set_hidpi_factor(&mut self, renderer: &Renderer, hidpi_factor: f32) {
+        self.context.fonts().clear();
+        add_fonts(&mut self.context, hidpi_factor);
+
+        self.renderer
+            .reload_font_texture(&mut self.context, &renderer.device, &renderer.queue);
+    }
+
+    /// Returns true if the mouse or a touch is over the UI, so it shouldn't
+    /// move the camera.
+    pub fn wants_pointer(&self) -> bool {
+        self.context.io().want_capture_mouse
+    }
+
     pub fn handle_event(&mut self, renderer: &Renderer, event: &Event<()>) {
         self.platform
             .handle_event::<()>(self.context.io_mut(), &renderer.window, event);
     }
 
-    pub fn draw(&mut self, renderer: &Renderer, view: &wgpu::TextureView, lofter: &mut Lofter) {
+    pub fn draw(
+        &mut self,
+        renderer: &Renderer,
+        view: &wgpu::TextureView,
+        lofter: &mut Lofter,
+        camera: &mut Camera,
+    ) {
         let now = Instant::now();
         self.context
             .io_mut()
@@ -99,103 +259,609 @@ impl ImguiState {
             .unwrap();
 
         let ui = self.context.frame();
+        let coordinate_format = COORDINATE_FORMATS[self.decimal_places as usize];
 
         ui.window("Lofter")
             .size([200.0, 500.0], Condition::FirstUseEver)
             .build(|| {
+                if ui.button("New from template") {
+                    ui.open_popup("New from template");
+                }
+                ui.modal_popup_config("New from template")
+                    .always_auto_resize(true)
+                    .build(|| {
+                        for template in Template::ALL {
+                            if ui.button(template.name()) {
+                                // Relofted with the UI's options.
+                                *lofter = Lofter::from_template(template);
+                                self.loft_state.reloft = true;
+                                self.frame_requested = true;
+                                ui.close_current_popup();
+                            }
+                        }
+
+                        ui.separator();
+                        if ui.button("Cancel") {
+                            ui.close_current_popup();
+                        }
+                    });
+
                 ui.separator();
 
+                let [upper_editor, lower_editor] = &mut self.sketch_editors;
+                let edit_settings = &mut self.edit_settings;
+
                 ui.child_window("Upper sketch")
                     .size([200.; _])
                     .movable(false)
-                    .build(|| {
-                        let window_pos = Vec2::from_array(ui.window_pos());
-                        let window_size = Vec2::from_array(ui.window_size());
-                        let window_center = window_pos + window_size / 2.;
-
-                        let draw_list = ui.get_window_draw_list();
-                        let points: Vec<_> = lofter
-                            .vertices(1)
-                            .unwrap()
-                            .map(|(_, pos)| (window_center + pos.xy() * 50.).to_array())
-                            .collect();
-
-                        draw_list
-                            .add_polyline(points.clone(), [1., 0., 0.])
-                            .filled(true)
-                            .build();
-
-                        for point in &points {
-                            draw_list
-                                .add_rect(
-                                    (Vec2::from_array(*point) - Vec2::splat(5.)).to_array(),
-                                    (Vec2::from_array(*point) + Vec2::splat(5.)).to_array(),
-                                    [1., 1., 1.],
-                                )
-                                .build();
-                        }
-                    });
+                    .build(|| upper_editor.draw(ui, lofter, edit_settings));
 
                 ui.separator();
 
                 ui.child_window("Lower sketch")
                     .size([200.; _])
                     .movable(false)
-                    .build(|| {
-                        let window_pos = Vec2::from_array(ui.window_pos());
-                        let window_size = Vec2::from_array(ui.window_size());
-                        let window_center = window_pos + window_size / 2.;
-
-                        let draw_list = ui.get_window_draw_list();
-                        let points: Vec<_> = lofter
-                            .vertices(0)
-                            .unwrap()
-                            .map(|(_, pos)| (window_center + pos.xy() * 50.).to_array())
-                            .collect();
-
-                        draw_list
-                            .add_polyline(points.clone(), [1., 0., 0.])
-                            .filled(true)
-                            .build();
-
-                        for point in &points {
-                            draw_list
-                                .add_rect(
-                                    (Vec2::from_array(*point) - Vec2::splat(5.)).to_array(),
-                                    (Vec2::from_array(*point) + Vec2::splat(5.)).to_array(),
-                                    [1., 1., 1.],
+                    .build(|| lower_editor.draw(ui, lofter, edit_settings));
+
+                ui.separator();
+
+                // Shift-click or drag a rectangle to select multiple vertices,
+                // then drag a selected vertex to transform the selection.
+                for (mode, label) in [
+                    (TransformMode::Translate, "Translate"),
+                    (TransformMode::Rotate, "Rotate"),
+                    (TransformMode::Scale, "Scale"),
+                    (TransformMode::Slide, "Slide"),
+                ] {
+                    if ui.radio_button_bool(label, edit_settings.transform_mode == mode) {
+                        edit_settings.transform_mode = mode;
+                    }
+                }
+
+                ui.checkbox(
+                    "Proportional editing",
+                    &mut edit_settings.proportional_editing,
+                );
+                ui.disabled(!edit_settings.proportional_editing, || {
+                    ui.slider("Radius", 0.05, 5., &mut edit_settings.proportional_radius);
+
+                    let mut falloff_index = Falloff::ALL
+                        .iter()
+                        .position(|&falloff| falloff == edit_settings.falloff)
+                        .unwrap();
+                    if ui.combo("Falloff", &mut falloff_index, &Falloff::ALL, |falloff| {
+                        falloff.label().into()
+                    }) {
+                        edit_settings.falloff = Falloff::ALL[falloff_index];
+                    }
+                });
+
+                ui.checkbox("Mirror editing", &mut edit_settings.mirror_editing);
+                for (axis, label) in [(MirrorAxis::Y, "Mirror x"), (MirrorAxis::X, "Mirror y")] {
+                    if ui.radio_button_bool(label, edit_settings.mirror_axis == axis) {
+                        edit_settings.mirror_axis = axis;
+                    }
+                }
+                if ui.button("Tag mirror pairs") {
+                    // Locked sketches keep their tags.
+                    for editor in &self.sketch_editors {
+                        let _ = lofter.tag_mirror_partners(
+                            editor.sketch_index,
+                            edit_settings.mirror_axis,
+                            MIRROR_TAG_TOLERANCE,
+                        );
+                    }
+                }
+
+                // Excluded vertices are still lofted, but never start a
+                // section, and are drawn dimmed.
+                for (excluded, label) in [
+                    (true, "Exclude selected from matching"),
+                    (false, "Include selected in matching"),
+                ] {
+                    if ui.button(label) {
+                        for editor in &self.sketch_editors {
+                            // Vertices of locked sketches, and those since
+                            // removed, such as by the console, are left as
+                            // they are.
+                            for &vertex_id in editor.selection() {
+                                let _ = lofter.set_vertex_excluded(
+                                    editor.sketch_index,
+                                    vertex_id,
+                                    excluded,
+                                );
+                            }
+                        }
+                    }
+                }
+
+                ui.separator();
+
+                ui.slider("Corner size", 0.01, 1., &mut edit_settings.corner_size);
+                ui.slider("Fillet segments", 1, 16, &mut edit_settings.fillet_segments);
+                let fillet = ui.button("Fillet selected");
+                ui.same_line();
+                let chamfer = ui.button("Chamfer selected");
+
+                if fillet || chamfer {
+                    for editor in &self.sketch_editors {
+                        for &vertex_id in editor.selection() {
+                            let sketch_index = editor.sketch_index;
+                            let size = edit_settings.corner_size;
+
+                            // Corners too small for the cut, and vertices
+                            // since removed, are left as they are.
+                            let _ = if fillet {
+                                lofter.fillet_vertex(
+                                    sketch_index,
+                                    vertex_id,
+                                    size,
+                                    edit_settings.fillet_segments,
                                 )
-                                .build();
+                            } else {
+                                lofter.chamfer_vertex(sketch_index, vertex_id, size)
+                            };
                         }
-                    });
+                    }
+                }
+
+                ui.separator();
+
+                for (sketch_index, label) in [(1, "Lock upper sketch"), (0, "Lock lower sketch")] {
+                    let mut locked = lofter.sketch_locked(sketch_index).unwrap_or_default();
+
+                    if ui.checkbox(label, &mut locked) {
+                        lofter.set_sketch_locked(sketch_index, locked).unwrap();
+                    }
+                }
 
                 ui.separator();
 
                 ui.slider("Max angle", 0.1, 60., &mut self.loft_state.max_angle);
-                ui.slider("Rotation", -180., 180., &mut self.loft_state.rotation);
+                for (matching, label) in [
+                    (Matching::Polar, "Match polar angle"),
+                    (Matching::TurningAngle, "Match turning angle"),
+                ] {
+                    if ui.radio_button_bool(label, self.loft_state.matching == matching) {
+                        self.loft_state.matching = matching;
+                    }
+                }
+
+                let loft_state = &mut self.loft_state;
+                ui.checkbox("Align features first", &mut loft_state.align_features);
+                if loft_state.align_features {
+                    ui.slider("Feature angle", 10., 170., &mut loft_state.feature_angle);
+                }
+                ui.checkbox(
+                    "Warn about station changes",
+                    &mut loft_state.check_station_changes,
+                );
+                if loft_state.check_station_changes {
+                    ui.slider(
+                        "Max change",
+                        0.01,
+                        5.,
+                        &mut loft_state.max_station_change_distance,
+                    );
+                    ui.slider(
+                        "Max change angle",
+                        1.,
+                        90.,
+                        &mut loft_state.max_station_change_angle,
+                    );
+                }
+
+                // The rotation slider drives the upper sketch, so it can't be
+                // used while that sketch is locked. It only sets the rotation
+                // when dragged, so replayed sessions aren't overridden.
+                let upper_locked = lofter.sketch_locked(1) == Some(true);
+                ui.disabled(upper_locked, || {
+                    let mut rotation = lofter.sketch_rotation(1).map_or(0., |rotation| rotation.z);
+                    if ui.slider("Rotation", -180., 180., &mut rotation) {
+                        let _ = lofter.set_sketch_rotation(1, &Vec3::new(0., 0., rotation));
+                    }
+                });
                 if ui.button("Loft") {
                     self.loft_state.reloft = true;
                 }
+                ui.same_line();
+                if ui.button("Explain loft") {
+                    self.playback.record_requested = true;
+                }
+                if let Some(result) = &self.loft_state.result {
+                    if result.is_empty() {
+                        ui.text_disabled("Add another sketch to loft");
+                    }
+                    for (pair, err) in result.errors() {
+                        let kept = if result.kept_previous.contains(&pair) {
+                            " (showing previous loft)"
+                        } else {
+                            ""
+                        };
+                        ui.text_colored(
+                            [1., 0.4, 0.4, 1.],
+                            format!("Sketches {pair} and {} failed: {err}{kept}", pair + 1),
+                        );
+                    }
+                    draw_station_changes(ui, &result.station_changes);
+                }
+
+                let playback = &mut self.playback;
+                if playback.is_active() {
+                    ui.separator();
+
+                    let label = if playback.playing { "Pause" } else { "Play" };
+                    if ui.button(label) {
+                        // Restart once the end is reached.
+                        if playback.shown_step_count() == playback.steps.len() {
+                            playback.position = 0.;
+                        }
+                        playback.playing = !playback.playing;
+                    }
+                    ui.same_line();
+                    if ui.button("Stop") {
+                        playback.stop();
+                    }
+
+                    let step_count = playback.steps.len() as f32;
+                    ui.slider("Step", 0., step_count, &mut playback.position);
+                    ui.slider("Steps per second", 1., 200., &mut playback.speed);
+
+                    if let Some(description) = playback.current_step_description() {
+                        ui.text(description);
+                    }
+                }
+
+                ui.separator();
+
+                let session = &mut self.session;
+
+                ui.input_text("Session file", &mut session.path).build();
+
+                if lofter.is_recording() {
+                    if ui.button("Stop recording") {
+                        session.request = Some(SessionRequest::StopRecording);
+                    }
+                } else if ui.button("Record session") {
+                    session.request = Some(SessionRequest::StartRecording);
+                }
+                ui.same_line();
+                if ui.button("Replay") {
+                    session.request = Some(SessionRequest::Replay);
+                }
+                ui.same_line();
+                if ui.button("Replay instantly") {
+                    session.request = Some(SessionRequest::ReplayInstantly);
+                }
+
+                if let Some(status) = &session.status {
+                    ui.text_wrapped(status);
+                }
             });
 
         ui.window("Vertices").build(|| {
-            let mut i = 0;
+            ui.checkbox("World space", &mut self.vertex_world_space);
+            ui.input_text("Filter", &mut self.vertex_filter)
+                .hint("Vertex ID")
+                .build();
+            ui.slider(
+                "Decimal places",
+                0,
+                COORDINATE_FORMATS.len() as u32 - 1,
+                &mut self.decimal_places,
+            );
+
+            ui.separator();
+
+            // List the topmost sketch first, matching the sketch editors.
+            for sketch_index in (0..lofter.sketch_count()).rev() {
+                let _id = ui.push_id_usize(sketch_index);
+
+                if ui.collapsing_header(
+                    format!("Sketch {sketch_index}"),
+                    TreeNodeFlags::DEFAULT_OPEN,
+                ) {
+                    vertex_inputs(
+                        ui,
+                        lofter,
+                        sketch_index,
+                        self.vertex_world_space,
+                        &self.vertex_filter,
+                        coordinate_format,
+                        &mut self.highlighted_vertex,
+                    );
+                }
+            }
+        });
 
-            lofter.vertices_mut(1, |(_, vert)| {
-                let label = i.to_string();
-                i += 1;
-                ui.input_float3(&label, vert.as_mut()).build();
+        ui.window("Profile library")
+            .collapsed(true, Condition::FirstUseEver)
+            .build(|| {
+                let library = &mut self.library;
+
+                ui.input_text("Directory", &mut library.directory).build();
+
+                let mut sketch_index = library.sketch_index as i32;
+                if ui.input_int("Sketch", &mut sketch_index).build() {
+                    library.sketch_index =
+                        sketch_index.clamp(0, lofter.sketch_count() as i32) as usize;
+                }
+
+                ui.separator();
+
+                ui.input_text("Name", &mut library.name).build();
+                ui.disabled(library.sketch_index >= lofter.sketch_count(), || {
+                    if ui.button("Save sketch") {
+                        library.request = Some(LibraryRequest::Save);
+                    }
+                });
+
+                ui.separator();
+
+                ui.input_float3("Offset", &mut library.offset)
+                    .display_format(coordinate_format)
+                    .build();
+                if ui.button("Refresh") {
+                    library.request = Some(LibraryRequest::Refresh);
+                }
+
+                for name in library.profiles.iter().flatten() {
+                    let _id = ui.push_id(name);
+
+                    if ui.button("Insert") {
+                        library.request = Some(LibraryRequest::Insert(name.clone()));
+                    }
+                    ui.same_line();
+                    ui.text(name);
+                }
+
+                if let Some(status) = &library.status {
+                    ui.text_wrapped(status);
+                }
             });
 
+        ui.window("View").build(|| {
+            for (projection, label) in [
+                (Projection::Perspective, "Perspective"),
+                (Projection::Orthographic, "Orthographic"),
+            ] {
+                if ui.radio_button_bool(label, camera.projection == projection) {
+                    camera.projection = projection;
+                }
+            }
+
+            match camera.projection {
+                Projection::Perspective => {
+                    ui.slider("FOV (deg)", 10., 120., &mut camera.fov_y);
+                }
+                Projection::Orthographic => {
+                    ui.slider_config("Scale", 0.1, 1000.)
+                        .flags(SliderFlags::LOGARITHMIC)
+                        .build(&mut camera.orthographic_scale);
+                }
+            }
+
+            ui.checkbox("Auto-fit clipping planes", &mut camera.auto_clip);
+            ui.disabled(camera.auto_clip, || {
+                ui.slider_config("Near", 0.001, 100.)
+                    .flags(SliderFlags::LOGARITHMIC)
+                    .build(&mut camera.near);
+                ui.slider_config("Far", 1., 10000.)
+                    .flags(SliderFlags::LOGARITHMIC)
+                    .build(&mut camera.far);
+            });
+
+            if ui.button("Frame loft") {
+                self.frame_requested = true;
+            }
+
+            // Keep the clipping planes in a valid order.
+            camera.far = camera.far.max(camera.near * 1.01);
+
+            ui.checkbox("Redraw only on change", &mut self.redraw_on_change);
+
+            ui.separator();
+
+            for (shading, label) in [
+                (Shading::Unlit, "Unlit"),
+                (Shading::Hemisphere, "Hemisphere shading"),
+            ] {
+                if ui.radio_button_bool(label, self.shading == shading) {
+                    self.shading = shading;
+                }
+            }
+
+            ui.slider("Explode sections", 0., 0.5, &mut self.explode_sections);
+
+            for (analysis, label) in [
+                (SurfaceAnalysis::None, "Section colors"),
+                (SurfaceAnalysis::DEFAULT_DRAFT, "Draft angles"),
+                (SurfaceAnalysis::DEFAULT_OVERHANG, "Overhangs"),
+            ] {
+                let selected = discriminant(&self.surface_analysis) == discriminant(&analysis);
+                if ui.radio_button_bool(label, selected) && !selected {
+                    self.surface_analysis = analysis;
+                }
+            }
+
+            match &mut self.surface_analysis {
+                SurfaceAnalysis::None => (),
+                SurfaceAnalysis::Draft {
+                    pull_direction,
+                    min_angle,
+                } => {
+                    direction_combo(ui, "Pull direction", pull_direction);
+                    ui.slider("Min draft (deg)", 0., 45., min_angle);
+                }
+                SurfaceAnalysis::Overhang {
+                    build_direction,
+                    max_angle,
+                } => {
+                    direction_combo(ui, "Build direction", build_direction);
+                    ui.slider("Max overhang (deg)", 1., 89., max_angle);
+                }
+            }
+            if self.surface_analysis != SurfaceAnalysis::None
+                && let Some(summary) = &self.analysis_summary
+            {
+                ui.text(summary);
+            }
+
             ui.separator();
 
-            lofter.vertices_mut(0, |(_, vert)| {
-                let label = i.to_string();
-                i += 1;
-                ui.input_float3(&label, vert.as_mut()).build();
+            let layer_preview = &mut self.layer_preview;
+            ui.checkbox("Layer preview", &mut layer_preview.enabled);
+            ui.disabled(!layer_preview.enabled, || {
+                ui.slider_config("Layer height", 0.001, 1.)
+                    .flags(SliderFlags::LOGARITHMIC)
+                    .build(&mut layer_preview.layer_height);
+
+                let layer_count = layer_preview.layer_count();
+                ui.slider("Layers", 0, layer_count, &mut layer_preview.shown_layers);
+                if let Some(z) = layer_preview.shown_height() {
+                    ui.text(format!(
+                        "Layer {} of {layer_count}, at z = {z:.3}",
+                        layer_preview.shown_layers
+                    ));
+                }
             });
+
+            ui.separator();
+
+            ui.checkbox("Sketch outlines", &mut self.show_sketch_outlines);
+            ui.checkbox("Ruling edges", &mut self.show_ruling_edges);
+
+            match self.picked_triangle {
+                Some((index, _)) => ui.text(format!("Picked triangle: {index}")),
+                None => ui.text_disabled("Right-click the loft to pick a triangle"),
+            }
+
+            ui.separator();
+
+            let turntable = &mut self.turntable;
+
+            ui.checkbox("Turntable", &mut turntable.enabled);
+            ui.slider("Speed (deg/s)", -180., 180., &mut turntable.speed);
+
+            ui.separator();
+
+            let export = &mut turntable.export;
+
+            ui.input_scalar("Frames", &mut export.frame_count).build();
+            ui.input_text("Directory", &mut export.directory).build();
+
+            #[cfg(feature = "mp4")]
+            for (format, label) in [
+                (ExportFormat::PngSequence, "PNG sequence"),
+                (ExportFormat::Mp4, "MP4"),
+            ] {
+                if ui.radio_button_bool(label, export.format == format) {
+                    export.format = format;
+                }
+            }
+
+            ui.disabled(export.frame_count == 0, || {
+                if ui.button("Export turntable") {
+                    export.requested = true;
+                }
+            });
+
+            if let Some(status) = &export.status {
+                ui.text_wrapped(status);
+            }
         });
 
+        ui.window("Display")
+            .collapsed(true, Condition::FirstUseEver)
+            .build(|| {
+                let info = &renderer.adapter_info;
+
+                ui.text(format!("Adapter: {}", info.name));
+                ui.text(format!("Type: {:?}", info.device_type));
+                ui.text(format!("Backend: {}", info.backend));
+                ui.text(format!("Driver: {} {}", info.driver, info.driver_info));
+                ui.text(format!(
+                    "Surface format: {:?}",
+                    renderer.surface_config.format
+                ));
+
+                ui.separator();
+
+                // Fifo waits for vsync, while Mailbox and Immediate don't, but
+                // only Mailbox avoids tearing.
+                ui.text("Present mode");
+                for &present_mode in &renderer.present_modes {
+                    let selected = renderer.surface_config.present_mode == present_mode;
+
+                    if ui.radio_button_bool(format!("{present_mode:?}"), selected) && !selected {
+                        self.present_mode_request = Some(present_mode);
+                    }
+                }
+
+                ui.separator();
+
+                ui.checkbox("Evaluate loft on GPU", &mut self.gpu_loft_evaluation);
+
+                #[cfg(feature = "profiling")]
+                {
+                    ui.separator();
+                    draw_profile(ui, &lofter.last_profile());
+                }
+            });
+
+        #[cfg(feature = "scripting")]
+        ui.window("Console")
+            .collapsed(true, Condition::FirstUseEver)
+            .build(|| {
+                let console = &mut self.console;
+
+                ui.input_text_multiline("##script", &mut console.script, [-1., 200.])
+                    .build();
+
+                if ui.button("Run") {
+                    console.run_requested = true;
+                }
+                ui.same_line();
+                if ui.button("Clear output") {
+                    console.output.clear();
+                }
+
+                ui.separator();
+
+                ui.child_window("Output").build(|| {
+                    for line in &console.output {
+                        ui.text_wrapped(line);
+                    }
+                });
+            });
+
+        #[cfg(feature = "sync")]
+        ui.window("Sync")
+            .collapsed(true, Condition::FirstUseEver)
+            .build(|| {
+                let sync = &mut self.sync;
+
+                if sync.is_connected() {
+                    ui.text(&sync.address);
+                    if ui.button("Disconnect") {
+                        sync.request = Some(SyncRequest::Disconnect);
+                    }
+                } else {
+                    ui.input_text("Address", &mut sync.address).build();
+                    if ui.button("Host") {
+                        sync.request = Some(SyncRequest::Host);
+                    }
+                    ui.same_line();
+                    if ui.button("Join") {
+                        sync.request = Some(SyncRequest::Join);
+                    }
+                }
+
+                if let Some(status) = &sync.status {
+                    ui.text_wrapped(status);
+                }
+            });
+
         let mut encoder: wgpu::CommandEncoder = renderer
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
@@ -234,3 +900,145 @@ impl ImguiState {
         renderer.queue.submit(Some(encoder.finish()));
     }
 }
+
+/// Draws a warning for each pair of sketches with ruling edges exceeding the
+/// station change limits, which are highlighted in the 3D view.
+fn draw_station_changes(ui: &imgui::Ui, station_changes: &[StationChange]) {
+    let mut pairs: Vec<(usize, usize, f32, f32)> = Vec::new();
+    for change in station_changes {
+        match pairs.last_mut() {
+            Some((pair, count, distance, angle)) if *pair == change.sketch_pair => {
+                *count += 1;
+                *distance = distance.max(change.distance);
+                *angle = angle.max(change.angle);
+            }
+            _ => pairs.push((change.sketch_pair, 1, change.distance, change.angle)),
+        }
+    }
+
+    for (pair, count, distance, angle) in pairs {
+        ui.text_colored(
+            [1., 0.6, 0.1, 1.],
+            format!(
+                "Sketches {pair} and {}: {count} edges change by up to {distance:.3} and {angle:.1} degrees",
+                pair + 1
+            ),
+        );
+    }
+}
+
+/// Draws the time spent in each phase of the last loft, summed over all pairs
+/// of sketches.
+#[cfg(feature = "profiling")]
+fn draw_profile(ui: &imgui::Ui, profile: &lofter::LoftProfile) {
+    use std::time::Duration;
+
+    let total = |phase: fn(&lofter::PairProfile) -> Duration| -> Duration {
+        profile.pairs.iter().map(phase).sum()
+    };
+
+    ui.text(format!("Last loft, {} pairs", profile.pairs.len()));
+    for (label, duration) in [
+        ("Candidates", total(|pair| pair.candidate_generation)),
+        ("Sorting", total(|pair| pair.sorting)),
+        ("Splitting", total(|pair| pair.splitting)),
+        ("Edges", total(|pair| pair.edge_building)),
+        ("Buffer", total(|pair| pair.buffer_assembly)),
+    ] {
+        ui.text(format!("{label}: {:.3} ms", duration.as_secs_f64() * 1e3));
+    }
+}
+
+/// Adds the UI fonts, rasterized at the scale factor's resolution.
+fn add_fonts(context: &mut imgui::Context, hidpi_factor: f32) {
+    let font_size = 13.0 * hidpi_factor;
+    context.io_mut().font_global_scale = 1.0 / hidpi_factor;
+
+    context.fonts().add_font(&[FontSource::DefaultFontData {
+        config: Some(imgui::FontConfig {
+            oversample_h: 1,
+            pixel_snap_h: true,
+            size_pixels: font_size,
+            ..Default::default()
+        }),
+    }]);
+}
+
+/// Draws a float input for each vertex in a sketch matching the filter, in
+/// either sketch-local or world coordinates, shown with the printf-style
+/// `display_format`. Locked sketches are shown read-only. Clicking a vertex's
+/// label highlights it in the 3D view.
+fn vertex_inputs(
+    ui: &imgui::Ui,
+    lofter: &mut Lofter,
+    sketch_index: usize,
+    world_space: bool,
+    filter: &str,
+    display_format: &'static str,
+    highlighted_vertex: &mut Option<(usize, VertexId)>,
+) {
+    let locked = lofter.sketch_locked(sketch_index) == Some(true);
+    let vertex_ids: Vec<_> = lofter
+        .vertices(sketch_index)
+        .unwrap()
+        .map(|(id, _)| id)
+        .filter(|id| id.to_string().contains(filter.trim()))
+        .collect();
+
+    for id in vertex_ids {
+        let highlighted = *highlighted_vertex == Some((sketch_index, id));
+
+        if ui
+            .selectable_config(format!("{id:>3}"))
+            .selected(highlighted)
+            .size([24., 0.])
+            .build()
+        {
+            *highlighted_vertex = if highlighted {
+                None
+            } else {
+                Some((sketch_index, id))
+            };
+        }
+
+        ui.same_line();
+
+        let label = format!("##{id}");
+
+        let mut position = if world_space {
+            lofter.vertex_world_position(sketch_index, id).unwrap()
+        } else {
+            *lofter.get_vertex(sketch_index, id).unwrap()
+        };
+
+        let changed = ui
+            .input_float3(&label, position.as_mut())
+            .display_format(display_format)
+            .read_only(locked)
+            .build();
+
+        if changed {
+            if world_space {
+                lofter
+                    .set_vertex_world_position(sketch_index, id, &position)
+                    .unwrap();
+            } else {
+                lofter
+                    .set_vertex_positions(sketch_index, &[(id, position)])
+                    .unwrap();
+            }
+        }
+    }
+}
+
+/// Shows a combo box choosing one of the axis directions.
+fn direction_combo(ui: &imgui::Ui, label: &str, direction: &mut Vec3) {
+    let mut index = DIRECTIONS
+        .iter()
+        .position(|&(axis, _)| axis == *direction)
+        .unwrap_or_default();
+
+    if ui.combo(label, &mut index, &DIRECTIONS, |(_, label)| (*label).into()) {
+        *direction = DIRECTIONS[index].0;
+    }
+}
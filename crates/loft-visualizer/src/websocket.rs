@@ -0,0 +1,556 @@
+//! A minimal WebSocket (RFC 6455) connection over a non-blocking TCP stream,
+//! which only exchanges text messages, for syncing lofts between visualizers.
+
+use std::{
+    io::{self, Read, Write},
+    net::TcpStream,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Appended to the client's key before hashing it into the server's accept
+/// key.
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+/// How long to wait for the other side's handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Messages longer than this close the connection, rather than being
+/// buffered.
+const MAX_MESSAGE_LEN: usize = 64 << 20;
+
+/// Handshake headers longer than this fail the handshake.
+const MAX_HEADERS_LEN: usize = 8 << 10;
+
+pub struct WebSocket {
+    stream: TcpStream,
+    /// Clients mask the frames they send, and servers don't.
+    is_client: bool,
+    /// Bytes read which don't form a whole frame yet.
+    incoming: Vec<u8>,
+    /// Bytes of frames which couldn't be written yet.
+    outgoing: Vec<u8>,
+    /// The payloads of a fragmented message's frames so far.
+    fragments: Vec<u8>,
+    closed: bool,
+}
+
+impl WebSocket {
+    /// Connects to a server at `address`, such as "127.0.0.1:9001".
+    pub fn connect(address: &str) -> io::Result<Self> {
+        let mut stream = TcpStream::connect(address)?;
+        stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+        let key = base64(&nonce());
+
+        write!(
+            stream,
+            "GET / HTTP/1.1\r\nHost: {address}\r\nUpgrade: websocket\r\n\
+             Connection: Upgrade\r\nSec-WebSocket-Key: {key}\r\n\
+             Sec-WebSocket-Version: 13\r\n\r\n"
+        )?;
+
+        let response = read_headers(&mut stream)?;
+        let accepted = response.starts_with("HTTP/1.1 101")
+            && header(&response, "sec-websocket-accept") == Some(accept_key(&key).as_str());
+        if !accepted {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the server didn't accept the WebSocket connection",
+            ));
+        }
+
+        Self::new(stream, true)
+    }
+
+    fn new(stream: TcpStream, is_client: bool) -> io::Result<Self> {
+        stream.set_read_timeout(None)?;
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+
+        Ok(Self {
+            stream,
+            is_client,
+            incoming: Vec::new(),
+            outgoing: Vec::new(),
+            fragments: Vec::new(),
+            closed: false,
+        })
+    }
+
+    /// Closes the connection, sending the other side a close frame if it can
+    /// be written right away.
+    pub fn close(&mut self) {
+        if !self.closed {
+            self.queue_frame(OPCODE_CLOSE, &[]);
+            let _ = self.flush();
+            self.closed = true;
+        }
+    }
+
+    /// Whether either side closed the connection, or it failed.
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Queues a text message, which is written by `poll`.
+    pub fn send(&mut self, text: &str) {
+        self.queue_frame(OPCODE_TEXT, text.as_bytes());
+    }
+
+    /// Writes queued messages and reads whatever has arrived, without
+    /// blocking, returning the complete text messages received.
+    pub fn poll(&mut self) -> io::Result<Vec<String>> {
+        let result = self.exchange();
+        if result.is_err() {
+            self.closed = true;
+        }
+
+        result
+    }
+
+    fn exchange(&mut self) -> io::Result<Vec<String>> {
+        self.flush()?;
+
+        let mut buffer = [0; 16 << 10];
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => {
+                    self.closed = true;
+                    break;
+                }
+                Ok(len) => self.incoming.extend_from_slice(&buffer[..len]),
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        let mut messages = Vec::new();
+        while let Some((fin, opcode, payload)) = self.take_frame()? {
+            match opcode {
+                OPCODE_TEXT | OPCODE_CONTINUATION => {
+                    self.fragments.extend_from_slice(&payload);
+                    if self.fragments.len() > MAX_MESSAGE_LEN {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "message too long",
+                        ));
+                    }
+
+                    if fin {
+                        let message = String::from_utf8(std::mem::take(&mut self.fragments))
+                            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                        messages.push(message);
+                    }
+                }
+                OPCODE_PING => self.queue_frame(OPCODE_PONG, &payload),
+                OPCODE_CLOSE if !self.closed => {
+                    self.queue_frame(OPCODE_CLOSE, &[]);
+                    self.closed = true;
+                }
+                // Pongs and binary messages aren't used.
+                _ => {}
+            }
+        }
+
+        self.flush()?;
+
+        Ok(messages)
+    }
+
+    /// Removes the first whole frame from the incoming bytes, returning
+    /// whether it's the final frame of its message, its opcode and its
+    /// unmasked payload.
+    fn take_frame(&mut self) -> io::Result<Option<(bool, u8, Vec<u8>)>> {
+        let bytes = &self.incoming;
+        if bytes.len() < 2 {
+            return Ok(None);
+        }
+
+        let fin = bytes[0] & 0x80 != 0;
+        let opcode = bytes[0] & 0x0F;
+        let masked = bytes[1] & 0x80 != 0;
+
+        let (payload_len, mut offset) = match bytes[1] & 0x7F {
+            126 if bytes.len() >= 4 => (u16::from_be_bytes([bytes[2], bytes[3]]) as usize, 4),
+            127 if bytes.len() >= 10 => {
+                let len = u64::from_be_bytes(bytes[2..10].try_into().unwrap());
+                (usize::try_from(len).unwrap_or(usize::MAX), 10)
+            }
+            126 | 127 => return Ok(None),
+            len => (len as usize, 2),
+        };
+        if payload_len > MAX_MESSAGE_LEN {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "frame too long"));
+        }
+
+        let mask = if masked {
+            let Some(mask) = bytes.get(offset..offset + 4) else {
+                return Ok(None);
+            };
+            offset += 4;
+            Some([mask[0], mask[1], mask[2], mask[3]])
+        } else {
+            None
+        };
+
+        let Some(payload) = bytes.get(offset..offset + payload_len) else {
+            return Ok(None);
+        };
+        let mut payload = payload.to_vec();
+        if let Some(mask) = mask {
+            apply_mask(&mut payload, mask);
+        }
+
+        self.incoming.drain(..offset + payload_len);
+
+        Ok(Some((fin, opcode, payload)))
+    }
+
+    fn queue_frame(&mut self, opcode: u8, payload: &[u8]) {
+        let frame = &mut self.outgoing;
+        frame.push(0x80 | opcode);
+
+        let mask_bit = if self.is_client { 0x80 } else { 0 };
+        match payload.len() {
+            len @ 0..=125 => frame.push(mask_bit | len as u8),
+            len @ 126..=0xFFFF => {
+                frame.push(mask_bit | 126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                frame.push(mask_bit | 127);
+                frame.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+
+        if self.is_client {
+            let mask: [u8; 4] = nonce()[..4].try_into().unwrap();
+            frame.extend_from_slice(&mask);
+
+            let start = frame.len();
+            frame.extend_from_slice(payload);
+            apply_mask(&mut frame[start..], mask);
+        } else {
+            frame.extend_from_slice(payload);
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(len) => {
+                    self.outgoing.drain(..len);
+                }
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A connection from a client whose handshake is still being received. It's
+/// read without blocking, so a client which never sends its handshake doesn't
+/// hold up the server.
+pub struct Handshake {
+    stream: TcpStream,
+    /// The request's headers so far.
+    headers: Vec<u8>,
+    started: Instant,
+}
+
+impl Handshake {
+    pub fn new(stream: TcpStream) -> io::Result<Self> {
+        // Accepted streams may not inherit the listener's non-blocking mode.
+        stream.set_nonblocking(true)?;
+
+        Ok(Self {
+            stream,
+            headers: Vec::new(),
+            started: Instant::now(),
+        })
+    }
+
+    /// Reads whatever of the client's request has arrived, returning whether
+    /// it's complete. Fails if the client closes the connection, or takes
+    /// longer than `HANDSHAKE_TIMEOUT`.
+    pub fn poll(&mut self) -> io::Result<bool> {
+        let mut byte = [0];
+
+        // Byte by byte, so nothing after the headers is read.
+        while !self.headers.ends_with(b"\r\n\r\n") {
+            if self.headers.len() > MAX_HEADERS_LEN {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "handshake headers too long",
+                ));
+            }
+
+            match self.stream.read(&mut byte) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(_) => self.headers.push(byte[0]),
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                    if self.started.elapsed() > HANDSHAKE_TIMEOUT {
+                        return Err(io::ErrorKind::TimedOut.into());
+                    }
+                    return Ok(false);
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Accepts the connection once `poll` has received the whole request. The
+    /// response is written by the connection's first `poll`.
+    pub fn accept(self) -> io::Result<WebSocket> {
+        let request = String::from_utf8(self.headers)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let key = header(&request, "sec-websocket-key")
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not a WebSocket request"))?;
+
+        let mut socket = WebSocket::new(self.stream, false)?;
+        socket.outgoing = format!(
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\
+             Connection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            accept_key(key)
+        )
+        .into_bytes();
+
+        Ok(socket)
+    }
+}
+
+fn apply_mask(payload: &mut [u8], mask: [u8; 4]) {
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+}
+
+/// Reads an HTTP request or response, up to the blank line ending its
+/// headers. The stream must be blocking.
+fn read_headers(stream: &mut TcpStream) -> io::Result<String> {
+    let mut headers = Vec::new();
+    let mut byte = [0];
+
+    // Byte by byte, so nothing after the headers is read.
+    while !headers.ends_with(b"\r\n\r\n") {
+        if headers.len() > MAX_HEADERS_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "handshake headers too long",
+            ));
+        }
+
+        stream.read_exact(&mut byte)?;
+        headers.push(byte[0]);
+    }
+
+    String::from_utf8(headers).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Returns the value of a header, whose name is case-insensitive.
+fn header<'a>(headers: &'a str, name: &str) -> Option<&'a str> {
+    headers.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then_some(value.trim())
+    })
+}
+
+/// Returns the `Sec-WebSocket-Accept` value for a client's key.
+fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{key}{ACCEPT_GUID}").as_bytes()))
+}
+
+/// Returns 16 bytes which differ between calls, for handshake keys and
+/// frame masks. They only need to be unpredictable to proxies, not secure.
+fn nonce() -> [u8; 16] {
+    static COUNT: AtomicU64 = AtomicU64::new(0);
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let count = COUNT.fetch_add(1, Ordering::Relaxed) as u128;
+
+    (nanos ^ (count << 64) ^ count.wrapping_mul(0x9E37_79B9_7F4A_7C15)).to_le_bytes()
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(group >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+/// SHA-1, which the handshake requires. It isn't used for anything which
+/// needs to be secure.
+fn sha1(message: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+    let mut padded = message.to_vec();
+    padded.push(0x80);
+    while padded.len() % 64 != 56 {
+        padded.push(0);
+    }
+    padded.extend_from_slice(&(message.len() as u64 * 8).to_be_bytes());
+
+    for block in padded.chunks_exact(64) {
+        let mut words = [0; 80];
+        for (word, bytes) in words.iter_mut().zip(block.chunks_exact(4)) {
+            *word = u32::from_be_bytes(bytes.try_into().unwrap());
+        }
+        for i in 16..80 {
+            words[i] = (words[i - 3] ^ words[i - 8] ^ words[i - 14] ^ words[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (i, &word) in words.iter().enumerate() {
+            let (f, k) = match i {
+                0..20 => ((b & c) | (!b & d), 0x5A827999),
+                20..40 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..60 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        for (value, new) in state.iter_mut().zip([a, b, c, d, e]) {
+            *value = value.wrapping_add(new);
+        }
+    }
+
+    let mut digest = [0; 20];
+    for (bytes, value) in digest.chunks_exact_mut(4).zip(state) {
+        bytes.copy_from_slice(&value.to_be_bytes());
+    }
+
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sha1_digests() {
+        let hex = |digest: [u8; 20]| {
+            digest
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<String>()
+        };
+
+        assert_eq!(hex(sha1(b"")), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        assert_eq!(
+            hex(sha1(b"abc")),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        // Padding spills into a second block.
+        assert_eq!(
+            hex(sha1(
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+            )),
+            "84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+        );
+    }
+
+    #[test]
+    fn base64_pads() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn handshake_without_blocking() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        // A connection which never sends its handshake doesn't block.
+        let _silent = TcpStream::connect(&address).unwrap();
+        let mut silent = Handshake::new(listener.accept().unwrap().0).unwrap();
+        assert!(!silent.poll().unwrap());
+
+        let server = std::thread::spawn(move || {
+            let mut handshake = Handshake::new(listener.accept().unwrap().0).unwrap();
+            while !handshake.poll().unwrap() {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+
+            let mut socket = handshake.accept().unwrap();
+            socket.send("hello");
+            loop {
+                let messages = socket.poll().unwrap();
+                if !messages.is_empty() {
+                    return messages;
+                }
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        });
+
+        let mut client = WebSocket::connect(&address).unwrap();
+        client.send("hi");
+        let messages = loop {
+            let messages = client.poll().unwrap();
+            if !messages.is_empty() {
+                break messages;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        };
+
+        assert_eq!(messages, ["hello"]);
+        assert_eq!(server.join().unwrap(), ["hi"]);
+    }
+
+    /// The example handshake of RFC 6455.
+    #[test]
+    fn accept_key_matches_rfc() {
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+}
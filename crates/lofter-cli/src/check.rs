@@ -0,0 +1,140 @@
+//! `lofter-cli check`, which validates a project's sketches, loft and mesh,
+//! so repositories of projects can gate merges on valid geometry.
+
+use std::path::Path;
+
+use lofter::{LoftOptions, Lofter, MeshIssue, export::IndexedMesh};
+use serde_json::{Value, json};
+
+use crate::project::Project;
+
+pub(crate) const USAGE: &str = "\
+Usage: lofter-cli check <PROJECT>
+
+Validates a project file's sketches, loft and mesh, printing the diagnostics
+as JSON, and exits with 1 if any are errors.
+
+Arguments:
+  <PROJECT>   A project TOML file
+
+Options:
+  -h, --help  Print this help";
+
+pub(crate) fn run(mut args: impl Iterator<Item = String>) -> i32 {
+    let path = match (args.next(), args.next()) {
+        (Some(arg), None) if arg == "-h" || arg == "--help" => {
+            println!("{USAGE}");
+            return 0;
+        }
+        (Some(path), None) if !path.starts_with('-') => path,
+        _ => {
+            eprintln!("error: a single project file is needed\n\n{USAGE}");
+            return 1;
+        }
+    };
+
+    let diagnostics = check(Path::new(&path));
+    let ok = diagnostics
+        .iter()
+        .all(|diagnostic| diagnostic["severity"] != "error");
+
+    let report = json!({
+        "project": path,
+        "ok": ok,
+        "diagnostics": diagnostics,
+    });
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&report).expect("JSON values always serialize")
+    );
+
+    if ok { 0 } else { 1 }
+}
+
+fn check(path: &Path) -> Vec<Value> {
+    let loaded = Project::read(path).and_then(|project| {
+        let lofter = project.input.load()?;
+        Ok((lofter, project.options))
+    });
+
+    match loaded {
+        Ok((mut lofter, options)) => check_lofter(&mut lofter, &options),
+        Err(err) => vec![diagnostic("error", "project", err, json!({}))],
+    }
+}
+
+fn check_lofter(lofter: &mut Lofter, options: &LoftOptions) -> Vec<Value> {
+    let mut diagnostics = Vec::new();
+
+    for sketch_index in 0..lofter.sketch_count() {
+        for issue in lofter.sketch_issues(sketch_index).unwrap_or_default() {
+            diagnostics.push(diagnostic(
+                "error",
+                "sketch",
+                issue,
+                json!({ "sketch": sketch_index }),
+            ));
+        }
+    }
+
+    let result = lofter.loft(options);
+    if result.is_empty() {
+        diagnostics.push(diagnostic(
+            "warning",
+            "loft",
+            "fewer than two sketches, so there is nothing to loft",
+            json!({}),
+        ));
+    }
+    for (pair, err) in result.errors() {
+        diagnostics.push(diagnostic(
+            "error",
+            "loft",
+            err,
+            json!({ "sketches": [pair, pair + 1] }),
+        ));
+    }
+
+    for change in &result.station_changes {
+        diagnostics.push(diagnostic(
+            "warning",
+            "loft",
+            format!(
+                "ruling edge {} changes too much, by {} and {} degrees",
+                change.ruling_edge, change.distance, change.angle
+            ),
+            json!({
+                "sketches": [change.sketch_pair, change.sketch_pair + 1],
+                "ruling_edge": change.ruling_edge,
+                "distance": change.distance,
+                "angle": change.angle,
+            }),
+        ));
+    }
+
+    for issue in IndexedMesh::from_lofter(lofter).issues() {
+        let location = match issue {
+            MeshIssue::NonFinitePosition(index) => json!({ "vertex": index }),
+            MeshIssue::DegenerateTriangle(index) => json!({ "triangle": index }),
+        };
+        diagnostics.push(diagnostic("error", "mesh", issue, location));
+    }
+
+    diagnostics
+}
+
+/// Returns a diagnostic, with the fields of `location` saying where the
+/// problem is, such as `"sketch"`.
+fn diagnostic(severity: &str, stage: &str, message: impl ToString, location: Value) -> Value {
+    let mut diagnostic = json!({
+        "severity": severity,
+        "stage": stage,
+        "message": message.to_string(),
+    });
+
+    if let (Value::Object(diagnostic), Value::Object(location)) = (&mut diagnostic, location) {
+        diagnostic.extend(location);
+    }
+
+    diagnostic
+}
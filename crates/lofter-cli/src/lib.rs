@@ -0,0 +1,507 @@
+//! The lofter command line tool, which exports lofts to mesh files, and
+//! checks project files.
+//!
+//! Custom formats are added by registering a `MeshExporter` with `Cli`, from
+//! a binary of your own:
+//!
+//! ```no_run
+//! # use lofter::export::Format;
+//! # let my_exporter = Format::Obj;
+//! let mut cli = lofter_cli::Cli::new();
+//! cli.register(Box::new(my_exporter));
+//!
+//! std::process::exit(cli.run(std::env::args().skip(1)));
+//! ```
+
+mod check;
+mod project;
+mod sha256;
+
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use lofter::{
+    Correspondence, LoftOptions, Lofter, Matching, Session, Template,
+    export::{self, CorrespondenceFormat, ExportMetadata, Format, MeshExporter, NumberFormat},
+};
+use serde_json::{Value, json};
+
+const USAGE: &str = "\
+Usage: lofter-cli [OPTIONS] [SESSION]
+       lofter-cli check <PROJECT>
+
+Lofts a session recorded by the visualizer, or a template, and exports its
+mesh in each format, along with a manifest.json describing the files. With
+check, validates a project file instead; see lofter-cli check --help.
+
+Arguments:
+  [SESSION]              A recorded session JSON file
+
+Options:
+  --template <NAME>      Start from a template instead: boat-hull, duct or
+                         wing-panel
+  --out <DIR>            The directory to export to [default: export]
+  --formats <LIST>       Comma-separated formats [default: obj]
+  --correspondences <FORMAT>
+                         Also export the ruling edges' ends on their sketches,
+                         as csv or json
+  --pins <FILE>          Keep the correspondences in a csv or json file, as
+                         exported with --correspondences, when matching
+  --max-angle <DEGREES>  The maximum radial edge angle [default: 50]
+  --matching <NAME>      polar or turning-angle [default: polar]
+  --feature-angle <DEGREES>
+                         Match features, where outlines turn by at least this
+                         within a short stretch, to each other first
+  --max-triangles-per-section <COUNT>
+                         Coarsen sections with more triangles than this
+  --max-candidates-per-vertex <COUNT>
+                         Only consider edges from each vertex to this many of
+                         the nearest vertices of the next sketch
+  --max-station-change-distance <DISTANCE>
+                         Warn about ruling edges whose ends are further apart
+                         than this, once their sketches' offset is taken away
+  --max-station-change-angle <DEGREES>
+                         Warn about ruling edges at a steeper angle than this
+                         to the line between their sketches
+  --precision <DIGITS>   The digits after the decimal separator in text
+                         formats [default: as many as needed]
+  --decimal-separator <CHAR>
+                         The decimal separator of numbers, including
+                         --max-angle and --pins, in OBJ, OFF and CSV files
+                         [default: .]
+  --separate-pairs       Export each sketch pair as its own object in OBJ
+                         files, and node in glTF files
+  -h, --help             Print this help
+
+Formats:";
+
+enum Input {
+    Session(PathBuf),
+    Template(Template),
+}
+
+impl Input {
+    fn load(&self) -> Result<Lofter, String> {
+        match self {
+            Input::Session(path) => {
+                let file = File::open(path).map_err(|err| format!("{}: {err}", path.display()))?;
+                let session: Session = serde_json::from_reader(BufReader::new(file))
+                    .map_err(|err| format!("{}: {err}", path.display()))?;
+
+                Ok(session.replay())
+            }
+            Input::Template(template) => Ok(Lofter::from_template(*template)),
+        }
+    }
+}
+
+struct Args {
+    input: Input,
+    out: PathBuf,
+    /// Indices into the registered exporters.
+    formats: Vec<usize>,
+    correspondences: Option<CorrespondenceFormat>,
+    options: LoftOptions,
+    numbers: NumberFormat,
+    separate_pairs: bool,
+}
+
+/// The command line tool, with the exporters its `--formats` can choose from.
+pub struct Cli {
+    exporters: Vec<Box<dyn MeshExporter>>,
+}
+
+impl Default for Cli {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Cli {
+    /// Returns a CLI with the built-in formats registered.
+    pub fn new() -> Self {
+        Self {
+            exporters: Format::ALL
+                .into_iter()
+                .map(|format| Box::new(format) as Box<dyn MeshExporter>)
+                .collect(),
+        }
+    }
+
+    /// Registers an exporter, replacing any registered with the same name.
+    pub fn register(&mut self, exporter: Box<dyn MeshExporter>) {
+        self.exporters
+            .retain(|registered| registered.name() != exporter.name());
+        self.exporters.push(exporter);
+    }
+
+    /// Runs the tool with the arguments, excluding the program name, and
+    /// returns the exit code.
+    pub fn run(&self, args: impl IntoIterator<Item = String>) -> i32 {
+        let mut args = args.into_iter().peekable();
+        if args.next_if(|arg| arg == "check").is_some() {
+            return check::run(args);
+        }
+
+        let args = match self.parse_args(args) {
+            Ok(Some(args)) => args,
+            Ok(None) => {
+                println!("{}", self.usage());
+                return 0;
+            }
+            Err(err) => {
+                eprintln!("error: {err}\n\n{}", self.usage());
+                return 1;
+            }
+        };
+
+        match self.export(&args) {
+            Ok(()) => 0,
+            Err(err) => {
+                eprintln!("error: {err}");
+                1
+            }
+        }
+    }
+
+    fn usage(&self) -> String {
+        let names: Vec<&str> = self
+            .exporters
+            .iter()
+            .map(|exporter| exporter.name())
+            .collect();
+
+        format!("{USAGE} {}", names.join(", "))
+    }
+
+    fn export(&self, args: &Args) -> Result<(), String> {
+        let mut lofter = args.input.load()?;
+
+        let result = lofter.loft(&args.options);
+        if result.is_empty() {
+            eprintln!("warning: fewer than two sketches, so there is nothing to loft");
+        }
+        for (pair, err) in result.errors() {
+            eprintln!("warning: sketches {pair} and {} failed: {err}", pair + 1);
+        }
+        for pair in 0..lofter.sketch_count().saturating_sub(1) {
+            let changes = result
+                .station_changes
+                .iter()
+                .filter(|change| change.sketch_pair == pair);
+            let (count, distance, angle) =
+                changes.fold((0, 0f32, 0f32), |(count, distance, angle), change| {
+                    (
+                        count + 1,
+                        distance.max(change.distance),
+                        angle.max(change.angle),
+                    )
+                });
+
+            if count > 0 {
+                eprintln!(
+                    "warning: sketches {pair} and {}: {count} ruling edges change too much, by up to {distance} and {angle} degrees",
+                    pair + 1
+                );
+            }
+        }
+
+        let exporters: Vec<&dyn MeshExporter> = args
+            .formats
+            .iter()
+            .map(|&index| self.exporters[index].as_ref())
+            .collect();
+        let metadata = ExportMetadata {
+            numbers: args.numbers,
+            separate_pairs: args.separate_pairs,
+            options: Some(args.options.clone()),
+            ..Default::default()
+        };
+
+        let paths = export::write_all_with(&lofter, &args.out, &exporters, &metadata)
+            .map_err(|err| format!("{}: {err}", args.out.display()))?;
+        for path in &paths {
+            println!("{}", path.display());
+        }
+
+        let correspondences_path = args
+            .correspondences
+            .map(|format| write_correspondences(&lofter, &args.out, format, args.numbers))
+            .transpose()?;
+        if let Some(path) = &correspondences_path {
+            println!("{}", path.display());
+        }
+
+        let manifest_path = args.out.join("manifest.json");
+        write_manifest(
+            &manifest_path,
+            args,
+            &exporters,
+            &lofter,
+            &paths,
+            correspondences_path.as_deref(),
+        )
+        .map_err(|err| format!("{}: {err}", manifest_path.display()))?;
+        println!("{}", manifest_path.display());
+
+        Ok(())
+    }
+
+    /// Returns `None` if help was requested.
+    fn parse_args(&self, mut args: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+        let mut session = None;
+        let mut template = None;
+        let mut out = PathBuf::from("export");
+        let mut formats = vec![self.exporter_index("obj")?];
+        let mut correspondences = None;
+        let mut options = LoftOptions::default();
+        let mut numbers = NumberFormat::default();
+        let mut separate_pairs = false;
+        // Parsed once the decimal separator is known.
+        let mut max_angle = None;
+        let mut pins = None;
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value"));
+
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "--template" => template = Some(parse_template(&value()?)?),
+                "--out" => out = value()?.into(),
+                "--formats" => {
+                    formats = value()?
+                        .split(',')
+                        .map(|name| self.exporter_index(name.trim()))
+                        .collect::<Result<_, _>>()?;
+                }
+                "--correspondences" => {
+                    let value = value()?;
+                    correspondences = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("unknown correspondence format {value:?}"))?,
+                    );
+                }
+                "--pins" => pins = Some(PathBuf::from(value()?)),
+                "--max-angle" => max_angle = Some(value()?),
+                "--matching" => {
+                    options.matching = match value()?.as_str() {
+                        "polar" => Matching::Polar,
+                        "turning-angle" => Matching::TurningAngle,
+                        other => return Err(format!("unknown matching {other:?}")),
+                    };
+                }
+                "--feature-angle" => {
+                    let value = value()?;
+                    options.feature_angle = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("invalid angle {value:?}"))?,
+                    );
+                }
+                "--max-triangles-per-section" => {
+                    let value = value()?;
+                    options.max_triangles_per_section = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("invalid triangle count {value:?}"))?,
+                    );
+                }
+                "--max-candidates-per-vertex" => {
+                    let value = value()?;
+                    options.max_candidates_per_vertex = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("invalid candidate count {value:?}"))?,
+                    );
+                }
+                "--max-station-change-distance" => {
+                    let value = value()?;
+                    options.max_station_change_distance = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("invalid distance {value:?}"))?,
+                    );
+                }
+                "--max-station-change-angle" => {
+                    let value = value()?;
+                    options.max_station_change_angle = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("invalid angle {value:?}"))?,
+                    );
+                }
+                "--precision" => {
+                    let value = value()?;
+                    numbers.precision = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("invalid precision {value:?}"))?,
+                    );
+                }
+                "--decimal-separator" => {
+                    let value = value()?;
+                    let mut chars = value.chars();
+                    numbers.decimal_separator = match (chars.next(), chars.next()) {
+                        (Some(separator), None) if !separator.is_whitespace() => separator,
+                        _ => return Err(format!("invalid decimal separator {value:?}")),
+                    };
+                }
+                "--separate-pairs" => separate_pairs = true,
+                _ if arg.starts_with('-') => return Err(format!("unknown option {arg}")),
+                _ if session.is_none() => session = Some(PathBuf::from(arg)),
+                _ => return Err(format!("unexpected argument {arg}")),
+            }
+        }
+
+        if let Some(value) = max_angle {
+            options.max_radial_edge_angle = numbers
+                .parse(&value)
+                .ok_or_else(|| format!("invalid angle {value:?}"))?;
+        }
+        if let Some(path) = pins {
+            options.pinned_correspondences = read_pins(&path, numbers)?;
+        }
+
+        let input = match (session, template) {
+            (Some(path), None) => Input::Session(path),
+            (None, Some(template)) => Input::Template(template),
+            (None, None) => return Err("a session or template is needed".into()),
+            (Some(_), Some(_)) => return Err("a session and template can't both be used".into()),
+        };
+
+        Ok(Some(Args {
+            input,
+            out,
+            formats,
+            correspondences,
+            options,
+            numbers,
+            separate_pairs,
+        }))
+    }
+
+    /// Finds a registered exporter by its name, or else its extension.
+    fn exporter_index(&self, name: &str) -> Result<usize, String> {
+        let find = |key: fn(&dyn MeshExporter) -> &str| {
+            self.exporters
+                .iter()
+                .position(|exporter| key(exporter.as_ref()).eq_ignore_ascii_case(name))
+        };
+
+        find(|exporter| exporter.name())
+            .or_else(|| find(|exporter| exporter.extension()))
+            .ok_or_else(|| format!("unknown format {name:?}"))
+    }
+}
+
+/// Writes the lofter's correspondences to "loft.correspondences.<extension>"
+/// in the directory, which `write_all_with` has created, returning the path.
+fn write_correspondences(
+    lofter: &Lofter,
+    directory: &Path,
+    format: CorrespondenceFormat,
+    numbers: NumberFormat,
+) -> Result<PathBuf, String> {
+    let path = directory.join(format!("loft.correspondences.{}", format.extension()));
+
+    let mut writer =
+        BufWriter::new(File::create(&path).map_err(|err| format!("{}: {err}", path.display()))?);
+    format
+        .write(&lofter.correspondences(), numbers, &mut writer)
+        .and_then(|()| writer.flush())
+        .map_err(|err| format!("{}: {err}", path.display()))?;
+
+    Ok(path)
+}
+
+/// Writes a manifest of the exported files, so build systems can verify them
+/// and tell when they're stale.
+fn write_manifest(
+    path: &Path,
+    args: &Args,
+    exporters: &[&dyn MeshExporter],
+    lofter: &Lofter,
+    paths: &[PathBuf],
+    correspondences_path: Option<&Path>,
+) -> Result<(), String> {
+    let triangle_count = lofter.triangle_count();
+
+    let files = paths
+        .iter()
+        .zip(exporters)
+        .map(|(path, exporter)| {
+            let contents = fs::read(path).map_err(|err| format!("{}: {err}", path.display()))?;
+
+            Ok(json!({
+                "path": path.file_name().map(|name| name.to_string_lossy()),
+                "format": exporter.name(),
+                "bytes": contents.len(),
+                "sha256": sha256::hex_digest(&contents),
+                "triangle_count": triangle_count,
+            }))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let correspondences = match (correspondences_path, args.correspondences) {
+        (Some(path), Some(format)) => {
+            let contents = fs::read(path).map_err(|err| format!("{}: {err}", path.display()))?;
+
+            json!({
+                "path": path.file_name().map(|name| name.to_string_lossy()),
+                "format": format.extension(),
+                "bytes": contents.len(),
+                "sha256": sha256::hex_digest(&contents),
+            })
+        }
+        _ => Value::Null,
+    };
+
+    let input = match &args.input {
+        Input::Session(path) => json!({ "session": path }),
+        Input::Template(template) => json!({ "template": template }),
+    };
+
+    let manifest = json!({
+        "input": input,
+        "options": args.options,
+        "numbers": args.numbers,
+        "files": files,
+        "correspondences": correspondences,
+    });
+
+    let contents = serde_json::to_string_pretty(&manifest).map_err(|err| err.to_string())?;
+    fs::write(path, contents + "\n").map_err(|err| err.to_string())
+}
+
+/// Reads correspondences to pin from a file written by `--correspondences`,
+/// in the format of its extension.
+fn read_pins(path: &Path, numbers: NumberFormat) -> Result<Vec<Correspondence>, String> {
+    let source = fs::read_to_string(path).map_err(|err| format!("{}: {err}", path.display()))?;
+
+    let extension = path.extension().and_then(|extension| extension.to_str());
+    let format: CorrespondenceFormat = extension
+        .unwrap_or_default()
+        .parse()
+        .map_err(|_| format!("{}: pins must be a csv or json file", path.display()))?;
+
+    match format {
+        CorrespondenceFormat::Csv => export::read_correspondences_csv(&source, numbers)
+            .map_err(|err| format!("{}: {err}", path.display())),
+        CorrespondenceFormat::Json => {
+            serde_json::from_str(&source).map_err(|err| format!("{}: {err}", path.display()))
+        }
+    }
+}
+
+fn parse_template(name: &str) -> Result<Template, String> {
+    match name {
+        "boat-hull" => Ok(Template::BoatHull),
+        "duct" => Ok(Template::Duct),
+        "wing-panel" => Ok(Template::WingPanel),
+        _ => Err(format!("unknown template {name:?}")),
+    }
+}
@@ -0,0 +1,5 @@
+use std::{env, process};
+
+fn main() {
+    process::exit(lofter_cli::Cli::new().run(env::args().skip(1)));
+}
@@ -0,0 +1,111 @@
+//! Project files, which describe a loft for `lofter-cli check` to validate,
+//! so they can be kept in version control alongside its session. Paths are
+//! relative to the project file:
+//!
+//! ```toml
+//! session = "hull.json" # or template = "boat-hull"
+//! pins = "hull-pins.csv" # correspondences to keep, as for --pins
+//!
+//! [options]
+//! max_angle = 50
+//! matching = "polar"
+//! feature_angle = 60
+//! max_triangles_per_section = 2000
+//! max_candidates_per_vertex = 8
+//! max_station_change_distance = 0.5
+//! max_station_change_angle = 30
+//! ```
+
+use std::{fs, path::Path};
+
+use lofter::{LoftOptions, Matching, export::NumberFormat};
+use toml_edit::{DocumentMut, Item};
+
+use crate::{Input, parse_template, read_pins};
+
+pub(crate) struct Project {
+    pub input: Input,
+    pub options: LoftOptions,
+}
+
+impl Project {
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let document: DocumentMut = text.parse().map_err(|err| format!("{err}"))?;
+        let directory = path.parent().unwrap_or(Path::new(""));
+
+        let mut session = None;
+        let mut template = None;
+        let mut options = LoftOptions::default();
+
+        for (key, item) in document.iter() {
+            match key {
+                "session" => session = Some(directory.join(string(key, item)?)),
+                "template" => template = Some(parse_template(string(key, item)?)?),
+                "pins" => {
+                    let path = directory.join(string(key, item)?);
+                    options.pinned_correspondences = read_pins(&path, NumberFormat::default())?;
+                }
+                "options" => {
+                    let table = item.as_table_like().ok_or("options must be a table")?;
+
+                    for (key, item) in table.iter() {
+                        read_option(&mut options, key, item)?;
+                    }
+                }
+                _ => return Err(format!("unknown key {key:?}")),
+            }
+        }
+
+        let input = match (session, template) {
+            (Some(path), None) => Input::Session(path),
+            (None, Some(template)) => Input::Template(template),
+            (None, None) => return Err("a session or template is needed".into()),
+            (Some(_), Some(_)) => return Err("a session and template can't both be used".into()),
+        };
+
+        Ok(Self { input, options })
+    }
+}
+
+fn read_option(options: &mut LoftOptions, key: &str, item: &Item) -> Result<(), String> {
+    match key {
+        "max_angle" => options.max_radial_edge_angle = number(key, item)?,
+        "matching" => {
+            options.matching = match string(key, item)? {
+                "polar" => Matching::Polar,
+                "turning-angle" => Matching::TurningAngle,
+                other => return Err(format!("unknown matching {other:?}")),
+            };
+        }
+        "feature_angle" => options.feature_angle = Some(number(key, item)?),
+        "max_triangles_per_section" => options.max_triangles_per_section = Some(count(key, item)?),
+        "max_candidates_per_vertex" => options.max_candidates_per_vertex = Some(count(key, item)?),
+        "max_station_change_distance" => {
+            options.max_station_change_distance = Some(number(key, item)?);
+        }
+        "max_station_change_angle" => options.max_station_change_angle = Some(number(key, item)?),
+        _ => return Err(format!("unknown option {key:?}")),
+    }
+
+    Ok(())
+}
+
+fn string<'a>(key: &str, item: &'a Item) -> Result<&'a str, String> {
+    item.as_str()
+        .ok_or_else(|| format!("{key} must be a string"))
+}
+
+/// Reads a float or integer.
+fn number(key: &str, item: &Item) -> Result<f32, String> {
+    item.as_float()
+        .or_else(|| item.as_integer().map(|value| value as f64))
+        .map(|value| value as f32)
+        .ok_or_else(|| format!("{key} must be a number"))
+}
+
+fn count(key: &str, item: &Item) -> Result<usize, String> {
+    item.as_integer()
+        .and_then(|value| value.try_into().ok())
+        .ok_or_else(|| format!("{key} must be a non-negative integer"))
+}
@@ -0,0 +1,171 @@
+use std::f32::consts::PI;
+
+use glam::{Vec2, Vec3};
+
+use crate::{error::AirfoilError, sketch::SketchDescriptor};
+
+/// Points closer than this, as a fraction of the chord, are duplicates.
+const DUPLICATE_TOLERANCE: f32 = 1e-5;
+
+impl SketchDescriptor {
+    /// Returns a NACA four-digit airfoil, such as "2412", with the given chord
+    /// along the x axis and roughly `points` vertices.
+    ///
+    /// Points are cosine-spaced along the chord, so they're densest at the
+    /// leading and trailing edges, and the trailing edge is closed. The origin
+    /// is on the profile's quarter chord, midway between its surfaces.
+    pub fn naca4(code: &str, chord: f32, points: usize) -> Result<Self, AirfoilError> {
+        let invalid_code = || AirfoilError::InvalidNacaCode(code.into());
+
+        let digits = code
+            .chars()
+            .map(|c| c.to_digit(10).map(|digit| digit as f32))
+            .collect::<Option<Vec<_>>>()
+            .filter(|digits| digits.len() == 4)
+            .ok_or_else(invalid_code)?;
+
+        let max_camber = digits[0] / 100.;
+        let max_camber_position = digits[1] / 10.;
+        let thickness = (digits[2] * 10. + digits[3]) / 100.;
+
+        if thickness == 0. || (max_camber > 0.) != (max_camber_position > 0.) {
+            return Err(invalid_code());
+        }
+
+        let thickness_at = |x: f32| {
+            5. * thickness
+                * (0.2969 * x.sqrt() - 0.1260 * x - 0.3516 * x.powi(2) + 0.2843 * x.powi(3)
+                    - 0.1036 * x.powi(4))
+        };
+
+        // Returns the mean camber line's height and slope.
+        let camber_at = |x: f32| {
+            let (m, p) = (max_camber, max_camber_position);
+
+            if m == 0. {
+                (0., 0.)
+            } else if x < p {
+                (
+                    m / p.powi(2) * (2. * p * x - x.powi(2)),
+                    2. * m / p.powi(2) * (p - x),
+                )
+            } else {
+                (
+                    m / (1. - p).powi(2) * (1. - 2. * p + 2. * p * x - x.powi(2)),
+                    2. * m / (1. - p).powi(2) * (p - x),
+                )
+            }
+        };
+
+        let surface_point = |x: f32, upper: bool| {
+            let half_thickness = thickness_at(x);
+            let (camber, slope) = camber_at(x);
+            let normal = Vec2::new(-slope.atan().sin(), slope.atan().cos());
+
+            let offset = if upper { normal } else { -normal } * half_thickness;
+
+            Vec2::new(x, camber) + offset
+        };
+
+        // Each surface has this many points, sharing the leading edge, and the
+        // trailing edge, which is closed.
+        let stations = (points / 2).max(2);
+        let station_x = |station: usize| (1. - (PI * station as f32 / stations as f32).cos()) / 2.;
+
+        // CCW, from the trailing edge over the upper surface.
+        let profile = (1..=stations)
+            .rev()
+            .map(|station| surface_point(station_x(station), true))
+            .chain(std::iter::once(Vec2::ZERO))
+            .chain((1..stations).map(|station| surface_point(station_x(station), false)))
+            .collect();
+
+        profile_descriptor(profile, chord)
+    }
+
+    /// Parses an airfoil in the Selig ".dat" format, as used by the UIUC
+    /// airfoil database, with the given chord along the x axis.
+    ///
+    /// The first line is the airfoil's name, and each following line is an x,
+    /// y pair normalized to the chord, running from the trailing edge over the
+    /// upper surface and back along the lower surface. The origin is placed as
+    /// for `naca4`.
+    pub fn from_selig(source: &str, chord: f32) -> Result<Self, AirfoilError> {
+        let mut profile = Vec::new();
+
+        for (line_index, line) in source.lines().enumerate().skip(1) {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let coordinates = line
+                .split_whitespace()
+                .map(|coordinate| {
+                    coordinate
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|value| value.is_finite())
+                })
+                .collect::<Option<Vec<_>>>()
+                .filter(|coordinates| coordinates.len() == 2)
+                .ok_or(AirfoilError::InvalidSeligLine(line_index + 1))?;
+
+            profile.push(Vec2::new(coordinates[0], coordinates[1]));
+        }
+
+        profile_descriptor(profile, chord)
+    }
+}
+
+/// Creates a sketch descriptor from a CCW airfoil profile, normalized to the
+/// chord.
+fn profile_descriptor(
+    mut profile: Vec<Vec2>,
+    chord: f32,
+) -> Result<SketchDescriptor, AirfoilError> {
+    profile.dedup_by(|a, b| a.distance(*b) < DUPLICATE_TOLERANCE);
+    // Selig files repeat the trailing edge.
+    if profile.len() > 1 && profile[0].distance(profile[profile.len() - 1]) < DUPLICATE_TOLERANCE {
+        profile.pop();
+    }
+
+    if profile.len() < 3 {
+        return Err(AirfoilError::TooFewPoints);
+    }
+
+    // Loft edges are matched by their angle about the origin, so it needs to
+    // be inside the profile.
+    let origin = quarter_chord_center(&profile);
+
+    Ok(SketchDescriptor {
+        vertices: profile
+            .into_iter()
+            .map(|point| ((point - origin) * chord).extend(0.))
+            .collect(),
+        relative_position: Vec3::ZERO,
+        rotation: Vec3::ZERO,
+    })
+}
+
+/// Returns the point on the profile's quarter chord midway between its
+/// surfaces.
+fn quarter_chord_center(profile: &[Vec2]) -> Vec2 {
+    let (min_x, max_x) = profile
+        .iter()
+        .fold((f32::MAX, f32::MIN), |(min, max), point| {
+            (min.min(point.x), max.max(point.x))
+        });
+    let x = min_x + (max_x - min_x) / 4.;
+
+    // The heights where the profile's edges cross the quarter chord.
+    let (min_y, max_y) = profile
+        .iter()
+        .zip(profile.iter().cycle().skip(1))
+        .filter(|(a, b)| (a.x <= x) != (b.x <= x))
+        .map(|(a, b)| a.lerp(*b, (x - a.x) / (b.x - a.x)).y)
+        .fold((f32::MAX, f32::MIN), |(min, max), y| {
+            (min.min(y), max.max(y))
+        });
+
+    Vec2::new(x, (min_y + max_y) / 2.)
+}
@@ -0,0 +1,268 @@
+//! Analyses of the loft's surface, for checking parts before they're 3D
+//! printed or molded.
+
+use std::collections::{HashMap, HashSet};
+
+use glam::Vec3;
+
+use crate::{CapFillet, DUPLICATE_VERTEX_TOLERANCE, Lofter, MeshPass, export::IndexedMesh};
+
+/// A connected region of the loft's surface thinner than the minimum, from
+/// `thin_regions`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ThinRegion {
+    /// Indices into the triangles of `IndexedMesh::from_lofter`, in ascending
+    /// order.
+    pub triangles: Vec<usize>,
+    /// The thickness at the region's thinnest triangle.
+    pub thickness: f32,
+}
+
+/// How a face drafts along a mold's pull direction, from `draft_angles`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DraftClass {
+    /// Faces along the pull direction by at least the minimum draft, so it
+    /// releases as the mold is pulled.
+    Positive,
+    /// Within the minimum draft of parallel to the pull direction, so it would
+    /// drag against the mold.
+    Insufficient,
+    /// Faces against the pull direction by at least the minimum draft, so it
+    /// releases from the opposite half of the mold, or is an undercut.
+    Negative,
+}
+
+/// A face's draft, from `draft_angles`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FaceDraft {
+    /// The angle between the face and the pull direction, in degrees from -90
+    /// to 90, positive when the face's normal is along the pull direction.
+    pub angle: f32,
+    pub class: DraftClass,
+}
+
+/// Returns the draft of each of the triangles of `IndexedMesh::from_lofter`
+/// along `pull_direction`, classified by whether it's drafted by at least
+/// `min_angle` degrees, such as for a heatmap of a molded part. Degenerate
+/// triangles have no draft.
+pub fn draft_angles(lofter: &Lofter, pull_direction: Vec3, min_angle: f32) -> Vec<FaceDraft> {
+    let mesh = IndexedMesh::from_lofter(lofter);
+    let pull_direction = pull_direction.normalize_or_zero();
+
+    mesh.triangles
+        .iter()
+        .map(|triangle| {
+            let [a, b, c] = triangle.map(|index| mesh.positions[index as usize]);
+            let normal = (b - a).cross(c - a).normalize_or_zero();
+            let angle = normal
+                .dot(pull_direction)
+                .clamp(-1., 1.)
+                .asin()
+                .to_degrees();
+
+            let class = if angle >= min_angle {
+                DraftClass::Positive
+            } else if angle <= -min_angle {
+                DraftClass::Negative
+            } else {
+                DraftClass::Insufficient
+            };
+
+            FaceDraft { angle, class }
+        })
+        .collect()
+}
+
+/// The overhangs of the loft's surface, from `overhangs`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OverhangReport {
+    /// The overhang of each of the triangles of `IndexedMesh::from_lofter`, in
+    /// degrees from vertical, from 0 for faces facing sideways or up to 90 for
+    /// faces facing straight down.
+    pub angles: Vec<f32>,
+    /// The share of the surface's area overhanging by more than the maximum
+    /// angle, from 0 to 1.
+    pub overhanging_fraction: f32,
+}
+
+/// Returns how far each face of the loft overhangs when printed along
+/// `build_direction`, and the share of the surface overhanging by more than
+/// `max_angle` degrees, which would need supports. Faces on the build plate,
+/// at the loft's lowest point along the build direction, don't overhang.
+pub fn overhangs(lofter: &Lofter, build_direction: Vec3, max_angle: f32) -> OverhangReport {
+    let mesh = IndexedMesh::from_lofter(lofter);
+    let build_direction = build_direction.normalize_or_zero();
+
+    let heights: Vec<f32> = mesh
+        .positions
+        .iter()
+        .map(|position| position.dot(build_direction))
+        .collect();
+    let plate = heights.iter().copied().fold(f32::INFINITY, f32::min);
+
+    let mut total_area = 0.;
+    let mut overhanging_area = 0.;
+
+    let angles = mesh
+        .triangles
+        .iter()
+        .map(|triangle| {
+            let [a, b, c] = triangle.map(|index| mesh.positions[index as usize]);
+            let cross = (b - a).cross(c - a);
+            let area = cross.length() / 2.;
+            total_area += area;
+
+            let on_plate = triangle
+                .iter()
+                .all(|&index| heights[index as usize] - plate <= DUPLICATE_VERTEX_TOLERANCE);
+            if on_plate {
+                return 0.;
+            }
+
+            let downwards = -cross.normalize_or_zero().dot(build_direction);
+            let angle = downwards.clamp(0., 1.).asin().to_degrees();
+            if angle > max_angle {
+                overhanging_area += area;
+            }
+
+            angle
+        })
+        .collect();
+
+    OverhangReport {
+        angles,
+        overhanging_fraction: if total_area > 0. {
+            overhanging_area / total_area
+        } else {
+            0.
+        },
+    }
+}
+
+/// Returns the regions of the loft's surface thinner than `min_thickness`,
+/// ordered by their first triangle.
+///
+/// The thickness at each triangle is sampled along a ray from its centroid
+/// into the loft, to the nearest surface on the far side. The loft is treated
+/// as solid, so its ends are capped first, as by `CapFillet` without a radius,
+/// and rays near the ends hit the caps. Regions are thin triangles connected by
+/// shared vertices.
+pub fn thin_regions(lofter: &Lofter, min_thickness: f32) -> Vec<ThinRegion> {
+    let mut mesh = IndexedMesh::from_lofter(lofter);
+    // Capping only appends triangles, so the wall's indices are unchanged.
+    let wall_triangles = mesh.triangles.len();
+    CapFillet {
+        radius: 0.,
+        segments: 1,
+    }
+    .apply(&mut mesh);
+
+    let triangles: Vec<[Vec3; 3]> = mesh
+        .triangles
+        .iter()
+        .map(|triangle| triangle.map(|index| mesh.positions[index as usize]))
+        .collect();
+    let bounds: Vec<(Vec3, f32)> = triangles.iter().map(|&triangle| sphere(triangle)).collect();
+
+    // Hits this close to the origin are the triangle's own neighbors.
+    let min_distance = min_thickness * 1e-3;
+
+    let thickness = |index: usize| {
+        let triangle = triangles[index];
+        let origin = triangle.iter().sum::<Vec3>() / 3.;
+        let inward = -(triangle[1] - triangle[0])
+            .cross(triangle[2] - triangle[0])
+            .try_normalize()?;
+
+        (0..triangles.len())
+            .filter(|&other| other != index)
+            .filter(|&other| {
+                let (center, radius) = bounds[other];
+                origin.distance(center) - radius < min_thickness
+            })
+            .filter_map(|other| ray_hit(origin, inward, triangles[other]))
+            .filter(|&distance| distance > min_distance)
+            .min_by(f32::total_cmp)
+            .filter(|&distance| distance < min_thickness)
+    };
+
+    let thin: HashMap<usize, f32> = (0..wall_triangles)
+        .filter_map(|index| Some((index, thickness(index)?)))
+        .collect();
+
+    // The thin triangles using each vertex.
+    let mut vertex_triangles: HashMap<u32, Vec<usize>> = HashMap::new();
+    for &index in thin.keys() {
+        for vertex in mesh.triangles[index] {
+            vertex_triangles.entry(vertex).or_default().push(index);
+        }
+    }
+
+    let mut starts: Vec<usize> = thin.keys().copied().collect();
+    starts.sort_unstable();
+
+    let mut assigned = HashSet::new();
+    let mut regions: Vec<ThinRegion> = Vec::new();
+
+    for start in starts {
+        if !assigned.insert(start) {
+            continue;
+        }
+
+        let mut region = ThinRegion {
+            triangles: Vec::new(),
+            thickness: f32::INFINITY,
+        };
+        let mut pending = vec![start];
+
+        while let Some(index) = pending.pop() {
+            region.triangles.push(index);
+            region.thickness = region.thickness.min(thin[&index]);
+
+            for vertex in mesh.triangles[index] {
+                for &neighbor in &vertex_triangles[&vertex] {
+                    if assigned.insert(neighbor) {
+                        pending.push(neighbor);
+                    }
+                }
+            }
+        }
+
+        region.triangles.sort_unstable();
+        regions.push(region);
+    }
+
+    regions
+}
+
+/// Returns a sphere bounding the triangle, about its centroid.
+fn sphere(triangle: [Vec3; 3]) -> (Vec3, f32) {
+    let center = triangle.iter().sum::<Vec3>() / 3.;
+    let radius = triangle
+        .iter()
+        .map(|&vertex| vertex.distance(center))
+        .fold(0., f32::max);
+
+    (center, radius)
+}
+
+/// Returns the distance along the ray to where it hits either side of the
+/// triangle, by the Möller-Trumbore algorithm.
+fn ray_hit(origin: Vec3, direction: Vec3, [a, b, c]: [Vec3; 3]) -> Option<f32> {
+    let (ab, ac) = (b - a, c - a);
+    let p = direction.cross(ac);
+    let determinant = ab.dot(p);
+    if determinant.abs() < f32::EPSILON {
+        return None;
+    }
+
+    let to_origin = origin - a;
+    let u = to_origin.dot(p) / determinant;
+    let q = to_origin.cross(ab);
+    let v = direction.dot(q) / determinant;
+    if u < 0. || v < 0. || u + v > 1. {
+        return None;
+    }
+
+    Some(ac.dot(q) / determinant).filter(|&distance| distance >= 0.)
+}
@@ -0,0 +1,25 @@
+use crate::sketch::VertexId;
+
+/// A point on a sketch's outline, `param` of the way along the edge from the
+/// vertex to the next vertex in CCW order. Sketch vertices have a `param` of
+/// 0.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SketchPoint {
+    pub vertex_id: VertexId,
+    pub param: f32,
+}
+
+/// The ends of a ruling edge on its two sketches, returned by
+/// `Lofter::correspondences`, which is what matching the sketches decided.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Correspondence {
+    /// The index of the lower sketch.
+    pub sketch_pair: usize,
+    /// The index of the edge's section within the pair. Sectionless lofts
+    /// have a single section.
+    pub section: usize,
+    pub lower: SketchPoint,
+    pub upper: SketchPoint,
+}
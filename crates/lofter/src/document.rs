@@ -0,0 +1,207 @@
+use std::collections::HashSet;
+
+use crate::{Command, LoftError, Lofter};
+
+/// Identifies an op by the client which applied it, and its place among that
+/// client's ops.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OpId {
+    pub client: u64,
+    /// Counts up from 0 for each op the client applies, including undone
+    /// ones, so IDs are never reused.
+    pub sequence: u64,
+}
+
+/// A command applied to a `LoftDocument`, as exchanged between clients.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Op {
+    pub id: OpId,
+    pub command: Command,
+}
+
+/// A lofter which is only mutated by applying ops, which are logged on top
+/// of the lofter it started from, so copies of the document edited by
+/// different clients can be merged.
+///
+/// The current lofter is always the base with the log replayed onto it. When
+/// merging or undoing changes the log, it's replayed again, and ops which no
+/// longer apply, such as moves of a vertex which was since removed, are
+/// dropped.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LoftDocument {
+    client: u64,
+    next_sequence: u64,
+    base: Lofter,
+    ops: Vec<Op>,
+    lofter: Lofter,
+}
+
+impl LoftDocument {
+    /// Creates a document starting from `base`, edited by `client`. Each
+    /// client editing a copy of the document needs its own ID.
+    pub fn new(base: Lofter, client: u64) -> Self {
+        Self {
+            client,
+            next_sequence: 0,
+            lofter: base.clone(),
+            base,
+            ops: Vec::new(),
+        }
+    }
+
+    /// Returns a copy of the document, with the same base and log, edited by
+    /// another client.
+    pub fn fork(&self, client: u64) -> Self {
+        Self {
+            client,
+            next_sequence: 0,
+            ..self.clone()
+        }
+    }
+
+    pub fn client(&self) -> u64 {
+        self.client
+    }
+
+    /// Returns the lofter with every op applied.
+    pub fn lofter(&self) -> &Lofter {
+        &self.lofter
+    }
+
+    /// Returns the lofter the ops are applied to.
+    pub fn base(&self) -> &Lofter {
+        &self.base
+    }
+
+    /// Returns the ops in the order they're applied.
+    pub fn ops(&self) -> &[Op] {
+        &self.ops
+    }
+
+    /// Applies a command as a new op of this document's client. Commands which
+    /// fail don't modify the lofter, and aren't logged.
+    pub fn apply(&mut self, command: Command) -> Result<OpId, LoftError> {
+        self.lofter.apply(&command)?;
+
+        let id = OpId {
+            client: self.client,
+            sequence: self.next_sequence,
+        };
+        self.next_sequence += 1;
+        self.ops.push(Op { id, command });
+
+        Ok(id)
+    }
+
+    /// Merges the log of another copy of the document. The other log's order is
+    /// kept, and this document's ops which aren't in it yet are rebased onto
+    /// the end of it, so copies which merge from the same log, such as a
+    /// server's, agree on the order. Returns the ops which no longer apply,
+    /// and were dropped.
+    pub fn merge(&mut self, ops: &[Op]) -> Vec<Op> {
+        // The other copy may have ops of this client from an earlier session.
+        if let Some(sequence) = ops
+            .iter()
+            .filter(|op| op.id.client == self.client)
+            .map(|op| op.id.sequence)
+            .max()
+        {
+            self.next_sequence = self.next_sequence.max(sequence + 1);
+        }
+
+        // Without ops of its own to rebase, only the new ops need applying.
+        if ops.starts_with(&self.ops) {
+            let new_ops = &ops[self.ops.len()..];
+            return self.append(new_ops);
+        }
+
+        let merged_ids: HashSet<OpId> = ops.iter().map(|op| op.id).collect();
+        let unmerged = self
+            .ops
+            .iter()
+            .filter(|op| !merged_ids.contains(&op.id))
+            .cloned();
+
+        let log = ops.iter().cloned().chain(unmerged).collect();
+
+        self.replay(log)
+    }
+
+    /// Applies ops from other copies of the document after this document's log,
+    /// skipping those it already has, as a server does with the ops its
+    /// clients send it. Returns the ops which don't apply, and were dropped.
+    pub fn append(&mut self, ops: &[Op]) -> Vec<Op> {
+        let mut ids: HashSet<OpId> = self.ops.iter().map(|op| op.id).collect();
+        let mut dropped = Vec::new();
+
+        for op in ops {
+            if !ids.insert(op.id) {
+                continue;
+            }
+
+            match self.lofter.apply(&op.command) {
+                Ok(()) => self.ops.push(op.clone()),
+                Err(_) => dropped.push(op.clone()),
+            }
+        }
+
+        dropped
+    }
+
+    /// Removes ops from the log, such as this client's ops which a server
+    /// dropped. Returns the ops which no longer apply without them, and were
+    /// also dropped.
+    pub fn discard(&mut self, ids: &[OpId]) -> Vec<Op> {
+        let log = self
+            .ops
+            .iter()
+            .filter(|op| !ids.contains(&op.id))
+            .cloned()
+            .collect();
+
+        self.replay(log)
+    }
+
+    /// Undoes the last op of this document's client, which may be before other
+    /// clients' ops. Returns the undone op, followed by later ops which no
+    /// longer apply without it and were dropped, or `None` if the client has
+    /// no ops to undo.
+    pub fn undo(&mut self) -> Option<Vec<Op>> {
+        let index = self
+            .ops
+            .iter()
+            .rposition(|op| op.id.client == self.client)?;
+
+        let mut log = self.ops.clone();
+        let undone = log.remove(index);
+
+        let mut removed = vec![undone];
+        removed.extend(self.replay(log));
+
+        Some(removed)
+    }
+
+    /// Replaces the log, rebuilding the lofter from the base. Returns the ops
+    /// which failed, which are left out of the log.
+    fn replay(&mut self, log: Vec<Op>) -> Vec<Op> {
+        let mut lofter = self.base.clone();
+        let mut dropped = Vec::new();
+
+        self.ops = log
+            .into_iter()
+            .filter_map(|op| match lofter.apply(&op.command) {
+                Ok(()) => Some(op),
+                Err(_) => {
+                    dropped.push(op);
+                    None
+                }
+            })
+            .collect();
+        self.lofter = lofter;
+
+        dropped
+    }
+}
@@ -0,0 +1,199 @@
+use std::fmt;
+
+use crate::sketch::VertexId;
+
+/// Errors returned by `Lofter` operations which fail without modifying the
+/// loft.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LoftError {
+    /// No sketch exists at the given index.
+    SketchNotFound(usize),
+    /// No vertex with the given ID exists in the sketch.
+    VertexNotFound {
+        sketch_index: usize,
+        vertex_id: VertexId,
+    },
+    /// The two vertices are not adjacent in the sketch.
+    VerticesNotAdjacent {
+        sketch_index: usize,
+        vertices: (VertexId, VertexId),
+    },
+    /// The sketch at the given index is locked, and rejects vertex and
+    /// transform mutations until it is unlocked.
+    SketchLocked(usize),
+    /// Sketches need at least three vertices, after merging duplicates, to
+    /// enclose an area.
+    TooFewVertices {
+        sketch_index: usize,
+        vertex_count: usize,
+    },
+    /// The fillet or chamfer doesn't fit the vertex's corner, since it would
+    /// reach the end of one of the corner's edges, or the corner is straight.
+    CornerTooSmall {
+        sketch_index: usize,
+        vertex_id: VertexId,
+    },
+    /// No lofted pair of sketches spans the world height, excluding the
+    /// heights of the sketches themselves.
+    NoLoftAtHeight(f32),
+    /// Lofting the profiles to blend them failed, such as from a non-finite
+    /// vertex.
+    BlendFailed(PairLoftError),
+}
+
+impl fmt::Display for LoftError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoftError::SketchNotFound(index) => write!(f, "no sketch at index {index}"),
+            LoftError::VertexNotFound {
+                sketch_index,
+                vertex_id,
+            } => write!(f, "no vertex {vertex_id} in sketch {sketch_index}"),
+            LoftError::VerticesNotAdjacent {
+                sketch_index,
+                vertices,
+            } => write!(
+                f,
+                "vertices {} and {} are not adjacent in sketch {sketch_index}",
+                vertices.0, vertices.1
+            ),
+            LoftError::SketchLocked(index) => write!(f, "sketch {index} is locked"),
+            LoftError::TooFewVertices {
+                sketch_index,
+                vertex_count,
+            } => write!(
+                f,
+                "sketch {sketch_index} has {vertex_count} distinct vertices, but needs at least 3"
+            ),
+            LoftError::CornerTooSmall {
+                sketch_index,
+                vertex_id,
+            } => write!(
+                f,
+                "the corner at vertex {vertex_id} in sketch {sketch_index} is too small to cut"
+            ),
+            LoftError::NoLoftAtHeight(z) => write!(f, "no loft spans height {z}"),
+            LoftError::BlendFailed(err) => write!(f, "blending the profiles failed: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for LoftError {}
+
+/// Errors returned when creating airfoil profiles.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AirfoilError {
+    /// The code isn't a valid NACA four-digit designation, such as "2412".
+    InvalidNacaCode(String),
+    /// The line, numbered from 1, of a Selig airfoil file isn't a pair of
+    /// finite coordinates.
+    InvalidSeligLine(usize),
+    /// The airfoil has fewer than three distinct points.
+    TooFewPoints,
+}
+
+impl fmt::Display for AirfoilError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AirfoilError::InvalidNacaCode(code) => write!(f, "invalid NACA 4-digit code {code:?}"),
+            AirfoilError::InvalidSeligLine(line) => {
+                write!(f, "line {line} of the Selig file isn't a coordinate pair")
+            }
+            AirfoilError::TooFewPoints => write!(f, "airfoils need at least three points"),
+        }
+    }
+}
+
+impl std::error::Error for AirfoilError {}
+
+/// Errors returned when building a wing with `WingBuilder`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WingError {
+    /// The airfoil was rejected as a sketch.
+    InvalidAirfoil(LoftError),
+    /// Wings need at least two stations.
+    TooFewStations,
+    /// The station at the given index isn't further along the span than the
+    /// previous station.
+    StationOutOfOrder(usize),
+}
+
+impl fmt::Display for WingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WingError::InvalidAirfoil(err) => write!(f, "invalid airfoil: {err}"),
+            WingError::TooFewStations => write!(f, "wings need at least two stations"),
+            WingError::StationOutOfOrder(index) => {
+                write!(
+                    f,
+                    "station {index} isn't further along the span than the last"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for WingError {}
+
+/// Why lofting a pair of sketches failed, as reported by `LoftResult`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PairLoftError {
+    /// A section of the loft has no edges, so its part of the surface can't
+    /// be built.
+    EmptySection(usize),
+    /// A loft vertex has a non-finite position, such as from a sketch vertex
+    /// at infinity.
+    NonFiniteVertex,
+    /// The correspondence, indexing `LoftOptions::pinned_correspondences`,
+    /// joins a vertex which doesn't exist, or crosses an earlier pin.
+    InvalidPin(usize),
+    /// The sketches' outlines can't be matched around each other, such as
+    /// when one doesn't enclose the z axis, which polar matching measures the
+    /// vertices' angles around.
+    UnmatchedOutlines,
+}
+
+impl fmt::Display for PairLoftError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PairLoftError::EmptySection(index) => write!(f, "section {index} has no edges"),
+            PairLoftError::NonFiniteVertex => write!(f, "a loft vertex has a non-finite position"),
+            PairLoftError::InvalidPin(index) => {
+                write!(f, "pinned correspondence {index} can't be kept")
+            }
+            PairLoftError::UnmatchedOutlines => {
+                write!(
+                    f,
+                    "the sketches' outlines can't be matched around each other"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for PairLoftError {}
+
+/// A name which isn't one of the export formats.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnknownFormat(pub String);
+
+impl fmt::Display for UnknownFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown export format {:?}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownFormat {}
+
+/// The line, numbered from 1, of a correspondence CSV file isn't a
+/// correspondence.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvalidCorrespondenceLine(pub usize);
+
+impl fmt::Display for InvalidCorrespondenceLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {} isn't a correspondence", self.0)
+    }
+}
+
+impl std::error::Error for InvalidCorrespondenceLine {}
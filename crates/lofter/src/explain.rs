@@ -0,0 +1,33 @@
+use glam::Vec3;
+
+use crate::sketch::VertexId;
+
+/// A decision made while lofting a pair of sketches, recorded by
+/// `Lofter::explain_loft` in the order the matcher made it.
+///
+/// `sketch_pair` is the index of the lower sketch of the pair.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LoftStep {
+    /// An edge candidate was accepted, splitting the section containing it,
+    /// or creating the first section.
+    CandidateAccepted {
+        sketch_pair: usize,
+        /// The lower and upper vertices of the edge.
+        vertices: (VertexId, VertexId),
+        /// In radians.
+        radial_error: f32,
+    },
+    /// An edge candidate was rejected, because its vertices lie in different
+    /// sections.
+    CandidateRejected {
+        sketch_pair: usize,
+        vertices: (VertexId, VertexId),
+        radial_error: f32,
+    },
+    /// A section's loft edges were built. These are world space line segments
+    /// from the lower to the upper sketch, like `Lofter::ruling_edges`.
+    SectionBuilt {
+        sketch_pair: usize,
+        ruling_edges: Vec<[Vec3; 2]>,
+    },
+}
@@ -0,0 +1,48 @@
+use std::io::{self, Write};
+
+use crate::export::{IndexedMesh, NumberFormat, Unit};
+
+/// Writes the mesh as an uncompressed AMF file, with each triangle colored by
+/// its loft section, like `write_3mf`.
+pub fn write_amf(
+    mesh: &IndexedMesh,
+    unit: Unit,
+    numbers: NumberFormat,
+    mut writer: impl Write,
+) -> io::Result<()> {
+    let colors = mesh.section_colors();
+    let numbers = numbers.with_decimal_point();
+
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(writer, r#"<amf unit="{}" version="1.1">"#, unit.name())?;
+    writeln!(writer, r#"  <object id="0">"#)?;
+    writeln!(writer, "    <mesh>")?;
+
+    writeln!(writer, "      <vertices>")?;
+    for position in &mesh.positions {
+        writeln!(
+            writer,
+            "        <vertex><coordinates><x>{}</x><y>{}</y><z>{}</z></coordinates></vertex>",
+            numbers.format(position.x),
+            numbers.format(position.y),
+            numbers.format(position.z)
+        )?;
+    }
+    writeln!(writer, "      </vertices>")?;
+
+    writeln!(writer, "      <volume>")?;
+    for ([v1, v2, v3], &section) in mesh.triangles.iter().zip(&mesh.triangle_sections) {
+        let color = colors[section];
+
+        writeln!(
+            writer,
+            "        <triangle><color><r>{}</r><g>{}</g><b>{}</b></color><v1>{v1}</v1><v2>{v2}</v2><v3>{v3}</v3></triangle>",
+            color.x, color.y, color.z
+        )?;
+    }
+    writeln!(writer, "      </volume>")?;
+
+    writeln!(writer, "    </mesh>")?;
+    writeln!(writer, "  </object>")?;
+    writeln!(writer, "</amf>")
+}
@@ -0,0 +1,185 @@
+use std::{
+    io::{self, Write},
+    str::FromStr,
+};
+
+use crate::{
+    Correspondence, SketchPoint,
+    error::{InvalidCorrespondenceLine, UnknownFormat},
+    export::NumberFormat,
+};
+
+/// A format for `Lofter::correspondences`, for tools such as CNC
+/// post-processors which need the matching rather than a mesh.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CorrespondenceFormat {
+    /// A row per correspondence, with the columns `sketch_pair`, `section`,
+    /// `lower_id`, `lower_param`, `upper_id` and `upper_param`.
+    Csv,
+    /// An array of the correspondences, as serde serializes them.
+    Json,
+}
+
+impl CorrespondenceFormat {
+    pub const ALL: [CorrespondenceFormat; 2] =
+        [CorrespondenceFormat::Csv, CorrespondenceFormat::Json];
+
+    /// The extension of exported files, without a dot, which is also the
+    /// format's name.
+    pub fn extension(self) -> &'static str {
+        match self {
+            CorrespondenceFormat::Csv => "csv",
+            CorrespondenceFormat::Json => "json",
+        }
+    }
+
+    /// Only CSV uses the number format.
+    pub fn write(
+        self,
+        correspondences: &[Correspondence],
+        numbers: NumberFormat,
+        writer: impl Write,
+    ) -> io::Result<()> {
+        match self {
+            CorrespondenceFormat::Csv => {
+                write_correspondences_csv(correspondences, numbers, writer)
+            }
+            CorrespondenceFormat::Json => write_correspondences_json(correspondences, writer),
+        }
+    }
+}
+
+impl FromStr for CorrespondenceFormat {
+    type Err = UnknownFormat;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.to_ascii_lowercase();
+
+        CorrespondenceFormat::ALL
+            .into_iter()
+            .find(|format| format.extension() == name)
+            .ok_or_else(|| UnknownFormat(s.into()))
+    }
+}
+
+/// Writes the correspondences as CSV, with a header row. Columns are
+/// separated by semicolons if the decimal separator is a comma, as
+/// spreadsheets in those locales expect.
+pub fn write_correspondences_csv(
+    correspondences: &[Correspondence],
+    numbers: NumberFormat,
+    mut writer: impl Write,
+) -> io::Result<()> {
+    let delimiter = csv_delimiter(numbers);
+
+    let header = [
+        "sketch_pair",
+        "section",
+        "lower_id",
+        "lower_param",
+        "upper_id",
+        "upper_param",
+    ];
+    writeln!(writer, "{}", header.join(delimiter))?;
+
+    for correspondence in correspondences {
+        let row = [
+            correspondence.sketch_pair.to_string(),
+            correspondence.section.to_string(),
+            correspondence.lower.vertex_id.to_string(),
+            numbers.format(correspondence.lower.param),
+            correspondence.upper.vertex_id.to_string(),
+            numbers.format(correspondence.upper.param),
+        ];
+        writeln!(writer, "{}", row.join(delimiter))?;
+    }
+
+    Ok(())
+}
+
+/// Writes the correspondences as a JSON array, which deserializes as a
+/// `Vec<Correspondence>` with the `serde` feature.
+pub fn write_correspondences_json(
+    correspondences: &[Correspondence],
+    mut writer: impl Write,
+) -> io::Result<()> {
+    writeln!(writer, "[")?;
+
+    for (index, correspondence) in correspondences.iter().enumerate() {
+        let comma = if index + 1 < correspondences.len() {
+            ","
+        } else {
+            ""
+        };
+
+        writeln!(
+            writer,
+            r#"  {{"sketch_pair":{},"section":{},"lower":{{"vertex_id":{},"param":{}}},"upper":{{"vertex_id":{},"param":{}}}}}{comma}"#,
+            correspondence.sketch_pair,
+            correspondence.section,
+            correspondence.lower.vertex_id,
+            correspondence.lower.param,
+            correspondence.upper.vertex_id,
+            correspondence.upper.param,
+        )?;
+    }
+
+    writeln!(writer, "]")
+}
+
+/// Parses correspondences written by `write_correspondences_csv` with the same
+/// number format, such as to pin them with
+/// `LoftOptions::pinned_correspondences`. The header row and blank lines are
+/// skipped.
+pub fn read_correspondences_csv(
+    source: &str,
+    numbers: NumberFormat,
+) -> Result<Vec<Correspondence>, InvalidCorrespondenceLine> {
+    let delimiter = csv_delimiter(numbers);
+    let mut correspondences = Vec::new();
+
+    for (line_index, line) in source.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with("sketch_pair") {
+            continue;
+        }
+
+        let invalid = || InvalidCorrespondenceLine(line_index + 1);
+        let fields: Vec<&str> = line.split(delimiter).map(str::trim).collect();
+        let [
+            sketch_pair,
+            section,
+            lower_id,
+            lower_param,
+            upper_id,
+            upper_param,
+        ] = fields[..]
+        else {
+            return Err(invalid());
+        };
+
+        let point = |vertex_id: &str, param: &str| {
+            Some(SketchPoint {
+                vertex_id: vertex_id.parse().ok()?,
+                param: numbers.parse(param)?,
+            })
+        };
+
+        correspondences.push(Correspondence {
+            sketch_pair: sketch_pair.parse().map_err(|_| invalid())?,
+            section: section.parse().map_err(|_| invalid())?,
+            lower: point(lower_id, lower_param).ok_or_else(invalid)?,
+            upper: point(upper_id, upper_param).ok_or_else(invalid)?,
+        });
+    }
+
+    Ok(correspondences)
+}
+
+fn csv_delimiter(numbers: NumberFormat) -> &'static str {
+    if numbers.decimal_separator == ',' {
+        ";"
+    } else {
+        ","
+    }
+}
@@ -0,0 +1,142 @@
+use std::io::{self, Write};
+
+use glam::Vec3;
+
+use crate::export::IndexedMesh;
+
+const GLB_MAGIC: u32 = 0x46546c67;
+const JSON_CHUNK: u32 = 0x4e4f534a;
+const BIN_CHUNK: u32 = 0x004e4942;
+
+/// Writes the mesh as a binary glTF (.glb) file. glTF is always read as
+/// meters, and is y-up, so the loft's z axis becomes y. With
+/// `separate_pairs`, each sketch pair is a node, named `pair_<index>`, with
+/// its own mesh.
+pub fn write_glb(
+    mesh: &IndexedMesh,
+    separate_pairs: bool,
+    mut writer: impl Write,
+) -> io::Result<()> {
+    let positions: Vec<Vec3> = mesh
+        .positions
+        .iter()
+        .map(|position| Vec3::new(position.x, position.z, -position.y))
+        .collect();
+
+    let mut binary = Vec::with_capacity(positions.len() * 12 + mesh.triangles.len() * 12);
+    for component in positions.iter().flat_map(|position| position.to_array()) {
+        binary.extend(component.to_le_bytes());
+    }
+    let positions_length = binary.len();
+
+    // The triangles of each node, of each pair with `separate_pairs`.
+    let mut groups: Vec<(Option<usize>, Vec<[u32; 3]>)> = Vec::new();
+    for (&triangle, pair) in mesh.triangles.iter().zip(mesh.triangle_pairs()) {
+        let pair = separate_pairs.then_some(pair);
+        match groups
+            .iter_mut()
+            .find(|(group_pair, _)| *group_pair == pair)
+        {
+            Some((_, triangles)) => triangles.push(triangle),
+            None => groups.push((pair, vec![triangle])),
+        }
+    }
+
+    let mut nodes = Vec::new();
+    let mut meshes = Vec::new();
+    let mut index_accessors = Vec::new();
+    for (node, (pair, triangles)) in groups.iter().enumerate() {
+        let name = pair.map_or_else(String::new, |pair| format!(r#","name":"pair_{pair}""#));
+        nodes.push(format!(r#"{{"mesh":{node}{name}}}"#));
+        meshes.push(format!(
+            r#"{{"primitives":[{{"attributes":{{"POSITION":0}},"indices":{}}}]{name}}}"#,
+            node + 1
+        ));
+        index_accessors.push(format!(
+            r#"{{"bufferView":1,"byteOffset":{},"componentType":5125,"count":{},"type":"SCALAR"}}"#,
+            binary.len() - positions_length,
+            triangles.len() * 3
+        ));
+
+        for index in triangles.iter().flatten() {
+            binary.extend(index.to_le_bytes());
+        }
+    }
+    let indices_length = binary.len() - positions_length;
+
+    // Accessors need at least one element, so empty meshes are an empty
+    // scene.
+    let mut json = if mesh.triangles.is_empty() {
+        r#"{"asset":{"version":"2.0","generator":"lofter"},"scene":0,"scenes":[{"nodes":[]}]}"#
+            .to_owned()
+    } else {
+        let (min, max) = positions.iter().fold(
+            (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+            |(min, max), position| (min.min(*position), max.max(*position)),
+        );
+
+        format!(
+            concat!(
+                r#"{{"asset":{{"version":"2.0","generator":"lofter"}},"#,
+                r#""scene":0,"scenes":[{{"nodes":[{node_indices}]}}],"nodes":[{nodes}],"#,
+                r#""meshes":[{meshes}],"#,
+                r#""buffers":[{{"byteLength":{buffer_length}}}],"#,
+                r#""bufferViews":["#,
+                r#"{{"buffer":0,"byteOffset":0,"byteLength":{positions_length},"target":34962}},"#,
+                r#"{{"buffer":0,"byteOffset":{positions_length},"byteLength":{indices_length},"target":34963}}],"#,
+                r#""accessors":["#,
+                r#"{{"bufferView":0,"componentType":5126,"count":{vertex_count},"type":"VEC3","min":[{},{},{}],"max":[{},{},{}]}},"#,
+                r#"{index_accessors}]}}"#,
+            ),
+            min.x,
+            min.y,
+            min.z,
+            max.x,
+            max.y,
+            max.z,
+            buffer_length = binary.len(),
+            positions_length = positions_length,
+            indices_length = indices_length,
+            vertex_count = positions.len(),
+            node_indices = (0..nodes.len())
+                .map(|node| node.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+            nodes = nodes.join(","),
+            meshes = meshes.join(","),
+            index_accessors = index_accessors.join(","),
+        )
+    };
+
+    // Chunks are padded to four bytes, JSON with spaces.
+    while !json.len().is_multiple_of(4) {
+        json.push(' ');
+    }
+    while !binary.len().is_multiple_of(4) {
+        binary.push(0);
+    }
+
+    let binary_chunk_length = if binary.is_empty() {
+        0
+    } else {
+        8 + binary.len()
+    };
+    let total_length = u32::try_from(12 + 8 + json.len() + binary_chunk_length)
+        .map_err(|_| io::Error::other("mesh too large for glTF"))?;
+
+    writer.write_all(&GLB_MAGIC.to_le_bytes())?;
+    writer.write_all(&2u32.to_le_bytes())?;
+    writer.write_all(&total_length.to_le_bytes())?;
+
+    writer.write_all(&(json.len() as u32).to_le_bytes())?;
+    writer.write_all(&JSON_CHUNK.to_le_bytes())?;
+    writer.write_all(json.as_bytes())?;
+
+    if !binary.is_empty() {
+        writer.write_all(&(binary.len() as u32).to_le_bytes())?;
+        writer.write_all(&BIN_CHUNK.to_le_bytes())?;
+        writer.write_all(&binary)?;
+    }
+
+    Ok(())
+}
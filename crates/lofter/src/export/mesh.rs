@@ -0,0 +1,179 @@
+use std::{collections::HashMap, iter};
+
+use glam::Vec3;
+
+use crate::{Lofter, MeshIssue, validate};
+
+/// The most vertices a `MeshChunk` can have, so its indices fit in 16 bits,
+/// leaving 0xFFFF free for engines which reserve it to restart strips.
+pub const MAX_CHUNK_VERTICES: usize = u16::MAX as usize;
+
+/// A loft's mesh, with the vertices shared between triangles merged, as
+/// written by the exporters.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IndexedMesh {
+    pub positions: Vec<Vec3>,
+    /// Triangles of indices into `positions`, CCW when viewed from outside
+    /// the loft.
+    pub triangles: Vec<[u32; 3]>,
+    /// The loft section each triangle is in, as from
+    /// `Lofter::triangle_sections`.
+    pub triangle_sections: Vec<usize>,
+    pub section_count: usize,
+    /// The index of the lower sketch of each section's sketch pair.
+    pub section_pairs: Vec<usize>,
+}
+
+impl IndexedMesh {
+    /// Builds the mesh of the loft's current surface, in world space. Vertices
+    /// are merged when their positions are identical, and then the post passes
+    /// of the options last lofted with are run.
+    pub fn from_lofter(lofter: &Lofter) -> Self {
+        let mut positions = Vec::new();
+        let mut indices = HashMap::new();
+
+        let triangles = lofter
+            .vertex_buffer()
+            .iter()
+            .map(|triangle| {
+                triangle.map(|[position, _]| {
+                    // Adding zero turns -0 into 0, so they're merged.
+                    let key = (position + Vec3::ZERO).to_array().map(f32::to_bits);
+
+                    *indices.entry(key).or_insert_with(|| {
+                        positions.push(position);
+                        (positions.len() - 1) as u32
+                    })
+                })
+            })
+            .collect();
+
+        let triangle_sections = lofter.triangle_sections();
+        let section_count = triangle_sections.iter().max().map_or(0, |max| max + 1);
+        let section_pairs = lofter
+            .loft_maps
+            .iter()
+            .enumerate()
+            .flat_map(|(pair, loft_map)| iter::repeat_n(pair, loft_map.section_count()))
+            .collect();
+
+        let mut mesh = Self {
+            positions,
+            triangles,
+            triangle_sections,
+            section_count,
+            section_pairs,
+        };
+
+        for pass in &lofter.post_passes {
+            pass.apply(&mut mesh);
+        }
+
+        mesh
+    }
+
+    /// Splits the mesh into chunks of at most `max_vertices` vertices,
+    /// clamped to 3 to `MAX_CHUNK_VERTICES`, for engines limited to 16-bit
+    /// indices. Triangles stay in order, and vertices shared between chunks
+    /// are duplicated.
+    pub fn chunks(&self, max_vertices: usize) -> Vec<MeshChunk> {
+        let max_vertices = max_vertices.clamp(3, MAX_CHUNK_VERTICES);
+        let mut chunks = Vec::new();
+        let mut chunk = MeshChunk::default();
+        // The chunk's index of each of the mesh's vertices in it.
+        let mut indices = HashMap::new();
+
+        for (triangle, &section) in self.triangles.iter().zip(&self.triangle_sections) {
+            let new_vertices = triangle
+                .iter()
+                .filter(|index| !indices.contains_key(*index))
+                .count();
+
+            if chunk.positions.len() + new_vertices > max_vertices {
+                chunks.push(std::mem::take(&mut chunk));
+                indices.clear();
+            }
+
+            let triangle = triangle.map(|index| {
+                *indices.entry(index).or_insert_with(|| {
+                    let position = self.positions[index as usize];
+                    chunk.add_position(position);
+                    (chunk.positions.len() - 1) as u16
+                })
+            });
+
+            chunk.triangles.push(triangle);
+            chunk.triangle_sections.push(section);
+        }
+
+        if !chunk.triangles.is_empty() {
+            chunks.push(chunk);
+        }
+
+        chunks
+    }
+
+    /// Checks the mesh for vertices and triangles which would break importers
+    /// or renderers, returning an empty list for a valid mesh.
+    pub fn issues(&self) -> Vec<MeshIssue> {
+        validate::mesh_issues(&self.positions, &self.triangles)
+    }
+
+    /// Returns the sketch pair of each triangle, from its section.
+    pub fn triangle_pairs(&self) -> Vec<usize> {
+        self.triangle_sections
+            .iter()
+            .map(|&section| self.section_pairs.get(section).copied().unwrap_or_default())
+            .collect()
+    }
+
+    /// Returns a distinct RGB color for each section, which stays the same
+    /// between exports.
+    pub fn section_colors(&self) -> Vec<Vec3> {
+        (0..self.section_count).map(section_color).collect()
+    }
+}
+
+/// Part of an `IndexedMesh`, with 16-bit indices, returned by
+/// `IndexedMesh::chunks`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MeshChunk {
+    pub positions: Vec<Vec3>,
+    /// Triangles of indices into `positions`, CCW when viewed from outside
+    /// the loft.
+    pub triangles: Vec<[u16; 3]>,
+    /// The loft section each triangle is in, as in the whole mesh.
+    pub triangle_sections: Vec<usize>,
+    /// The minimum corner of the chunk's bounding box.
+    pub min: Vec3,
+    /// The maximum corner of the chunk's bounding box.
+    pub max: Vec3,
+}
+
+impl MeshChunk {
+    fn add_position(&mut self, position: Vec3) {
+        if self.positions.is_empty() {
+            (self.min, self.max) = (position, position);
+        } else {
+            self.min = self.min.min(position);
+            self.max = self.max.max(position);
+        }
+
+        self.positions.push(position);
+    }
+}
+
+/// Spaces the hues of consecutive sections by the golden angle, so
+/// neighboring sections contrast however many there are.
+fn section_color(section: usize) -> Vec3 {
+    const GOLDEN_RATIO_CONJUGATE: f32 = 0.618_034;
+
+    let hue = (section as f32 * GOLDEN_RATIO_CONJUGATE).fract() * 6.;
+    let channel = |offset: f32| {
+        let distance = ((hue + offset) % 6. - 3.).abs();
+
+        (distance - 1.).clamp(0., 1.) * 0.7 + 0.2
+    };
+
+    Vec3::new(channel(0.), channel(4.), channel(2.))
+}
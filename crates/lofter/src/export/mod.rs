@@ -0,0 +1,300 @@
+//! Writing lofts to mesh files, from an `IndexedMesh` shared by all formats.
+
+mod amf;
+mod correspondence;
+mod gltf;
+mod mesh;
+mod obj;
+mod off;
+mod stl;
+mod stream;
+mod three_mf;
+mod zip;
+
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+pub use amf::write_amf;
+pub use correspondence::{
+    CorrespondenceFormat, read_correspondences_csv, write_correspondences_csv,
+    write_correspondences_json,
+};
+pub use gltf::write_glb;
+pub use mesh::{IndexedMesh, MAX_CHUNK_VERTICES, MeshChunk};
+pub use obj::write_obj;
+pub use off::write_off;
+pub use stl::write_stl;
+pub use stream::{stream_obj, stream_stl};
+pub use three_mf::write_3mf;
+
+use glam::Vec3;
+
+use crate::{LoftOptions, Lofter, error::UnknownFormat};
+
+/// The unit of length which a mesh's coordinates are in, for formats which
+/// record one.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Unit {
+    Micron,
+    #[default]
+    Millimeter,
+    Centimeter,
+    Inch,
+    Foot,
+    Meter,
+}
+
+impl Unit {
+    /// Returns the unit's name, as used by 3MF and AMF.
+    pub fn name(self) -> &'static str {
+        match self {
+            Unit::Micron => "micron",
+            Unit::Millimeter => "millimeter",
+            Unit::Centimeter => "centimeter",
+            Unit::Inch => "inch",
+            Unit::Foot => "foot",
+            Unit::Meter => "meter",
+        }
+    }
+}
+
+/// How the text formats write numbers, for consumers which can't read long
+/// numbers, or expect a locale's decimal separator.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NumberFormat {
+    /// The digits written after the decimal separator, or `None` for the
+    /// fewest digits which read back exactly.
+    pub precision: Option<usize>,
+    /// Ignored by the XML formats, 3MF and AMF, which always use a point.
+    pub decimal_separator: char,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self {
+            precision: None,
+            decimal_separator: '.',
+        }
+    }
+}
+
+impl NumberFormat {
+    /// Returns a format with the given digits after a decimal point, which
+    /// shrinks the text formats' files for dense lofts.
+    pub fn with_precision(precision: usize) -> Self {
+        Self {
+            precision: Some(precision),
+            ..Default::default()
+        }
+    }
+
+    pub fn format(self, value: f32) -> String {
+        let text = match self.precision {
+            Some(precision) => format!("{value:.precision$}"),
+            None => value.to_string(),
+        };
+
+        if self.decimal_separator == '.' {
+            text
+        } else {
+            text.replace('.', &self.decimal_separator.to_string())
+        }
+    }
+
+    /// Parses a number written with this format's decimal separator, or with
+    /// a point.
+    pub fn parse(self, text: &str) -> Option<f32> {
+        text.trim()
+            .replace(self.decimal_separator, ".")
+            .parse()
+            .ok()
+    }
+
+    /// Formats a position's coordinates, separated by spaces.
+    pub(crate) fn format_position(self, position: Vec3) -> String {
+        position
+            .to_array()
+            .map(|coordinate| self.format(coordinate))
+            .join(" ")
+    }
+
+    /// Returns this format with a point as its decimal separator, as XML
+    /// requires.
+    pub(crate) fn with_decimal_point(self) -> Self {
+        Self {
+            decimal_separator: '.',
+            ..self
+        }
+    }
+}
+
+/// Information about an export, besides the mesh, which exporters may record.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExportMetadata {
+    /// The name of exported files, without an extension.
+    pub name: String,
+    pub unit: Unit,
+    pub numbers: NumberFormat,
+    /// The options the loft was built with, if they're known.
+    pub options: Option<LoftOptions>,
+    /// Whether each sketch pair's surface, with any caps, is written as a
+    /// separate object named `pair_<index>`, by the lower sketch's index, so
+    /// it can be hidden or given a material on its own. OBJ writes objects,
+    /// and glTF nodes, while the other formats ignore this.
+    pub separate_pairs: bool,
+}
+
+impl Default for ExportMetadata {
+    fn default() -> Self {
+        Self {
+            name: "loft".into(),
+            unit: Unit::default(),
+            numbers: NumberFormat::default(),
+            options: None,
+            separate_pairs: false,
+        }
+    }
+}
+
+/// Writes meshes in a file format. Implementing this adds a custom format,
+/// which can be exported alongside the built-in `Format`s, such as by
+/// registering it with the CLI.
+pub trait MeshExporter {
+    /// The name the format is chosen by, such as in the CLI's `--formats`.
+    fn name(&self) -> &str;
+
+    /// The extension of exported files, without a dot.
+    fn extension(&self) -> &str;
+
+    fn write(
+        &self,
+        mesh: &IndexedMesh,
+        metadata: &ExportMetadata,
+        writer: &mut dyn Write,
+    ) -> io::Result<()>;
+}
+
+/// A built-in mesh file format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Format {
+    Obj,
+    Stl,
+    /// Binary glTF.
+    Gltf,
+    ThreeMf,
+    Amf,
+    Off,
+}
+
+impl Format {
+    pub const ALL: [Format; 6] = [
+        Format::Obj,
+        Format::Stl,
+        Format::Gltf,
+        Format::ThreeMf,
+        Format::Amf,
+        Format::Off,
+    ];
+}
+
+impl MeshExporter for Format {
+    fn name(&self) -> &str {
+        match self {
+            Format::Gltf => "gltf",
+            _ => self.extension(),
+        }
+    }
+
+    fn extension(&self) -> &str {
+        match self {
+            Format::Obj => "obj",
+            Format::Stl => "stl",
+            Format::Gltf => "glb",
+            Format::ThreeMf => "3mf",
+            Format::Amf => "amf",
+            Format::Off => "off",
+        }
+    }
+
+    /// The metadata's unit is recorded by formats which support one, and its
+    /// number format is used by the text formats.
+    fn write(
+        &self,
+        mesh: &IndexedMesh,
+        metadata: &ExportMetadata,
+        writer: &mut dyn Write,
+    ) -> io::Result<()> {
+        match self {
+            Format::Obj => write_obj(mesh, metadata.numbers, metadata.separate_pairs, writer),
+            Format::Stl => write_stl(mesh, writer),
+            Format::Gltf => write_glb(mesh, metadata.separate_pairs, writer),
+            Format::ThreeMf => write_3mf(mesh, metadata.unit, metadata.numbers, writer),
+            Format::Amf => write_amf(mesh, metadata.unit, metadata.numbers, writer),
+            Format::Off => write_off(mesh, metadata.numbers, writer),
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = UnknownFormat;
+
+    /// Parses a format from its name or extension.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.to_ascii_lowercase();
+
+        Format::ALL
+            .into_iter()
+            .find(|format| format.name() == name || format.extension() == name)
+            .ok_or_else(|| UnknownFormat(s.into()))
+    }
+}
+
+/// Writes the loft's mesh to "loft.<extension>" in the directory for each
+/// format, creating the directory if needed, and returns the paths written.
+/// Formats which record a unit are written in millimeters.
+pub fn write_all(
+    lofter: &Lofter,
+    directory: impl AsRef<Path>,
+    formats: &[Format],
+) -> io::Result<Vec<PathBuf>> {
+    let exporters: Vec<&dyn MeshExporter> = formats
+        .iter()
+        .map(|format| format as &dyn MeshExporter)
+        .collect();
+
+    write_all_with(lofter, directory, &exporters, &ExportMetadata::default())
+}
+
+/// Writes the loft's mesh with each exporter, like `write_all`, to files
+/// named by the metadata.
+pub fn write_all_with(
+    lofter: &Lofter,
+    directory: impl AsRef<Path>,
+    exporters: &[&dyn MeshExporter],
+    metadata: &ExportMetadata,
+) -> io::Result<Vec<PathBuf>> {
+    let directory = directory.as_ref();
+    fs::create_dir_all(directory)?;
+
+    let mesh = IndexedMesh::from_lofter(lofter);
+
+    exporters
+        .iter()
+        .map(|exporter| {
+            let path = directory.join(format!("{}.{}", metadata.name, exporter.extension()));
+
+            let mut writer = BufWriter::new(File::create(&path)?);
+            exporter.write(&mesh, metadata, &mut writer)?;
+            writer.flush()?;
+
+            Ok(path)
+        })
+        .collect()
+}
@@ -0,0 +1,48 @@
+use std::io::{self, Write};
+
+use crate::export::{IndexedMesh, NumberFormat};
+
+/// Writes the mesh as a Wavefront OBJ file, with a group for each loft
+/// section. With `separate_pairs`, each sketch pair's sections are grouped
+/// into an object named `pair_<index>`.
+pub fn write_obj(
+    mesh: &IndexedMesh,
+    numbers: NumberFormat,
+    separate_pairs: bool,
+    mut writer: impl Write,
+) -> io::Result<()> {
+    for position in &mesh.positions {
+        writeln!(writer, "v {}", numbers.format_position(*position))?;
+    }
+
+    let triangle_pairs = mesh.triangle_pairs();
+    let mut order: Vec<usize> = (0..mesh.triangles.len()).collect();
+    if separate_pairs {
+        // Passes may append triangles, such as caps, after every pair's
+        // walls, so triangles are sorted to keep each object together.
+        order.sort_by_key(|&index| triangle_pairs[index]);
+    }
+
+    let mut object = None;
+    let mut group = None;
+    for index in order {
+        let pair = triangle_pairs[index];
+        if separate_pairs && object != Some(pair) {
+            writeln!(writer, "o pair_{pair}")?;
+            object = Some(pair);
+            group = None;
+        }
+
+        let section = mesh.triangle_sections[index];
+        if group != Some(section) {
+            writeln!(writer, "g section_{section}")?;
+            group = Some(section);
+        }
+
+        // OBJ indices start at 1.
+        let [a, b, c] = mesh.triangles[index];
+        writeln!(writer, "f {} {} {}", a + 1, b + 1, c + 1)?;
+    }
+
+    Ok(())
+}
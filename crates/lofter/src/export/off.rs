@@ -0,0 +1,28 @@
+use std::io::{self, Write};
+
+use crate::export::{IndexedMesh, NumberFormat};
+
+/// Writes the mesh in the Object File Format, as read by many mesh processing
+/// tools. OFF has no units or colors.
+pub fn write_off(
+    mesh: &IndexedMesh,
+    numbers: NumberFormat,
+    mut writer: impl Write,
+) -> io::Result<()> {
+    writeln!(writer, "OFF")?;
+    writeln!(
+        writer,
+        "{} {} 0",
+        mesh.positions.len(),
+        mesh.triangles.len()
+    )?;
+
+    for position in &mesh.positions {
+        writeln!(writer, "{}", numbers.format_position(*position))?;
+    }
+    for [a, b, c] in &mesh.triangles {
+        writeln!(writer, "3 {a} {b} {c}")?;
+    }
+
+    Ok(())
+}
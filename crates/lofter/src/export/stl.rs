@@ -0,0 +1,44 @@
+use std::io::{self, Write};
+
+use glam::Vec3;
+
+use crate::export::IndexedMesh;
+
+/// Writes the mesh as a binary STL file. STL has no units, but is usually
+/// read as millimeters.
+pub fn write_stl(mesh: &IndexedMesh, mut writer: impl Write) -> io::Result<()> {
+    write_header(&mut writer, mesh.triangles.len())?;
+
+    for triangle in &mesh.triangles {
+        write_triangle(
+            &mut writer,
+            triangle.map(|index| mesh.positions[index as usize]),
+        )?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn write_header(mut writer: impl Write, triangle_count: usize) -> io::Result<()> {
+    let triangle_count = u32::try_from(triangle_count)
+        .map_err(|_| io::Error::other("too many triangles for STL"))?;
+
+    let mut header = [0; 80];
+    let name = b"lofter";
+    header[..name.len()].copy_from_slice(name);
+
+    writer.write_all(&header)?;
+    writer.write_all(&triangle_count.to_le_bytes())
+}
+
+pub(crate) fn write_triangle(mut writer: impl Write, [a, b, c]: [Vec3; 3]) -> io::Result<()> {
+    let normal = (b - a).cross(c - a).normalize_or_zero();
+
+    for vector in [normal, a, b, c] {
+        for component in vector.to_array() {
+            writer.write_all(&component.to_le_bytes())?;
+        }
+    }
+    // Attribute byte count.
+    writer.write_all(&0u16.to_le_bytes())
+}
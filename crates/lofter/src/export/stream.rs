@@ -0,0 +1,62 @@
+//! Writers which stream a loft's triangles straight from the lofter, for
+//! lofts too large to hold as an `IndexedMesh`.
+
+use std::io::{self, Write};
+
+use glam::Vec3;
+
+use crate::{
+    Lofter,
+    export::{NumberFormat, stl},
+};
+
+/// Writes the loft like `write_obj`, a triangle at a time, without building
+/// its mesh. Vertices aren't merged, and post passes aren't run. Each
+/// triangle's face refers to the vertices written just before it, with
+/// negative indices.
+pub fn stream_obj(
+    lofter: &Lofter,
+    numbers: NumberFormat,
+    mut writer: impl Write,
+) -> io::Result<()> {
+    let mut group = None;
+
+    for_each_triangle(lofter, |triangle, section| {
+        if group != Some(section) {
+            writeln!(writer, "g section_{section}")?;
+            group = Some(section);
+        }
+
+        for position in triangle {
+            writeln!(writer, "v {}", numbers.format_position(position))?;
+        }
+
+        writeln!(writer, "f -3 -2 -1")
+    })
+}
+
+/// Writes the loft like `write_stl`, a triangle at a time, without building
+/// its mesh. Post passes aren't run.
+pub fn stream_stl(lofter: &Lofter, mut writer: impl Write) -> io::Result<()> {
+    stl::write_header(&mut writer, lofter.triangle_count())?;
+
+    for_each_triangle(lofter, |triangle, _| {
+        stl::write_triangle(&mut writer, triangle)
+    })
+}
+
+/// Calls `f` with each triangle and its section, stopping at the first error.
+fn for_each_triangle(
+    lofter: &Lofter,
+    mut f: impl FnMut([Vec3; 3], usize) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut result = Ok(());
+
+    lofter.for_each_world_triangle(|triangle, section| {
+        if result.is_ok() {
+            result = f(triangle, section);
+        }
+    });
+
+    result
+}
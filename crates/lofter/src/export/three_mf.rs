@@ -0,0 +1,106 @@
+use std::{
+    fmt::Write as _,
+    io::{self, Write},
+};
+
+use glam::Vec3;
+
+use crate::export::{IndexedMesh, NumberFormat, Unit, zip};
+
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">
+  <Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>
+  <Default Extension="model" ContentType="application/vnd.ms-package.3dmanufacturing-3dmodel+xml"/>
+</Types>
+"#;
+
+const RELATIONSHIPS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+  <Relationship Target="/3D/3dmodel.model" Id="rel0" Type="http://schemas.microsoft.com/3dmanufacturing/2013/01/3dmodel"/>
+</Relationships>
+"#;
+
+/// Writes the mesh as a 3MF package. Each loft section's triangles are given
+/// their own color from a color group, so slicers can tell the sections
+/// apart.
+pub fn write_3mf(
+    mesh: &IndexedMesh,
+    unit: Unit,
+    numbers: NumberFormat,
+    writer: impl Write,
+) -> io::Result<()> {
+    let model = model_xml(mesh, unit, numbers.with_decimal_point());
+
+    zip::write_stored(
+        writer,
+        &[
+            ("[Content_Types].xml", CONTENT_TYPES.as_bytes()),
+            ("_rels/.rels", RELATIONSHIPS.as_bytes()),
+            ("3D/3dmodel.model", model.as_bytes()),
+        ],
+    )
+}
+
+fn model_xml(mesh: &IndexedMesh, unit: Unit, numbers: NumberFormat) -> String {
+    // Resource IDs.
+    const COLOR_GROUP: u32 = 1;
+    const OBJECT: u32 = 2;
+
+    let mut xml = String::new();
+
+    // Writing to a string can't fail.
+    let _ = writeln!(xml, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = writeln!(
+        xml,
+        r#"<model unit="{}" xml:lang="en-US" xmlns="http://schemas.microsoft.com/3dmanufacturing/core/2015/02" xmlns:m="http://schemas.microsoft.com/3dmanufacturing/material/2015/02">"#,
+        unit.name()
+    );
+    let _ = writeln!(xml, "  <resources>");
+
+    let _ = writeln!(xml, r#"    <m:colorgroup id="{COLOR_GROUP}">"#);
+    for color in mesh.section_colors() {
+        let _ = writeln!(xml, r#"      <m:color color="{}"/>"#, hex_color(color));
+    }
+    let _ = writeln!(xml, "    </m:colorgroup>");
+
+    let _ = writeln!(xml, r#"    <object id="{OBJECT}" type="model">"#);
+    let _ = writeln!(xml, "      <mesh>");
+    let _ = writeln!(xml, "        <vertices>");
+    for position in &mesh.positions {
+        let _ = writeln!(
+            xml,
+            r#"          <vertex x="{}" y="{}" z="{}"/>"#,
+            numbers.format(position.x),
+            numbers.format(position.y),
+            numbers.format(position.z)
+        );
+    }
+    let _ = writeln!(xml, "        </vertices>");
+    let _ = writeln!(xml, "        <triangles>");
+    for (&[v1, v2, v3], section) in mesh.triangles.iter().zip(&mesh.triangle_sections) {
+        let _ = writeln!(
+            xml,
+            r#"          <triangle v1="{v1}" v2="{v2}" v3="{v3}" pid="{COLOR_GROUP}" p1="{section}"/>"#
+        );
+    }
+    let _ = writeln!(xml, "        </triangles>");
+    let _ = writeln!(xml, "      </mesh>");
+    let _ = writeln!(xml, "    </object>");
+
+    let _ = writeln!(xml, "  </resources>");
+    let _ = writeln!(xml, "  <build>");
+    let _ = writeln!(xml, r#"    <item objectid="{OBJECT}"/>"#);
+    let _ = writeln!(xml, "  </build>");
+    let _ = writeln!(xml, "</model>");
+
+    xml
+}
+
+/// Formats a color with channels from 0 to 1 as "#RRGGBB".
+fn hex_color(color: Vec3) -> String {
+    let [r, g, b] = color
+        .to_array()
+        .map(|channel| (channel.clamp(0., 1.) * 255.).round() as u8);
+
+    format!("#{r:02X}{g:02X}{b:02X}")
+}
@@ -0,0 +1,79 @@
+use std::io::{self, Write};
+
+/// The MS-DOS date of 1980-01-01, the earliest zip files can record.
+const DOS_EPOCH_DATE: u16 = 0x21;
+
+/// Writes a zip archive of the files, as `(name, contents)`, without
+/// compression.
+pub fn write_stored(mut writer: impl Write, files: &[(&str, &[u8])]) -> io::Result<()> {
+    let mut central_directory = Vec::new();
+    let mut offset = 0;
+
+    for (name, contents) in files {
+        let (Ok(size), Ok(name_length), Ok(header_offset)) = (
+            u32::try_from(contents.len()),
+            u16::try_from(name.len()),
+            u32::try_from(offset),
+        ) else {
+            return Err(io::Error::other("file too large for a zip archive"));
+        };
+        let crc = crc32fast::hash(contents);
+
+        // Fields shared by the local header and the central directory, from
+        // the version needed to extract up to the file name's length.
+        let mut common = Vec::with_capacity(26);
+        common.extend(20u16.to_le_bytes());
+        common.extend(0u16.to_le_bytes()); // Flags.
+        common.extend(0u16.to_le_bytes()); // Stored.
+        common.extend(0u16.to_le_bytes()); // Time.
+        common.extend(DOS_EPOCH_DATE.to_le_bytes());
+        common.extend(crc.to_le_bytes());
+        common.extend(size.to_le_bytes()); // Compressed size.
+        common.extend(size.to_le_bytes());
+        common.extend(name_length.to_le_bytes());
+
+        let mut local_header = Vec::with_capacity(30 + name.len());
+        local_header.extend(0x04034b50u32.to_le_bytes());
+        local_header.extend(&common);
+        local_header.extend(0u16.to_le_bytes()); // Extra field length.
+        local_header.extend(name.as_bytes());
+
+        writer.write_all(&local_header)?;
+        writer.write_all(contents)?;
+
+        central_directory.extend(0x02014b50u32.to_le_bytes());
+        central_directory.extend(20u16.to_le_bytes()); // Version made by.
+        central_directory.extend(&common);
+        central_directory.extend(0u16.to_le_bytes()); // Extra field length.
+        central_directory.extend(0u16.to_le_bytes()); // Comment length.
+        central_directory.extend(0u16.to_le_bytes()); // Disk number.
+        central_directory.extend(0u16.to_le_bytes()); // Internal attributes.
+        central_directory.extend(0u32.to_le_bytes()); // External attributes.
+        central_directory.extend(header_offset.to_le_bytes());
+        central_directory.extend(name.as_bytes());
+
+        offset += local_header.len() + contents.len();
+    }
+
+    let (Ok(file_count), Ok(directory_size), Ok(directory_offset)) = (
+        u16::try_from(files.len()),
+        u32::try_from(central_directory.len()),
+        u32::try_from(offset),
+    ) else {
+        return Err(io::Error::other("too much data for a zip archive"));
+    };
+
+    writer.write_all(&central_directory)?;
+
+    let mut end = Vec::with_capacity(22);
+    end.extend(0x06054b50u32.to_le_bytes());
+    end.extend(0u16.to_le_bytes()); // Disk number.
+    end.extend(0u16.to_le_bytes()); // Disk with the central directory.
+    end.extend(file_count.to_le_bytes()); // Files on this disk.
+    end.extend(file_count.to_le_bytes());
+    end.extend(directory_size.to_le_bytes());
+    end.extend(directory_offset.to_le_bytes());
+    end.extend(0u16.to_le_bytes()); // Comment length.
+
+    writer.write_all(&end)
+}
@@ -0,0 +1,92 @@
+use glam::Vec2;
+
+use crate::sketch::{Sketch, VertexId};
+
+/// The share of a sketch's perimeter over which its outline's turning is
+/// summed for each vertex, so a corner rounded by several short edges turns as
+/// sharply as a single vertex.
+const FEATURE_WINDOW: f32 = 0.05;
+
+/// Returns the sketch's feature vertices, such as corners, in CCW order.
+///
+/// A vertex is a feature if the outline turns by at least `min_angle`, in
+/// radians, within `FEATURE_WINDOW` of the perimeter centered on it. Each run
+/// of consecutive such vertices is a single feature, at the vertex where the
+/// outline turns most, unless every vertex is sharp, when each is a feature.
+/// Vertices excluded from matching aren't features.
+pub(crate) fn feature_vertices(sketch: &Sketch, min_angle: f32) -> Vec<VertexId> {
+    let ids = &sketch.vertex_order;
+    let count = ids.len();
+    let positions: Vec<Vec2> = ids
+        .iter()
+        .map(|id| sketch.vertex_map[id].truncate())
+        .collect();
+
+    let at = |i: usize| positions[i % count];
+    // The length of the edge from each vertex to the next.
+    let lengths: Vec<f32> = (0..count).map(|i| at(i).distance(at(i + 1))).collect();
+    let turns: Vec<f32> = (0..count)
+        .map(|i| (at(i + count) - at(i + count - 1)).angle_to(at(i + 1) - at(i)))
+        .map(|turn| if turn.is_finite() { turn } else { 0. })
+        .collect();
+
+    let half_window = lengths.iter().sum::<f32>() * FEATURE_WINDOW / 2.;
+
+    let windowed_turns: Vec<f32> = (0..count)
+        .map(|i| {
+            let mut turn = turns[i];
+
+            // Walk forwards, then backwards, while within the window.
+            let mut distance = 0.;
+            for step in 1..count {
+                distance += lengths[(i + step - 1) % count];
+                if distance > half_window {
+                    break;
+                }
+                turn += turns[(i + step) % count];
+            }
+
+            let mut distance = 0.;
+            for step in 1..count {
+                distance += lengths[(i + count - step) % count];
+                if distance > half_window {
+                    break;
+                }
+                turn += turns[(i + count - step) % count];
+            }
+
+            turn.abs()
+        })
+        .collect();
+
+    let is_sharp = |i: usize| windowed_turns[i % count] >= min_angle;
+    let is_candidate = sketch.matching_filter();
+
+    // Start from a vertex which isn't sharp, so runs don't wrap around.
+    let Some(start) = (0..count).find(|&i| !is_sharp(i)) else {
+        // Every vertex is a corner, such as of a square.
+        return ids.iter().copied().filter(|&id| is_candidate(id)).collect();
+    };
+
+    let mut features = Vec::new();
+    let mut run: Option<usize> = None;
+
+    for i in (start + 1..=start + count).map(|i| i % count) {
+        if is_sharp(i) {
+            run = match run {
+                Some(sharpest) if windowed_turns[sharpest] >= windowed_turns[i] => Some(sharpest),
+                _ => Some(i),
+            };
+        } else if let Some(sharpest) = run.take() {
+            features.push(sharpest);
+        }
+    }
+
+    features.sort_unstable();
+
+    features
+        .into_iter()
+        .map(|i| ids[i])
+        .filter(|&id| is_candidate(id))
+        .collect()
+}
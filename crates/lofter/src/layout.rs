@@ -0,0 +1,103 @@
+use glam::{Vec2, Vec3};
+
+/// The attributes of each vertex of `Lofter::layout_vertex_buffer`,
+/// interleaved in the order they're named as 32-bit floats, so GPU consumers
+/// can take their vertex buffer layouts from `stride` and the offsets rather
+/// than assuming them.
+///
+/// - `position`: the world position, 3 floats.
+/// - `normal`: the triangle's unit normal, facing out of the loft, 3 floats.
+///   Triangles are flat shaded, so each of their vertices has the same normal.
+/// - `uv`: `u` is the distance around the vertex's sketch from its first
+///   vertex in CCW order, as a share of the sketch's perimeter, and `v` is the
+///   sketch's index as a share of the last sketch's, 2 floats. `u` wraps from 1
+///   to 0 at each sketch's first vertex, so the triangles across the seam span
+///   the whole texture.
+/// - `color`: the section's RGB color, as in `Lofter::vertex_buffer`, 3 floats.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum OutputLayout {
+    /// A stride of 12 bytes.
+    Position,
+    /// The layout of `Lofter::vertex_buffer`, with a stride of 24 bytes.
+    #[default]
+    PositionColor,
+    /// A stride of 24 bytes.
+    PositionNormal,
+    /// A stride of 44 bytes.
+    PositionNormalUvColor,
+}
+
+impl OutputLayout {
+    pub const ALL: [OutputLayout; 4] = [
+        OutputLayout::Position,
+        OutputLayout::PositionColor,
+        OutputLayout::PositionNormal,
+        OutputLayout::PositionNormalUvColor,
+    ];
+
+    /// The number of floats in each vertex.
+    pub const fn floats_per_vertex(self) -> usize {
+        match self {
+            OutputLayout::Position => 3,
+            OutputLayout::PositionColor | OutputLayout::PositionNormal => 6,
+            OutputLayout::PositionNormalUvColor => 11,
+        }
+    }
+
+    /// The number of bytes from each vertex to the next.
+    pub const fn stride(self) -> usize {
+        self.floats_per_vertex() * size_of::<f32>()
+    }
+
+    /// The byte offset of the position in each vertex.
+    pub const fn position_offset(self) -> usize {
+        0
+    }
+
+    /// The byte offset of the normal in each vertex, if the layout has one.
+    pub const fn normal_offset(self) -> Option<usize> {
+        match self {
+            OutputLayout::Position | OutputLayout::PositionColor => None,
+            OutputLayout::PositionNormal | OutputLayout::PositionNormalUvColor => Some(12),
+        }
+    }
+
+    /// The byte offset of the UV in each vertex, if the layout has one.
+    pub const fn uv_offset(self) -> Option<usize> {
+        match self {
+            OutputLayout::PositionNormalUvColor => Some(24),
+            _ => None,
+        }
+    }
+
+    /// The byte offset of the color in each vertex, if the layout has one.
+    pub const fn color_offset(self) -> Option<usize> {
+        match self {
+            OutputLayout::PositionColor => Some(12),
+            OutputLayout::PositionNormalUvColor => Some(32),
+            _ => None,
+        }
+    }
+
+    /// Appends a vertex's attributes in the layout, skipping those it doesn't
+    /// have.
+    pub(crate) fn push_vertex(
+        self,
+        buffer: &mut Vec<f32>,
+        position: Vec3,
+        normal: Vec3,
+        uv: Vec2,
+        color: Vec3,
+    ) {
+        buffer.extend(position.to_array());
+        if self.normal_offset().is_some() {
+            buffer.extend(normal.to_array());
+        }
+        if self.uv_offset().is_some() {
+            buffer.extend(uv.to_array());
+        }
+        if self.color_offset().is_some() {
+            buffer.extend(color.to_array());
+        }
+    }
+}
@@ -1,45 +1,256 @@
-use std::iter::zip;
+use std::{collections::HashSet, iter::zip, sync::Arc};
 
 use glam::Vec3;
 
-pub use crate::sketch::SketchDescriptor;
+/// The version of glam the API uses, so apps on another version can name its
+/// types, and convert them with the `mint` feature.
+pub use glam;
+#[cfg(feature = "mint")]
+pub use mint;
+
+#[cfg(feature = "profiling")]
+pub use crate::profiling::{LoftProfile, PairProfile};
+pub use crate::{
+    correspondence::{Correspondence, SketchPoint},
+    document::{LoftDocument, Op, OpId},
+    error::{
+        AirfoilError, InvalidCorrespondenceLine, LoftError, PairLoftError, UnknownFormat, WingError,
+    },
+    explain::LoftStep,
+    layout::OutputLayout,
+    loft::LocalLoftVertex,
+    matching::Matching,
+    pass::{CapFillet, Decimate, MeshPass, Smooth, Weld},
+    profile::Superellipse,
+    session::{Command, Session, TimedCommand},
+    sketch::{
+        DUPLICATE_VERTEX_TOLERANCE, MAX_FILLET_SEGMENTS, MirrorAxis, SketchDescriptor, VertexId,
+    },
+    slice::{MAX_SLICE_LAYERS, SliceLayer},
+    stats::SessionStats,
+    strategy::{DefaultMatching, MatchingPair, MatchingStrategy},
+    template::Template,
+    validate::{MeshIssue, SketchIssue, StationChange},
+    wing::{WingBuilder, WingStation},
+};
 use crate::{
+    export::IndexedMesh,
     loft::{Loft, LoftBuilder},
-    sketch::{Sketch, VertexId},
-    util::{SketchPair, radial_error},
+    matching::MatchingAngles,
+    profiling::timed,
+    session::Recording,
+    sketch::{CornerCut, Sketch},
+    util::SketchPair,
 };
 
+mod airfoil;
+pub mod analysis;
+mod correspondence;
+mod document;
+mod error;
+mod explain;
+pub mod export;
+mod feature;
+mod layout;
 mod loft;
+mod matching;
+mod pass;
+mod profile;
+mod profiling;
+mod session;
 mod sketch;
+mod slice;
+mod stats;
+mod strategy;
+mod template;
 mod util;
+mod validate;
+mod wing;
 
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LoftOptions {
     /// In degrees.
     pub max_radial_edge_angle: f32,
+    pub matching: Matching,
+    /// Sections with more triangles than this are coarsened, by replacing
+    /// ruling edges which end partway along a sketch edge with edges between
+    /// sketch vertices, for predictable output sizes. Sections can't be
+    /// coarsened below one triangle per sketch vertex, so this isn't a hard
+    /// limit.
+    pub max_triangles_per_section: Option<usize>,
+    /// Each lower vertex is only a candidate for ruling edges to this many of
+    /// the upper vertices, those with the lowest scores, which by default are
+    /// the angularly nearest. This bounds the candidates sorted for large
+    /// profiles. At least one upper vertex is always a candidate.
+    pub max_candidates_per_vertex: Option<usize>,
+    /// Ruling edges whose ends are further apart than this in the plane of
+    /// their sketches, once the sketches' offset is taken away, are reported
+    /// in `LoftResult::station_changes`, as a sign of an outlier sketch.
+    pub max_station_change_distance: Option<f32>,
+    /// Like `max_station_change_distance`, but for the angle in degrees
+    /// between a ruling edge and the line between its sketches' origins.
+    pub max_station_change_angle: Option<f32>,
+    /// Correspondences to keep, such as hand-corrected ones read from an
+    /// exported file. Those joining two sketch vertices split their pair into
+    /// sections before any candidate is tried, so matching only fills in the
+    /// edges between them. Others are ignored, as they follow from the
+    /// sections. Pins with missing vertices, or which cross an earlier pin,
+    /// fail their pair with `PairLoftError::InvalidPin`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub pinned_correspondences: Vec<Correspondence>,
+    /// If set, features such as corners, where a sketch's outline turns by
+    /// at least this many degrees within a short stretch, are matched to each
+    /// other before any other candidates, so a square lofts corner to corner
+    /// with a rounded square. See `Lofter::feature_vertices`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub feature_angle: Option<f32>,
+    /// Run in order on the generated mesh, when it's built with
+    /// `IndexedMesh::from_lofter`. These aren't serialized, so they aren't
+    /// part of recorded sessions.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub post_passes: Vec<Arc<dyn MeshPass>>,
 }
 
 impl Default for LoftOptions {
     fn default() -> Self {
         Self {
             max_radial_edge_angle: 50.,
+            matching: Matching::Polar,
+            max_triangles_per_section: None,
+            max_candidates_per_vertex: None,
+            max_station_change_distance: None,
+            max_station_change_angle: None,
+            pinned_correspondences: Vec::new(),
+            feature_angle: None,
+            post_passes: Vec::new(),
         }
     }
 }
 
+impl PartialEq for LoftOptions {
+    /// Post passes are equal when they're the same instances.
+    fn eq(&self, other: &Self) -> bool {
+        self.max_radial_edge_angle == other.max_radial_edge_angle
+            && self.matching == other.matching
+            && self.max_triangles_per_section == other.max_triangles_per_section
+            && self.max_candidates_per_vertex == other.max_candidates_per_vertex
+            && self.max_station_change_distance == other.max_station_change_distance
+            && self.max_station_change_angle == other.max_station_change_angle
+            && self.pinned_correspondences == other.pinned_correspondences
+            && self.feature_angle == other.feature_angle
+            && self.post_passes.len() == other.post_passes.len()
+            && zip(&self.post_passes, &other.post_passes).all(|(a, b)| Arc::ptr_eq(a, b))
+    }
+}
+
+impl LoftOptions {
+    /// Returns the pins of a pair which join two sketch vertices, with their
+    /// indices in `pinned_correspondences`, without repeats.
+    fn vertex_pins(&self, sketch_pair: usize) -> Vec<(usize, SketchPair<VertexId>)> {
+        let mut pins: Vec<(usize, SketchPair<VertexId>)> = Vec::new();
+
+        for (index, pin) in self.pinned_correspondences.iter().enumerate() {
+            let vertices = SketchPair::new(pin.lower, pin.upper);
+            if pin.sketch_pair != sketch_pair || vertices.iter().any(|point| point.param != 0.) {
+                continue;
+            }
+
+            let vertices = vertices.map(|point| point.vertex_id);
+            if pins.iter().all(|(_, pinned)| *pinned != vertices) {
+                pins.push((index, vertices));
+            }
+        }
+
+        pins
+    }
+}
+
+/// The outcome of lofting each pair of adjacent sketches, returned by
+/// `Lofter::loft`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoftResult {
+    /// Indexed by the pair's lower sketch.
+    pub pairs: Vec<Result<(), PairLoftError>>,
+    /// The lower sketch indices of failed pairs which kept their previous
+    /// loft, rather than being left without a surface.
+    pub kept_previous: Vec<usize>,
+    /// Ruling edges which exceed the options' station change limits, in the
+    /// order of `Lofter::ruling_edges`.
+    pub station_changes: Vec<StationChange>,
+}
+
+impl LoftResult {
+    pub fn is_ok(&self) -> bool {
+        self.pairs.iter().all(Result::is_ok)
+    }
+
+    /// Returns whether there were no pairs to loft, because the lofter has
+    /// fewer than two sketches. Such a loft is ok, but has no surface.
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    /// Returns each failed pair's lower sketch index and error.
+    pub fn errors(&self) -> impl Iterator<Item = (usize, &PairLoftError)> {
+        self.pairs
+            .iter()
+            .enumerate()
+            .filter_map(|(pair, result)| Some((pair, result.as_ref().err()?)))
+    }
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Lofter {
     sketches: Vec<Sketch>,
-    /// Mappings for each pair of sketches. There will always be one-fewer
-    /// mappings than the number of sketches.
+    /// Mappings for each pair of adjacent sketches, so one fewer than the
+    /// sketches, or none without sketches. Pairs which haven't been lofted
+    /// since their sketches were inserted or removed have empty mappings.
     loft_maps: Vec<Loft>,
+    /// Incremented by every mutation.
+    revision: u64,
+    /// Incremented by every mutation except to sketch transforms.
+    shape_revision: u64,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    recording: Option<Recording>,
+    /// The commands applied since they were last taken, while capturing.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    captured: Option<Vec<Command>>,
+    /// The post passes of the options last lofted with.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    post_passes: Vec<Arc<dyn MeshPass>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    session_stats: SessionStats,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    profile: profiling::ProfileCell,
 }
 
 impl Default for Lofter {
+    /// Returns an empty lofter, like `new`.
     fn default() -> Self {
-        let mut lofter = Self {
-            sketches: Default::default(),
-            loft_maps: Default::default(),
-        };
+        Self::new()
+    }
+}
 
+impl Lofter {
+    /// Creates a lofter without any sketches.
+    pub fn new() -> Self {
+        Self {
+            sketches: Vec::new(),
+            loft_maps: Vec::new(),
+            revision: 0,
+            shape_revision: 0,
+            recording: None,
+            captured: None,
+            post_passes: Vec::new(),
+            session_stats: SessionStats::default(),
+            profile: Default::default(),
+        }
+    }
+
+    /// Creates a lofted demo of two squares, one above the other.
+    pub fn demo() -> Self {
         let vertices = vec![
             Vec3::new(1., 0., 0.),
             Vec3::new(0., 1., 0.),
@@ -47,35 +258,303 @@ impl Default for Lofter {
             Vec3::new(0., -1., 0.),
         ];
 
-        lofter.push_sketch(&SketchDescriptor {
-            vertices: vertices.clone(),
-            relative_position: Vec3::ZERO,
-            rotation: Vec3::ZERO,
+        let mut lofter = Self::from_sketches(&[
+            SketchDescriptor {
+                vertices: vertices.clone(),
+                relative_position: Vec3::ZERO,
+                rotation: Vec3::ZERO,
+            },
+            SketchDescriptor {
+                vertices,
+                relative_position: Vec3::new(0., 0., 3.),
+                rotation: Vec3::ZERO,
+            },
+        ])
+        .unwrap();
+
+        lofter.loft(&Default::default());
+
+        lofter
+    }
+
+    /// Creates a lofter with the given sketches, bottom first, which are
+    /// validated like `insert_sketch`. It isn't lofted until `loft` is called.
+    pub fn from_sketches(sketches: &[SketchDescriptor]) -> Result<Self, LoftError> {
+        let mut lofter = Self::new();
+        lofter.sketches.reserve(sketches.len());
+
+        for sketch in sketches {
+            lofter.push_sketch(sketch)?;
+        }
+
+        Ok(lofter)
+    }
+
+    pub fn sketch_count(&self) -> usize {
+        self.sketches.len()
+    }
+
+    /// Returns a number which changes whenever the sketches or loft are
+    /// modified, so callers can tell when to update anything derived from
+    /// them, such as a vertex buffer.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Returns a number which changes like `revision`, except for changes to
+    /// sketch rotations and relative positions. Anything derived from
+    /// `local_vertex_buffer` only needs to be updated when this changes.
+    pub fn shape_revision(&self) -> u64 {
+        self.shape_revision
+    }
+
+    /// Returns the time spent in each phase of the last `loft` or `loft_with`,
+    /// and of assembling each pair's triangles in the last `vertex_buffer`
+    /// since.
+    #[cfg(feature = "profiling")]
+    pub fn last_profile(&self) -> LoftProfile {
+        self.profile.get()
+    }
+
+    /// Returns counts of the lofts, vertex edits and failures since the lofter
+    /// was created. These aren't serialized.
+    pub fn session_stats(&self) -> SessionStats {
+        self.session_stats
+    }
+
+    /// Pushes a sketch, like `insert_sketch`.
+    pub fn push_sketch(&mut self, sketch: &SketchDescriptor) -> Result<usize, LoftError> {
+        self.insert_sketch(self.sketches.len(), sketch)
+    }
+
+    /// Pushes `count` sketches, created by calling `f` with a parameter running
+    /// evenly from 0 to 1, for formula-driven lofts such as airfoil stacks or
+    /// tapered masts. With a count of 1, the parameter is 0.
+    ///
+    /// Stops at the first sketch which is rejected by `push_sketch`, leaving
+    /// the sketches pushed before it.
+    pub fn generate_stations(
+        &mut self,
+        count: usize,
+        f: impl Fn(f32) -> SketchDescriptor,
+    ) -> Result<(), LoftError> {
+        for station in 0..count {
+            let t = station as f32 / (count - 1).max(1) as f32;
+
+            self.push_sketch(&f(t))?;
+        }
+
+        Ok(())
+    }
+
+    /// Inserts a sketch. Consecutive vertices within
+    /// `DUPLICATE_VERTEX_TOLERANCE` of each other are merged, since they would
+    /// form zero-length edges, and the number of vertices merged away is
+    /// returned. Sketches left with fewer than three vertices are rejected.
+    pub fn insert_sketch(
+        &mut self,
+        sketch_index: usize,
+        sketch: &SketchDescriptor,
+    ) -> Result<usize, LoftError> {
+        self.record(|| Command::InsertSketch {
+            sketch_index,
+            sketch: sketch.clone(),
         });
 
-        lofter.push_sketch(&SketchDescriptor {
-            vertices,
-            relative_position: Vec3::new(0., 0., 3.),
-            rotation: Vec3::ZERO,
+        if sketch_index > self.sketches.len() {
+            return Err(LoftError::SketchNotFound(sketch_index));
+        }
+
+        let new_sketch = Sketch::from(sketch);
+        let vertex_count = new_sketch.vertex_order.len();
+        if vertex_count < 3 {
+            return Err(LoftError::TooFewVertices {
+                sketch_index,
+                vertex_count,
+            });
+        }
+        let merged_count = sketch.vertices.len() - vertex_count;
+
+        self.sketches.insert(sketch_index, new_sketch);
+        if self.sketches.len() > 1 {
+            // The loft of a pair the sketch splits references the pair's
+            // vertices, so both new pairs are left without a surface until
+            // lofted.
+            if sketch_index + 1 < self.sketches.len()
+                && let Some(loft_map) = sketch_index
+                    .checked_sub(1)
+                    .and_then(|pair| self.loft_maps.get_mut(pair))
+            {
+                *loft_map = Loft::empty();
+            }
+            self.loft_maps
+                .insert(sketch_index.min(self.loft_maps.len()), Loft::empty());
+        }
+        // Restored lofters may not have a mapping for every pair.
+        self.loft_maps
+            .resize_with(self.sketches.len().saturating_sub(1), Loft::empty);
+        self.revision += 1;
+        self.shape_revision += 1;
+
+        Ok(merged_count)
+    }
+
+    /// Inserts a sketch at world height `z`, between the pair of sketches
+    /// whose loft spans it, with the loft's cross-section there as its
+    /// profile, returning the new sketch's index. The sketch is placed and
+    /// rotated in proportion to its height between the pair, and the upper
+    /// sketch's relative position is adjusted to keep it in place, so it
+    /// can't be locked.
+    ///
+    /// Both new pairs are left without a surface until lofted.
+    pub fn infer_sketch_at(&mut self, z: f32) -> Result<usize, LoftError> {
+        let origins = self.sketch_world_positions();
+        // How far the height is from the lower to the upper sketch.
+        let (pair, t) = origins
+            .windows(2)
+            .take(self.loft_maps.len())
+            .enumerate()
+            .find_map(|(pair, origins)| {
+                let t = (z - origins[0].z) / (origins[1].z - origins[0].z);
+                (t > 0. && t < 1.).then_some((pair, t))
+            })
+            .ok_or(LoftError::NoLoftAtHeight(z))?;
+
+        let sketches = SketchPair::new(&self.sketches[pair], &self.sketches[pair + 1]);
+        if sketches.upper.locked {
+            return Err(LoftError::SketchLocked(pair + 1));
+        }
+
+        let mut ruling_edges = Vec::new();
+        self.loft_maps[pair].append_ruling_edges(
+            &mut ruling_edges,
+            sketches,
+            SketchPair::new(origins[pair], origins[pair + 1]),
+        );
+        if ruling_edges.is_empty() {
+            return Err(LoftError::NoLoftAtHeight(z));
+        }
+
+        let origin = origins[pair].lerp(origins[pair + 1], t);
+        let rotation = sketches.lower.rotation.lerp(sketches.upper.rotation, t);
+        let upper_relative_position = origins[pair + 1] - origin;
+
+        let vertices = ruling_edges
+            .into_iter()
+            .map(|[lower, upper]| {
+                // Sketch vertices may not lie exactly in their sketch's plane.
+                let t = (z - lower.z) / (upper.z - lower.z);
+                let position = lower.lerp(upper, if t.is_finite() { t } else { 0. }) - origin;
+
+                position.rotate_z(-rotation.z.to_radians()).with_z(0.)
+            })
+            .collect();
+
+        let sketch_index = pair + 1;
+        self.insert_sketch(
+            sketch_index,
+            &SketchDescriptor {
+                vertices,
+                relative_position: origin - origins[pair],
+                rotation,
+            },
+        )?;
+        self.set_sketch_relative_position(sketch_index + 1, &upper_relative_position)?;
+
+        Ok(sketch_index)
+    }
+
+    /// Removes a sketch, keeping the sketches above it in place by adding its
+    /// relative position to the next sketch's, even if that sketch is locked.
+    /// The pair the removal joins is left without a surface until lofted, and
+    /// a lofter left with fewer than two sketches has no surface.
+    pub fn remove_sketch(&mut self, sketch_index: usize) -> Result<(), LoftError> {
+        self.remove_sketch_with(sketch_index, true)
+    }
+
+    /// Removes a sketch like `remove_sketch`, but without `keep_positions`,
+    /// the sketches above it move down by its relative position.
+    pub fn remove_sketch_with(
+        &mut self,
+        sketch_index: usize,
+        keep_positions: bool,
+    ) -> Result<(), LoftError> {
+        self.record(|| Command::RemoveSketch {
+            sketch_index,
+            keep_positions,
         });
 
-        lofter.loft(&Default::default());
+        if sketch_index >= self.sketches.len() {
+            return Err(LoftError::SketchNotFound(sketch_index));
+        }
 
-        lofter
+        let removed = self.sketches.remove(sketch_index);
+        if keep_positions && let Some(next) = self.sketches.get_mut(sketch_index) {
+            next.relative_position += removed.relative_position;
+        }
+
+        // The lofts of the pairs the sketch was in reference its vertices.
+        if sketch_index < self.loft_maps.len() {
+            self.loft_maps.remove(sketch_index);
+        }
+        if let Some(loft_map) = sketch_index
+            .checked_sub(1)
+            .and_then(|pair| self.loft_maps.get_mut(pair))
+        {
+            *loft_map = Loft::empty();
+        }
+        self.loft_maps
+            .resize_with(self.sketches.len().saturating_sub(1), Loft::empty);
+        self.revision += 1;
+        self.shape_revision += 1;
+
+        Ok(())
     }
-}
 
-impl Lofter {
-    pub fn push_sketch(&mut self, sketch: &SketchDescriptor) {
-        self.insert_sketch(self.sketches.len(), sketch);
+    /// Returns a descriptor of a sketch, with its vertices in CCW order and
+    /// its transform, for copying it or inserting it again. Vertex IDs, locks
+    /// and mirror partners aren't included.
+    pub fn sketch_descriptor(&self, sketch_index: usize) -> Option<SketchDescriptor> {
+        let sketch = self.sketches.get(sketch_index)?;
+
+        Some(SketchDescriptor::from(sketch))
     }
 
-    pub fn insert_sketch(&mut self, sketch_index: usize, sketch: &SketchDescriptor) {
-        self.sketches.insert(sketch_index, sketch.into());
+    /// Checks a sketch's outline for problems which lofting doesn't reject,
+    /// such as crossing edges, returning an empty list for a valid sketch.
+    pub fn sketch_issues(&self, sketch_index: usize) -> Option<Vec<SketchIssue>> {
+        let sketch = self.sketches.get(sketch_index)?;
+
+        Some(validate::sketch_issues(sketch))
     }
 
-    pub fn remove_sketch(&mut self, sketch_index: usize) {
-        self.sketches.remove(sketch_index);
+    pub fn sketch_locked(&self, sketch_index: usize) -> Option<bool> {
+        let sketch = self.sketches.get(sketch_index)?;
+
+        Some(sketch.locked)
+    }
+
+    /// Locks or unlocks a sketch. Locked sketches reject vertex and transform
+    /// mutations with `LoftError::SketchLocked`.
+    pub fn set_sketch_locked(
+        &mut self,
+        sketch_index: usize,
+        locked: bool,
+    ) -> Result<(), LoftError> {
+        self.record(|| Command::SetSketchLocked {
+            sketch_index,
+            locked,
+        });
+
+        let sketch = self
+            .sketches
+            .get_mut(sketch_index)
+            .ok_or(LoftError::SketchNotFound(sketch_index))?;
+        self.revision += 1;
+
+        sketch.locked = locked;
+
+        Ok(())
     }
 
     pub fn sketch_rotation(&self, sketch_index: usize) -> Option<&Vec3> {
@@ -84,12 +563,22 @@ impl Lofter {
         Some(&sketch.rotation)
     }
 
-    pub fn set_sketch_rotation(&mut self, sketch_index: usize, rotation: &Vec3) {
-        let Some(sketch) = self.sketches.get_mut(sketch_index) else {
-            return;
-        };
+    pub fn set_sketch_rotation(
+        &mut self,
+        sketch_index: usize,
+        rotation: &Vec3,
+    ) -> Result<(), LoftError> {
+        self.record(|| Command::SetSketchRotation {
+            sketch_index,
+            rotation: *rotation,
+        });
+
+        let sketch = self.unlocked_sketch_mut(sketch_index)?;
 
         sketch.rotation = *rotation;
+        self.revision += 1;
+
+        Ok(())
     }
 
     pub fn sketch_relative_position(&self, sketch_index: usize) -> Option<&Vec3> {
@@ -98,17 +587,406 @@ impl Lofter {
         Some(&sketch.relative_position)
     }
 
-    pub fn set_sketch_relative_position(&mut self, sketch_index: usize, relative_position: &Vec3) {
-        let Some(sketch) = self.sketches.get_mut(sketch_index) else {
-            return;
-        };
+    pub fn set_sketch_relative_position(
+        &mut self,
+        sketch_index: usize,
+        relative_position: &Vec3,
+    ) -> Result<(), LoftError> {
+        self.record(|| Command::SetSketchRelativePosition {
+            sketch_index,
+            relative_position: *relative_position,
+        });
+
+        let sketch = self.unlocked_sketch_mut(sketch_index)?;
 
         sketch.relative_position = *relative_position;
+        self.revision += 1;
+
+        Ok(())
+    }
+
+    /// Inserts a new vertex at the midpoint of the edge formed by two adjacent
+    /// vertices, returning the ID of the new vertex.
+    pub fn insert_vertex(
+        &mut self,
+        sketch_index: usize,
+        between_vertices: (VertexId, VertexId),
+    ) -> Result<VertexId, LoftError> {
+        self.record(|| Command::InsertVertex {
+            sketch_index,
+            between_vertices,
+        });
+
+        let result = self.try_insert_vertex(sketch_index, between_vertices);
+        self.count_vertex_edit(result)
+    }
+
+    fn try_insert_vertex(
+        &mut self,
+        sketch_index: usize,
+        between_vertices: (VertexId, VertexId),
+    ) -> Result<VertexId, LoftError> {
+        let sketch = self.unlocked_sketch_mut(sketch_index)?;
+
+        let (a, b) = between_vertices;
+        let vertex_count = sketch.vertex_order.len();
+
+        let position = |id| {
+            sketch
+                .vertex_order
+                .iter()
+                .position(|&other| other == id)
+                .ok_or(LoftError::VertexNotFound {
+                    sketch_index,
+                    vertex_id: id,
+                })
+        };
+
+        let index_a = position(a)?;
+        let index_b = position(b)?;
+
+        // The new vertex is inserted after whichever vertex comes first in CCW
+        // order.
+        let insert_index = if (index_a + 1) % vertex_count == index_b {
+            index_b
+        } else if (index_b + 1) % vertex_count == index_a {
+            index_a
+        } else {
+            return Err(LoftError::VerticesNotAdjacent {
+                sketch_index,
+                vertices: between_vertices,
+            });
+        };
+
+        let midpoint = (sketch.vertex_map[&a] + sketch.vertex_map[&b]) / 2.;
+
+        let vertex_id = sketch.next_vertex_id;
+        sketch.next_vertex_id += 1;
+
+        sketch.vertex_map.insert(vertex_id, midpoint);
+        // Inserting at index 0 would place the vertex before the first vertex,
+        // which is equivalent to placing it after the last vertex.
+        if insert_index == 0 {
+            sketch.vertex_order.push(vertex_id);
+        } else {
+            sketch.vertex_order.insert(insert_index, vertex_id);
+        }
+        self.revision += 1;
+        self.shape_revision += 1;
+
+        Ok(vertex_id)
+    }
+
+    pub fn remove_vertex(
+        &mut self,
+        sketch_index: usize,
+        vertex_id: VertexId,
+    ) -> Result<(), LoftError> {
+        self.record(|| Command::RemoveVertex {
+            sketch_index,
+            vertex_id,
+        });
+
+        let result = self.try_remove_vertex(sketch_index, vertex_id);
+        self.count_vertex_edit(result)
+    }
+
+    fn try_remove_vertex(
+        &mut self,
+        sketch_index: usize,
+        vertex_id: VertexId,
+    ) -> Result<(), LoftError> {
+        let sketch = self.unlocked_sketch_mut(sketch_index)?;
+
+        if !sketch.vertex_map.contains_key(&vertex_id) {
+            return Err(LoftError::VertexNotFound {
+                sketch_index,
+                vertex_id,
+            });
+        }
+
+        // Sketches can't be left with fewer than three vertices.
+        if sketch.vertex_order.len() <= 3 {
+            return Err(LoftError::TooFewVertices {
+                sketch_index,
+                vertex_count: sketch.vertex_order.len() - 1,
+            });
+        }
+
+        sketch.vertex_map.remove(&vertex_id);
+
+        sketch.vertex_order.retain(|&id| id != vertex_id);
+
+        if let Some(partner) = sketch.mirror_partners.remove(&vertex_id) {
+            sketch.mirror_partners.remove(&partner);
+        }
+        sketch.excluded_vertices.remove(&vertex_id);
+        self.revision += 1;
+        self.shape_revision += 1;
+
+        Ok(())
+    }
+
+    /// Rounds the corner at a vertex with an arc of `segments` edges, up to
+    /// `MAX_FILLET_SEGMENTS`, tangent to both of the corner's edges. The vertex is moved to the start of the
+    /// arc, and the rest of the arc's vertices are inserted after it. Returns
+    /// the arc's vertices in CCW order.
+    ///
+    /// Fails with `LoftError::CornerTooSmall` if the arc doesn't fit within the
+    /// corner's edges.
+    pub fn fillet_vertex(
+        &mut self,
+        sketch_index: usize,
+        vertex_id: VertexId,
+        radius: f32,
+        segments: usize,
+    ) -> Result<Vec<VertexId>, LoftError> {
+        self.record(|| Command::FilletVertex {
+            sketch_index,
+            vertex_id,
+            radius,
+            segments,
+        });
+
+        let cut = CornerCut::Fillet {
+            radius,
+            segments: segments.clamp(1, MAX_FILLET_SEGMENTS),
+        };
+        let result = self.try_cut_corner(sketch_index, vertex_id, cut);
+        self.count_vertex_edit(result)
+    }
+
+    /// Bevels the corner at a vertex with a straight edge, from `distance`
+    /// along each of the corner's edges, like `fillet_vertex` with a single
+    /// segment. Returns the bevel's two vertices, the first of which is the
+    /// moved vertex.
+    pub fn chamfer_vertex(
+        &mut self,
+        sketch_index: usize,
+        vertex_id: VertexId,
+        distance: f32,
+    ) -> Result<Vec<VertexId>, LoftError> {
+        self.record(|| Command::ChamferVertex {
+            sketch_index,
+            vertex_id,
+            distance,
+        });
+
+        let result = self.try_cut_corner(sketch_index, vertex_id, CornerCut::Chamfer { distance });
+        self.count_vertex_edit(result)
+    }
+
+    fn try_cut_corner(
+        &mut self,
+        sketch_index: usize,
+        vertex_id: VertexId,
+        cut: CornerCut,
+    ) -> Result<Vec<VertexId>, LoftError> {
+        let sketch = self.unlocked_sketch_mut(sketch_index)?;
+
+        let index = sketch
+            .vertex_order
+            .iter()
+            .position(|&id| id == vertex_id)
+            .ok_or(LoftError::VertexNotFound {
+                sketch_index,
+                vertex_id,
+            })?;
+
+        let count = sketch.vertex_order.len();
+        let [prev, next] = [index + count - 1, index + 1]
+            .map(|neighbor| sketch.vertex_map[&sketch.vertex_order[neighbor % count]]);
+
+        let points = cut
+            .points(prev, sketch.vertex_map[&vertex_id], next)
+            .ok_or(LoftError::CornerTooSmall {
+                sketch_index,
+                vertex_id,
+            })?;
+
+        sketch.vertex_map.insert(vertex_id, points[0]);
+
+        let mut vertex_ids = vec![vertex_id];
+        for (offset, &point) in points[1..].iter().enumerate() {
+            let new_id = sketch.next_vertex_id;
+            sketch.next_vertex_id += 1;
+
+            sketch.vertex_map.insert(new_id, point);
+            sketch.vertex_order.insert(index + 1 + offset, new_id);
+            vertex_ids.push(new_id);
+        }
+        self.revision += 1;
+        self.shape_revision += 1;
+
+        Ok(vertex_ids)
     }
 
-    pub fn insert_vertex(&mut self, sketch_index: usize, between_vertices: (VertexId, VertexId)) {}
+    /// Returns a sketch's features, such as corners, in CCW order, where the
+    /// outline turns by at least `angle` degrees within a twentieth of its
+    /// perimeter. A corner rounded by several vertices is a single feature, at
+    /// the vertex where it turns most. Vertices excluded from matching aren't
+    /// features.
+    pub fn feature_vertices(&self, sketch_index: usize, angle: f32) -> Option<Vec<VertexId>> {
+        let sketch = self.sketches.get(sketch_index)?;
+
+        Some(feature::feature_vertices(sketch, angle.to_radians()))
+    }
 
-    pub fn remove_vertex(&mut self, sketch_index: usize, vertex_id: VertexId) {}
+    /// Returns whether a vertex is excluded from matching, if it exists.
+    pub fn vertex_excluded(&self, sketch_index: usize, vertex_id: VertexId) -> Option<bool> {
+        let sketch = self.sketches.get(sketch_index)?;
+        sketch.vertex_map.get(&vertex_id)?;
+
+        Some(sketch.excluded_vertices.contains(&vertex_id))
+    }
+
+    /// Marks a vertex as decorative, or not. Excluded vertices are still part
+    /// of the sketch's outline and the loft's surface, but sections never
+    /// start or end at them, so a patch of dense detail doesn't pull every
+    /// section towards it. If every vertex of a sketch is excluded, none are.
+    pub fn set_vertex_excluded(
+        &mut self,
+        sketch_index: usize,
+        vertex_id: VertexId,
+        excluded: bool,
+    ) -> Result<(), LoftError> {
+        self.record(|| Command::SetVertexExcluded {
+            sketch_index,
+            vertex_id,
+            excluded,
+        });
+
+        let sketch = self.unlocked_sketch_mut(sketch_index)?;
+
+        if !sketch.vertex_map.contains_key(&vertex_id) {
+            return Err(LoftError::VertexNotFound {
+                sketch_index,
+                vertex_id,
+            });
+        }
+
+        if excluded {
+            sketch.excluded_vertices.insert(vertex_id);
+        } else {
+            sketch.excluded_vertices.remove(&vertex_id);
+        }
+        self.revision += 1;
+
+        Ok(())
+    }
+
+    /// Returns the tagged mirror partner of a vertex, if it has one. A vertex
+    /// lying on the mirror axis is its own partner.
+    pub fn mirror_partner(&self, sketch_index: usize, vertex_id: VertexId) -> Option<VertexId> {
+        self.sketches
+            .get(sketch_index)?
+            .mirror_partners
+            .get(&vertex_id)
+            .copied()
+    }
+
+    /// Tags two vertices as mirror partners, replacing any existing partners
+    /// of either vertex. Tagging a vertex with itself marks it as lying on the
+    /// mirror axis.
+    pub fn set_mirror_partners(
+        &mut self,
+        sketch_index: usize,
+        vertices: (VertexId, VertexId),
+    ) -> Result<(), LoftError> {
+        self.record(|| Command::SetMirrorPartners {
+            sketch_index,
+            vertices,
+        });
+
+        let sketch = self.unlocked_sketch_mut(sketch_index)?;
+
+        for vertex_id in [vertices.0, vertices.1] {
+            if !sketch.vertex_map.contains_key(&vertex_id) {
+                return Err(LoftError::VertexNotFound {
+                    sketch_index,
+                    vertex_id,
+                });
+            }
+        }
+
+        for vertex_id in [vertices.0, vertices.1] {
+            if let Some(partner) = sketch.mirror_partners.remove(&vertex_id) {
+                sketch.mirror_partners.remove(&partner);
+            }
+        }
+
+        sketch.mirror_partners.insert(vertices.0, vertices.1);
+        sketch.mirror_partners.insert(vertices.1, vertices.0);
+        self.revision += 1;
+
+        Ok(())
+    }
+
+    /// Removes the mirror partner tag from a vertex and its partner.
+    pub fn clear_mirror_partner(
+        &mut self,
+        sketch_index: usize,
+        vertex_id: VertexId,
+    ) -> Result<(), LoftError> {
+        self.record(|| Command::ClearMirrorPartner {
+            sketch_index,
+            vertex_id,
+        });
+
+        let sketch = self.unlocked_sketch_mut(sketch_index)?;
+
+        if let Some(partner) = sketch.mirror_partners.remove(&vertex_id) {
+            sketch.mirror_partners.remove(&partner);
+            self.revision += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Replaces all mirror partner tags in a sketch, pairing each vertex with
+    /// the vertex closest to its mirrored position, if one lies within
+    /// `tolerance`. Returns the number of tagged vertices.
+    pub fn tag_mirror_partners(
+        &mut self,
+        sketch_index: usize,
+        axis: MirrorAxis,
+        tolerance: f32,
+    ) -> Result<usize, LoftError> {
+        self.record(|| Command::TagMirrorPartners {
+            sketch_index,
+            axis,
+            tolerance,
+        });
+
+        let sketch = self.unlocked_sketch_mut(sketch_index)?;
+
+        sketch.mirror_partners.clear();
+
+        for &id in &sketch.vertex_order {
+            if sketch.mirror_partners.contains_key(&id) {
+                continue;
+            }
+
+            let mirrored = axis.mirror(sketch.vertex_map[&id]);
+
+            let partner = sketch
+                .vertex_order
+                .iter()
+                .filter(|other| !sketch.mirror_partners.contains_key(other))
+                .map(|&other| (other, sketch.vertex_map[&other].distance(mirrored)))
+                .filter(|(_, distance)| *distance <= tolerance)
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+
+            if let Some((partner, _)) = partner {
+                sketch.mirror_partners.insert(id, partner);
+                sketch.mirror_partners.insert(partner, id);
+            }
+        }
+        let tagged = sketch.mirror_partners.len();
+        self.revision += 1;
+
+        Ok(tagged)
+    }
 
     /// Returns an iterator over all vertices in a sketch, in CCW order.
     pub fn vertices(&self, sketch_index: usize) -> Option<impl Iterator<Item = (VertexId, &Vec3)>> {
@@ -123,110 +1001,917 @@ impl Lofter {
     }
 
     /// Iterates over all vertices in a sketch, in CCW order.
-    pub fn vertices_mut<F>(&mut self, sketch_index: usize, mut f: F)
+    ///
+    /// While recording, this is recorded as setting the positions of all
+    /// vertices in the sketch.
+    pub fn vertices_mut<F>(&mut self, sketch_index: usize, mut f: F) -> Result<(), LoftError>
     where
         F: FnMut((VertexId, &mut Vec3)),
     {
-        let sketch = &mut self.sketches[sketch_index];
+        let sketch = match self.unlocked_sketch_mut(sketch_index) {
+            Ok(sketch) => sketch,
+            Err(err) => return self.count_vertex_edit(Err(err)),
+        };
 
         for id in &sketch.vertex_order {
-            f((*id, &mut sketch.vertex_map.get_mut(id).unwrap()));
+            f((*id, sketch.vertex_map.get_mut(id).unwrap()));
         }
+        self.revision += 1;
+        self.shape_revision += 1;
+        self.session_stats.vertex_edits += 1;
+
+        if self.recording.is_some() {
+            let positions = self.vertices(sketch_index).unwrap();
+            let positions = positions.map(|(id, position)| (id, *position)).collect();
+
+            self.record(|| Command::SetVertexPositions {
+                sketch_index,
+                positions,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Sets the positions of multiple vertices in a sketch at once. If any of
+    /// the vertices don't exist, no positions are modified.
+    pub fn set_vertex_positions(
+        &mut self,
+        sketch_index: usize,
+        positions: &[(VertexId, Vec3)],
+    ) -> Result<(), LoftError> {
+        self.record(|| Command::SetVertexPositions {
+            sketch_index,
+            positions: positions.to_vec(),
+        });
+
+        let result = self.try_set_vertex_positions(sketch_index, positions);
+        self.count_vertex_edit(result)
+    }
+
+    fn try_set_vertex_positions(
+        &mut self,
+        sketch_index: usize,
+        positions: &[(VertexId, Vec3)],
+    ) -> Result<(), LoftError> {
+        let sketch = self.unlocked_sketch_mut(sketch_index)?;
+
+        if let Some(&(vertex_id, _)) = positions
+            .iter()
+            .find(|(id, _)| !sketch.vertex_map.contains_key(id))
+        {
+            return Err(LoftError::VertexNotFound {
+                sketch_index,
+                vertex_id,
+            });
+        }
+
+        for (id, position) in positions {
+            sketch.vertex_map.insert(*id, *position);
+        }
+        self.revision += 1;
+        self.shape_revision += 1;
+
+        Ok(())
+    }
+
+    /// Returns the world position of a sketch, which is the sum of its
+    /// relative position and the relative positions of all sketches below it.
+    pub fn sketch_world_position(&self, sketch_index: usize) -> Option<Vec3> {
+        let sketches = self.sketches.get(..=sketch_index)?;
+
+        Some(sketches.iter().map(|sketch| sketch.relative_position).sum())
+    }
+
+    /// Returns the world position of a vertex, with the sketch's rotation and
+    /// world position applied.
+    pub fn vertex_world_position(&self, sketch_index: usize, vertex_id: VertexId) -> Option<Vec3> {
+        let sketch = self.sketches.get(sketch_index)?;
+
+        if !sketch.vertex_map.contains_key(&vertex_id) {
+            return None;
+        }
+
+        Some(sketch.vertex_rotated(vertex_id) + self.sketch_world_position(sketch_index)?)
+    }
+
+    /// Sets the position of a vertex from a world position, by removing the
+    /// sketch's world position and rotation.
+    pub fn set_vertex_world_position(
+        &mut self,
+        sketch_index: usize,
+        vertex_id: VertexId,
+        world_position: &Vec3,
+    ) -> Result<(), LoftError> {
+        self.record(|| Command::SetVertexWorldPosition {
+            sketch_index,
+            vertex_id,
+            world_position: *world_position,
+        });
+
+        let result = self.try_set_vertex_world_position(sketch_index, vertex_id, world_position);
+        self.count_vertex_edit(result)
+    }
+
+    fn try_set_vertex_world_position(
+        &mut self,
+        sketch_index: usize,
+        vertex_id: VertexId,
+        world_position: &Vec3,
+    ) -> Result<(), LoftError> {
+        let origin = self
+            .sketch_world_position(sketch_index)
+            .ok_or(LoftError::SketchNotFound(sketch_index))?;
+
+        let rotation = self.sketches[sketch_index].rotation.z.to_radians();
+
+        *self.get_vertex_mut(sketch_index, vertex_id)? =
+            (*world_position - origin).rotate_z(-rotation);
+
+        Ok(())
     }
 
     pub fn get_vertex(&self, sketch_index: usize, vertex_id: VertexId) -> Option<&Vec3> {
         self.sketches.get(sketch_index)?.vertex_map.get(&vertex_id)
     }
 
+    /// Changes made through the returned reference aren't recorded; use
+    /// `set_vertex_positions` while recording.
     pub fn get_vertex_mut(
         &mut self,
         sketch_index: usize,
         vertex_id: VertexId,
-    ) -> Option<&mut Vec3> {
-        self.sketches
-            .get_mut(sketch_index)?
+    ) -> Result<&mut Vec3, LoftError> {
+        self.unlocked_sketch_mut(sketch_index)?;
+
+        let vertex = self.sketches[sketch_index]
             .vertex_map
             .get_mut(&vertex_id)
+            .ok_or(LoftError::VertexNotFound {
+                sketch_index,
+                vertex_id,
+            })?;
+        self.revision += 1;
+        self.shape_revision += 1;
+
+        Ok(vertex)
     }
 
-    /// Create (or recreate) the loft shape.
-    pub fn loft(&mut self, options: &LoftOptions) {
-        self.loft_maps = self
+    /// Lofts each pair of adjacent sketches, returning which pairs failed.
+    /// Failed pairs keep their previous loft if it still fits their sketches,
+    /// and are otherwise left without a surface.
+    pub fn loft(&mut self, options: &LoftOptions) -> LoftResult {
+        self.loft_with(options, &DefaultMatching)
+    }
+
+    /// Lofts like `loft`, choosing ruling edges with a custom strategy.
+    ///
+    /// Recorded sessions only record the options, so they're replayed with
+    /// `DefaultMatching`.
+    pub fn loft_with(
+        &mut self,
+        options: &LoftOptions,
+        strategy: &dyn MatchingStrategy,
+    ) -> LoftResult {
+        self.record(|| Command::Loft(options.clone()));
+        self.post_passes.clone_from(&options.post_passes);
+
+        let mut pair_profiles = Vec::new();
+        let loft_maps = self
             .sketches
             .windows(2)
-            .map(|sketches| loft_sketches(SketchPair::new(&sketches[0], &sketches[1]), options))
+            .enumerate()
+            .map(|(sketch_pair, sketches)| {
+                let mut pair_profile = profiling::PairProfile::default();
+                let loft = loft_sketches(
+                    SketchPair::new(&sketches[0], &sketches[1]),
+                    options,
+                    &options.vertex_pins(sketch_pair),
+                    strategy,
+                    &mut pair_profile,
+                    |_, _| {},
+                );
+
+                pair_profiles.push(pair_profile);
+                loft
+            })
             .collect();
+
+        self.profile.set(profiling::LoftProfile {
+            pairs: pair_profiles,
+        });
+
+        self.set_loft_maps(loft_maps, options)
+    }
+
+    /// Lofts like `loft`, returning each decision the matcher made along the
+    /// way, and which pairs failed.
+    pub fn explain_loft(&mut self, options: &LoftOptions) -> (Vec<LoftStep>, LoftResult) {
+        self.record(|| Command::Loft(options.clone()));
+        self.post_passes.clone_from(&options.post_passes);
+
+        let mut steps = Vec::new();
+        let origins = self.sketch_world_positions();
+
+        let loft_maps = self
+            .sketches
+            .windows(2)
+            .enumerate()
+            .map(|(sketch_pair, sketches)| {
+                let sketches = SketchPair::new(&sketches[0], &sketches[1]);
+
+                let loft = loft_sketches(
+                    sketches,
+                    options,
+                    &options.vertex_pins(sketch_pair),
+                    &DefaultMatching,
+                    &mut Default::default(),
+                    |candidate, accepted| {
+                        let vertices = (candidate.vertices.lower, candidate.vertices.upper);
+                        let radial_error = candidate.radial_error;
+
+                        steps.push(if accepted {
+                            LoftStep::CandidateAccepted {
+                                sketch_pair,
+                                vertices,
+                                radial_error,
+                            }
+                        } else {
+                            LoftStep::CandidateRejected {
+                                sketch_pair,
+                                vertices,
+                                radial_error,
+                            }
+                        });
+                    },
+                );
+
+                let origins = SketchPair::new(origins[sketch_pair], origins[sketch_pair + 1]);
+                let sections = loft
+                    .iter()
+                    .flat_map(|loft| loft.section_ruling_edges(sketches, origins));
+                for ruling_edges in sections {
+                    steps.push(LoftStep::SectionBuilt {
+                        sketch_pair,
+                        ruling_edges,
+                    });
+                }
+
+                loft
+            })
+            .collect();
+
+        (steps, self.set_loft_maps(loft_maps, options))
+    }
+
+    /// Replaces the loft maps. Failed pairs keep their previous loft while it
+    /// still fits their sketches, and are otherwise left without a surface.
+    fn set_loft_maps(
+        &mut self,
+        loft_maps: Vec<Result<Loft, PairLoftError>>,
+        options: &LoftOptions,
+    ) -> LoftResult {
+        let mut pairs = Vec::with_capacity(loft_maps.len());
+        let mut kept_previous = Vec::new();
+        let mut previous_loft_maps = std::mem::take(&mut self.loft_maps).into_iter();
+
+        self.loft_maps = zip(loft_maps, self.sketches.windows(2))
+            .enumerate()
+            .map(|(pair, (loft, sketches))| {
+                let sketches = SketchPair::new(&sketches[0], &sketches[1]);
+                let previous = previous_loft_maps.next();
+
+                let result = match &loft {
+                    Ok(loft) => loft.validate(sketches),
+                    Err(err) => Err(err.clone()),
+                };
+                let loft = match (loft, &result, previous) {
+                    (Ok(loft), Ok(()), _) => loft,
+                    // An empty previous loft has nothing to keep showing.
+                    (_, Err(_), Some(previous))
+                        if !previous.is_empty()
+                            && previous.references_existing_vertices(sketches)
+                            && previous.validate(sketches).is_ok() =>
+                    {
+                        kept_previous.push(pair);
+                        previous
+                    }
+                    _ => Loft::empty(),
+                };
+
+                pairs.push(result);
+                loft
+            })
+            .collect();
+        self.revision += 1;
+        self.shape_revision += 1;
+
+        self.session_stats.lofts += 1;
+        self.session_stats.failed_pairs +=
+            pairs.iter().filter(|result| result.is_err()).count() as u64;
+
+        LoftResult {
+            pairs,
+            kept_previous,
+            station_changes: self.station_changes(options),
+        }
+    }
+
+    /// Returns the ruling edges exceeding the options' station change limits.
+    fn station_changes(&self, options: &LoftOptions) -> Vec<StationChange> {
+        if options.max_station_change_distance.is_none()
+            && options.max_station_change_angle.is_none()
+        {
+            return Vec::new();
+        }
+
+        let origins = self.sketch_world_positions();
+        let ruling_edges = zip(
+            zip(&self.loft_maps, self.sketches.windows(2)),
+            origins.windows(2),
+        )
+        .enumerate()
+        .flat_map(|(sketch_pair, ((loft_map, sketches), origins))| {
+            let sketches = SketchPair::new(&sketches[0], &sketches[1]);
+            let origins = SketchPair::new(origins[0], origins[1]);
+
+            let mut lines = Vec::new();
+            loft_map.append_ruling_edges(&mut lines, sketches, origins);
+
+            lines
+                .into_iter()
+                .map(move |line| (sketch_pair, line, origins.upper - origins.lower))
+        });
+
+        ruling_edges
+            .enumerate()
+            .filter_map(|(ruling_edge, (sketch_pair, [lower, upper], axis))| {
+                let distance = (upper - lower - axis).length();
+                let angle = (upper - lower).angle_between(axis).to_degrees();
+
+                let exceeds =
+                    |limit: Option<f32>, value: f32| limit.is_some_and(|limit| value > limit);
+                (exceeds(options.max_station_change_distance, distance)
+                    || exceeds(options.max_station_change_angle, angle))
+                .then_some(StationChange {
+                    sketch_pair,
+                    ruling_edge,
+                    distance,
+                    angle,
+                })
+            })
+            .collect()
     }
 
     /// Returns a vertex buffer containing interleaved vertex positions and
-    /// colors.
+    /// colors, in `OutputLayout::PositionColor`.
     ///
     /// `[Vec3; 2] == vertex [position, color]`
     /// `[[Vec3; 2]; 3] == triangle with three vertices`
     pub fn vertex_buffer(&self) -> Vec<[[Vec3; 2]; 3]> {
         let mut vertex_buffer = Vec::new();
 
+        for (pair, (loft_map, sketches, origins)) in self.pairs().enumerate() {
+            let mut duration = Default::default();
+            timed(&mut duration, || {
+                loft_map.append_vertex_buffer(&mut vertex_buffer, sketches, origins);
+            });
+
+            self.profile.set_buffer_assembly(pair, duration);
+        }
+
+        vertex_buffer
+    }
+
+    /// Returns the triangles of `vertex_buffer` as interleaved floats in the
+    /// layout, three vertices per triangle, for GPU consumers which need
+    /// normals or UVs, or only positions.
+    pub fn layout_vertex_buffer(&self, layout: OutputLayout) -> Vec<f32> {
+        let mut vertex_buffer = Vec::new();
+        let last_sketch = self.sketch_count().saturating_sub(1).max(1) as f32;
+
+        for (pair, (loft_map, sketches, origins)) in self.pairs().enumerate() {
+            let v = SketchPair::new(pair as f32 / last_sketch, (pair + 1) as f32 / last_sketch);
+            loft_map.append_layout_vertex_buffer(&mut vertex_buffer, layout, sketches, origins, v);
+        }
+
+        vertex_buffer
+    }
+
+    /// Calls `f` with the world positions of each triangle of `vertex_buffer`,
+    /// and the section it's in, like `triangle_sections`, without building
+    /// the whole buffer.
+    pub(crate) fn for_each_world_triangle(&self, mut f: impl FnMut([Vec3; 3], usize)) {
+        let mut first_section = 0;
+
+        for (loft_map, sketches, origins) in self.pairs() {
+            for (triangle, section) in loft_map.world_triangles(sketches, origins) {
+                f(
+                    triangle.map(|[position, _]| position),
+                    first_section + section,
+                );
+            }
+
+            first_section += loft_map.section_count();
+        }
+    }
+
+    /// Returns the triangles of `vertex_buffer` as they're iterated, a pair of
+    /// sketches and a section at a time, so they can be streamed, filtered or
+    /// stopped early without building the whole buffer.
+    pub fn triangles_iter(&self) -> impl Iterator<Item = [[Vec3; 2]; 3]> + '_ {
+        self.pairs().flat_map(|(loft_map, sketches, origins)| {
+            loft_map
+                .world_triangles(sketches, origins)
+                .map(|(triangle, _)| triangle)
+        })
+    }
+
+    /// Returns the number of triangles in `vertex_buffer`, without building
+    /// it.
+    pub fn triangle_count(&self) -> usize {
+        self.loft_maps.iter().map(Loft::triangle_count).sum()
+    }
+
+    /// Returns the index of the loft section each triangle of `vertex_buffer`
+    /// is in. Sections are numbered consecutively across all sketch pairs.
+    pub fn triangle_sections(&self) -> Vec<usize> {
+        let mut sections = Vec::new();
+        let mut section_count = 0;
+
+        for loft_map in &self.loft_maps {
+            section_count += loft_map.append_triangle_sections(&mut sections, section_count);
+        }
+
+        sections
+    }
+
+    /// Returns the edges of the loft mesh which connect adjacent sketches, as
+    /// world space line segments.
+    pub fn ruling_edges(&self) -> Vec<[Vec3; 2]> {
+        let mut lines = Vec::new();
+
         let sketches = self.sketches.windows(2);
+        let origins = self.sketch_world_positions();
 
-        for (loft_map, sketches) in zip(&self.loft_maps, sketches) {
+        for ((loft_map, sketches), origins) in
+            zip(zip(&self.loft_maps, sketches), origins.windows(2))
+        {
             let sketches = SketchPair::new(&sketches[0], &sketches[1]);
-            loft_map.append_vertex_buffer(&mut vertex_buffer, sketches);
+            let origins = SketchPair::new(origins[0], origins[1]);
+            loft_map.append_ruling_edges(&mut lines, sketches, origins);
+        }
+
+        lines
+    }
+
+    /// Slices the loft's mesh, as exported, into horizontal layers
+    /// `layer_height` thick, from the loft's lowest point up, such as to
+    /// preview how it prints. Each layer is sliced at its middle, so the open
+    /// ends of an uncapped loft don't matter. Returns no layers without a
+    /// surface, or if `layer_height` isn't positive. Layers thinner than the
+    /// loft's height over `MAX_SLICE_LAYERS` are thickened to that, so there
+    /// are at most `MAX_SLICE_LAYERS` layers.
+    pub fn slice_layers(&self, layer_height: f32) -> Vec<SliceLayer> {
+        slice::slice_layers(&IndexedMesh::from_lofter(self), layer_height)
+    }
+
+    /// Returns where each ruling edge meets its sketches, in the order of
+    /// `ruling_edges`, for tools which need the matching rather than the mesh.
+    pub fn correspondences(&self) -> Vec<Correspondence> {
+        let mut correspondences = Vec::new();
+
+        for (sketch_pair, (loft_map, sketches)) in
+            zip(&self.loft_maps, self.sketches.windows(2)).enumerate()
+        {
+            let sketches = SketchPair::new(&sketches[0], &sketches[1]);
+            correspondences.extend(loft_map.sketch_points(sketches).into_iter().map(
+                |(section, points)| Correspondence {
+                    sketch_pair,
+                    section,
+                    lower: points.lower,
+                    upper: points.upper,
+                },
+            ));
+        }
+
+        correspondences
+    }
+
+    /// Returns the loft mesh like `vertex_buffer`, but with each vertex in the
+    /// local, unrotated space of the sketch it lies on. Rotating a vertex by
+    /// its sketch's rotation and adding the sketch's world position gives the
+    /// vertex's position in `vertex_buffer`, so the mesh can be re-evaluated
+    /// for new sketch transforms without rebuilding it (e.g. on the GPU).
+    pub fn local_vertex_buffer(&self) -> Vec<[LocalLoftVertex; 3]> {
+        let mut vertex_buffer = Vec::new();
+
+        for (sketch_index, (loft_map, sketches)) in
+            zip(&self.loft_maps, self.sketches.windows(2)).enumerate()
+        {
+            let sketches = SketchPair::new(&sketches[0], &sketches[1]);
+            let sketch_indices = SketchPair::new(sketch_index, sketch_index + 1);
+            loft_map.append_local_vertex_buffer(&mut vertex_buffer, sketches, sketch_indices);
         }
 
         vertex_buffer
     }
+
+    /// Starts recording a session of every command applied to the lofter,
+    /// replacing any session already being recorded.
+    pub fn start_recording(&mut self) {
+        let mut initial = self.clone();
+        initial.recording = None;
+        initial.captured = None;
+
+        self.recording = Some(Recording::new(initial));
+    }
+
+    /// Stops recording, returning the recorded session, if one was being
+    /// recorded.
+    pub fn stop_recording(&mut self) -> Option<Session> {
+        self.recording.take().map(Recording::finish)
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Starts capturing every command applied to the lofter, like recording,
+    /// but untimed and without the lofter's state, for `take_captured` to pass
+    /// on, such as to a `LoftDocument`. Captures alongside any recording.
+    pub fn start_capturing(&mut self) {
+        self.captured = Some(Vec::new());
+    }
+
+    pub fn stop_capturing(&mut self) {
+        self.captured = None;
+    }
+
+    pub fn is_capturing(&self) -> bool {
+        self.captured.is_some()
+    }
+
+    /// Returns the commands captured since they were last taken, in the order
+    /// they were applied, including those which returned an error.
+    pub fn take_captured(&mut self) -> Vec<Command> {
+        self.captured
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// Applies a command, by calling the method it corresponds to.
+    pub fn apply(&mut self, command: &Command) -> Result<(), LoftError> {
+        match command {
+            Command::InsertSketch {
+                sketch_index,
+                sketch,
+            } => {
+                self.insert_sketch(*sketch_index, sketch)?;
+            }
+            Command::RemoveSketch {
+                sketch_index,
+                keep_positions,
+            } => return self.remove_sketch_with(*sketch_index, *keep_positions),
+            Command::SetSketchLocked {
+                sketch_index,
+                locked,
+            } => return self.set_sketch_locked(*sketch_index, *locked),
+            Command::SetSketchRotation {
+                sketch_index,
+                rotation,
+            } => return self.set_sketch_rotation(*sketch_index, rotation),
+            Command::SetSketchRelativePosition {
+                sketch_index,
+                relative_position,
+            } => return self.set_sketch_relative_position(*sketch_index, relative_position),
+            Command::InsertVertex {
+                sketch_index,
+                between_vertices,
+            } => {
+                self.insert_vertex(*sketch_index, *between_vertices)?;
+            }
+            Command::RemoveVertex {
+                sketch_index,
+                vertex_id,
+            } => return self.remove_vertex(*sketch_index, *vertex_id),
+            Command::SetMirrorPartners {
+                sketch_index,
+                vertices,
+            } => return self.set_mirror_partners(*sketch_index, *vertices),
+            Command::ClearMirrorPartner {
+                sketch_index,
+                vertex_id,
+            } => return self.clear_mirror_partner(*sketch_index, *vertex_id),
+            Command::FilletVertex {
+                sketch_index,
+                vertex_id,
+                radius,
+                segments,
+            } => {
+                self.fillet_vertex(*sketch_index, *vertex_id, *radius, *segments)?;
+            }
+            Command::ChamferVertex {
+                sketch_index,
+                vertex_id,
+                distance,
+            } => {
+                self.chamfer_vertex(*sketch_index, *vertex_id, *distance)?;
+            }
+            Command::SetVertexExcluded {
+                sketch_index,
+                vertex_id,
+                excluded,
+            } => return self.set_vertex_excluded(*sketch_index, *vertex_id, *excluded),
+            Command::TagMirrorPartners {
+                sketch_index,
+                axis,
+                tolerance,
+            } => {
+                self.tag_mirror_partners(*sketch_index, *axis, *tolerance)?;
+            }
+            Command::SetVertexPositions {
+                sketch_index,
+                positions,
+            } => return self.set_vertex_positions(*sketch_index, positions),
+            Command::SetVertexWorldPosition {
+                sketch_index,
+                vertex_id,
+                world_position,
+            } => return self.set_vertex_world_position(*sketch_index, *vertex_id, world_position),
+            Command::Loft(options) => {
+                self.loft(options);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns each pair's loft, sketches and sketch world positions.
+    fn pairs(&self) -> impl Iterator<Item = (&Loft, SketchPair<&Sketch>, SketchPair<Vec3>)> {
+        let origins = self
+            .sketches
+            .windows(2)
+            .scan(Vec3::ZERO, |origin, sketches| {
+                *origin += sketches[0].relative_position;
+
+                Some(SketchPair::new(
+                    *origin,
+                    *origin + sketches[1].relative_position,
+                ))
+            });
+
+        zip(zip(&self.loft_maps, self.sketches.windows(2)), origins).map(
+            |((loft_map, sketches), origins)| {
+                (
+                    loft_map,
+                    SketchPair::new(&sketches[0], &sketches[1]),
+                    origins,
+                )
+            },
+        )
+    }
+
+    /// Records a command, if recording. The command is only built while
+    /// recording.
+    fn record(&mut self, command: impl FnOnce() -> Command) {
+        if self.recording.is_none() && self.captured.is_none() {
+            return;
+        }
+
+        let command = command();
+        if let Some(captured) = &mut self.captured {
+            captured.push(command.clone());
+        }
+        if let Some(recording) = &mut self.recording {
+            recording.push(command);
+        }
+    }
+
+    /// Counts a vertex edit in the session stats, or a failed edit if it was
+    /// rejected.
+    fn count_vertex_edit<T>(&mut self, result: Result<T, LoftError>) -> Result<T, LoftError> {
+        match result {
+            Ok(_) => self.session_stats.vertex_edits += 1,
+            Err(_) => self.session_stats.failed_vertex_edits += 1,
+        }
+
+        result
+    }
+
+    /// Returns the world positions of all sketches.
+    fn sketch_world_positions(&self) -> Vec<Vec3> {
+        self.sketches
+            .iter()
+            .scan(Vec3::ZERO, |origin, sketch| {
+                *origin += sketch.relative_position;
+                Some(*origin)
+            })
+            .collect()
+    }
+
+    /// Returns the sketch at the given index, if it exists and is not locked.
+    /// Callers bump the revision, and the shape revision for changes to more
+    /// than its transform, once their change succeeds.
+    fn unlocked_sketch_mut(&mut self, sketch_index: usize) -> Result<&mut Sketch, LoftError> {
+        let sketch = self
+            .sketches
+            .get_mut(sketch_index)
+            .ok_or(LoftError::SketchNotFound(sketch_index))?;
+
+        if sketch.locked {
+            return Err(LoftError::SketchLocked(sketch_index));
+        }
+
+        Ok(sketch)
+    }
 }
 
-fn loft_sketches(sketches: SketchPair<&Sketch>, options: &LoftOptions) -> Loft {
-    let mut loft_map_builder = LoftBuilder::new(sketches);
+/// Lofts two sketches, calling `on_candidate` with each edge candidate
+/// considered, and whether it was accepted.
+///
+/// The sketches are first split into sections at the pinned vertices, which
+/// are given with their indices in `LoftOptions::pinned_correspondences`,
+/// then, with `LoftOptions::feature_angle`, at matched features, before the
+/// remaining candidates are tried. The time spent in each phase is added to
+/// `profile` when profiling.
+fn loft_sketches(
+    sketches: SketchPair<&Sketch>,
+    options: &LoftOptions,
+    pins: &[(usize, SketchPair<VertexId>)],
+    strategy: &dyn MatchingStrategy,
+    profile: &mut profiling::PairProfile,
+    mut on_candidate: impl FnMut(&EdgeCandidate, bool),
+) -> Result<Loft, PairLoftError> {
+    let angles = MatchingAngles::new(sketches, options.matching);
+    let pair = MatchingPair { angles: &angles };
+    let mut loft_map_builder = LoftBuilder::new(&angles);
 
-    // Get edge candidates, which are all combinations of vertices between
+    // Get edge candidates, which are combinations of vertices between
     // sketches.
-    let mut edge_candidates = edge_candidates(sketches);
+    let mut edge_candidates = timed(&mut profile.candidate_generation, || {
+        edge_candidates(&pair, strategy, options.max_candidates_per_vertex)
+    });
 
     // Sort edge candidates by increasing radial error.
-    edge_candidates.sort_unstable_by(|a, b| a.radial_error.total_cmp(&b.radial_error));
+    timed(&mut profile.sorting, || {
+        edge_candidates.sort_unstable_by(|a, b| a.radial_error.total_cmp(&b.radial_error));
+    });
+    let best_candidate = edge_candidates
+        .first()
+        .ok_or(PairLoftError::UnmatchedOutlines)?
+        .vertices;
 
     let max_radial_error = options.max_radial_edge_angle.to_radians();
 
-    // Iterate edge candidates, taking edges as long as they are valid, until
-    // radial error > max error.
-    for edge_candidate in edge_candidates {
-        if edge_candidate.radial_error > max_radial_error {
-            break;
+    // Candidates which have already been tried, since splitting at an edge
+    // again would leave an empty section.
+    let mut tried = HashSet::new();
+
+    for &(index, vertices) in pins {
+        let exists = vertices
+            .zip(sketches)
+            .iter()
+            .all(|(id, sketch)| sketch.vertex_map.contains_key(id));
+
+        if !exists || !loft_map_builder.try_split_section(vertices) {
+            return Err(PairLoftError::InvalidPin(index));
         }
+        tried.insert((vertices.lower, vertices.upper));
+    }
+
+    if let Some(feature_angle) = options.feature_angle {
+        let feature_candidates = timed(&mut profile.candidate_generation, || {
+            feature_candidates(&pair, strategy, feature_angle.to_radians())
+        });
+
+        for edge_candidate in feature_candidates {
+            if edge_candidate.radial_error > max_radial_error {
+                break;
+            }
 
-        loft_map_builder.try_split_section(edge_candidate.vertices);
+            let vertices = (edge_candidate.vertices.lower, edge_candidate.vertices.upper);
+            if !tried.insert(vertices) {
+                continue;
+            }
+
+            let accepted = strategy.validate_split(&pair, vertices)
+                && loft_map_builder.try_split_section(edge_candidate.vertices);
+            on_candidate(&edge_candidate, accepted);
+        }
     }
 
+    // Iterate edge candidates, taking edges as long as they are valid, until
+    // radial error > max error.
+    timed(&mut profile.splitting, || {
+        for edge_candidate in edge_candidates {
+            if edge_candidate.radial_error > max_radial_error {
+                break;
+            }
+
+            let vertices = (edge_candidate.vertices.lower, edge_candidate.vertices.upper);
+            if tried.contains(&vertices) {
+                continue;
+            }
+
+            let accepted = strategy.validate_split(&pair, vertices)
+                && loft_map_builder.try_split_section(edge_candidate.vertices);
+            on_candidate(&edge_candidate, accepted);
+        }
+    });
+
     // resolve sections
 
-    loft_map_builder.build(max_radial_error)
+    timed(&mut profile.edge_building, || {
+        loft_map_builder.build(
+            best_candidate,
+            max_radial_error,
+            options.max_triangles_per_section,
+        )
+    })
+}
+
+/// Returns the combinations of the sketches' features, sorted by increasing
+/// score. Features are found like `Lofter::feature_vertices`.
+fn feature_candidates(
+    pair: &MatchingPair,
+    strategy: &dyn MatchingStrategy,
+    feature_angle: f32,
+) -> Vec<EdgeCandidate> {
+    let features = pair
+        .angles
+        .sketches
+        .map(|sketch| feature::feature_vertices(sketch, feature_angle));
+
+    let mut candidates: Vec<EdgeCandidate> = features
+        .lower
+        .iter()
+        .flat_map(|&lower_id| {
+            features.upper.iter().map(move |&upper_id| EdgeCandidate {
+                radial_error: strategy.score(pair, (lower_id, upper_id)),
+                vertices: SketchPair::new(lower_id, upper_id),
+            })
+        })
+        .collect();
+    candidates.sort_unstable_by(|a, b| a.radial_error.total_cmp(&b.radial_error));
+
+    candidates
 }
 
 #[derive(Debug)]
 struct EdgeCandidate {
-    /// The radial angle difference, from the xy origin, of the two vertices.
+    /// The candidate's score from the matching strategy, which by default is
+    /// the radial angle difference, from the xy origin, of the two vertices.
     radial_error: f32,
     vertices: SketchPair<VertexId>,
 }
 
-/// Returns a vector of all combinations of vertices between two sketches.
-fn edge_candidates(sketches: SketchPair<&Sketch>) -> Vec<EdgeCandidate> {
-    let a = sketches.lower;
-    let b = sketches.upper;
+/// Returns a vector of combinations of vertices between two sketches, which
+/// are all of them unless each lower vertex is limited to its
+/// `max_per_vertex` lowest scoring upper vertices. Vertices excluded from
+/// matching aren't combined.
+fn edge_candidates(
+    pair: &MatchingPair,
+    strategy: &dyn MatchingStrategy,
+    max_per_vertex: Option<usize>,
+) -> Vec<EdgeCandidate> {
+    let lower_ids = pair.vertex_ids(0);
+    let upper_ids = pair.vertex_ids(1);
+    let is_lower_candidate = pair.angles.sketches.lower.matching_filter();
+    let is_upper_candidate = pair.angles.sketches.upper.matching_filter();
 
-    a.vertex_order
+    let upper_count = upper_ids
         .iter()
-        .map(|&id| (id, a.vertex_rotated(id)))
-        .flat_map(|v_a| {
-            b.vertex_order
-                .iter()
-                .map(move |&id| (v_a, (id, b.vertex_rotated(id))))
-        })
-        .map(|(v_a, v_b)| EdgeCandidate {
-            radial_error: radial_error(&v_a.1, &v_b.1),
-            vertices: SketchPair::new(v_a.0, v_b.0),
-        })
-        .collect()
+        .filter(|&&id| is_upper_candidate(id))
+        .count();
+    let row_len = max_per_vertex.map_or(upper_count, |max| max.clamp(1, upper_count));
+
+    let mut edge_candidates = Vec::with_capacity(lower_ids.len() * row_len);
+    let mut scores = vec![0.; upper_ids.len()];
+
+    for &lower_id in lower_ids.iter().filter(|&&id| is_lower_candidate(id)) {
+        strategy.score_row(pair, lower_id, &mut scores);
+
+        let row_start = edge_candidates.len();
+        edge_candidates.extend(
+            zip(upper_ids, &scores)
+                .filter(|&(&upper_id, _)| is_upper_candidate(upper_id))
+                .map(|(&upper_id, &radial_error)| EdgeCandidate {
+                    radial_error,
+                    vertices: SketchPair::new(lower_id, upper_id),
+                }),
+        );
+
+        // Keep only the row's lowest scores, without sorting it.
+        let row = &mut edge_candidates[row_start..];
+        if row_len < row.len() {
+            row.select_nth_unstable_by(row_len - 1, |a, b| {
+                a.radial_error.total_cmp(&b.radial_error)
+            });
+            edge_candidates.truncate(row_start + row_len);
+        }
+    }
+
+    edge_candidates
 }
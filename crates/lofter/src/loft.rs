@@ -1,15 +1,20 @@
-use std::{array::from_fn, f32::consts::PI};
+use std::{array::from_fn, collections::HashMap, iter::zip};
 
-use glam::{Vec3, Vec3Swizzles};
+use glam::{Vec2, Vec3};
 use rand::Rng;
 
 use crate::{
+    correspondence::SketchPoint,
+    error::PairLoftError,
+    layout::OutputLayout,
+    matching::MatchingAngles,
     sketch::{Sketch, VertexId},
-    util::{SketchPair, radial_error},
+    util::SketchPair,
 };
 
 /// A loft describes how two sketches are connected.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Loft {
     sections: Vec<LoftSection>,
 
@@ -18,89 +23,470 @@ pub struct Loft {
     sectionless_loft_map: Option<Vec<LoftEdge>>,
 }
 
+/// A vertex of the loft mesh in the local, unrotated space of the sketch it
+/// lies on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LocalLoftVertex {
+    pub sketch_index: usize,
+    pub position: Vec3,
+    pub color: Vec3,
+}
+
+/// A triangle of the loft mesh. Each vertex is paired with the pair index of
+/// the sketch it lies on, i.e. 0 for the lower sketch and 1 for the upper.
+type LoftTriangle = [(usize, LoftVertex); 3];
+
 impl Loft {
-    /// Generates a renderable, non-indexed vertex buffer.
+    /// Returns a loft without any surface, for pairs of sketches which failed
+    /// to loft.
+    pub fn empty() -> Self {
+        Self {
+            sections: Vec::new(),
+            sectionless_loft_map: None,
+        }
+    }
+
+    /// Returns whether the loft has no surface, like `Loft::empty`.
+    pub fn is_empty(&self) -> bool {
+        self.loft_edges()
+            .iter()
+            .all(|loft_edges| loft_edges.is_empty())
+    }
+
+    /// Checks the loft can be built into a surface between the sketches.
+    pub fn validate(&self, sketches: SketchPair<&Sketch>) -> Result<(), PairLoftError> {
+        let loft_edges = self.loft_edges();
+
+        if let Some(index) = loft_edges.iter().position(|edges| edges.is_empty()) {
+            return Err(PairLoftError::EmptySection(index));
+        }
+
+        let finite = loft_edges.iter().copied().flatten().all(|loft_edge| {
+            loft_edge
+                .edge
+                .zip(sketches)
+                .iter()
+                .all(|(vertex, sketch)| vertex.to_local_pos(sketch).is_finite())
+        });
+        if !finite {
+            return Err(PairLoftError::NonFiniteVertex);
+        }
+
+        Ok(())
+    }
+
+    /// Returns whether every sketch vertex the loft references still exists,
+    /// so it can be rebuilt against edited sketches.
+    pub fn references_existing_vertices(&self, sketches: SketchPair<&Sketch>) -> bool {
+        self.loft_edges().into_iter().flatten().all(|loft_edge| {
+            loft_edge
+                .edge
+                .zip(sketches)
+                .iter()
+                .all(|(vertex, sketch)| match vertex {
+                    LoftVertex::SketchVertex(id) => sketch.vertex_map.contains_key(id),
+                    LoftVertex::SketchEdge { edge, .. } => {
+                        sketch.vertex_map.contains_key(&edge.0)
+                            && sketch.vertex_map.contains_key(&edge.1)
+                    }
+                })
+        })
+    }
+
+    /// Returns the loft edges of each section, or the sectionless loft map.
+    fn loft_edges(&self) -> Vec<&[LoftEdge]> {
+        match &self.sectionless_loft_map {
+            Some(loft_map) => vec![loft_map],
+            None => self
+                .sections
+                .iter()
+                .map(|section| section.loft_edges.as_slice())
+                .collect(),
+        }
+    }
+
+    /// Generates a renderable, non-indexed vertex buffer. `origins` are the
+    /// world positions of the sketches.
     pub fn append_vertex_buffer(
         &self,
         vertex_buffer: &mut Vec<[[Vec3; 2]; 3]>,
         sketches: SketchPair<&Sketch>,
+        origins: SketchPair<Vec3>,
     ) {
-        if let Some(loft_map) = &self.sectionless_loft_map {
-            let prev_loft_edge = loft_map.last().unwrap();
-            let first_loft_edge = [loft_map[0]];
-            let loft_edges = loft_map.iter().chain(&first_loft_edge);
+        vertex_buffer.extend(
+            self.world_triangles(sketches, origins)
+                .map(|(triangle, _)| triangle),
+        );
+    }
 
-            append_iterator(vertex_buffer, sketches, prev_loft_edge, loft_edges);
-        } else {
-            let prev_loft_edge = self.sections.last().unwrap().loft_edges.last().unwrap();
-            let first_loft_edge = [self.sections[0].loft_edges[0]];
-            let loft_edges = self
+    /// Returns an iterator over the triangles of `append_vertex_buffer`, and
+    /// the index of the section each is in, generated as it's iterated.
+    pub fn world_triangles<'a>(
+        &'a self,
+        sketches: SketchPair<&'a Sketch>,
+        origins: SketchPair<Vec3>,
+    ) -> impl Iterator<Item = ([[Vec3; 2]; 3], usize)> + 'a {
+        self.triangles()
+            .map(move |(triangle, color, section_index)| {
+                let triangle = triangle.map(|(pair_index, vertex)| {
+                    [
+                        vertex.to_pos(sketches[pair_index], origins[pair_index]),
+                        color,
+                    ]
+                });
+
+                (triangle, section_index)
+            })
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        let mut count = 0;
+        self.for_each_triangle(|_, _, _| count += 1);
+
+        count
+    }
+
+    /// Returns the number of sections, which is 1 for a sectionless loft.
+    pub fn section_count(&self) -> usize {
+        self.sections.len().max(1)
+    }
+
+    /// Generates a non-indexed vertex buffer like `append_vertex_buffer`, but
+    /// with vertices in the local space of their sketches, which have the
+    /// given indices.
+    pub fn append_local_vertex_buffer(
+        &self,
+        vertex_buffer: &mut Vec<[LocalLoftVertex; 3]>,
+        sketches: SketchPair<&Sketch>,
+        sketch_indices: SketchPair<usize>,
+    ) {
+        self.for_each_triangle(|triangle, color, _| {
+            vertex_buffer.push(triangle.map(|(pair_index, vertex)| LocalLoftVertex {
+                sketch_index: sketch_indices[pair_index],
+                position: vertex.to_local_pos(sketches[pair_index]),
+                color,
+            }));
+        });
+    }
+
+    /// Generates a non-indexed vertex buffer like `append_vertex_buffer`, but as
+    /// interleaved floats in the layout. `v` is the UV `v` of each sketch.
+    pub fn append_layout_vertex_buffer(
+        &self,
+        vertex_buffer: &mut Vec<f32>,
+        layout: OutputLayout,
+        sketches: SketchPair<&Sketch>,
+        origins: SketchPair<Vec3>,
+        v: SketchPair<f32>,
+    ) {
+        let outlines = SketchPair::new(
+            sketches.lower.outline_distances(),
+            sketches.upper.outline_distances(),
+        );
+
+        self.for_each_triangle(|triangle, color, _| {
+            let positions = triangle.map(|(pair_index, vertex)| {
+                vertex.to_pos(sketches[pair_index], origins[pair_index])
+            });
+            let [a, b, c] = positions;
+            let normal = (b - a).cross(c - a).normalize_or_zero();
+
+            for ((pair_index, vertex), position) in zip(triangle, positions) {
+                let (distances, perimeter) = &outlines[pair_index];
+                let u = if *perimeter > 0. {
+                    vertex.outline_distance(distances) / perimeter
+                } else {
+                    0.
+                };
+
+                layout.push_vertex(
+                    vertex_buffer,
+                    position,
+                    normal,
+                    Vec2::new(u, v[pair_index]),
+                    color,
+                );
+            }
+        });
+    }
+
+    /// Appends the loft's edges between the two sketches, as world space line
+    /// segments from the lower to the upper sketch.
+    pub fn append_ruling_edges(
+        &self,
+        lines: &mut Vec<[Vec3; 2]>,
+        sketches: SketchPair<&Sketch>,
+        origins: SketchPair<Vec3>,
+    ) {
+        for section_lines in self.section_ruling_edges(sketches, origins) {
+            lines.extend(section_lines);
+        }
+    }
+
+    /// Returns the ruling edges of each section, like `append_ruling_edges`.
+    /// A sectionless loft has a single section.
+    pub fn section_ruling_edges(
+        &self,
+        sketches: SketchPair<&Sketch>,
+        origins: SketchPair<Vec3>,
+    ) -> Vec<Vec<[Vec3; 2]>> {
+        let to_line = |loft_edge: &LoftEdge| {
+            let edge = loft_edge.edge;
+
+            [
+                edge.lower.to_pos(sketches.lower, origins.lower),
+                edge.upper.to_pos(sketches.upper, origins.upper),
+            ]
+        };
+
+        match &self.sectionless_loft_map {
+            Some(loft_map) => vec![loft_map.iter().map(to_line).collect()],
+            None => self
                 .sections
                 .iter()
-                .map(|section| &section.loft_edges)
-                .flatten()
-                .chain(&first_loft_edge);
+                .map(|section| section.loft_edges.iter().map(to_line).collect())
+                .collect(),
+        }
+    }
 
-            append_iterator(vertex_buffer, sketches, prev_loft_edge, loft_edges);
-        };
+    /// Returns the ends of each ruling edge on the sketches, with the index of
+    /// its section, in the order of `append_ruling_edges`.
+    pub fn sketch_points(
+        &self,
+        sketches: SketchPair<&Sketch>,
+    ) -> Vec<(usize, SketchPair<SketchPoint>)> {
+        self.section_loft_edges()
+            .into_iter()
+            .enumerate()
+            .flat_map(|(section, loft_edges)| {
+                loft_edges.iter().map(move |loft_edge| {
+                    let points = loft_edge
+                        .edge
+                        .zip(sketches)
+                        .map(|(vertex, sketch)| vertex.to_sketch_point(sketch));
+
+                    (section, points)
+                })
+            })
+            .collect()
+    }
 
-        fn append_iterator<'a>(
-            vertex_buffer: &mut Vec<[[Vec3; 2]; 3]>,
-            sketches: SketchPair<&Sketch>,
-            mut prev_loft_edge: &'a LoftEdge,
-            loft_edges: impl Iterator<Item = &'a LoftEdge>,
-        ) {
-            for loft_edge in loft_edges {
-                // Color each face a different random color.
-                let color = loft_edge.color;
-
-                if prev_loft_edge.edge.lower == loft_edge.edge.lower {
-                    // Tri.
-                    vertex_buffer.push([
-                        [prev_loft_edge.edge.upper.to_pos(sketches.upper), color],
-                        [loft_edge.edge.lower.to_pos(sketches.lower), color],
-                        [loft_edge.edge.upper.to_pos(sketches.upper), color],
-                    ]);
-                } else if prev_loft_edge.edge.upper == loft_edge.edge.upper {
-                    // Tri.
-                    vertex_buffer.push([
-                        [prev_loft_edge.edge.upper.to_pos(sketches.upper), color],
-                        [prev_loft_edge.edge.lower.to_pos(sketches.lower), color],
-                        [loft_edge.edge.lower.to_pos(sketches.lower), color],
-                    ]);
-                } else {
-                    // Quad.
-                    vertex_buffer.push([
-                        [prev_loft_edge.edge.upper.to_pos(sketches.upper), color],
-                        [prev_loft_edge.edge.lower.to_pos(sketches.lower), color],
-                        [loft_edge.edge.lower.to_pos(sketches.lower), color],
-                    ]);
-                    vertex_buffer.push([
-                        [prev_loft_edge.edge.upper.to_pos(sketches.upper), color],
-                        [loft_edge.edge.lower.to_pos(sketches.lower), color],
-                        [loft_edge.edge.upper.to_pos(sketches.upper), color],
-                    ]);
+    /// Appends the section index of each triangle, in the order of
+    /// `append_vertex_buffer`, offset by `first_section`. Returns the number
+    /// of sections, like `section_count`.
+    pub fn append_triangle_sections(
+        &self,
+        sections: &mut Vec<usize>,
+        first_section: usize,
+    ) -> usize {
+        self.for_each_triangle(|_, _, section_index| {
+            sections.push(first_section + section_index);
+        });
+
+        self.section_count()
+    }
+
+    /// Coarsens sections with more than `max_triangles` triangles, by
+    /// replacing intermediate edges, which end partway along a sketch edge,
+    /// with edges between sketch vertices. The edges closest to the allowed
+    /// radial error are replaced first, until the section fits or has no
+    /// intermediate edges left, so sections can't be coarsened below one
+    /// triangle per sketch vertex.
+    fn limit_section_triangles(&mut self, angles: &MatchingAngles, max_triangles: usize) {
+        loop {
+            let triangle_counts = self.section_triangle_counts();
+
+            // The intermediate edge to replace, as `(section, edge, angle)`.
+            let replacement = self
+                .section_loft_edges_mut()
+                .into_iter()
+                .enumerate()
+                .filter(|(section, _)| triangle_counts[*section] > max_triangles)
+                .flat_map(|(section, loft_edges)| {
+                    loft_edges
+                        .iter()
+                        .enumerate()
+                        .filter_map(move |(edge, loft_edge)| {
+                            let vertices = loft_edge.coarsened()?.vertex_ids()?;
+
+                            Some((section, edge, angles.angle_between(vertices).abs()))
+                        })
+                })
+                .min_by(|a, b| a.2.total_cmp(&b.2));
+
+            let Some((section, edge, _)) = replacement else {
+                break;
+            };
+
+            let loft_edge = &mut self.section_loft_edges_mut()[section][edge];
+            *loft_edge = loft_edge.coarsened().unwrap();
+        }
+    }
+
+    /// Returns the number of triangles in each section, as in the vertex
+    /// buffer. A sectionless loft has a single section.
+    fn section_triangle_counts(&self) -> Vec<usize> {
+        let section_count = self.sections.len().max(1);
+        let mut counts = vec![0; section_count];
+
+        self.for_each_triangle(|_, _, section| counts[section] += 1);
+
+        counts
+    }
+
+    /// Returns the loft edges of each section, or of the sectionless loft map.
+    fn section_loft_edges(&self) -> Vec<&[LoftEdge]> {
+        match &self.sectionless_loft_map {
+            Some(loft_map) => vec![loft_map.as_slice()],
+            None => self
+                .sections
+                .iter()
+                .map(|section| section.loft_edges.as_slice())
+                .collect(),
+        }
+    }
+
+    /// Like `section_loft_edges`, but mutable.
+    fn section_loft_edges_mut(&mut self) -> Vec<&mut Vec<LoftEdge>> {
+        match &mut self.sectionless_loft_map {
+            Some(loft_map) => vec![loft_map],
+            None => self
+                .sections
+                .iter_mut()
+                .map(|section| &mut section.loft_edges)
+                .collect(),
+        }
+    }
+
+    /// Calls `f` with each triangle of the loft mesh, its color, and the index
+    /// of the section it's in, like `triangles`.
+    fn for_each_triangle(&self, mut f: impl FnMut(LoftTriangle, Vec3, usize)) {
+        for (triangle, color, section_index) in self.triangles() {
+            f(triangle, color, section_index);
+        }
+    }
+
+    /// Returns an iterator over each triangle of the loft mesh, its color, and
+    /// the index of the section it's in, generated as it's iterated. Empty
+    /// lofts have no triangles, and sections without edges are skipped.
+    fn triangles(&self) -> LoftTriangles<'_> {
+        let sections = self.section_loft_edges();
+
+        let prev_loft_edge = sections
+            .iter()
+            .rev()
+            .find_map(|loft_edges| loft_edges.last());
+
+        LoftTriangles {
+            sections,
+            next_edge: (0, 0),
+            closed: false,
+            prev_loft_edge,
+            pending: None,
+        }
+    }
+}
+
+/// The triangles of a loft, returned by `Loft::triangles`. The loft's edges
+/// are walked in CCW order, forming a triangle or a quad with the previous
+/// edge, ending with the first edge again to close the loft. Each triangle is
+/// in the section of the edge ending it.
+struct LoftTriangles<'a> {
+    /// The loft edges of each section.
+    sections: Vec<&'a [LoftEdge]>,
+    /// The `(section, edge)` index of the next edge.
+    next_edge: (usize, usize),
+    /// Whether the first edge has been walked again.
+    closed: bool,
+    prev_loft_edge: Option<&'a LoftEdge>,
+    /// The second triangle of a quad.
+    pending: Option<(LoftTriangle, Vec3, usize)>,
+}
+
+impl<'a> LoftTriangles<'a> {
+    fn next_loft_edge(&mut self) -> Option<(usize, &'a LoftEdge)> {
+        loop {
+            let (section, edge) = self.next_edge;
+
+            let Some(loft_edges) = self.sections.get(section) else {
+                if self.closed {
+                    return None;
                 }
+                self.closed = true;
+
+                return self
+                    .sections
+                    .iter()
+                    .enumerate()
+                    .find_map(|(section, loft_edges)| Some((section, loft_edges.first()?)));
+            };
 
-                prev_loft_edge = loft_edge;
+            match loft_edges.get(edge) {
+                Some(loft_edge) => {
+                    self.next_edge = (section, edge + 1);
+                    return Some((section, loft_edge));
+                }
+                None => self.next_edge = (section + 1, 0),
             }
         }
     }
 }
 
+impl Iterator for LoftTriangles<'_> {
+    type Item = (LoftTriangle, Vec3, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(triangle) = self.pending.take() {
+            return Some(triangle);
+        }
+
+        let (section_index, loft_edge) = self.next_loft_edge()?;
+        let prev_edge = self.prev_loft_edge?.edge;
+        self.prev_loft_edge = Some(loft_edge);
+
+        // Color each face a different random color.
+        let color = loft_edge.color;
+        let edge = loft_edge.edge;
+
+        let triangle = if prev_edge.lower == edge.lower {
+            // Tri.
+            [(1, prev_edge.upper), (0, edge.lower), (1, edge.upper)]
+        } else if prev_edge.upper == edge.upper {
+            // Tri.
+            [(1, prev_edge.upper), (0, prev_edge.lower), (0, edge.lower)]
+        } else {
+            // Quad.
+            self.pending = Some((
+                [(1, prev_edge.upper), (0, edge.lower), (1, edge.upper)],
+                color,
+                section_index,
+            ));
+
+            [(1, prev_edge.upper), (0, prev_edge.lower), (0, edge.lower)]
+        };
+
+        Some((triangle, color, section_index))
+    }
+}
+
 pub struct LoftBuilder<'a> {
     loft: Loft,
     sketches: SketchPair<&'a Sketch>,
+    angles: &'a MatchingAngles<'a>,
 }
 
 impl<'a> LoftBuilder<'a> {
-    pub fn new(sketches: SketchPair<&'a Sketch>) -> Self {
+    pub fn new(angles: &'a MatchingAngles<'a>) -> Self {
         let loft = Loft {
             sections: Vec::new(),
             sectionless_loft_map: None,
         };
 
-        Self { loft, sketches }
+        Self {
+            loft,
+            sketches: angles.sketches,
+            angles,
+        }
     }
 
     /// Splits, or subdivides, a section into two different sections, creating
@@ -109,8 +495,8 @@ impl<'a> LoftBuilder<'a> {
     /// sketches.
     ///
     /// If the passed-in vertices lie in two different sections, the split is
-    /// invalid, and the function returns without modifying the loft.
-    pub fn try_split_section(&mut self, edge_candidate_vertices: SketchPair<VertexId>) {
+    /// invalid, and the function returns false without modifying the loft.
+    pub fn try_split_section(&mut self, edge_candidate_vertices: SketchPair<VertexId>) -> bool {
         // Check if this is the first split.
         if self.loft.sections.is_empty() {
             // Create an initial section encompassing the entirety of the
@@ -122,14 +508,14 @@ impl<'a> LoftBuilder<'a> {
 
             self.loft.sections.push(initial_section);
 
-            return;
+            return true;
         }
 
         // Make sure the edge candidate vertices lie within the same section. If
         // they don't, this is not a valid split.
         let Some(section_index) = self.edge_candidate_section_index(edge_candidate_vertices) else {
             // Invalid split.
-            return;
+            return false;
         };
 
         // Split the section by removing it and inserting two new sections.
@@ -165,6 +551,8 @@ impl<'a> LoftBuilder<'a> {
         // order.
         self.loft.sections.insert(section_index, new_section_a);
         self.loft.sections.insert(section_index + 1, new_section_b);
+
+        true
     }
 
     /// Returns the index in `Loft::sections` of the section containing the edge
@@ -195,35 +583,43 @@ impl<'a> LoftBuilder<'a> {
         })
     }
 
-    pub fn build(self, max_radial_error: f32) -> Loft {
+    /// Builds the loft. If no candidate split a section, the loft starts from
+    /// `best_candidate`, the edge candidate with the smallest radial error.
+    /// Sections with more than `max_triangles_per_section` triangles are
+    /// coarsened, like `Loft::limit_section_triangles`.
+    pub fn build(
+        self,
+        best_candidate: SketchPair<VertexId>,
+        max_radial_error: f32,
+        max_triangles_per_section: Option<usize>,
+    ) -> Result<Loft, PairLoftError> {
         let mut loft = self.loft;
 
         if loft.sections.is_empty() {
-            // Use starting vertices with the smallest radial error.
-            let mut edge_candidates = crate::edge_candidates(self.sketches);
-            edge_candidates.sort_unstable_by(|a, b| a.radial_error.total_cmp(&b.radial_error));
-
-            let sketch_vertex_ranges = edge_candidates[0]
-                .vertices
-                .map(|id| SketchVertexRange::entire(id));
+            let sketch_vertex_ranges = best_candidate.map(SketchVertexRange::entire);
 
             let loft_edges =
-                build_loft_edges(sketch_vertex_ranges, self.sketches, max_radial_error);
+                build_loft_edges(sketch_vertex_ranges, self.angles, max_radial_error, false)?;
 
             loft.sectionless_loft_map = Some(loft_edges);
         } else {
             for section in &mut loft.sections {
-                section.build_loft(self.sketches, max_radial_error);
+                section.build_loft(self.angles, max_radial_error)?;
             }
         }
 
-        loft
+        if let Some(max_triangles) = max_triangles_per_section {
+            loft.limit_section_triangles(self.angles, max_triangles);
+        }
+
+        Ok(loft)
     }
 }
 
 /// A "section" of a loft connects a range of vertices from one sketch to a
 /// range of vertices in another sketch.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct LoftSection {
     /// Ranges of vertices that this section covers in the original sketches.
     sketch_vertex_ranges: SketchPair<SketchVertexRange>,
@@ -256,9 +652,17 @@ impl LoftSection {
     }
 
     /// Initializes the "physical" loft vertices and edges from the section's
-    /// vertex ranges.
-    fn build_loft(&mut self, sketches: SketchPair<&Sketch>, max_radial_error: f32) {
-        self.loft_edges = build_loft_edges(self.sketch_vertex_ranges, sketches, max_radial_error);
+    /// vertex ranges. The section starts at a split, which is kept as an edge
+    /// even if it's pinned past the allowed radial error.
+    fn build_loft(
+        &mut self,
+        angles: &MatchingAngles,
+        max_radial_error: f32,
+    ) -> Result<(), PairLoftError> {
+        self.loft_edges =
+            build_loft_edges(self.sketch_vertex_ranges, angles, max_radial_error, true)?;
+
+        Ok(())
     }
 }
 
@@ -268,6 +672,7 @@ impl LoftSection {
 /// may form an edge or a series of edges, or the range of vertices may contain
 /// only a single vertex.
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct SketchVertexRange {
     /// Range of vertices in the original sketches in this section (range
     /// inclusive).
@@ -360,6 +765,7 @@ impl Iterator for SketchVertexRangeIter<'_> {
 /// of a sketch, i.e. it might not be present in the original set of sketch
 /// vertices.
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 enum LoftVertex {
     /// A loft vertex at the same position as a sketch's vertex.
     SketchVertex(VertexId),
@@ -374,9 +780,11 @@ enum LoftVertex {
 }
 
 impl LoftVertex {
-    fn to_pos(&self, sketch: &Sketch) -> Vec3 {
+    /// Returns the world position of the vertex, where `origin` is the world
+    /// position of the sketch.
+    fn to_pos(self, sketch: &Sketch, origin: Vec3) -> Vec3 {
         let relative_pos = match self {
-            LoftVertex::SketchVertex(id) => sketch.vertex_rotated(*id),
+            LoftVertex::SketchVertex(id) => sketch.vertex_rotated(id),
             LoftVertex::SketchEdge { edge, edge_length } => {
                 let a = sketch.vertex_rotated(edge.0);
                 let b = sketch.vertex_rotated(edge.1);
@@ -385,16 +793,89 @@ impl LoftVertex {
             }
         };
 
-        relative_pos + sketch.relative_position
+        relative_pos + origin
+    }
+
+    /// Returns the vertex as a point along the sketch's outline.
+    fn to_sketch_point(self, sketch: &Sketch) -> SketchPoint {
+        match self {
+            LoftVertex::SketchVertex(vertex_id) => SketchPoint {
+                vertex_id,
+                param: 0.,
+            },
+            LoftVertex::SketchEdge { edge, edge_length } => {
+                let length = sketch.vertex_map[&edge.0].distance(sketch.vertex_map[&edge.1]);
+
+                SketchPoint {
+                    vertex_id: edge.0,
+                    param: if length > 0. {
+                        edge_length / length
+                    } else {
+                        0.
+                    },
+                }
+            }
+        }
+    }
+
+    /// Returns the distance around the sketch's outline from its first vertex
+    /// to the vertex, given each sketch vertex's from `Sketch::outline_distances`.
+    fn outline_distance(self, distances: &HashMap<VertexId, f32>) -> f32 {
+        match self {
+            LoftVertex::SketchVertex(id) => distances[&id],
+            LoftVertex::SketchEdge { edge, edge_length } => distances[&edge.0] + edge_length,
+        }
+    }
+
+    /// Returns the position of the vertex in the sketch's local space, without
+    /// its rotation applied.
+    fn to_local_pos(self, sketch: &Sketch) -> Vec3 {
+        match self {
+            LoftVertex::SketchVertex(id) => sketch.vertex_map[&id],
+            LoftVertex::SketchEdge { edge, edge_length } => {
+                let a = sketch.vertex_map[&edge.0];
+                let b = sketch.vertex_map[&edge.1];
+
+                a + (b - a).normalize() * edge_length
+            }
+        }
     }
 }
 
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct LoftEdge {
     edge: SketchPair<LoftVertex>,
     color: Vec3,
 }
 
+impl LoftEdge {
+    /// Returns the edge between the sketch vertices at the start of each
+    /// side, if this is an intermediate edge ending partway along a sketch
+    /// edge.
+    fn coarsened(self) -> Option<Self> {
+        let coarsen = |vertex| match vertex {
+            LoftVertex::SketchVertex(_) => vertex,
+            LoftVertex::SketchEdge { edge, .. } => LoftVertex::SketchVertex(edge.0),
+        };
+
+        let edge = self.edge.map(coarsen);
+
+        (edge != self.edge).then_some(Self { edge, ..self })
+    }
+
+    /// Returns the sketch vertices of each side, unless either is
+    /// intermediate.
+    fn vertex_ids(self) -> Option<SketchPair<VertexId>> {
+        match (self.edge.lower, self.edge.upper) {
+            (LoftVertex::SketchVertex(lower), LoftVertex::SketchVertex(upper)) => {
+                Some(SketchPair::new(lower, upper))
+            }
+            _ => None,
+        }
+    }
+}
+
 impl From<SketchPair<LoftVertex>> for LoftEdge {
     fn from(value: SketchPair<LoftVertex>) -> Self {
         let mut rng = rand::rng();
@@ -407,12 +888,19 @@ impl From<SketchPair<LoftVertex>> for LoftEdge {
 }
 
 /// Initializes the "physical" loft vertices and edges from a section's vertex
-/// ranges.
+/// ranges. If `keep_first_edge`, the first vertices form an edge regardless
+/// of their radial error.
+///
+/// Fails if one sketch's vertices run out while the other's are still too
+/// far around to meet them, as when one doesn't enclose the axis its
+/// vertices' angles are measured around.
 fn build_loft_edges(
     sketch_vertex_ranges: SketchPair<SketchVertexRange>,
-    sketches: SketchPair<&Sketch>,
+    angles: &MatchingAngles,
     max_radial_error: f32,
-) -> Vec<LoftEdge> {
+    keep_first_edge: bool,
+) -> Result<Vec<LoftEdge>, PairLoftError> {
+    let sketches = angles.sketches;
     let mut loft_edges = Vec::new();
 
     // Iterate vertices of each sketch edge in parallel.
@@ -421,9 +909,14 @@ fn build_loft_edges(
         .zip(sketches)
         .map(|(range, sketch)| range.iter(sketch).peekable());
 
-    let mut current_vertex_ids = sketch_vertex_iters
-        .as_mut()
-        .map(|iter| iter.next().unwrap());
+    let (Some(lower), Some(upper)) = (
+        sketch_vertex_iters.lower.next(),
+        sketch_vertex_iters.upper.next(),
+    ) else {
+        return Err(PairLoftError::UnmatchedOutlines);
+    };
+    let mut current_vertex_ids = SketchPair::new(lower, upper);
+    let mut keep_edge = keep_first_edge;
 
     // Iterate until the current vertices are the last ones in the section.
     while sketch_vertex_iters
@@ -432,22 +925,12 @@ fn build_loft_edges(
         .iter()
         .any(|next| next.is_some())
     {
-        let current_vertex_positions = current_vertex_ids
-            .zip(sketches)
-            .map(|(id, sketch)| sketch.vertex_rotated(id));
+        let current_angle = angles.angle_between(current_vertex_ids);
 
         // If the current vertices can form a valid edge (i.e it is within
         // the allowed radial error), create the edge.
-        if radial_error(
-            &current_vertex_positions.lower,
-            &current_vertex_positions.upper,
-        ) <= max_radial_error
-        {
-            loft_edges.push(
-                current_vertex_ids
-                    .map(|id| LoftVertex::SketchVertex(id))
-                    .into(),
-            );
+        if std::mem::take(&mut keep_edge) || current_angle.abs() <= max_radial_error {
+            loft_edges.push(current_vertex_ids.map(LoftVertex::SketchVertex).into());
         } else {
             // Form an intermediate edge for the CCW-most current vertex.
 
@@ -456,16 +939,7 @@ fn build_loft_edges(
             // current vertices. The "pair index" is the index into the
             // `SketchPair`, as a programmatic way of accessing the lower or
             // upper sketch.
-            let pair_vertex_index = if current_vertex_positions
-                .lower
-                .xy()
-                .angle_to(current_vertex_positions.upper.xy())
-                < 0.
-            {
-                0
-            } else {
-                1
-            };
+            let pair_vertex_index = if current_angle < 0. { 0 } else { 1 };
 
             // The pair index of the edge to split (just the opposite of
             // `pair_vertex_index`).
@@ -474,19 +948,12 @@ fn build_loft_edges(
             let vertex_id = current_vertex_ids[pair_vertex_index];
             let edge_vertex_ids = (
                 current_vertex_ids[pair_edge_index],
-                *sketch_vertex_iters[pair_edge_index].peek().unwrap(),
+                *sketch_vertex_iters[pair_edge_index]
+                    .peek()
+                    .ok_or(PairLoftError::UnmatchedOutlines)?,
             );
 
-            let vertex_position = sketches[pair_vertex_index].vertex_rotated(vertex_id);
-            let edge_vertex_positions = {
-                let sketch = &sketches[pair_edge_index];
-                (
-                    &sketch.vertex_rotated(edge_vertex_ids.0),
-                    &sketch.vertex_rotated(edge_vertex_ids.1),
-                )
-            };
-
-            let edge_length = edge_length(&vertex_position, edge_vertex_positions);
+            let edge_length = angles.edge_length(pair_vertex_index, vertex_id, edge_vertex_ids);
 
             let loft_vertex_vertex = LoftVertex::SketchVertex(vertex_id);
             let loft_vertex_edge = LoftVertex::SketchEdge {
@@ -505,67 +972,45 @@ fn build_loft_edges(
 
         // Increment the vertex iterator for one of the sketches.
 
-        let next_vertex_positions = sketch_vertex_iters
+        let next_vertex_ids = sketch_vertex_iters
             .as_mut()
-            .zip(sketches)
-            .map(|(iter, sketch)| iter.peek().map(|id| sketch.vertex_rotated(*id)));
+            .map(|iter| iter.peek().copied());
 
-        if next_vertex_positions.lower.is_some() && next_vertex_positions.upper.is_none() {
-            current_vertex_ids.lower = sketch_vertex_iters.lower.next().unwrap();
-        } else if next_vertex_positions.lower.is_none() && next_vertex_positions.upper.is_some() {
-            current_vertex_ids.upper = sketch_vertex_iters.upper.next().unwrap();
-        } else {
-            // There are still vertices to iterate on both sketches. In this
-            // case, check the positions of both of the next vertices, and
-            // only increment the CW-most of the next two vertices.
-
-            let next_vertex_positions = next_vertex_positions.map(Option::unwrap);
-
-            let angle = next_vertex_positions
-                .lower
-                .xy()
-                .angle_to(next_vertex_positions.upper.xy());
-
-            if angle.abs() <= max_radial_error {
-                // If the next two vertices can form a valid edge, we've
-                // reached the end of the section.
-                break;
+        match (next_vertex_ids.lower, next_vertex_ids.upper) {
+            (Some(lower), None) => {
+                current_vertex_ids.lower = lower;
+                sketch_vertex_iters.lower.next();
             }
+            (None, Some(upper)) => {
+                current_vertex_ids.upper = upper;
+                sketch_vertex_iters.upper.next();
+            }
+            (Some(lower), Some(upper)) => {
+                // There are still vertices to iterate on both sketches. In
+                // this case, check the positions of both of the next
+                // vertices, and only increment the CW-most of the next two
+                // vertices.
+
+                let angle = angles.angle_between(SketchPair::new(lower, upper));
+
+                if angle.abs() <= max_radial_error {
+                    // If the next two vertices can form a valid edge, we've
+                    // reached the end of the section.
+                    break;
+                }
 
-            if angle > 0. {
-                current_vertex_ids.lower = sketch_vertex_iters.lower.next().unwrap();
-            } else {
-                current_vertex_ids.upper = sketch_vertex_iters.upper.next().unwrap();
+                if angle > 0. {
+                    current_vertex_ids.lower = lower;
+                    sketch_vertex_iters.lower.next();
+                } else {
+                    current_vertex_ids.upper = upper;
+                    sketch_vertex_iters.upper.next();
+                }
             }
+            // The loop only continues while either sketch has vertices left.
+            (None, None) => break,
         }
     }
 
-    loft_edges
-}
-
-fn edge_length(vertex_position: &Vec3, edge_vertex_positions: (&Vec3, &Vec3)) -> f32 {
-    // Variable names reference graphic here:
-    // <https://www.mathsisfun.com/algebra/trig-sine-law.html>.
-
-    // First solve for the edge vertices, to get B.
-    let angle_b = {
-        let angle_a = edge_vertex_positions
-            .0
-            .xy()
-            .angle_to(edge_vertex_positions.1.xy());
-        let edge_a = edge_vertex_positions
-            .0
-            .xy()
-            .distance(edge_vertex_positions.1.xy());
-        let edge_b = edge_vertex_positions.1.length();
-
-        (edge_b * angle_a.sin() / edge_a).asin()
-    };
-
-    // Now solve for a.
-    let angle_a = edge_vertex_positions.0.xy().angle_to(vertex_position.xy());
-    let edge_c = edge_vertex_positions.0.xy().length();
-    let angle_c = PI - angle_a - angle_b;
-
-    edge_c * angle_a.sin() / angle_c.sin()
+    Ok(loft_edges)
 }
@@ -0,0 +1,357 @@
+use std::{
+    collections::HashMap,
+    f32::consts::{PI, TAU},
+};
+
+use glam::{Vec2, Vec3, Vec3Swizzles, Vec4};
+
+use crate::{
+    sketch::{Sketch, VertexId},
+    util::SketchPair,
+};
+
+/// How vertices of adjacent sketches are matched when lofting.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Matching {
+    /// Matches vertices by their polar angle about the sketch origin.
+    #[default]
+    Polar,
+    /// Matches vertices by how far around the outline they are, measured by
+    /// the outline's cumulative turning angle, with a little weight on its arc
+    /// length so vertices along straight edges are spread out. Corners of
+    /// wildly different profiles, such as a cross and a circle, are then
+    /// spread evenly around each other, reducing spiraling edges.
+    TurningAngle,
+}
+
+/// How much of the turning-angle parameter comes from arc length.
+const ARC_LENGTH_WEIGHT: f32 = 0.2;
+
+/// Vertices closer than this to a sketch's z axis have no meaningful polar
+/// angle.
+const AXIS_TOLERANCE: f32 = 1e-6;
+
+/// The angles of two sketches' vertices which they are matched by, in radians.
+pub struct MatchingAngles<'a> {
+    pub sketches: SketchPair<&'a Sketch>,
+    /// The angle of each vertex when matching by turning angle, aligned to the
+    /// polar angles of the sketch's vertices. Polar angles are computed when
+    /// needed.
+    turning_angles: Option<SketchPair<HashMap<VertexId, f32>>>,
+    /// The polar direction of each of the upper sketch's vertices, in CCW
+    /// order, when matching by polar angle, so they're only computed once for
+    /// `abs_angles_from`.
+    upper_directions: Option<Vec<Vec2>>,
+}
+
+impl<'a> MatchingAngles<'a> {
+    pub fn new(sketches: SketchPair<&'a Sketch>, matching: Matching) -> Self {
+        let turning_angles = match matching {
+            Matching::Polar => None,
+            Matching::TurningAngle => Some(sketches.map(turning_angles)),
+        };
+        let upper_directions = match matching {
+            Matching::Polar => Some(
+                sketches
+                    .upper
+                    .vertex_order
+                    .iter()
+                    .map(|&id| polar_direction(sketches.upper, id))
+                    .collect(),
+            ),
+            Matching::TurningAngle => None,
+        };
+
+        Self {
+            sketches,
+            turning_angles,
+            upper_directions,
+        }
+    }
+
+    /// Writes the absolute angle from a vertex of the lower sketch to each
+    /// vertex of the upper sketch, in CCW order, to `angles`. The same as
+    /// `angle_between(..).abs()` for each, but polar angles are computed four
+    /// at a time.
+    pub fn abs_angles_from(&self, lower_id: VertexId, angles: &mut [f32]) {
+        let upper_ids = &self.sketches.upper.vertex_order;
+        debug_assert_eq!(angles.len(), upper_ids.len());
+
+        let Some(upper_directions) = &self.upper_directions else {
+            for (angle, &upper_id) in angles.iter_mut().zip(upper_ids) {
+                *angle = self
+                    .angle_between(SketchPair::new(lower_id, upper_id))
+                    .abs();
+            }
+            return;
+        };
+
+        let from = polar_direction(self.sketches.lower, lower_id);
+
+        let mut direction_chunks = upper_directions.chunks_exact(4);
+        let mut angle_chunks = angles.chunks_exact_mut(4);
+        for (directions, angles) in (&mut direction_chunks).zip(&mut angle_chunks) {
+            let x = Vec4::from_array(std::array::from_fn(|i| directions[i].x));
+            let y = Vec4::from_array(std::array::from_fn(|i| directions[i].y));
+
+            angles.copy_from_slice(&abs_angles_to(from, x, y).to_array());
+        }
+
+        for (angle, &direction) in angle_chunks
+            .into_remainder()
+            .iter_mut()
+            .zip(direction_chunks.remainder())
+        {
+            *angle = from.angle_to(direction).abs();
+        }
+    }
+
+    /// Returns the signed angle from a vertex of the lower sketch to a vertex
+    /// of the upper sketch, which is positive when the upper vertex is CCW of
+    /// the lower vertex.
+    pub fn angle_between(&self, vertices: SketchPair<VertexId>) -> f32 {
+        match &self.turning_angles {
+            Some(angles) => {
+                wrap_angle(angles.upper[&vertices.upper] - angles.lower[&vertices.lower])
+            }
+            None => {
+                let directions = self
+                    .sketches
+                    .zip(vertices)
+                    .map(|(sketch, id)| polar_direction(sketch, id));
+
+                directions.lower.angle_to(directions.upper)
+            }
+        }
+    }
+
+    /// Returns the distance along a sketch's edge, from its first vertex, of
+    /// the point matched to a vertex of the other sketch. `pair_vertex_index`
+    /// is the pair index of the vertex's sketch.
+    pub fn edge_length(
+        &self,
+        pair_vertex_index: usize,
+        vertex_id: VertexId,
+        edge_vertex_ids: (VertexId, VertexId),
+    ) -> f32 {
+        let pair_edge_index = (pair_vertex_index + 1) % 2;
+
+        let edge_vertex_positions = {
+            let sketch = self.sketches[pair_edge_index];
+            (
+                sketch.vertex_rotated(edge_vertex_ids.0),
+                sketch.vertex_rotated(edge_vertex_ids.1),
+            )
+        };
+
+        match &self.turning_angles {
+            Some(angles) => {
+                let vertex_angle = angles[pair_vertex_index][&vertex_id];
+                let edge_angles = &angles[pair_edge_index];
+                let edge_start_angle = edge_angles[&edge_vertex_ids.0];
+                let edge_angle = wrap_angle(edge_angles[&edge_vertex_ids.1] - edge_start_angle);
+
+                let fraction = if edge_angle > 0. {
+                    (wrap_angle(vertex_angle - edge_start_angle) / edge_angle).clamp(0., 1.)
+                } else {
+                    0.
+                };
+
+                fraction * edge_vertex_positions.0.distance(edge_vertex_positions.1)
+            }
+            None => polar_edge_length(
+                polar_direction(self.sketches[pair_vertex_index], vertex_id),
+                (&edge_vertex_positions.0, &edge_vertex_positions.1),
+            ),
+        }
+    }
+}
+
+/// Returns the direction of a vertex from its sketch's z axis, which polar
+/// matching compares the angles of.
+///
+/// Vertices on the axis have no direction of their own, so they're matched as
+/// if between their nearest neighbors off the axis, rather than giving NaN
+/// angles. Consecutive vertices on the axis are spread evenly between them, so
+/// their angles still increase in CCW order.
+fn polar_direction(sketch: &Sketch, vertex_id: VertexId) -> Vec2 {
+    let position = sketch.vertex_rotated(vertex_id).xy();
+    if position.length() > AXIS_TOLERANCE {
+        return position;
+    }
+
+    let count = sketch.vertex_order.len();
+    let index = sketch
+        .vertex_order
+        .iter()
+        .position(|&id| id == vertex_id)
+        .unwrap();
+    let neighbor = |offset| {
+        let id = sketch.vertex_order[(index + offset) % count];
+        sketch.vertex_rotated(id).xy()
+    };
+    let is_off_axis = |offset| neighbor(offset).length() > AXIS_TOLERANCE;
+
+    // How many vertices back and forward the nearest off-axis neighbors are.
+    let Some(previous) = (1..count).find(|&offset| is_off_axis(count - offset)) else {
+        // Every vertex is on the axis, so they're spread around it.
+        return Vec2::from_angle(index as f32 / count as f32 * TAU);
+    };
+    let next = (1..count).find(|&offset| is_off_axis(offset)).unwrap();
+
+    let previous_angle = neighbor(count - previous).to_angle();
+    // The neighbors are in CCW order, so the vertex is CCW of the previous
+    // neighbor, by a full turn if it's the only vertex off the axis.
+    let span = if previous + next == count {
+        TAU
+    } else {
+        (neighbor(next).to_angle() - previous_angle).rem_euclid(TAU)
+    };
+
+    Vec2::from_angle(previous_angle + span * previous as f32 / (previous + next) as f32)
+}
+
+/// Returns the absolute angle from `from` to each of four directions, given
+/// by their x and y components. This is `Vec2::angle_to`, including its
+/// approximation of `acos`, so the results are identical to computing them
+/// one at a time.
+fn abs_angles_to(from: Vec2, x: Vec4, y: Vec4) -> Vec4 {
+    let dot = x * from.x + y * from.y;
+    let cos = dot / (from.length_squared() * (x * x + y * y)).map(f32::sqrt);
+
+    // Based on https://github.com/microsoft/DirectXMath `XMScalarAcos`, as in
+    // glam.
+    let abs_cos = cos.abs();
+    let one_minus = Vec4::ONE - abs_cos;
+    let root = Vec4::select(one_minus.cmplt(Vec4::ZERO), Vec4::ZERO, one_minus).map(f32::sqrt);
+
+    #[allow(clippy::approx_constant)]
+    let acos = ((((((Vec4::splat(-0.001_262_491_1) * abs_cos + 0.006_670_09) * abs_cos
+        - 0.017_088_126)
+        * abs_cos
+        + 0.030_891_88)
+        * abs_cos
+        - 0.050_174_303)
+        * abs_cos
+        + 0.088_978_99)
+        * abs_cos
+        - 0.214_598_8)
+        * abs_cos
+        + 1.570_796_3;
+    let acos = acos * root;
+
+    // acos(x) = pi - acos(-x) when x < 0. The angle's sign is dropped.
+    Vec4::select(cos.cmpge(Vec4::ZERO), acos, PI - acos)
+}
+
+/// Returns the length along the edge to where it's crossed by the ray from
+/// the origin in the direction of a vertex.
+fn polar_edge_length(vertex_direction: Vec2, edge_vertex_positions: (&Vec3, &Vec3)) -> f32 {
+    // Every other point on an edge from the axis has the same direction, so
+    // the ray crosses the edge at the axis.
+    if edge_vertex_positions.0.xy().length() <= AXIS_TOLERANCE {
+        return 0.;
+    }
+    if edge_vertex_positions.1.xy().length() <= AXIS_TOLERANCE {
+        return edge_vertex_positions.0.distance(*edge_vertex_positions.1);
+    }
+
+    // Variable names reference graphic here:
+    // <https://www.mathsisfun.com/algebra/trig-sine-law.html>.
+
+    // First solve for the edge vertices, to get B.
+    let angle_b = {
+        let angle_a = edge_vertex_positions
+            .0
+            .xy()
+            .angle_to(edge_vertex_positions.1.xy());
+        let edge_a = edge_vertex_positions
+            .0
+            .xy()
+            .distance(edge_vertex_positions.1.xy());
+        let edge_b = edge_vertex_positions.1.length();
+
+        (edge_b * angle_a.sin() / edge_a).asin()
+    };
+
+    // Now solve for a.
+    let angle_a = edge_vertex_positions.0.xy().angle_to(vertex_direction);
+    let edge_c = edge_vertex_positions.0.xy().length();
+    let angle_c = PI - angle_a - angle_b;
+
+    edge_c * angle_a.sin() / angle_c.sin()
+}
+
+/// Returns the angle of each of the sketch's vertices around its outline, as
+/// for `Matching::TurningAngle`.
+fn turning_angles(sketch: &Sketch) -> HashMap<VertexId, f32> {
+    let positions: Vec<Vec2> = sketch
+        .vertex_order
+        .iter()
+        .map(|&id| sketch.vertex_rotated(id).xy())
+        .collect();
+    let count = positions.len();
+
+    let edge = |index: usize| positions[(index + 1) % count] - positions[index];
+
+    // The absolute turning angle at each vertex, so the parameter increases
+    // monotonically even around concave corners.
+    let turns: Vec<f32> = (0..count)
+        .map(|index| {
+            edge((index + count - 1) % count)
+                .angle_to(edge(index))
+                .abs()
+        })
+        .collect();
+    let lengths: Vec<f32> = (0..count).map(|index| edge(index).length()).collect();
+
+    let total_turn: f32 = turns.iter().sum();
+    let total_length: f32 = lengths.iter().sum();
+
+    let mut turn = 0.;
+    let mut length = 0.;
+    let parameters: Vec<f32> = (0..count)
+        .map(|index| {
+            // Each corner is placed in the middle of its turn.
+            let turn_fraction = if total_turn > 0. {
+                (turn + turns[index] / 2.) / total_turn
+            } else {
+                index as f32 / count as f32
+            };
+            let length_fraction = if total_length > 0. {
+                length / total_length
+            } else {
+                index as f32 / count as f32
+            };
+
+            turn += turns[index];
+            length += lengths[index];
+
+            (1. - ARC_LENGTH_WEIGHT) * turn_fraction + ARC_LENGTH_WEIGHT * length_fraction
+        })
+        .collect();
+
+    // Align the parameters with the vertices' polar angles, so the outlines'
+    // starting vertices don't matter.
+    let offset = parameters
+        .iter()
+        .zip(&positions)
+        .map(|(parameter, position)| Vec2::from_angle(position.to_angle() - TAU * parameter))
+        .sum::<Vec2>()
+        .to_angle();
+
+    sketch
+        .vertex_order
+        .iter()
+        .zip(parameters)
+        .map(|(&id, parameter)| (id, wrap_angle(TAU * parameter + offset)))
+        .collect()
+}
+
+/// Wraps an angle to the range (-π, π].
+fn wrap_angle(angle: f32) -> f32 {
+    let wrapped = angle.rem_euclid(TAU);
+
+    if wrapped > PI { wrapped - TAU } else { wrapped }
+}
@@ -0,0 +1,411 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    iter::zip,
+};
+
+use glam::{IVec3, Vec2, Vec3};
+
+use crate::{
+    export::IndexedMesh,
+    sketch::{CornerCut, MAX_FILLET_SEGMENTS},
+};
+
+/// A processing step run on the loft's mesh after it's generated, such as by
+/// `IndexedMesh::from_lofter`. Passes are listed in
+/// `LoftOptions::post_passes`, and run in order, so they can be composed, and
+/// custom passes can be added by implementing this.
+pub trait MeshPass: fmt::Debug + Send + Sync {
+    fn apply(&self, mesh: &mut IndexedMesh);
+}
+
+/// Merges vertices within `tolerance` of each other, such as the duplicated
+/// vertices where sections meet, removing triangles which collapse.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Weld {
+    pub tolerance: f32,
+}
+
+impl MeshPass for Weld {
+    fn apply(&self, mesh: &mut IndexedMesh) {
+        let cell_size = self.tolerance.max(f32::EPSILON);
+        let mut cells: HashMap<IVec3, Vec<u32>> = HashMap::new();
+        let mut positions: Vec<Vec3> = Vec::new();
+
+        let remap = mesh
+            .positions
+            .iter()
+            .map(|&position| {
+                let cell = cell_of(position, cell_size);
+
+                // A vertex within the tolerance is in this cell or one of its
+                // neighbors.
+                let existing = neighbor_cells(cell)
+                    .filter_map(|neighbor| cells.get(&neighbor))
+                    .flatten()
+                    .copied()
+                    .find(|&index| positions[index as usize].distance(position) <= self.tolerance);
+
+                existing.unwrap_or_else(|| {
+                    let index = positions.len() as u32;
+                    positions.push(position);
+                    cells.entry(cell).or_default().push(index);
+                    index
+                })
+            })
+            .collect::<Vec<_>>();
+
+        remap_vertices(mesh, positions, &remap);
+    }
+}
+
+/// Moves each vertex towards the average of its neighbors, `iterations`
+/// times, by `factor` from 0 to 1. Vertices on the mesh's open boundaries,
+/// such as the first and last sketches, stay put.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Smooth {
+    pub iterations: usize,
+    pub factor: f32,
+}
+
+impl MeshPass for Smooth {
+    fn apply(&self, mesh: &mut IndexedMesh) {
+        // How many triangles use each edge, which is one for boundary edges.
+        let mut edge_counts: HashMap<(u32, u32), usize> = HashMap::new();
+        for triangle in &mesh.triangles {
+            for (a, b) in triangle_edges(triangle) {
+                *edge_counts.entry((a.min(b), a.max(b))).or_default() += 1;
+            }
+        }
+
+        let mut neighbors = vec![Vec::new(); mesh.positions.len()];
+        let mut boundary = vec![false; mesh.positions.len()];
+        for (&(a, b), &count) in &edge_counts {
+            neighbors[a as usize].push(b as usize);
+            neighbors[b as usize].push(a as usize);
+
+            if count == 1 {
+                boundary[a as usize] = true;
+                boundary[b as usize] = true;
+            }
+        }
+
+        for _ in 0..self.iterations {
+            let previous = mesh.positions.clone();
+
+            for (index, position) in mesh.positions.iter_mut().enumerate() {
+                let neighbors = &neighbors[index];
+                if boundary[index] || neighbors.is_empty() {
+                    continue;
+                }
+
+                let average = neighbors
+                    .iter()
+                    .map(|&neighbor| previous[neighbor])
+                    .sum::<Vec3>()
+                    / neighbors.len() as f32;
+
+                *position += (average - previous[index]) * self.factor;
+            }
+        }
+    }
+}
+
+/// Reduces the mesh's detail by merging all vertices within each cube of a
+/// grid with `cell_size` sides into their average, removing triangles which
+/// collapse.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Decimate {
+    pub cell_size: f32,
+}
+
+impl MeshPass for Decimate {
+    fn apply(&self, mesh: &mut IndexedMesh) {
+        let cell_size = self.cell_size.max(f32::EPSILON);
+        let mut cells = HashMap::new();
+        // The sum of each cluster's positions, and its vertex count.
+        let mut clusters: Vec<(Vec3, usize)> = Vec::new();
+
+        let remap = mesh
+            .positions
+            .iter()
+            .map(|&position| {
+                let index = *cells
+                    .entry(cell_of(position, cell_size))
+                    .or_insert_with(|| {
+                        clusters.push((Vec3::ZERO, 0));
+                        clusters.len() as u32 - 1
+                    });
+
+                let cluster = &mut clusters[index as usize];
+                cluster.0 += position;
+                cluster.1 += 1;
+
+                index
+            })
+            .collect::<Vec<_>>();
+
+        let positions = clusters
+            .into_iter()
+            .map(|(sum, count)| sum / count as f32)
+            .collect();
+
+        remap_vertices(mesh, positions, &remap);
+    }
+}
+
+/// Closes each of the mesh's open boundaries, such as the first and last
+/// sketches, with a flat cap, rounding the crease where the cap meets the wall
+/// with an arc of `radius` and `segments` edges, up to `MAX_FILLET_SEGMENTS`,
+/// for print-friendly edges.
+///
+/// The arc starts along the wall's ruling edges, so the cap is inset from the
+/// boundary rather than the loft growing. Where the arc doesn't fit within
+/// half the wall, or the distance to the cap's centroid, the crease is left
+/// sharp, and a `radius` of 0 caps without rounding. Run it after `Weld` if
+/// the sections' vertices don't coincide exactly, so the only boundaries are
+/// the loft's ends.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CapFillet {
+    pub radius: f32,
+    pub segments: usize,
+}
+
+impl MeshPass for CapFillet {
+    fn apply(&self, mesh: &mut IndexedMesh) {
+        // Boundary edges are those without a twin in the opposite direction,
+        // which is robust to triangles repeated in the same direction.
+        let edges: HashSet<(u32, u32)> = mesh.triangles.iter().flat_map(triangle_edges).collect();
+
+        // The end and triangle section of the boundary edge from each vertex.
+        let mut boundary_edges = HashMap::new();
+        let mut neighbors = vec![HashSet::new(); mesh.positions.len()];
+        for (triangle, &section) in zip(&mesh.triangles, &mesh.triangle_sections) {
+            for (a, b) in triangle_edges(triangle) {
+                if !edges.contains(&(b, a)) {
+                    boundary_edges.insert(a, (b, section));
+                }
+
+                neighbors[a as usize].insert(b);
+                neighbors[b as usize].insert(a);
+            }
+        }
+
+        for boundary in boundary_loops(&boundary_edges) {
+            self.cap(mesh, &boundary, &neighbors);
+        }
+    }
+}
+
+impl CapFillet {
+    /// Caps a boundary loop of vertices, each with the section of the
+    /// boundary edge to the next.
+    fn cap(&self, mesh: &mut IndexedMesh, boundary: &[(u32, usize)], neighbors: &[HashSet<u32>]) {
+        let count = boundary.len();
+        let points: Vec<Vec3> = boundary
+            .iter()
+            .map(|&(index, _)| mesh.positions[index as usize])
+            .collect();
+        let centroid = points.iter().sum::<Vec3>() / count as f32;
+
+        // Newell's method, so the boundary is CCW about the normal.
+        let normal = (0..count)
+            .map(|i| (points[i] - centroid).cross(points[(i + 1) % count] - centroid))
+            .sum::<Vec3>()
+            .normalize_or_zero();
+        if normal == Vec3::ZERO {
+            return;
+        }
+
+        let in_boundary: HashSet<u32> = boundary.iter().map(|&(index, _)| index).collect();
+        let segments = self.segments.clamp(1, MAX_FILLET_SEGMENTS);
+        let cut = CornerCut::Fillet {
+            radius: self.radius,
+            segments,
+        };
+
+        // The arc of each boundary vertex, from the wall onto the cap, where the
+        // vertex itself moves to the start of the arc.
+        let mut arcs = Vec::with_capacity(count);
+        for (i, &(index, _)) in boundary.iter().enumerate() {
+            let position = points[i];
+            let tangent = points[(i + 1) % count] - points[(i + count - 1) % count];
+            let inward = normal.cross(tangent).normalize_or_zero();
+
+            let wall: Vec<Vec3> = neighbors[index as usize]
+                .iter()
+                .filter(|neighbor| !in_boundary.contains(neighbor))
+                .map(|&neighbor| mesh.positions[neighbor as usize] - position)
+                .collect();
+            let wall_direction = wall
+                .iter()
+                .map(|edge| edge.normalize_or_zero())
+                .sum::<Vec3>()
+                .normalize_or_zero();
+            let wall_length = wall
+                .iter()
+                .map(|edge| edge.length())
+                .fold(f32::INFINITY, f32::min);
+
+            let arc = (!wall.is_empty())
+                .then(|| {
+                    cut.points(
+                        position + wall_direction * wall_length / 2.,
+                        position,
+                        position + inward * position.distance(centroid),
+                    )
+                })
+                .flatten();
+
+            arcs.push(match arc {
+                Some(arc) => {
+                    mesh.positions[index as usize] = arc[0];
+
+                    let mut indices = vec![index];
+                    for &point in &arc[1..] {
+                        indices.push(mesh.positions.len() as u32);
+                        mesh.positions.push(point);
+                    }
+                    indices
+                }
+                None => vec![index; segments + 1],
+            });
+        }
+
+        // The wall's triangles use each boundary edge from a to b, so the
+        // arcs' triangles use it from b to a.
+        for (i, &(_, section)) in boundary.iter().enumerate() {
+            let (arc_a, arc_b) = (&arcs[i], &arcs[(i + 1) % count]);
+
+            for k in 0..segments {
+                for triangle in [
+                    [arc_b[k], arc_a[k], arc_a[k + 1]],
+                    [arc_b[k], arc_a[k + 1], arc_b[k + 1]],
+                ] {
+                    let [a, b, c] = triangle;
+                    if a != b && b != c && c != a {
+                        mesh.triangles.push(triangle);
+                        mesh.triangle_sections.push(section);
+                    }
+                }
+            }
+        }
+
+        let ring: Vec<u32> = arcs.iter().map(|arc| arc[segments]).collect();
+        let u = normal.any_orthonormal_vector();
+        let v = normal.cross(u);
+        let projected: Vec<Vec2> = ring
+            .iter()
+            .map(|&index| {
+                let offset = mesh.positions[index as usize] - centroid;
+                Vec2::new(offset.dot(u), offset.dot(v))
+            })
+            .collect();
+
+        // The ring is CCW about the normal, and the cap uses its edges in the
+        // opposite direction to the arcs, so its triangles are reversed.
+        for [a, b, c] in triangulate(&projected) {
+            mesh.triangles.push([ring[c], ring[b], ring[a]]);
+            mesh.triangle_sections.push(boundary[0].1);
+        }
+    }
+}
+
+/// Returns the closed loops of boundary edges, from the end and section of the
+/// boundary edge from each vertex, ignoring open chains.
+fn boundary_loops(boundary_edges: &HashMap<u32, (u32, usize)>) -> Vec<Vec<(u32, usize)>> {
+    let mut starts: Vec<u32> = boundary_edges.keys().copied().collect();
+    starts.sort_unstable();
+
+    let mut visited = HashSet::new();
+    let mut loops = Vec::new();
+
+    for start in starts {
+        let mut boundary = Vec::new();
+        let mut vertex = start;
+
+        while visited.insert(vertex) {
+            let Some(&(next, section)) = boundary_edges.get(&vertex) else {
+                break;
+            };
+
+            boundary.push((vertex, section));
+            vertex = next;
+        }
+
+        if vertex == start && boundary.len() >= 3 {
+            loops.push(boundary);
+        }
+    }
+
+    loops
+}
+
+/// Triangulates a simple CCW polygon by ear clipping, returning CCW triangles
+/// of indices into `points`.
+fn triangulate(points: &[Vec2]) -> Vec<[usize; 3]> {
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut triangles = Vec::new();
+
+    let cross = |a: Vec2, b: Vec2, c: Vec2| (b - a).perp_dot(c - a);
+
+    while remaining.len() > 3 {
+        let count = remaining.len();
+        let ear = (0..count).find(|&i| {
+            let [a, b, c] = [i + count - 1, i, i + 1].map(|j| remaining[j % count]);
+            let [pa, pb, pc] = [a, b, c].map(|j| points[j]);
+
+            cross(pa, pb, pc) > 0.
+                && remaining.iter().all(|&j| {
+                    let p = points[j];
+                    [a, b, c].contains(&j)
+                        || cross(pa, pb, p) < 0.
+                        || cross(pb, pc, p) < 0.
+                        || cross(pc, pa, p) < 0.
+                })
+        });
+
+        // Without an ear, the polygon is degenerate, so clip any vertex.
+        let i = ear.unwrap_or(0);
+        triangles.push([i + count - 1, i, i + 1].map(|j| remaining[j % count]));
+        remaining.remove(i);
+    }
+
+    if let [a, b, c] = remaining[..] {
+        triangles.push([a, b, c]);
+    }
+
+    triangles
+}
+
+fn cell_of(position: Vec3, cell_size: f32) -> IVec3 {
+    (position / cell_size).floor().as_ivec3()
+}
+
+fn neighbor_cells(cell: IVec3) -> impl Iterator<Item = IVec3> {
+    (-1..=1).flat_map(move |x| {
+        (-1..=1).flat_map(move |y| (-1..=1).map(move |z| cell + IVec3::new(x, y, z)))
+    })
+}
+
+fn triangle_edges(triangle: &[u32; 3]) -> [(u32, u32); 3] {
+    let [a, b, c] = *triangle;
+
+    [(a, b), (b, c), (c, a)]
+}
+
+/// Replaces the mesh's vertices, where `remap` is the new index of each old
+/// vertex, removing triangles left with repeated vertices.
+fn remap_vertices(mesh: &mut IndexedMesh, positions: Vec<Vec3>, remap: &[u32]) {
+    let (triangles, triangle_sections) = mesh
+        .triangles
+        .iter()
+        .zip(&mesh.triangle_sections)
+        .map(|(triangle, &section)| (triangle.map(|index| remap[index as usize]), section))
+        .filter(|([a, b, c], _)| a != b && b != c && c != a)
+        .unzip();
+
+    mesh.positions = positions;
+    mesh.triangles = triangles;
+    mesh.triangle_sections = triangle_sections;
+}
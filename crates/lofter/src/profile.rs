@@ -0,0 +1,169 @@
+use std::f32::consts::TAU;
+
+use glam::Vec3;
+
+use crate::{
+    DefaultMatching, LoftError, LoftOptions, loft_sketches,
+    sketch::{Sketch, SketchDescriptor},
+    util::SketchPair,
+};
+
+/// Blended vertices closer than this are merged.
+const DUPLICATE_TOLERANCE: f32 = 1e-6;
+
+impl SketchDescriptor {
+    /// Returns a profile between `a`, at a `t` of 0, and `b`, at a `t` of 1,
+    /// with the default loft options. See `blend_with_options`.
+    pub fn blend(a: &Self, b: &Self, t: f32) -> Result<Self, LoftError> {
+        Self::blend_with_options(a, b, t, &Default::default())
+    }
+
+    /// Returns a profile between `a` and `b` by lofting them, and taking the
+    /// cross-section of the loft at `t`, so corresponding vertices are matched
+    /// exactly as when lofting. Lofting `a` to the blend and the blend to `b`
+    /// gives the same surface as lofting `a` to `b`.
+    ///
+    /// The blend's rotation is applied to its vertices, so it's zero, while its
+    /// relative position is interpolated.
+    ///
+    /// Like `Lofter::insert_sketch`, profiles with fewer than three distinct
+    /// vertices are rejected, with `a` as sketch 0 and `b` as sketch 1.
+    pub fn blend_with_options(
+        a: &Self,
+        b: &Self,
+        t: f32,
+        options: &LoftOptions,
+    ) -> Result<Self, LoftError> {
+        let sketches = SketchPair::new(Sketch::from(a), Sketch::from(b));
+        for (sketch_index, sketch) in [&sketches.lower, &sketches.upper].into_iter().enumerate() {
+            let vertex_count = sketch.vertex_order.len();
+            if vertex_count < 3 {
+                return Err(LoftError::TooFewVertices {
+                    sketch_index,
+                    vertex_count,
+                });
+            }
+        }
+
+        let loft = loft_sketches(
+            sketches.as_ref(),
+            options,
+            &[],
+            &DefaultMatching,
+            &mut Default::default(),
+            |_, _| {},
+        )
+        .and_then(|loft| loft.validate(sketches.as_ref()).map(|()| loft))
+        .map_err(LoftError::BlendFailed)?;
+
+        let mut ruling_edges = Vec::new();
+        loft.append_ruling_edges(
+            &mut ruling_edges,
+            sketches.as_ref(),
+            SketchPair::new(Vec3::ZERO, Vec3::ZERO),
+        );
+
+        let mut vertices: Vec<_> = ruling_edges
+            .iter()
+            .map(|[lower, upper]| lower.lerp(*upper, t))
+            .collect();
+
+        // Edges fanning from one vertex meet at either end of the blend.
+        vertices.dedup_by(|a, b| a.abs_diff_eq(*b, DUPLICATE_TOLERANCE));
+        if vertices.len() > 1
+            && vertices[0].abs_diff_eq(vertices[vertices.len() - 1], DUPLICATE_TOLERANCE)
+        {
+            vertices.pop();
+        }
+
+        Ok(Self {
+            vertices,
+            relative_position: a.relative_position.lerp(b.relative_position, t),
+            rotation: Vec3::ZERO,
+        })
+    }
+}
+
+/// A superellipse profile, |x / a|^n + |y / b|^n = 1, for fuselage-style
+/// lofts. An exponent of 2 is an ellipse, larger exponents approach a
+/// rectangle, and smaller exponents pinch towards a diamond.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Superellipse {
+    /// The semi-axis along x.
+    pub a: f32,
+    /// The semi-axis along y.
+    pub b: f32,
+    pub exponent: f32,
+}
+
+impl Superellipse {
+    /// Returns a superellipse whose semi-axis along y is shortened by the
+    /// eccentricity, from 0 for a circle towards 1 for a flat line.
+    pub fn from_eccentricity(a: f32, eccentricity: f32, exponent: f32) -> Self {
+        Self {
+            a,
+            b: a * (1. - eccentricity.clamp(0., 1.).powi(2)).sqrt(),
+            exponent,
+        }
+    }
+
+    /// Returns the profile's eccentricity, as for `from_eccentricity`.
+    pub fn eccentricity(&self) -> f32 {
+        let (major, minor) = (self.a.max(self.b), self.a.min(self.b));
+
+        (1. - (minor / major).powi(2)).sqrt()
+    }
+
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        Self {
+            a: self.a + (other.a - self.a) * t,
+            b: self.b + (other.b - self.b) * t,
+            exponent: self.exponent + (other.exponent - self.exponent) * t,
+        }
+    }
+
+    /// Interpolates between keyframes of `(t, profile)`, sorted by `t`, for
+    /// ramping a profile across stations, such as with
+    /// `Lofter::generate_stations`. Outside the keyframes, the first or last
+    /// profile is returned, and without any keyframes, `None`.
+    pub fn ramp(keyframes: &[(f32, Self)], t: f32) -> Option<Self> {
+        let next = keyframes.partition_point(|(key_t, _)| *key_t <= t);
+
+        let profile = match (next.checked_sub(1), keyframes.get(next)) {
+            (Some(previous), Some((next_t, next))) => {
+                let (previous_t, previous) = &keyframes[previous];
+
+                previous.lerp(next, (t - previous_t) / (next_t - previous_t))
+            }
+            (Some(previous), None) => keyframes[previous].1,
+            (None, _) => keyframes.first()?.1,
+        };
+
+        Some(profile)
+    }
+
+    /// Returns a sketch of the profile, centered on the origin, with `points`
+    /// vertices evenly spaced in angle.
+    pub fn sketch(&self, points: usize) -> SketchDescriptor {
+        let points = points.max(3);
+        let power = 2. / self.exponent;
+
+        let vertices = (0..points)
+            .map(|i| {
+                let (sin, cos) = (TAU * i as f32 / points as f32).sin_cos();
+
+                Vec3::new(
+                    self.a * cos.signum() * cos.abs().powf(power),
+                    self.b * sin.signum() * sin.abs().powf(power),
+                    0.,
+                )
+            })
+            .collect();
+
+        SketchDescriptor {
+            vertices,
+            relative_position: Vec3::ZERO,
+            rotation: Vec3::ZERO,
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! Timing the phases of lofting, when the `profiling` feature is enabled.
+//! Without it, nothing is timed or stored.
+
+#![cfg_attr(not(feature = "profiling"), allow(dead_code, unused_variables))]
+
+#[cfg(feature = "profiling")]
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The time spent in each phase of the last loft, returned by
+/// `Lofter::last_profile`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LoftProfile {
+    /// Indexed by the pair's lower sketch.
+    pub pairs: Vec<PairProfile>,
+}
+
+/// The time spent lofting a pair of sketches, in each phase.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PairProfile {
+    /// Scoring every combination of the sketches' vertices.
+    pub candidate_generation: Duration,
+    /// Sorting the edge candidates by their scores.
+    pub sorting: Duration,
+    /// Splitting sections by the accepted candidates.
+    pub splitting: Duration,
+    /// Building each section's ruling edges.
+    pub edge_building: Duration,
+    /// Assembling the pair's triangles in the last `Lofter::vertex_buffer`
+    /// since the loft.
+    pub buffer_assembly: Duration,
+}
+
+/// Runs `f`, adding the time it takes to `duration` when profiling.
+pub(crate) fn timed<T>(duration: &mut Duration, f: impl FnOnce() -> T) -> T {
+    if cfg!(feature = "profiling") {
+        let start = Instant::now();
+        let result = f();
+        *duration += start.elapsed();
+
+        result
+    } else {
+        f()
+    }
+}
+
+/// A lofter's last profile, which buffer assembly times are recorded to
+/// through shared references.
+#[derive(Debug, Default)]
+pub(crate) struct ProfileCell {
+    #[cfg(feature = "profiling")]
+    profile: Mutex<LoftProfile>,
+}
+
+impl ProfileCell {
+    #[cfg(feature = "profiling")]
+    pub fn get(&self) -> LoftProfile {
+        self.profile.lock().unwrap().clone()
+    }
+
+    pub fn set(&self, profile: LoftProfile) {
+        #[cfg(feature = "profiling")]
+        {
+            *self.profile.lock().unwrap() = profile;
+        }
+    }
+
+    pub fn set_buffer_assembly(&self, pair: usize, duration: Duration) {
+        #[cfg(feature = "profiling")]
+        if let Some(pair) = self.profile.lock().unwrap().pairs.get_mut(pair) {
+            pair.buffer_assembly = duration;
+        }
+    }
+}
+
+impl Clone for ProfileCell {
+    fn clone(&self) -> Self {
+        Self {
+            #[cfg(feature = "profiling")]
+            profile: Mutex::new(self.get()),
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use std::time::{Duration, Instant};
+
+use glam::Vec3;
+
+use crate::{LoftOptions, Lofter, MirrorAxis, SketchDescriptor, VertexId};
+
+/// A mutation of a `Lofter`, as recorded in a `Session`. Each command
+/// corresponds to the `Lofter` method of the same name, and is applied with
+/// `Lofter::apply`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Command {
+    InsertSketch {
+        sketch_index: usize,
+        sketch: SketchDescriptor,
+    },
+    RemoveSketch {
+        sketch_index: usize,
+        /// Applied with `remove_sketch_with`. Missing from sessions recorded
+        /// before sketches were kept in place, which replay without it.
+        #[cfg_attr(feature = "serde", serde(default))]
+        keep_positions: bool,
+    },
+    SetSketchLocked {
+        sketch_index: usize,
+        locked: bool,
+    },
+    SetSketchRotation {
+        sketch_index: usize,
+        rotation: Vec3,
+    },
+    SetSketchRelativePosition {
+        sketch_index: usize,
+        relative_position: Vec3,
+    },
+    InsertVertex {
+        sketch_index: usize,
+        between_vertices: (VertexId, VertexId),
+    },
+    RemoveVertex {
+        sketch_index: usize,
+        vertex_id: VertexId,
+    },
+    SetMirrorPartners {
+        sketch_index: usize,
+        vertices: (VertexId, VertexId),
+    },
+    ClearMirrorPartner {
+        sketch_index: usize,
+        vertex_id: VertexId,
+    },
+    FilletVertex {
+        sketch_index: usize,
+        vertex_id: VertexId,
+        radius: f32,
+        segments: usize,
+    },
+    ChamferVertex {
+        sketch_index: usize,
+        vertex_id: VertexId,
+        distance: f32,
+    },
+    SetVertexExcluded {
+        sketch_index: usize,
+        vertex_id: VertexId,
+        excluded: bool,
+    },
+    TagMirrorPartners {
+        sketch_index: usize,
+        axis: MirrorAxis,
+        tolerance: f32,
+    },
+    SetVertexPositions {
+        sketch_index: usize,
+        positions: Vec<(VertexId, Vec3)>,
+    },
+    SetVertexWorldPosition {
+        sketch_index: usize,
+        vertex_id: VertexId,
+        world_position: Vec3,
+    },
+    Loft(LoftOptions),
+}
+
+/// A command recorded in a `Session`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimedCommand {
+    /// The time since recording started.
+    pub time: Duration,
+    pub command: Command,
+}
+
+/// The commands applied to a lofter while recording, started with
+/// `Lofter::start_recording`, along with the lofter's state when recording
+/// started.
+///
+/// Replaying a session reproduces the loft's shape exactly, since vertex IDs
+/// are preserved, but not the randomly chosen face colors of new lofts.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Session {
+    initial: Box<Lofter>,
+    /// Commands in the order they were applied, including those which
+    /// returned an error.
+    pub commands: Vec<TimedCommand>,
+}
+
+impl Session {
+    pub(crate) fn new(initial: Lofter) -> Self {
+        Self {
+            initial: Box::new(initial),
+            commands: Vec::new(),
+        }
+    }
+
+    /// Returns the lofter as it was when recording started, for replaying the
+    /// commands onto.
+    pub fn initial_lofter(&self) -> Lofter {
+        (*self.initial).clone()
+    }
+
+    /// Returns the lofter as it was when recording stopped, by applying every
+    /// command to the initial lofter.
+    pub fn replay(&self) -> Lofter {
+        let mut lofter = self.initial_lofter();
+
+        for TimedCommand { command, .. } in &self.commands {
+            // Commands which failed when recorded fail again, without
+            // modifying the lofter.
+            let _ = lofter.apply(command);
+        }
+
+        lofter
+    }
+}
+
+/// A session being recorded by a lofter.
+#[derive(Clone, Debug)]
+pub(crate) struct Recording {
+    started: Instant,
+    session: Session,
+}
+
+impl Recording {
+    pub(crate) fn new(initial: Lofter) -> Self {
+        Self {
+            started: Instant::now(),
+            session: Session::new(initial),
+        }
+    }
+
+    pub(crate) fn push(&mut self, command: Command) {
+        self.session.commands.push(TimedCommand {
+            time: self.started.elapsed(),
+            command,
+        });
+    }
+
+    pub(crate) fn finish(self) -> Session {
+        self.session
+    }
+}
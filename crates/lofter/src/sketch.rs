@@ -1,18 +1,126 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    f32::consts::PI,
+};
 
-use glam::Vec3;
+use glam::{Quat, Vec3};
 
 pub type VertexId = u32;
 
+/// Consecutive vertices of a new sketch closer than this are merged.
+pub const DUPLICATE_VERTEX_TOLERANCE: f32 = 1e-5;
+
+/// An axis in a sketch's local xy plane, about which vertices can be mirrored.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MirrorAxis {
+    /// Mirror across the x axis, negating y.
+    X,
+    /// Mirror across the y axis, negating x.
+    Y,
+}
+
+impl MirrorAxis {
+    /// Returns the position mirrored across this axis.
+    pub fn mirror(self, position: Vec3) -> Vec3 {
+        match self {
+            MirrorAxis::X => Vec3::new(position.x, -position.y, position.z),
+            MirrorAxis::Y => Vec3::new(-position.x, position.y, position.z),
+        }
+    }
+}
+
+/// The most edges `Lofter::fillet_vertex` and `CapFillet` round a corner with,
+/// since each adds a vertex.
+pub const MAX_FILLET_SEGMENTS: usize = 256;
+
+/// How a corner is cut by `Lofter::fillet_vertex` or `chamfer_vertex`.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum CornerCut {
+    /// An arc tangent to both edges.
+    Fillet { radius: f32, segments: usize },
+    /// A straight bevel, starting `distance` along each edge.
+    Chamfer { distance: f32 },
+}
+
+impl CornerCut {
+    /// Returns the points replacing the corner at `vertex`, from the edge to
+    /// `prev` to the edge to `next`, which is CCW order on a sketch. The corner
+    /// may be in any plane, such as for `CapFillet`. Returns `None` if the cut
+    /// doesn't fit within either edge, or the corner is straight.
+    pub fn points(self, prev: Vec3, vertex: Vec3, next: Vec3) -> Option<Vec<Vec3>> {
+        let to_prev = prev - vertex;
+        let to_next = next - vertex;
+        let (dir_prev, dir_next) = (to_prev.normalize(), to_next.normalize());
+        let angle = dir_prev.angle_between(dir_next);
+
+        // The distance from the vertex along each edge where the cut starts.
+        let setback = match self {
+            CornerCut::Fillet { radius, .. } => radius / (angle / 2.).tan(),
+            CornerCut::Chamfer { distance } => distance,
+        };
+
+        let fits = |edge: Vec3| setback < edge.length() - DUPLICATE_VERTEX_TOLERANCE;
+        let straight = PI - angle <= STRAIGHT_ANGLE_TOLERANCE;
+        if !(setback > 0. && fits(to_prev) && fits(to_next)) || straight {
+            return None;
+        }
+
+        let start = vertex + dir_prev * setback;
+        let end = vertex + dir_next * setback;
+
+        Some(match self {
+            CornerCut::Fillet { radius, segments } => {
+                let bisector = (dir_prev + dir_next).normalize();
+                let center = vertex + bisector * (radius / (angle / 2.).sin());
+                let (from, to) = (start - center, end - center);
+                let axis = from.cross(to).normalize();
+                let sweep = from.angle_between(to);
+
+                (0..=segments)
+                    .map(|i| {
+                        let rotation =
+                            Quat::from_axis_angle(axis, sweep * i as f32 / segments as f32);
+                        center + rotation * from
+                    })
+                    .collect()
+            }
+            CornerCut::Chamfer { .. } => vec![start, end],
+        })
+    }
+}
+
+/// Corners within this many radians of straight can't be cut.
+const STRAIGHT_ANGLE_TOLERANCE: f32 = 1e-4;
+
 /// A simplified sketch data structure, used by callers to insert initial
 /// sketches.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SketchDescriptor {
     pub vertices: Vec<Vec3>,
     pub relative_position: Vec3,
     pub rotation: Vec3,
 }
 
-#[derive(Debug)]
+impl SketchDescriptor {
+    /// Returns a sketch descriptor from any vector types which convert into
+    /// glam's, such as mint's with the `mint` feature.
+    pub fn new(
+        vertices: impl IntoIterator<Item = impl Into<Vec3>>,
+        relative_position: impl Into<Vec3>,
+        rotation: impl Into<Vec3>,
+    ) -> Self {
+        Self {
+            vertices: vertices.into_iter().map(Into::into).collect(),
+            relative_position: relative_position.into(),
+            rotation: rotation.into(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Sketch {
     /// The vertices of the sketch. These are stored in a HashMap so that their
     /// IDs are stable on insertion/removal (as opposed to indices in a `Vec`).
@@ -24,6 +132,17 @@ pub struct Sketch {
     pub relative_position: Vec3,
     /// Rotation, in radians.
     pub rotation: Vec3,
+    /// Locked sketches reject vertex and transform mutations.
+    pub locked: bool,
+    /// The ID assigned to the next inserted vertex.
+    pub next_vertex_id: VertexId,
+    /// Tagged mirror partners of vertices. Pairs are stored in both
+    /// directions, and a vertex lying on the mirror axis is its own partner.
+    pub mirror_partners: HashMap<VertexId, VertexId>,
+    /// Decorative vertices, which are lofted but never edge candidates, so
+    /// dense detail doesn't attract the sections of the loft.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub excluded_vertices: HashSet<VertexId>,
 }
 
 impl Sketch {
@@ -32,14 +151,67 @@ impl Sketch {
     pub fn vertex_rotated(&self, vertex_id: VertexId) -> Vec3 {
         self.vertex_map[&vertex_id].rotate_z(self.rotation.z.to_radians())
     }
+
+    /// Returns whether vertices can be edge candidates, which is every vertex
+    /// not excluded from matching, or every vertex if all are excluded.
+    pub fn matching_filter(&self) -> impl Fn(VertexId) -> bool {
+        let all_excluded = self
+            .vertex_order
+            .iter()
+            .all(|id| self.excluded_vertices.contains(id));
+
+        move |id| all_excluded || !self.excluded_vertices.contains(&id)
+    }
+
+    /// Returns the distance around the outline from the first vertex to each
+    /// vertex, in CCW order, and the outline's perimeter.
+    pub(crate) fn outline_distances(&self) -> (HashMap<VertexId, f32>, f32) {
+        let mut distances = HashMap::new();
+        let mut distance = 0.;
+
+        for (i, &id) in self.vertex_order.iter().enumerate() {
+            distances.insert(id, distance);
+
+            let next = self.vertex_order[(i + 1) % self.vertex_order.len()];
+            distance += self.vertex_map[&id].distance(self.vertex_map[&next]);
+        }
+
+        (distances, distance)
+    }
+}
+
+impl From<&Sketch> for SketchDescriptor {
+    /// Returns the sketch's vertices in CCW order, and its transform.
+    fn from(value: &Sketch) -> Self {
+        Self {
+            vertices: value
+                .vertex_order
+                .iter()
+                .map(|id| value.vertex_map[id])
+                .collect(),
+            relative_position: value.relative_position,
+            rotation: value.rotation,
+        }
+    }
 }
 
 impl From<&SketchDescriptor> for Sketch {
+    /// Creates a sketch from a descriptor, merging consecutive vertices within
+    /// `DUPLICATE_VERTEX_TOLERANCE` of each other, since they would form
+    /// zero-length edges.
     fn from(value: &SketchDescriptor) -> Self {
-        let mut vertex_map = HashMap::with_capacity(value.vertices.len());
-        let mut vertex_order = Vec::with_capacity(value.vertices.len());
+        let mut vertices = value.vertices.clone();
+        vertices.dedup_by(|a, b| a.distance(*b) <= DUPLICATE_VERTEX_TOLERANCE);
+        if vertices.len() > 1
+            && vertices[0].distance(vertices[vertices.len() - 1]) <= DUPLICATE_VERTEX_TOLERANCE
+        {
+            vertices.pop();
+        }
+
+        let mut vertex_map = HashMap::with_capacity(vertices.len());
+        let mut vertex_order = Vec::with_capacity(vertices.len());
 
-        for (i, &vertex) in value.vertices.iter().enumerate() {
+        for (i, &vertex) in vertices.iter().enumerate() {
             let vertex_id = i as VertexId;
 
             vertex_map.insert(vertex_id, vertex);
@@ -51,6 +223,10 @@ impl From<&SketchDescriptor> for Sketch {
             vertex_order,
             relative_position: value.relative_position,
             rotation: value.rotation,
+            locked: false,
+            next_vertex_id: vertices.len() as VertexId,
+            mirror_partners: HashMap::new(),
+            excluded_vertices: HashSet::new(),
         }
     }
 }
@@ -0,0 +1,126 @@
+use std::collections::{HashMap, HashSet};
+
+use glam::Vec3;
+
+use crate::export::IndexedMesh;
+
+/// The most layers `Lofter::slice_layers` slices the loft into, since each
+/// layer slices the whole mesh.
+pub const MAX_SLICE_LAYERS: usize = 10_000;
+
+/// A horizontal slice through the loft, from `Lofter::slice_layers`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SliceLayer {
+    /// The world height of the slicing plane, in the middle of the layer.
+    pub z: f32,
+    /// The closed contours where the plane cuts the loft's surface, without
+    /// repeating their first point. Contours are CCW viewed from above, with
+    /// the surface's outside on their right, so holes are CW.
+    pub contours: Vec<Vec<Vec3>>,
+}
+
+/// Slices the mesh into layers `layer_height` thick, from its lowest point
+/// up, with each layer's plane in its middle. Layers are thickened to keep to
+/// `MAX_SLICE_LAYERS`.
+pub(crate) fn slice_layers(mesh: &IndexedMesh, layer_height: f32) -> Vec<SliceLayer> {
+    let Some((min_z, max_z)) = mesh
+        .positions
+        .iter()
+        .map(|position| (position.z, position.z))
+        .reduce(|(min, max), (z, _)| (min.min(z), max.max(z)))
+    else {
+        return Vec::new();
+    };
+
+    if !(layer_height > 0. && layer_height.is_finite()) {
+        return Vec::new();
+    }
+
+    let layer_height = layer_height.max((max_z - min_z) / MAX_SLICE_LAYERS as f32);
+    let layer_count = (((max_z - min_z) / layer_height).ceil() as usize).min(MAX_SLICE_LAYERS);
+
+    (0..layer_count)
+        .map(|layer| min_z + (layer as f32 + 0.5) * layer_height)
+        .filter(|&z| z < max_z)
+        .map(|z| SliceLayer {
+            z,
+            contours: slice(mesh, z),
+        })
+        .collect()
+}
+
+/// Returns the closed contours where the plane at height `z` cuts the mesh.
+fn slice(mesh: &IndexedMesh, z: f32) -> Vec<Vec<Vec3>> {
+    // Vertices on the plane count as above it, so each triangle crosses it on
+    // two edges or none.
+    let above: Vec<bool> = mesh
+        .positions
+        .iter()
+        .map(|position| position.z >= z)
+        .collect();
+
+    // Where the plane crosses each edge, keyed by the edge's vertices, so
+    // neighboring triangles' segments join exactly.
+    let mut points = HashMap::new();
+    // The end of the contour segment from each crossed edge.
+    let mut next_edge = HashMap::new();
+
+    for triangle in &mesh.triangles {
+        let crossings: Vec<(u32, u32)> = [(0, 1), (1, 2), (2, 0)]
+            .into_iter()
+            .map(|(a, b)| (triangle[a], triangle[b]))
+            .filter(|&(a, b)| above[a as usize] != above[b as usize])
+            .map(|(a, b)| (a.min(b), a.max(b)))
+            .collect();
+
+        let [start, end] = crossings[..] else {
+            continue;
+        };
+
+        let [a, b, c] = triangle.map(|index| mesh.positions[index as usize]);
+        let normal = (b - a).cross(c - a);
+
+        let [start_point, end_point] = [start, end].map(|edge| {
+            *points.entry(edge).or_insert_with(|| {
+                let (a, b) = (
+                    mesh.positions[edge.0 as usize],
+                    mesh.positions[edge.1 as usize],
+                );
+                a.lerp(b, (z - a.z) / (b.z - a.z))
+            })
+        });
+
+        // The outside is on the segment's right.
+        if (end_point - start_point).cross(Vec3::Z).dot(normal) >= 0. {
+            next_edge.insert(start, end);
+        } else {
+            next_edge.insert(end, start);
+        }
+    }
+
+    let mut starts: Vec<(u32, u32)> = next_edge.keys().copied().collect();
+    starts.sort_unstable();
+
+    let mut visited = HashSet::new();
+    let mut contours = Vec::new();
+
+    for start in starts {
+        let mut contour = Vec::new();
+        let mut edge = start;
+
+        while visited.insert(edge) {
+            contour.push(points[&edge]);
+
+            match next_edge.get(&edge) {
+                Some(&next) => edge = next,
+                None => break,
+            }
+        }
+
+        if edge == start && contour.len() >= 3 {
+            contours.push(contour);
+        }
+    }
+
+    contours
+}
@@ -0,0 +1,17 @@
+/// Counts of the operations performed on a `Lofter` since it was created,
+/// returned by `Lofter::session_stats`. These are only kept in memory, for
+/// applications to show in their own panels; nothing is sent or written
+/// anywhere.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SessionStats {
+    /// Calls to `loft`, `loft_with` and `explain_loft`.
+    pub lofts: u64,
+    /// Pairs of sketches which failed to loft, summed over all lofts.
+    pub failed_pairs: u64,
+    /// Vertices inserted, removed or moved, counting each call rather than
+    /// each vertex. Changes through `get_vertex_mut` aren't counted.
+    pub vertex_edits: u64,
+    /// Vertex edits rejected with an error, which aren't counted in
+    /// `vertex_edits`.
+    pub failed_vertex_edits: u64,
+}
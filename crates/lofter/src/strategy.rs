@@ -0,0 +1,83 @@
+use glam::Vec3;
+
+use crate::{matching::MatchingAngles, sketch::VertexId};
+
+/// Decides which vertices of two adjacent sketches are joined by ruling
+/// edges, for experimenting with custom correspondence heuristics with
+/// `Lofter::loft_with`.
+///
+/// Every combination of a lower and upper vertex is a candidate edge. They're
+/// tried in order of increasing score, until the score exceeds
+/// `LoftOptions::max_radial_edge_angle`, and each accepted candidate splits a
+/// section of the loft in two. The edges within each section are then built
+/// by `LoftOptions::matching`.
+pub trait MatchingStrategy {
+    /// Returns the cost of joining the lower and upper vertices. The default
+    /// is the absolute angle between them, as matched by
+    /// `LoftOptions::matching`, in radians.
+    fn score(&self, pair: &MatchingPair, vertices: (VertexId, VertexId)) -> f32 {
+        pair.matching_angle(vertices).abs()
+    }
+
+    /// Writes the scores of joining a lower vertex to each of the upper
+    /// vertices, in CCW order, to `scores`. By default, `score` is called for
+    /// each, so strategies only need to override this to score faster.
+    fn score_row(&self, pair: &MatchingPair, lower_id: VertexId, scores: &mut [f32]) {
+        for (score, &upper_id) in scores.iter_mut().zip(pair.vertex_ids(1)) {
+            *score = self.score(pair, (lower_id, upper_id));
+        }
+    }
+
+    /// Returns whether a candidate may split its section. Candidates are
+    /// also rejected when their vertices lie in different sections. By
+    /// default, every candidate may.
+    fn validate_split(&self, pair: &MatchingPair, vertices: (VertexId, VertexId)) -> bool {
+        let _ = (pair, vertices);
+        true
+    }
+}
+
+/// The strategy used by `Lofter::loft`, which matches candidates by
+/// `LoftOptions::matching` alone.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultMatching;
+
+impl MatchingStrategy for DefaultMatching {
+    /// Scores four candidates at a time, since scoring dominates the time
+    /// lofting dense profiles takes.
+    fn score_row(&self, pair: &MatchingPair, lower_id: VertexId, scores: &mut [f32]) {
+        pair.angles.abs_angles_from(lower_id, scores);
+    }
+}
+
+/// A pair of adjacent sketches being lofted, as seen by a `MatchingStrategy`.
+/// `pair_index` is 0 for the lower sketch and 1 for the upper.
+pub struct MatchingPair<'a> {
+    pub(crate) angles: &'a MatchingAngles<'a>,
+}
+
+impl MatchingPair<'_> {
+    /// Returns the IDs of a sketch's vertices, in CCW order.
+    pub fn vertex_ids(&self, pair_index: usize) -> &[VertexId] {
+        &self.angles.sketches[pair_index].vertex_order
+    }
+
+    /// Returns a vertex's position relative to its sketch's origin, with the
+    /// sketch's rotation applied.
+    pub fn vertex_position(&self, pair_index: usize, vertex_id: VertexId) -> Vec3 {
+        self.angles.sketches[pair_index].vertex_rotated(vertex_id)
+    }
+
+    /// Returns the position of the upper sketch's origin relative to the
+    /// lower sketch's.
+    pub fn upper_offset(&self) -> Vec3 {
+        self.angles.sketches.upper.relative_position
+    }
+
+    /// Returns the signed angle from the lower vertex to the upper vertex, as
+    /// matched by `LoftOptions::matching`, in radians, which is positive when
+    /// the upper vertex is CCW of the lower vertex.
+    pub fn matching_angle(&self, vertices: (VertexId, VertexId)) -> f32 {
+        self.angles.angle_between(vertices.into())
+    }
+}
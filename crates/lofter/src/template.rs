@@ -0,0 +1,154 @@
+use std::f32::consts::PI;
+
+use glam::Vec3;
+
+use crate::{
+    Lofter,
+    profile::Superellipse,
+    sketch::SketchDescriptor,
+    wing::{WingBuilder, WingStation},
+};
+
+/// A starting setup of several stations for a common kind of loft, created
+/// with `Lofter::from_template`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Template {
+    /// A hull along the z axis, from a blunt stern to a fine bow, with its
+    /// deck at the top of each section.
+    BoatHull,
+    /// A duct along the z axis, transitioning from a round inlet to a
+    /// rectangular outlet.
+    Duct,
+    /// A tapered, swept wing panel along the z axis, from a NACA 2412
+    /// airfoil.
+    WingPanel,
+}
+
+impl Template {
+    pub const ALL: [Template; 3] = [Template::BoatHull, Template::Duct, Template::WingPanel];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Template::BoatHull => "Boat hull",
+            Template::Duct => "Duct",
+            Template::WingPanel => "Wing panel",
+        }
+    }
+}
+
+impl Lofter {
+    /// Creates a lofter from a template, lofted with the default options.
+    pub fn from_template(template: Template) -> Self {
+        let mut lofter = match template {
+            Template::BoatHull => {
+                let mut lofter = Lofter::from_sketches(&[]).unwrap();
+                lofter
+                    .generate_stations(7, |t| station(hull_section(t), t, 10. / 6.))
+                    .unwrap();
+                lofter
+            }
+            Template::Duct => {
+                let keyframes = [
+                    (
+                        0.,
+                        Superellipse {
+                            a: 1.,
+                            b: 1.,
+                            exponent: 2.,
+                        },
+                    ),
+                    (
+                        0.5,
+                        Superellipse {
+                            a: 1.2,
+                            b: 0.9,
+                            exponent: 3.,
+                        },
+                    ),
+                    (
+                        1.,
+                        Superellipse {
+                            a: 1.5,
+                            b: 0.75,
+                            exponent: 8.,
+                        },
+                    ),
+                ];
+
+                let mut lofter = Lofter::from_sketches(&[]).unwrap();
+                lofter
+                    .generate_stations(5, |t| {
+                        station(Superellipse::ramp(&keyframes, t).unwrap().sketch(32), t, 1.)
+                    })
+                    .unwrap();
+                lofter
+            }
+            Template::WingPanel => {
+                let airfoil = SketchDescriptor::naca4("2412", 1., 40).unwrap();
+                let wing = WingBuilder::new(airfoil)
+                    .station(WingStation {
+                        span: 0.,
+                        chord: 2.,
+                        twist: 2.,
+                        sweep: 0.,
+                        dihedral: 0.,
+                    })
+                    .station(WingStation {
+                        span: 5.,
+                        chord: 1.,
+                        twist: -1.,
+                        sweep: 10.,
+                        dihedral: 3.,
+                    });
+
+                Lofter::from_sketches(&wing.sketches().unwrap()).unwrap()
+            }
+        };
+
+        lofter.loft(&Default::default());
+
+        lofter
+    }
+}
+
+/// Places a sketch `spacing` above the previous station, or at the origin for
+/// the first station.
+fn station(mut sketch: SketchDescriptor, t: f32, spacing: f32) -> SketchDescriptor {
+    if t > 0. {
+        sketch.relative_position = Vec3::new(0., 0., spacing);
+    }
+
+    sketch
+}
+
+/// Returns the hull's cross section at `t` along its length, from the stern.
+/// The sections are superellipses below the deck, which are fullest
+/// midships and narrow to a vee at the bow.
+fn hull_section(t: f32) -> SketchDescriptor {
+    const POINTS: usize = 16;
+
+    let beam = 0.3 + 0.7 * (PI * t.powf(0.8)).sin().max(0.) + 0.5 * (1. - t);
+    let depth = 0.6 + 0.4 * (1. - t);
+    let power = 2. / (3.5 - 2.3 * t);
+
+    // CCW from the port side of the deck, under the keel to starboard. The
+    // deck closes the outline, and the origin is midway down it.
+    let vertices = (0..=POINTS)
+        .map(|i| {
+            let (sin, cos) = (PI + PI * i as f32 / POINTS as f32).sin_cos();
+
+            Vec3::new(
+                beam / 2. * cos.signum() * cos.abs().powf(power),
+                depth * (sin.signum() * sin.abs().powf(power) + 0.5),
+                0.,
+            )
+        })
+        .collect();
+
+    SketchDescriptor {
+        vertices,
+        relative_position: Vec3::ZERO,
+        rotation: Vec3::ZERO,
+    }
+}
@@ -1,10 +1,9 @@
 use std::ops::{Index, IndexMut};
 
-use glam::{Vec3, Vec3Swizzles};
-
 /// Paired values. Used for i.e. edge connections or paired vertex ranges
 /// between sketches.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SketchPair<T> {
     pub lower: T,
     pub upper: T,
@@ -78,9 +77,3 @@ impl<T> From<(T, T)> for SketchPair<T> {
         Self::new(value.0, value.1)
     }
 }
-
-/// Returns the radial difference of two points in polar coordinates from the
-/// origin along the z axis, in radians.
-pub fn radial_error(a: &Vec3, b: &Vec3) -> f32 {
-    a.xy().angle_to(b.xy()).abs()
-}
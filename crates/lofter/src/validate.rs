@@ -0,0 +1,164 @@
+use std::fmt;
+
+use glam::{Vec2, Vec3};
+
+use crate::sketch::{DUPLICATE_VERTEX_TOLERANCE, Sketch, VertexId};
+
+/// A problem with a sketch's outline, found by `Lofter::sketch_issues`.
+/// Sketches with issues can still be lofted, but their surfaces are likely
+/// wrong.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SketchIssue {
+    /// The vertex has a non-finite position.
+    NonFiniteVertex(VertexId),
+    /// The edges, each given by its two vertices in CCW order, cross.
+    SelfIntersection { edges: [(VertexId, VertexId); 2] },
+    /// The vertices are ordered clockwise, so the loft's triangles face
+    /// inwards.
+    Clockwise,
+    /// The outline encloses no area, such as when every vertex lies on a
+    /// line.
+    ZeroArea,
+}
+
+impl fmt::Display for SketchIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SketchIssue::NonFiniteVertex(vertex_id) => {
+                write!(f, "vertex {vertex_id} has a non-finite position")
+            }
+            SketchIssue::SelfIntersection { edges: [a, b] } => {
+                write!(f, "edges {}-{} and {}-{} cross", a.0, a.1, b.0, b.1)
+            }
+            SketchIssue::Clockwise => write!(f, "the vertices are ordered clockwise"),
+            SketchIssue::ZeroArea => write!(f, "the outline encloses no area"),
+        }
+    }
+}
+
+/// A problem with a loft's mesh, found by `IndexedMesh::issues`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MeshIssue {
+    /// The vertex, indexing `IndexedMesh::positions`, has a non-finite
+    /// position.
+    NonFinitePosition(u32),
+    /// The triangle, indexing `IndexedMesh::triangles`, is thinner than
+    /// `DUPLICATE_VERTEX_TOLERANCE`, so it has no normal.
+    DegenerateTriangle(usize),
+}
+
+impl fmt::Display for MeshIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshIssue::NonFinitePosition(index) => {
+                write!(f, "vertex {index} has a non-finite position")
+            }
+            MeshIssue::DegenerateTriangle(index) => write!(f, "triangle {index} is degenerate"),
+        }
+    }
+}
+
+/// A ruling edge which changes more between its sketches than the options
+/// allow, reported in `LoftResult::station_changes`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StationChange {
+    /// The index of the lower sketch.
+    pub sketch_pair: usize,
+    /// The index of the edge in `Lofter::ruling_edges`, until the lofter is
+    /// lofted again.
+    pub ruling_edge: usize,
+    /// The distance between the edge's ends in the plane of the sketches,
+    /// like `LoftOptions::max_station_change_distance`.
+    pub distance: f32,
+    /// In degrees, like `LoftOptions::max_station_change_angle`.
+    pub angle: f32,
+}
+
+pub(crate) fn sketch_issues(sketch: &Sketch) -> Vec<SketchIssue> {
+    let mut issues: Vec<SketchIssue> = sketch
+        .vertex_order
+        .iter()
+        .filter(|id| !sketch.vertex_map[id].is_finite())
+        .map(|&id| SketchIssue::NonFiniteVertex(id))
+        .collect();
+
+    // Crossings and areas are meaningless without finite positions.
+    if !issues.is_empty() {
+        return issues;
+    }
+
+    let edges: Vec<((VertexId, VertexId), [Vec2; 2])> = sketch
+        .vertex_order
+        .iter()
+        .zip(sketch.vertex_order.iter().cycle().skip(1))
+        .map(|(&a, &b)| {
+            let positions = [a, b].map(|id| sketch.vertex_map[&id].truncate());
+            ((a, b), positions)
+        })
+        .collect();
+
+    for (i, (a, a_positions)) in edges.iter().enumerate() {
+        // Adjacent edges share a vertex, so only later, non-adjacent edges are
+        // tested.
+        for (j, (b, b_positions)) in edges.iter().enumerate().skip(i + 2) {
+            if i == 0 && j == edges.len() - 1 {
+                continue;
+            }
+
+            if segments_cross(*a_positions, *b_positions) {
+                issues.push(SketchIssue::SelfIntersection { edges: [*a, *b] });
+            }
+        }
+    }
+
+    // Nor is winding meaningful for crossing edges.
+    if !issues.is_empty() {
+        return issues;
+    }
+
+    // A thin outline has an area of about its perimeter times its width.
+    let (twice_area, perimeter) = edges
+        .iter()
+        .fold((0., 0.), |(area, perimeter), (_, [a, b])| {
+            (area + a.perp_dot(*b), perimeter + a.distance(*b))
+        });
+    if twice_area.abs() <= 2. * DUPLICATE_VERTEX_TOLERANCE * perimeter {
+        issues.push(SketchIssue::ZeroArea);
+    } else if twice_area < 0. {
+        issues.push(SketchIssue::Clockwise);
+    }
+
+    issues
+}
+
+pub(crate) fn mesh_issues(positions: &[Vec3], triangles: &[[u32; 3]]) -> Vec<MeshIssue> {
+    let mut issues: Vec<MeshIssue> = positions
+        .iter()
+        .enumerate()
+        .filter(|(_, position)| !position.is_finite())
+        .map(|(index, _)| MeshIssue::NonFinitePosition(index as u32))
+        .collect();
+
+    for (index, triangle) in triangles.iter().enumerate() {
+        let [a, b, c] = triangle.map(|index| positions[index as usize]);
+        if !(a.is_finite() && b.is_finite() && c.is_finite()) {
+            continue;
+        }
+
+        // The triangle's height over its longest edge.
+        let longest = a.distance(b).max(b.distance(c)).max(c.distance(a));
+        if (b - a).cross(c - a).length() <= DUPLICATE_VERTEX_TOLERANCE * longest {
+            issues.push(MeshIssue::DegenerateTriangle(index));
+        }
+    }
+
+    issues
+}
+
+/// Returns whether the segments cross at a single point, other than at
+/// their ends.
+fn segments_cross([a0, a1]: [Vec2; 2], [b0, b1]: [Vec2; 2]) -> bool {
+    let side = |p: Vec2, q: Vec2, r: Vec2| (q - p).perp_dot(r - p);
+
+    side(a0, a1, b0) * side(a0, a1, b1) < 0. && side(b0, b1, a0) * side(b0, b1, a1) < 0.
+}
@@ -0,0 +1,102 @@
+use glam::Vec3;
+
+use crate::{LoftOptions, Lofter, error::WingError, sketch::SketchDescriptor};
+
+/// A spanwise station of a wing built with `WingBuilder`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WingStation {
+    /// The distance from the root along the span, which is the z axis.
+    pub span: f32,
+    pub chord: f32,
+    /// The angle of incidence about the quarter chord, in degrees, positive
+    /// nose up.
+    pub twist: f32,
+    /// The sweep of the quarter-chord line between the previous station and
+    /// this one, in degrees, positive towards the trailing edge. Ignored for
+    /// the first station.
+    pub sweep: f32,
+    /// The dihedral between the previous station and this one, in degrees,
+    /// positive upwards. Ignored for the first station.
+    pub dihedral: f32,
+}
+
+/// Generates the sketch stack of a wing from its spanwise stations and an
+/// airfoil, then lofts it.
+///
+/// The airfoil is normalized to a unit chord, such as one from
+/// `SketchDescriptor::naca4` with a chord of 1, and is scaled and twisted
+/// about its origin. Since sketches are always parallel to the xy plane,
+/// sweep and dihedral shear the sections rather than tilting them.
+#[derive(Clone, Debug)]
+pub struct WingBuilder {
+    airfoil: SketchDescriptor,
+    stations: Vec<WingStation>,
+}
+
+impl WingBuilder {
+    pub fn new(airfoil: SketchDescriptor) -> Self {
+        Self {
+            airfoil,
+            stations: Vec::new(),
+        }
+    }
+
+    /// Adds a station, which must be further along the span than the last.
+    pub fn station(mut self, station: WingStation) -> Self {
+        self.stations.push(station);
+        self
+    }
+
+    /// Returns a sketch for each station, root first.
+    pub fn sketches(&self) -> Result<Vec<SketchDescriptor>, WingError> {
+        if self.stations.len() < 2 {
+            return Err(WingError::TooFewStations);
+        }
+
+        let mut sketches = Vec::with_capacity(self.stations.len());
+        let mut previous_station: Option<&WingStation> = None;
+
+        for (index, station) in self.stations.iter().enumerate() {
+            let relative_position = match previous_station {
+                Some(previous) => {
+                    let span = station.span - previous.span;
+                    if span <= 0. {
+                        return Err(WingError::StationOutOfOrder(index));
+                    }
+
+                    Vec3::new(
+                        span * station.sweep.to_radians().tan(),
+                        span * station.dihedral.to_radians().tan(),
+                        span,
+                    )
+                }
+                None => Vec3::new(0., 0., station.span),
+            };
+
+            sketches.push(SketchDescriptor {
+                vertices: self
+                    .airfoil
+                    .vertices
+                    .iter()
+                    .map(|vertex| *vertex * station.chord)
+                    .collect(),
+                relative_position,
+                // Positive rotations raise the trailing edge.
+                rotation: Vec3::new(0., 0., -station.twist),
+            });
+
+            previous_station = Some(station);
+        }
+
+        Ok(sketches)
+    }
+
+    /// Returns a lofter with the wing's sketches, lofted with `options`.
+    pub fn build(&self, options: &LoftOptions) -> Result<Lofter, WingError> {
+        let mut lofter =
+            Lofter::from_sketches(&self.sketches()?).map_err(WingError::InvalidAirfoil)?;
+        lofter.loft(options);
+
+        Ok(lofter)
+    }
+}
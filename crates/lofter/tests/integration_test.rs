@@ -1,33 +1,57 @@
+use std::{
+    collections::{HashMap, HashSet},
+    f32::consts::FRAC_PI_2,
+    iter::zip,
+    sync::Arc,
+};
+
 use glam::Vec3;
-use lofter::{LoftOptions, Lofter, SketchDescriptor};
+use lofter::{
+    AirfoilError, CapFillet, Command, Correspondence, Decimate, DefaultMatching,
+    InvalidCorrespondenceLine, LoftDocument, LoftError, LoftOptions, LoftStep, Lofter,
+    MAX_FILLET_SEGMENTS, MAX_SLICE_LAYERS, Matching, MatchingPair, MatchingStrategy, MeshIssue,
+    MeshPass, MirrorAxis, OutputLayout, PairLoftError, SessionStats, SketchDescriptor, SketchIssue,
+    SketchPoint, Smooth, StationChange, Superellipse, Template, VertexId, Weld, WingBuilder,
+    WingError, WingStation,
+    analysis::{self, DraftClass},
+    export::{
+        self, CorrespondenceFormat, ExportMetadata, Format, IndexedMesh, MAX_CHUNK_VERTICES,
+        MeshExporter, NumberFormat, Unit,
+    },
+};
 
 #[test]
 fn integration() {
-    let mut lofter = Lofter::default();
+    let mut lofter = Lofter::demo();
 
-    lofter.push_sketch(&SketchDescriptor {
-        vertices: vec![
-            Vec3::new(1., 0., 0.),
-            Vec3::new(0., 1., 0.),
-            Vec3::new(-1., -1., 0.),
-        ],
-        relative_position: Vec3::ZERO,
-        rotation: Vec3::ZERO,
-    });
+    lofter
+        .push_sketch(&SketchDescriptor {
+            vertices: vec![
+                Vec3::new(1., 0., 0.),
+                Vec3::new(0., 1., 0.),
+                Vec3::new(-1., -1., 0.),
+            ],
+            relative_position: Vec3::ZERO,
+            rotation: Vec3::ZERO,
+        })
+        .unwrap();
 
-    lofter.push_sketch(&SketchDescriptor {
-        vertices: vec![
-            Vec3::new(1., 0., 0.),
-            Vec3::new(0., 1., 0.),
-            Vec3::new(-1., 0., 0.),
-            Vec3::new(0., -1., 0.),
-        ],
-        relative_position: Vec3::new(0., 0., 1.),
-        rotation: Vec3::ZERO,
-    });
+    lofter
+        .push_sketch(&SketchDescriptor {
+            vertices: vec![
+                Vec3::new(1., 0., 0.),
+                Vec3::new(0., 1., 0.),
+                Vec3::new(-1., 0., 0.),
+                Vec3::new(0., -1., 0.),
+            ],
+            relative_position: Vec3::new(0., 0., 1.),
+            rotation: Vec3::ZERO,
+        })
+        .unwrap();
 
     lofter.loft(&LoftOptions {
         max_radial_edge_angle: 5.,
+        ..Default::default()
     });
 
     let vb = lofter.vertex_buffer();
@@ -56,3 +80,2492 @@ fn integration() {
 
     dbg!(obj_string);
 }
+
+#[test]
+fn locked_sketch_rejects_mutations() {
+    let mut lofter = Lofter::demo();
+
+    lofter.set_sketch_locked(0, true).unwrap();
+
+    assert_eq!(
+        lofter.set_sketch_rotation(0, &Vec3::new(0., 0., 45.)),
+        Err(LoftError::SketchLocked(0))
+    );
+    assert_eq!(
+        lofter.get_vertex_mut(0, 0).map(|_| ()),
+        Err(LoftError::SketchLocked(0))
+    );
+    assert_eq!(lofter.remove_vertex(0, 0), Err(LoftError::SketchLocked(0)));
+    assert_eq!(
+        lofter.set_vertex_excluded(0, 0, true),
+        Err(LoftError::SketchLocked(0))
+    );
+    assert_eq!(lofter.vertex_excluded(0, 0), Some(false));
+
+    // Other sketches are unaffected.
+    assert!(lofter.insert_vertex(1, (0, 1)).is_ok());
+
+    lofter.set_sketch_locked(0, false).unwrap();
+
+    assert!(lofter.remove_vertex(0, 0).is_ok());
+}
+
+#[test]
+fn tag_mirror_partners() {
+    let mut lofter = Lofter::demo();
+
+    // The default sketches are diamonds, symmetric about both axes.
+    assert_eq!(lofter.tag_mirror_partners(0, MirrorAxis::Y, 1e-3), Ok(4));
+
+    assert_eq!(lofter.mirror_partner(0, 0), Some(2));
+    assert_eq!(lofter.mirror_partner(0, 2), Some(0));
+    assert_eq!(lofter.mirror_partner(0, 1), Some(1));
+
+    lofter.set_mirror_partners(0, (0, 1)).unwrap();
+
+    assert_eq!(lofter.mirror_partner(0, 1), Some(0));
+    assert_eq!(lofter.mirror_partner(0, 2), None);
+
+    // Failed and empty calls change nothing.
+    let revision = lofter.revision();
+    assert_eq!(
+        lofter.set_mirror_partners(0, (0, 9)),
+        Err(LoftError::VertexNotFound {
+            sketch_index: 0,
+            vertex_id: 9,
+        })
+    );
+    assert_eq!(lofter.mirror_partner(0, 0), Some(1));
+    lofter.clear_mirror_partner(0, 2).unwrap();
+    assert!(lofter.tag_mirror_partners(5, MirrorAxis::Y, 1e-3).is_err());
+    assert_eq!(lofter.revision(), revision);
+
+    // Locked sketches keep their tags.
+    lofter.set_sketch_locked(0, true).unwrap();
+    let revision = lofter.revision();
+    assert_eq!(
+        lofter.set_mirror_partners(0, (2, 3)),
+        Err(LoftError::SketchLocked(0))
+    );
+    assert_eq!(
+        lofter.clear_mirror_partner(0, 0),
+        Err(LoftError::SketchLocked(0))
+    );
+    assert_eq!(
+        lofter.tag_mirror_partners(0, MirrorAxis::Y, 1e-3),
+        Err(LoftError::SketchLocked(0))
+    );
+    assert_eq!(lofter.mirror_partner(0, 0), Some(1));
+    assert_eq!(lofter.mirror_partner(0, 2), None);
+    assert_eq!(lofter.revision(), revision);
+    lofter.set_sketch_locked(0, false).unwrap();
+
+    lofter.remove_vertex(0, 0).unwrap();
+
+    assert_eq!(lofter.mirror_partner(0, 1), None);
+}
+
+#[test]
+fn world_positions_accumulate_relative_positions() {
+    let mut lofter = Lofter::demo();
+
+    lofter
+        .push_sketch(&SketchDescriptor {
+            vertices: vec![
+                Vec3::new(1., 0., 0.),
+                Vec3::new(0., 1., 0.),
+                Vec3::new(-1., -1., 0.),
+            ],
+            relative_position: Vec3::new(0., 0., 1.),
+            rotation: Vec3::new(0., 0., 90.),
+        })
+        .unwrap();
+
+    assert_eq!(lofter.sketch_world_position(2), Some(Vec3::new(0., 0., 4.)));
+
+    let world_position = lofter.vertex_world_position(2, 0).unwrap();
+    assert!(world_position.abs_diff_eq(Vec3::new(0., 1., 4.), 1e-6));
+
+    lofter
+        .set_vertex_world_position(2, 0, &Vec3::new(0., 2., 4.))
+        .unwrap();
+
+    assert!(
+        lofter
+            .get_vertex(2, 0)
+            .unwrap()
+            .abs_diff_eq(Vec3::new(2., 0., 0.), 1e-6)
+    );
+}
+
+#[test]
+fn revision_changes_on_mutation() {
+    let mut lofter = Lofter::demo();
+
+    let revision = lofter.revision();
+    let shape_revision = lofter.shape_revision();
+    lofter
+        .set_vertex_positions(0, &[(0, Vec3::X * 2.)])
+        .unwrap();
+    assert_ne!(lofter.revision(), revision);
+    assert_ne!(lofter.shape_revision(), shape_revision);
+
+    // Rejected mutations don't change anything.
+    let revisions = (lofter.revision(), lofter.shape_revision());
+    assert!(matches!(
+        lofter.insert_vertex(0, (0, 2)),
+        Err(LoftError::VerticesNotAdjacent { .. })
+    ));
+    assert!(matches!(
+        lofter.remove_vertex(0, 9),
+        Err(LoftError::VertexNotFound { .. })
+    ));
+    assert!(matches!(
+        lofter.fillet_vertex(0, 0, 100., 4),
+        Err(LoftError::CornerTooSmall { .. })
+    ));
+    assert!(matches!(
+        lofter.set_vertex_positions(0, &[(0, Vec3::X), (9, Vec3::Y)]),
+        Err(LoftError::VertexNotFound { .. })
+    ));
+    assert!(matches!(
+        lofter.get_vertex_mut(0, 9),
+        Err(LoftError::VertexNotFound { .. })
+    ));
+    assert_eq!((lofter.revision(), lofter.shape_revision()), revisions);
+
+    lofter.remove_vertex(0, 0).unwrap();
+    let revisions = (lofter.revision(), lofter.shape_revision());
+    assert!(matches!(
+        lofter.remove_vertex(0, 1),
+        Err(LoftError::TooFewVertices { .. })
+    ));
+    assert_eq!((lofter.revision(), lofter.shape_revision()), revisions);
+
+    lofter.set_sketch_locked(0, true).unwrap();
+    let revisions = (lofter.revision(), lofter.shape_revision());
+    assert!(lofter.remove_vertex(0, 1).is_err());
+    assert!(lofter.set_sketch_rotation(0, &Vec3::Z).is_err());
+    assert_eq!((lofter.revision(), lofter.shape_revision()), revisions);
+}
+
+#[test]
+fn local_vertex_buffer_matches_vertex_buffer() {
+    let mut lofter = Lofter::demo();
+
+    lofter
+        .set_sketch_rotation(1, &Vec3::new(0., 0., 30.))
+        .unwrap();
+    lofter.loft(&Default::default());
+
+    // Transforms don't change the loft's shape.
+    let shape_revision = lofter.shape_revision();
+    lofter
+        .set_sketch_rotation(1, &Vec3::new(0., 0., 60.))
+        .unwrap();
+    assert_eq!(lofter.shape_revision(), shape_revision);
+
+    let vertex_buffer = lofter.vertex_buffer();
+    let local_vertex_buffer = lofter.local_vertex_buffer();
+
+    assert_eq!(vertex_buffer.len(), local_vertex_buffer.len());
+
+    for (triangle, local_triangle) in vertex_buffer.iter().zip(&local_vertex_buffer) {
+        for ([position, color], local) in triangle.iter().zip(local_triangle) {
+            let rotation = lofter.sketch_rotation(local.sketch_index).unwrap().z;
+            let origin = lofter.sketch_world_position(local.sketch_index).unwrap();
+
+            let evaluated = local.position.rotate_z(rotation.to_radians()) + origin;
+
+            assert!(evaluated.abs_diff_eq(*position, 1e-5));
+            assert_eq!(local.color, *color);
+        }
+    }
+}
+
+#[test]
+fn layout_vertex_buffer() {
+    let lofter = Lofter::from_template(Template::Duct);
+    let vertex_buffer = lofter.vertex_buffer();
+
+    for layout in OutputLayout::ALL {
+        let floats = lofter.layout_vertex_buffer(layout);
+        let stride = layout.floats_per_vertex();
+        assert_eq!(layout.stride(), stride * 4);
+        assert_eq!(floats.len(), vertex_buffer.len() * 3 * stride);
+
+        let vertices = zip(floats.chunks(stride), vertex_buffer.iter().flatten());
+        for (vertex, [position, color]) in vertices {
+            let attribute = |offset: usize, len: usize| &vertex[offset / 4..offset / 4 + len];
+
+            assert_eq!(attribute(layout.position_offset(), 3), position.to_array());
+            if let Some(offset) = layout.color_offset() {
+                assert_eq!(attribute(offset, 3), color.to_array());
+            }
+            if let Some(offset) = layout.normal_offset() {
+                let normal = Vec3::from_slice(attribute(offset, 3));
+                assert!((normal.length() - 1.).abs() < 1e-4);
+            }
+            if let Some(offset) = layout.uv_offset() {
+                assert!(attribute(offset, 2).iter().all(|t| (0. ..=1.).contains(t)));
+            }
+        }
+    }
+
+    assert_eq!(OutputLayout::PositionNormalUvColor.stride(), 44);
+}
+
+#[test]
+fn ruling_edges_connect_adjacent_sketches() {
+    let lofter = Lofter::demo();
+
+    let ruling_edges = lofter.ruling_edges();
+    assert!(!ruling_edges.is_empty());
+
+    for [lower, upper] in ruling_edges {
+        assert_eq!(lower.z, 0.);
+        assert_eq!(upper.z, 3.);
+    }
+}
+
+#[test]
+fn triangle_sections_match_vertex_buffer() {
+    let lofter = Lofter::demo();
+
+    let sections = lofter.triangle_sections();
+
+    assert_eq!(sections.len(), lofter.vertex_buffer().len());
+    // Sections are numbered in order around the loft.
+    assert!(
+        sections
+            .windows(2)
+            .all(|pair| pair[0] <= pair[1] || pair[1] == 0)
+    );
+}
+
+#[test]
+fn explain_loft_matches_loft() {
+    let mut lofter = Lofter::demo();
+
+    let (steps, _) = lofter.explain_loft(&Default::default());
+
+    // The first accepted candidate creates the initial section.
+    assert!(matches!(steps[0], LoftStep::CandidateAccepted { .. }));
+
+    let explained_edges: Vec<_> = steps
+        .into_iter()
+        .filter_map(|step| match step {
+            LoftStep::SectionBuilt { ruling_edges, .. } => Some(ruling_edges),
+            _ => None,
+        })
+        .flatten()
+        .collect();
+
+    lofter.loft(&Default::default());
+    assert_eq!(explained_edges, lofter.ruling_edges());
+}
+
+#[test]
+fn replayed_session_matches_recorded_lofter() {
+    let mut lofter = Lofter::demo();
+    lofter.remove_vertex(1, 0).unwrap();
+
+    lofter.start_recording();
+
+    let vertex_id = lofter.insert_vertex(1, (1, 2)).unwrap();
+    lofter
+        .set_vertex_positions(1, &[(vertex_id, Vec3::new(-1., 1., 0.))])
+        .unwrap();
+    // Failed commands are recorded too.
+    assert!(lofter.remove_vertex(1, 0).is_err());
+    lofter.loft(&Default::default());
+
+    let session = lofter.stop_recording().unwrap();
+    assert_eq!(session.commands.len(), 4);
+
+    let replayed = session.replay();
+
+    let positions = |lofter: &Lofter| -> Vec<_> {
+        lofter
+            .vertex_buffer()
+            .iter()
+            .flatten()
+            .map(|[position, _]| *position)
+            .collect()
+    };
+
+    assert_eq!(positions(&replayed), positions(&lofter));
+}
+
+#[test]
+fn generate_stations_spans_parameter() {
+    let mut lofter = Lofter::demo();
+    let sketch_count = lofter.sketch_count();
+
+    lofter
+        .generate_stations(5, |t| SketchDescriptor {
+            vertices: vec![
+                Vec3::new(2. - t, 0., 0.),
+                Vec3::new(0., 2. - t, 0.),
+                Vec3::new(-2. + t, 0., 0.),
+            ],
+            relative_position: Vec3::new(0., 0., t),
+            rotation: Vec3::ZERO,
+        })
+        .unwrap();
+
+    assert_eq!(lofter.sketch_count(), sketch_count + 5);
+    assert_eq!(
+        lofter.sketch_relative_position(sketch_count),
+        Some(&Vec3::ZERO)
+    );
+    assert_eq!(
+        lofter.sketch_relative_position(sketch_count + 4),
+        Some(&Vec3::Z)
+    );
+}
+
+#[test]
+fn airfoil_profiles() {
+    let naca = SketchDescriptor::naca4("0012", 2., 40).unwrap();
+    assert_eq!(naca.vertices.len(), 40);
+
+    // Symmetric airfoils are centered on the chord line.
+    let max_y = naca.vertices.iter().map(|v| v.y).fold(f32::MIN, f32::max);
+    let min_y = naca.vertices.iter().map(|v| v.y).fold(f32::MAX, f32::min);
+    assert!((max_y + min_y).abs() < 1e-4);
+    assert!((max_y - min_y - 0.24).abs() < 1e-2);
+
+    let mut lofter = Lofter::demo();
+    lofter
+        .generate_stations(2, |t| SketchDescriptor {
+            relative_position: Vec3::new(0., 0., t),
+            ..SketchDescriptor::naca4("2412", 1. - t / 2., 40).unwrap()
+        })
+        .unwrap();
+    lofter.loft(&Default::default());
+    assert!(!lofter.vertex_buffer().is_empty());
+
+    assert!(SketchDescriptor::naca4("2012", 1., 40).is_err());
+    assert!(SketchDescriptor::naca4("24121", 1., 40).is_err());
+
+    let selig = "Diamond\n1.0 0.0\n0.5 0.1\n0.0 0.0\n0.5 -0.1\n1.0 0.0\n";
+    let diamond = SketchDescriptor::from_selig(selig, 1.).unwrap();
+    // The repeated trailing edge is removed.
+    assert_eq!(diamond.vertices.len(), 4);
+    assert_eq!(diamond.vertices[0], Vec3::new(0.75, 0., 0.));
+
+    assert_eq!(
+        SketchDescriptor::from_selig("Bad\n1.0 0.0\n0.5\n", 1.),
+        Err(AirfoilError::InvalidSeligLine(3))
+    );
+    for coordinates in ["NaN 0.0", "0.5 inf", "-infinity 0.0"] {
+        assert_eq!(
+            SketchDescriptor::from_selig(&format!("Bad\n1.0 0.0\n{coordinates}\n"), 1.),
+            Err(AirfoilError::InvalidSeligLine(3))
+        );
+    }
+}
+
+#[test]
+fn wing_builder_schedules_stations() {
+    let station = |span, chord| WingStation {
+        span,
+        chord,
+        twist: -2.,
+        sweep: 45.,
+        dihedral: 0.,
+    };
+
+    let wing = WingBuilder::new(SketchDescriptor::naca4("2412", 1., 30).unwrap())
+        .station(station(0., 2.))
+        .station(station(4., 1.));
+
+    let lofter = wing.build(&Default::default()).unwrap();
+    assert_eq!(lofter.sketch_count(), 2);
+    assert!(!lofter.vertex_buffer().is_empty());
+
+    // The quarter-chord line is swept back by the span.
+    let tip = lofter.sketch_world_position(1).unwrap();
+    assert!(tip.abs_diff_eq(Vec3::new(4., 0., 4.), 1e-4));
+
+    let out_of_order = wing.clone().station(station(2., 1.));
+    assert_eq!(
+        out_of_order.sketches(),
+        Err(WingError::StationOutOfOrder(2))
+    );
+}
+
+#[test]
+fn superellipse_ramps_between_keyframes() {
+    let nose = Superellipse::from_eccentricity(0.2, 0., 2.);
+    let cabin = Superellipse::from_eccentricity(1., 0.5, 4.);
+    let keyframes = [(0., nose), (0.5, cabin), (1., nose)];
+
+    assert_eq!(Superellipse::ramp(&keyframes, -1.), Some(nose));
+    assert_eq!(Superellipse::ramp(&keyframes, 0.5), Some(cabin));
+    assert_eq!(
+        Superellipse::ramp(&keyframes, 0.25),
+        Some(nose.lerp(&cabin, 0.5))
+    );
+    assert_eq!(Superellipse::ramp(&[], 0.5), None);
+    assert!((cabin.eccentricity() - 0.5).abs() < 1e-5);
+
+    let mut lofter = Lofter::demo();
+    lofter
+        .generate_stations(9, |t| SketchDescriptor {
+            relative_position: Vec3::new(0., 0., 0.5),
+            ..Superellipse::ramp(&keyframes, t).unwrap().sketch(32)
+        })
+        .unwrap();
+    lofter.loft(&Default::default());
+    assert!(!lofter.vertex_buffer().is_empty());
+}
+
+#[test]
+fn blend_interpolates_matched_vertices() {
+    let square = SketchDescriptor {
+        vertices: vec![
+            Vec3::new(1., 1., 0.),
+            Vec3::new(-1., 1., 0.),
+            Vec3::new(-1., -1., 0.),
+            Vec3::new(1., -1., 0.),
+        ],
+        relative_position: Vec3::ZERO,
+        rotation: Vec3::ZERO,
+    };
+    let large_square = SketchDescriptor {
+        vertices: square.vertices.iter().map(|v| *v * 3.).collect(),
+        relative_position: Vec3::new(0., 0., 2.),
+        ..square.clone()
+    };
+
+    let blend = SketchDescriptor::blend(&square, &large_square, 0.5).unwrap();
+    assert_eq!(blend.relative_position, Vec3::Z);
+
+    let mut vertices = blend.vertices.clone();
+    vertices.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    assert_eq!(
+        vertices,
+        [
+            Vec3::new(-2., -2., 0.),
+            Vec3::new(-2., 2., 0.),
+            Vec3::new(2., -2., 0.),
+            Vec3::new(2., 2., 0.),
+        ]
+    );
+
+    assert_eq!(
+        SketchDescriptor::blend(&square, &large_square, 0.)
+            .unwrap()
+            .vertices
+            .len(),
+        4
+    );
+
+    // Invalid profiles are rejected rather than lofted.
+    let point = SketchDescriptor {
+        vertices: vec![Vec3::ZERO],
+        ..square.clone()
+    };
+    assert_eq!(
+        SketchDescriptor::blend(&square, &point, 0.5),
+        Err(LoftError::TooFewVertices {
+            sketch_index: 1,
+            vertex_count: 1,
+        })
+    );
+    let empty = SketchDescriptor {
+        vertices: Vec::new(),
+        ..square.clone()
+    };
+    assert_eq!(
+        SketchDescriptor::blend(&empty, &square, 0.5),
+        Err(LoftError::TooFewVertices {
+            sketch_index: 0,
+            vertex_count: 0,
+        })
+    );
+
+    let mut infinite = square.clone();
+    infinite.vertices[0] = Vec3::new(f32::INFINITY, 0., 0.);
+    assert_eq!(
+        SketchDescriptor::blend(&square, &infinite, 0.5),
+        Err(LoftError::BlendFailed(PairLoftError::NonFiniteVertex))
+    );
+
+    // Outlines which can't be matched fail the blend rather than panicking,
+    // whether the profile is off the z axis or has no area.
+    let off_origin = SketchDescriptor {
+        vertices: vec![
+            Vec3::new(2., 0., 0.),
+            Vec3::new(3., 0., 0.),
+            Vec3::new(3., 1., 0.),
+        ],
+        ..square.clone()
+    };
+    let collinear = SketchDescriptor {
+        vertices: vec![
+            Vec3::new(1., 1., 0.),
+            Vec3::new(2., 1., 0.),
+            Vec3::new(3., 1., 0.),
+        ],
+        ..square.clone()
+    };
+    for profile in [&off_origin, &collinear] {
+        assert_eq!(
+            SketchDescriptor::blend(&square, profile, 0.5),
+            Err(LoftError::BlendFailed(PairLoftError::UnmatchedOutlines))
+        );
+    }
+}
+
+#[test]
+fn turning_angle_matching_lofts_cross_to_circle() {
+    let cross = [
+        (1., 3.),
+        (-1., 3.),
+        (-1., 1.),
+        (-3., 1.),
+        (-3., -1.),
+        (-1., -1.),
+        (-1., -3.),
+        (1., -3.),
+        (1., -1.),
+        (3., -1.),
+        (3., 1.),
+        (1., 1.),
+    ];
+
+    let mut lofter = Lofter::from_sketches(&[
+        SketchDescriptor {
+            vertices: cross.iter().map(|&(x, y)| Vec3::new(x, y, 0.)).collect(),
+            relative_position: Vec3::ZERO,
+            rotation: Vec3::ZERO,
+        },
+        SketchDescriptor {
+            relative_position: Vec3::Z,
+            ..Superellipse::from_eccentricity(2., 0., 2.).sketch(24)
+        },
+    ])
+    .unwrap();
+
+    lofter.loft(&LoftOptions {
+        max_radial_edge_angle: 10.,
+        matching: Matching::TurningAngle,
+        ..Default::default()
+    });
+
+    let vertex_buffer = lofter.vertex_buffer();
+    assert!(!vertex_buffer.is_empty());
+    assert!(
+        vertex_buffer
+            .iter()
+            .flatten()
+            .all(|[position, _]| position.is_finite())
+    );
+}
+
+#[test]
+fn vertices_on_sketch_axis_loft_without_nan() {
+    // A "D" profile with a vertex on its straight side at the origin, and a
+    // triangle with a corner at the origin.
+    let d_profile = SketchDescriptor {
+        vertices: vec![
+            Vec3::new(0., -1., 0.),
+            Vec3::new(1., -0.5, 0.),
+            Vec3::new(1., 0.5, 0.),
+            Vec3::new(0., 1., 0.),
+            Vec3::new(0., 0., 0.),
+        ],
+        relative_position: Vec3::ZERO,
+        rotation: Vec3::ZERO,
+    };
+    let triangle = SketchDescriptor {
+        vertices: vec![
+            Vec3::new(0., 0., 0.),
+            Vec3::new(1., 0., 0.),
+            Vec3::new(0., 1., 0.),
+        ],
+        relative_position: Vec3::Z,
+        rotation: Vec3::ZERO,
+    };
+    // Consecutive vertices on the axis, with just one vertex off it.
+    let adjacent_on_axis = SketchDescriptor {
+        vertices: vec![
+            Vec3::new(0., 0., 0.),
+            Vec3::new(0., 0., 1e-4),
+            Vec3::new(1e-3, 0., 0.),
+        ],
+        relative_position: Vec3::Z,
+        rotation: Vec3::ZERO,
+    };
+
+    let pairs = [
+        [d_profile.clone(), triangle.clone()],
+        [d_profile, adjacent_on_axis.clone()],
+        [adjacent_on_axis, triangle],
+    ];
+    for matching in [Matching::Polar, Matching::TurningAngle] {
+        for sketches in &pairs {
+            for max_radial_edge_angle in [5., 50.] {
+                let mut lofter = Lofter::from_sketches(sketches).unwrap();
+                lofter.loft(&LoftOptions {
+                    max_radial_edge_angle,
+                    matching,
+                    ..Default::default()
+                });
+
+                let (steps, result) = lofter.explain_loft(&LoftOptions {
+                    max_radial_edge_angle,
+                    matching,
+                    ..Default::default()
+                });
+                assert!(result.is_ok());
+                assert!(steps.iter().all(|step| match step {
+                    LoftStep::CandidateAccepted { radial_error, .. }
+                    | LoftStep::CandidateRejected { radial_error, .. } => !radial_error.is_nan(),
+                    LoftStep::SectionBuilt { .. } => true,
+                }));
+
+                let vertex_buffer = lofter.vertex_buffer();
+                assert!(!vertex_buffer.is_empty());
+                assert!(
+                    vertex_buffer
+                        .iter()
+                        .flatten()
+                        .all(|[position, _]| position.is_finite())
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn push_sketch_merges_duplicate_vertices() {
+    let mut lofter = Lofter::demo();
+
+    let merged_count = lofter
+        .push_sketch(&SketchDescriptor {
+            vertices: vec![
+                Vec3::new(1., 0., 0.),
+                Vec3::new(1., 0., 0.),
+                Vec3::new(0., 1., 0.),
+                Vec3::new(0., 1. + 1e-7, 0.),
+                Vec3::new(-1., 0., 0.),
+                Vec3::new(1., 0., 0.),
+            ],
+            relative_position: Vec3::Z,
+            rotation: Vec3::ZERO,
+        })
+        .unwrap();
+
+    assert_eq!(merged_count, 3);
+    assert_eq!(lofter.vertices(2).unwrap().count(), 3);
+
+    lofter.loft(&Default::default());
+    assert!(
+        lofter
+            .vertex_buffer()
+            .iter()
+            .flatten()
+            .all(|[position, _]| position.is_finite())
+    );
+}
+
+#[test]
+fn sketches_need_three_vertices() {
+    let mut lofter = Lofter::demo();
+
+    let line = SketchDescriptor {
+        vertices: vec![Vec3::X, Vec3::Y, Vec3::Y],
+        relative_position: Vec3::Z,
+        rotation: Vec3::ZERO,
+    };
+    assert_eq!(
+        lofter.push_sketch(&line),
+        Err(LoftError::TooFewVertices {
+            sketch_index: 2,
+            vertex_count: 2,
+        })
+    );
+    assert_eq!(lofter.sketch_count(), 2);
+
+    lofter.remove_vertex(0, 0).unwrap();
+    assert_eq!(
+        lofter.remove_vertex(0, 1),
+        Err(LoftError::TooFewVertices {
+            sketch_index: 0,
+            vertex_count: 2,
+        })
+    );
+}
+
+#[test]
+fn loft_reports_failed_pairs() {
+    let mut lofter = Lofter::demo();
+
+    lofter
+        .push_sketch(&SketchDescriptor {
+            vertices: vec![
+                Vec3::new(f32::INFINITY, 0., 0.),
+                Vec3::new(0., 1., 0.),
+                Vec3::new(-1., -1., 0.),
+            ],
+            relative_position: Vec3::Z,
+            rotation: Vec3::ZERO,
+        })
+        .unwrap();
+
+    let result = lofter.loft(&Default::default());
+    assert!(!result.is_ok());
+    assert_eq!(result.pairs[0], Ok(()));
+    assert_eq!(result.pairs[1], Err(PairLoftError::NonFiniteVertex));
+
+    // The failed pair has no triangles, but the rest of the loft is kept.
+    assert!(!lofter.vertex_buffer().is_empty());
+}
+
+#[test]
+fn off_origin_sketch_fails_its_pairs() {
+    let square = |x: f32| SketchDescriptor {
+        vertices: vec![
+            Vec3::new(x - 1., -1., 0.),
+            Vec3::new(x + 1., -1., 0.),
+            Vec3::new(x + 1., 1., 0.),
+            Vec3::new(x - 1., 1., 0.),
+        ],
+        relative_position: Vec3::Z,
+        rotation: Vec3::ZERO,
+    };
+    let collinear = SketchDescriptor {
+        vertices: vec![
+            Vec3::new(1., 1., 0.),
+            Vec3::new(2., 1., 0.),
+            Vec3::new(3., 1., 0.),
+        ],
+        relative_position: Vec3::Z,
+        rotation: Vec3::ZERO,
+    };
+
+    // Neither the square spanning x in [2, 4] nor the collinear sketch
+    // encloses its z axis, so only the pair of centred squares is lofted.
+    for sketch in [square(3.), collinear] {
+        let mut lofter = Lofter::from_sketches(&[square(0.), square(0.), sketch]).unwrap();
+
+        let result = lofter.loft(&Default::default());
+        assert_eq!(
+            result.pairs,
+            [Ok(()), Err(PairLoftError::UnmatchedOutlines)]
+        );
+        assert!(!lofter.vertex_buffer().is_empty());
+    }
+}
+
+#[test]
+fn failed_pair_keeps_previous_loft() {
+    let mut lofter = Lofter::demo();
+    let vertex_buffer = lofter.vertex_buffer();
+
+    // The matching overflows for such a distant vertex.
+    lofter
+        .set_vertex_positions(0, &[(0, Vec3::new(1e30, 1e30, 0.))])
+        .unwrap();
+
+    let result = lofter.loft(&Default::default());
+    assert_eq!(result.pairs, [Err(PairLoftError::NonFiniteVertex)]);
+    assert_eq!(result.kept_previous, [0]);
+    assert_eq!(lofter.vertex_buffer().len(), vertex_buffer.len());
+
+    // Once the previous loft references a removed vertex, it's dropped.
+    lofter.remove_vertex(0, 1).unwrap();
+
+    let result = lofter.loft(&Default::default());
+    assert!(result.kept_previous.is_empty());
+    assert!(lofter.vertex_buffer().is_empty());
+}
+
+#[test]
+fn failed_pair_keeps_no_empty_loft() {
+    let mut lofter = Lofter::demo();
+
+    // Both pairs the sketch splits are left without a surface, and fail.
+    let sketch = SketchDescriptor {
+        vertices: vec![
+            Vec3::new(1e30, 1e30, 0.),
+            Vec3::new(0., 1., 0.),
+            Vec3::new(-1., 0., 0.),
+        ],
+        relative_position: Vec3::new(0., 0., 1.),
+        rotation: Vec3::ZERO,
+    };
+    lofter.insert_sketch(1, &sketch).unwrap();
+
+    let result = lofter.loft(&Default::default());
+    assert!(result.pairs.iter().all(Result::is_err));
+    assert!(result.kept_previous.is_empty());
+    assert_eq!(lofter.triangle_count(), 0);
+}
+
+#[test]
+fn templates_loft() {
+    for template in Template::ALL {
+        let mut lofter = Lofter::from_template(template);
+
+        assert!(lofter.sketch_count() >= 2, "{}", template.name());
+        assert!(lofter.loft(&Default::default()).is_ok());
+        let vertex_buffer = lofter.vertex_buffer();
+        assert!(!vertex_buffer.is_empty(), "{}", template.name());
+        assert!(
+            vertex_buffer
+                .iter()
+                .flatten()
+                .all(|[position, _]| position.is_finite())
+        );
+    }
+}
+
+#[test]
+fn export_3mf() {
+    let lofter = Lofter::demo();
+    let mesh = IndexedMesh::from_lofter(&lofter);
+
+    // The diamonds' vertices are shared between triangles.
+    assert_eq!(mesh.positions.len(), 8);
+    assert_eq!(mesh.triangles.len(), lofter.vertex_buffer().len());
+
+    let mut package = Vec::new();
+    export::write_3mf(
+        &mesh,
+        Unit::Millimeter,
+        NumberFormat::default(),
+        &mut package,
+    )
+    .unwrap();
+    assert!(package.starts_with(b"PK\x03\x04"));
+
+    // Files are stored uncompressed, so the model can be read directly.
+    let package = String::from_utf8_lossy(&package);
+    assert!(package.contains(r#"<model unit="millimeter""#));
+    assert_eq!(package.matches("<triangle ").count(), mesh.triangles.len());
+    assert_eq!(package.matches("<m:color ").count(), mesh.section_count);
+}
+
+#[test]
+fn export_off_and_amf() {
+    let mesh = IndexedMesh::from_lofter(&Lofter::demo());
+
+    let mut off = Vec::new();
+    export::write_off(&mesh, NumberFormat::default(), &mut off).unwrap();
+    let off = String::from_utf8(off).unwrap();
+
+    let mut lines = off.lines();
+    assert_eq!(lines.next(), Some("OFF"));
+    assert_eq!(
+        lines.next(),
+        Some(format!("{} {} 0", mesh.positions.len(), mesh.triangles.len()).as_str())
+    );
+    assert_eq!(lines.count(), mesh.positions.len() + mesh.triangles.len());
+
+    let mut amf = Vec::new();
+    export::write_amf(&mesh, Unit::Inch, NumberFormat::default(), &mut amf).unwrap();
+    let amf = String::from_utf8(amf).unwrap();
+
+    assert!(amf.contains(r#"<amf unit="inch""#));
+    assert_eq!(amf.matches("<vertex>").count(), mesh.positions.len());
+    assert_eq!(amf.matches("<triangle>").count(), mesh.triangles.len());
+}
+
+#[test]
+fn export_separate_pairs() {
+    let lofter = Lofter::from_template(Template::Duct);
+    let mesh = IndexedMesh::from_lofter(&lofter);
+    let pairs = lofter.sketch_count() - 1;
+
+    let mut obj = Vec::new();
+    export::write_obj(&mesh, NumberFormat::default(), true, &mut obj).unwrap();
+    let obj = String::from_utf8(obj).unwrap();
+
+    let objects: Vec<&str> = obj.lines().filter(|line| line.starts_with("o ")).collect();
+    let expected: Vec<String> = (0..pairs).map(|pair| format!("o pair_{pair}")).collect();
+    assert_eq!(objects, expected);
+    assert_eq!(obj.matches("\nf ").count(), mesh.triangles.len());
+
+    let mut glb = Vec::new();
+    export::write_glb(&mesh, true, &mut glb).unwrap();
+    let glb = String::from_utf8_lossy(&glb);
+    for pair in 0..pairs {
+        assert_eq!(glb.matches(&format!(r#""name":"pair_{pair}""#)).count(), 2);
+    }
+
+    let mut glb = Vec::new();
+    export::write_glb(&mesh, false, &mut glb).unwrap();
+    assert!(!String::from_utf8_lossy(&glb).contains("pair_"));
+}
+
+#[test]
+fn write_all_formats() {
+    let formats: Vec<Format> = "obj,STL,gltf,3mf"
+        .split(',')
+        .map(|name| name.parse().unwrap())
+        .collect();
+    assert_eq!(
+        formats,
+        [Format::Obj, Format::Stl, Format::Gltf, Format::ThreeMf]
+    );
+    assert!("step".parse::<Format>().is_err());
+
+    let directory = std::env::temp_dir().join("lofter-write-all-formats");
+    let paths = export::write_all(&Lofter::demo(), &directory, &formats).unwrap();
+
+    assert_eq!(paths.len(), formats.len());
+    for (path, format) in paths.iter().zip(&formats) {
+        assert_eq!(path.extension().unwrap(), format.extension());
+        assert!(std::fs::metadata(path).unwrap().len() > 0);
+    }
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn custom_mesh_exporter() {
+    struct TriangleCount;
+
+    impl MeshExporter for TriangleCount {
+        fn name(&self) -> &str {
+            "count"
+        }
+
+        fn extension(&self) -> &str {
+            "txt"
+        }
+
+        fn write(
+            &self,
+            mesh: &IndexedMesh,
+            metadata: &ExportMetadata,
+            writer: &mut dyn std::io::Write,
+        ) -> std::io::Result<()> {
+            write!(writer, "{} {}", mesh.triangles.len(), metadata.unit.name())
+        }
+    }
+
+    let lofter = Lofter::demo();
+    let directory = std::env::temp_dir().join("lofter-custom-mesh-exporter");
+    let metadata = ExportMetadata {
+        name: "custom".into(),
+        ..Default::default()
+    };
+
+    let paths = export::write_all_with(
+        &lofter,
+        &directory,
+        &[&TriangleCount, &Format::Obj],
+        &metadata,
+    )
+    .unwrap();
+
+    assert_eq!(paths[0], directory.join("custom.txt"));
+    assert_eq!(paths[1], directory.join("custom.obj"));
+    assert_eq!(
+        std::fs::read_to_string(&paths[0]).unwrap(),
+        format!("{} millimeter", lofter.vertex_buffer().len())
+    );
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn custom_matching_strategy() {
+    /// Joins the closest vertices in 3D, and never splits the first section.
+    struct Closest;
+
+    impl MatchingStrategy for Closest {
+        fn score(&self, pair: &MatchingPair, vertices: (VertexId, VertexId)) -> f32 {
+            let lower = pair.vertex_position(0, vertices.0);
+            let upper = pair.vertex_position(1, vertices.1) + pair.upper_offset();
+
+            lower.distance(upper)
+        }
+
+        fn validate_split(&self, pair: &MatchingPair, _: (VertexId, VertexId)) -> bool {
+            pair.vertex_ids(0).is_empty()
+        }
+    }
+
+    let mut lofter = Lofter::demo();
+    let options = LoftOptions {
+        // Scores are distances here, so every candidate is tried.
+        max_radial_edge_angle: 1e6,
+        ..Default::default()
+    };
+
+    let default_edges = {
+        lofter.loft(&options);
+        lofter.ruling_edges()
+    };
+
+    assert!(lofter.loft_with(&options, &Closest).is_ok());
+    assert!(!lofter.vertex_buffer().is_empty());
+    assert_ne!(lofter.ruling_edges(), default_edges);
+
+    // The default strategy is the same as `loft`.
+    lofter.loft_with(&options, &DefaultMatching);
+    assert_eq!(lofter.ruling_edges(), default_edges);
+}
+
+#[test]
+fn batched_scoring() {
+    /// Scores candidates one at a time, like `DefaultMatching` without its
+    /// batched `score_row`.
+    struct Scalar;
+
+    impl MatchingStrategy for Scalar {}
+
+    for template in [Template::BoatHull, Template::Duct, Template::WingPanel] {
+        let mut lofter = Lofter::from_template(template);
+        lofter.loft(&LoftOptions::default());
+        let edges = lofter.ruling_edges();
+
+        lofter.loft_with(&LoftOptions::default(), &Scalar);
+        assert_eq!(lofter.ruling_edges(), edges);
+    }
+}
+
+#[test]
+fn mesh_post_passes() {
+    /// Doubles the mesh's size.
+    #[derive(Debug)]
+    struct Scale;
+
+    impl MeshPass for Scale {
+        fn apply(&self, mesh: &mut IndexedMesh) {
+            for position in &mut mesh.positions {
+                *position *= 2.;
+            }
+        }
+    }
+
+    let mut lofter = Lofter::from_template(Template::Duct);
+    let mesh = IndexedMesh::from_lofter(&lofter);
+
+    let mut loft = |post_passes: Vec<Arc<dyn MeshPass>>| {
+        lofter.loft(&LoftOptions {
+            post_passes,
+            ..Default::default()
+        });
+        IndexedMesh::from_lofter(&lofter)
+    };
+
+    // Identical vertices are already merged.
+    let welded = loft(vec![Arc::new(Weld { tolerance: 1e-6 })]);
+    assert_eq!(welded, mesh);
+
+    // The inlet and outlet are boundaries, which stay put.
+    let smoothed = loft(vec![Arc::new(Smooth {
+        iterations: 3,
+        factor: 0.5,
+    })]);
+    assert_ne!(smoothed.positions, mesh.positions);
+    for (smoothed, position) in zip(&smoothed.positions, &mesh.positions) {
+        if position.z == 0. {
+            assert_eq!(smoothed, position);
+        }
+    }
+
+    let decimated = loft(vec![Arc::new(Decimate { cell_size: 0.5 })]);
+    assert!(decimated.positions.len() < mesh.positions.len());
+    assert!(decimated.triangles.len() < mesh.triangles.len());
+    assert_eq!(decimated.triangles.len(), decimated.triangle_sections.len());
+
+    // Passes run in order, so the cells are half the size after scaling.
+    let scaled = loft(vec![Arc::new(Scale), Arc::new(Decimate { cell_size: 1. })]);
+    assert_eq!(scaled.triangles, decimated.triangles);
+}
+
+#[test]
+fn cap_fillet() {
+    let mut lofter = Lofter::from_template(Template::Duct);
+    let mesh = IndexedMesh::from_lofter(&lofter);
+    let max_z = mesh
+        .positions
+        .iter()
+        .map(|position| position.z)
+        .fold(0., f32::max);
+    let boundary_vertices = mesh
+        .positions
+        .iter()
+        .filter(|position| position.z == 0. || position.z == max_z)
+        .count();
+
+    let mut loft = |radius: f32, segments: usize| {
+        lofter.loft(&LoftOptions {
+            post_passes: vec![Arc::new(CapFillet { radius, segments })],
+            ..Default::default()
+        });
+        IndexedMesh::from_lofter(&lofter)
+    };
+
+    // Every edge is used in both directions, so the mesh is closed.
+    let closed = |mesh: &IndexedMesh| {
+        let edges: HashSet<(u32, u32)> = mesh
+            .triangles
+            .iter()
+            .flat_map(|&[a, b, c]| [(a, b), (b, c), (c, a)])
+            .collect();
+        edges.iter().all(|&(a, b)| edges.contains(&(b, a)))
+    };
+
+    // Without a radius, the ends are only capped.
+    let capped = loft(0., 4);
+    assert_eq!(capped.positions, mesh.positions);
+    assert!(closed(&capped));
+
+    // Each boundary vertex gains an arc of four edges, and the inlet's cap
+    // caps don't grow the loft.
+    let filleted = loft(0.05, 4);
+    assert_eq!(
+        filleted.positions.len(),
+        mesh.positions.len() + boundary_vertices * 4
+    );
+    assert_eq!(
+        loft(0.05, usize::MAX).positions.len(),
+        mesh.positions.len() + boundary_vertices * MAX_FILLET_SEGMENTS
+    );
+    assert!(closed(&filleted));
+    assert_eq!(filleted.triangles.len(), filleted.triangle_sections.len());
+
+    // The caps face outwards, like the wall, so the volume is positive.
+    let volume: f32 = filleted
+        .triangles
+        .iter()
+        .map(|triangle| {
+            let [a, b, c] = triangle.map(|index| filleted.positions[index as usize]);
+            a.dot(b.cross(c)) / 6.
+        })
+        .sum();
+    assert!(volume > 0.);
+
+    assert!(
+        filleted
+            .positions
+            .iter()
+            .all(|position| position.z > -1e-5 && position.z < max_z + 1e-5)
+    );
+}
+
+#[test]
+fn thin_regions() {
+    // A slab, 0.1 thick between its long sides, and 2 between its short ones.
+    let slab = |z: f32| SketchDescriptor {
+        vertices: vec![
+            Vec3::new(1., 0.05, 0.),
+            Vec3::new(-1., 0.05, 0.),
+            Vec3::new(-1., -0.05, 0.),
+            Vec3::new(1., -0.05, 0.),
+        ],
+        relative_position: Vec3::new(0., 0., z),
+        rotation: Vec3::ZERO,
+    };
+    let mut lofter = Lofter::from_sketches(&[slab(0.), slab(1.)]).unwrap();
+    lofter.loft(&LoftOptions::default());
+    let mesh = IndexedMesh::from_lofter(&lofter);
+
+    assert!(analysis::thin_regions(&lofter, 0.05).is_empty());
+
+    // Each long side is a region, which the short sides separate.
+    let regions = analysis::thin_regions(&lofter, 0.2);
+    assert_eq!(regions.len(), 2);
+    for region in &regions {
+        assert!((region.thickness - 0.1).abs() < 1e-5);
+
+        let normal_y = region.triangles.iter().map(|&index| {
+            let [a, b, c] = mesh.triangles[index].map(|index| mesh.positions[index as usize]);
+            (b - a).cross(c - a).normalize().y
+        });
+        assert!(normal_y.map(f32::abs).all(|y| (y - 1.).abs() < 1e-5));
+    }
+}
+
+#[test]
+fn draft_angles() {
+    // A frustum, narrowing upwards, so its walls draft along z.
+    let square = |z: f32, half_width: f32| SketchDescriptor {
+        vertices: vec![
+            Vec3::new(half_width, half_width, 0.),
+            Vec3::new(-half_width, half_width, 0.),
+            Vec3::new(-half_width, -half_width, 0.),
+            Vec3::new(half_width, -half_width, 0.),
+        ],
+        relative_position: Vec3::new(0., 0., z),
+        rotation: Vec3::ZERO,
+    };
+    let mut lofter = Lofter::from_sketches(&[square(0., 1.), square(1., 0.9)]).unwrap();
+    lofter.loft(&LoftOptions::default());
+
+    // The walls lean in by atan(0.1), about 5.7 degrees.
+    let drafts = analysis::draft_angles(&lofter, Vec3::Z, 5.);
+    assert_eq!(
+        drafts.len(),
+        IndexedMesh::from_lofter(&lofter).triangles.len()
+    );
+    for draft in &drafts {
+        assert!((draft.angle - 0.1f32.atan().to_degrees()).abs() < 1e-3);
+        assert_eq!(draft.class, DraftClass::Positive);
+    }
+
+    let classes = |pull_direction, min_angle| {
+        analysis::draft_angles(&lofter, pull_direction, min_angle)
+            .into_iter()
+            .map(|draft| draft.class)
+            .collect::<HashSet<_>>()
+    };
+    assert_eq!(
+        classes(Vec3::Z, 10.),
+        HashSet::from([DraftClass::Insufficient])
+    );
+    assert_eq!(classes(-Vec3::Z, 5.), HashSet::from([DraftClass::Negative]));
+    assert_eq!(
+        classes(Vec3::X, 45.),
+        HashSet::from([
+            DraftClass::Positive,
+            DraftClass::Insufficient,
+            DraftClass::Negative
+        ])
+    );
+}
+
+#[test]
+fn slice_layers() {
+    let square = |z: f32, half_width: f32| SketchDescriptor {
+        vertices: vec![
+            Vec3::new(half_width, half_width, 0.),
+            Vec3::new(-half_width, half_width, 0.),
+            Vec3::new(-half_width, -half_width, 0.),
+            Vec3::new(half_width, -half_width, 0.),
+        ],
+        relative_position: Vec3::new(0., 0., z),
+        rotation: Vec3::ZERO,
+    };
+    let mut lofter = Lofter::from_sketches(&[square(0., 1.), square(1., 0.5)]).unwrap();
+    assert!(lofter.slice_layers(0.25).is_empty());
+    lofter.loft(&LoftOptions::default());
+
+    let layers = lofter.slice_layers(0.25);
+    let heights: Vec<f32> = layers.iter().map(|layer| layer.z).collect();
+    assert_eq!(heights, vec![0.125, 0.375, 0.625, 0.875]);
+
+    // Each layer is a single CCW square, narrowing with height.
+    for layer in &layers {
+        let [contour] = &layer.contours[..] else {
+            panic!("expected a single contour at {}", layer.z);
+        };
+        assert!(contour.iter().all(|point| point.z == layer.z));
+
+        let area: f32 = (0..contour.len())
+            .map(|i| {
+                contour[i]
+                    .truncate()
+                    .perp_dot(contour[(i + 1) % contour.len()].truncate())
+                    / 2.
+            })
+            .sum();
+        let half_width = 1. - 0.5 * layer.z;
+        assert!((area - 4. * half_width * half_width).abs() < 1e-4);
+    }
+
+    assert!(lofter.slice_layers(0.).is_empty());
+
+    // Tiny layer heights are thickened, rather than slicing without end.
+    let layers = lofter.slice_layers(1e-9);
+    assert_eq!(layers.len(), MAX_SLICE_LAYERS);
+    assert!(layers.windows(2).all(|pair| pair[0].z < pair[1].z));
+}
+
+#[test]
+fn overhangs() {
+    // A frustum, widening upwards, so its walls overhang by atan(0.5).
+    let square = |z: f32, half_width: f32| SketchDescriptor {
+        vertices: vec![
+            Vec3::new(half_width, half_width, 0.),
+            Vec3::new(-half_width, half_width, 0.),
+            Vec3::new(-half_width, -half_width, 0.),
+            Vec3::new(half_width, -half_width, 0.),
+        ],
+        relative_position: Vec3::new(0., 0., z),
+        rotation: Vec3::ZERO,
+    };
+    let mut lofter = Lofter::from_sketches(&[square(0., 0.5), square(1., 1.)]).unwrap();
+    lofter.loft(&LoftOptions::default());
+
+    let report = analysis::overhangs(&lofter, Vec3::Z, 20.);
+    assert!(
+        report
+            .angles
+            .iter()
+            .all(|angle| (angle - 0.5f32.atan().to_degrees()).abs() < 1e-3)
+    );
+    assert_eq!(report.overhanging_fraction, 1.);
+    assert_eq!(
+        analysis::overhangs(&lofter, Vec3::Z, 30.).overhanging_fraction,
+        0.
+    );
+
+    // Upside down, the walls face upwards.
+    let report = analysis::overhangs(&lofter, Vec3::NEG_Z, 20.);
+    assert!(report.angles.iter().all(|&angle| angle == 0.));
+
+    // The bottom cap is on the plate, and the top cap faces up, so only the
+    // walls overhang.
+    lofter.loft(&LoftOptions {
+        post_passes: vec![Arc::new(CapFillet {
+            radius: 0.,
+            segments: 1,
+        })],
+        ..Default::default()
+    });
+    let mesh = IndexedMesh::from_lofter(&lofter);
+    let report = analysis::overhangs(&lofter, Vec3::Z, 20.);
+    for (triangle, &angle) in zip(&mesh.triangles, &report.angles) {
+        let [a, b, c] = triangle.map(|index| mesh.positions[index as usize].z);
+        let cap = a == b && b == c;
+        assert_eq!(angle == 0., cap);
+    }
+    assert!(report.overhanging_fraction > 0. && report.overhanging_fraction < 1.);
+}
+
+#[test]
+fn session_stats() {
+    let mut lofter = Lofter::demo();
+    let stats = lofter.session_stats();
+    assert_eq!(stats.lofts, 1);
+    assert_eq!(stats.vertex_edits, 0);
+
+    let vertex = lofter.vertices(0).unwrap().next().unwrap().0;
+    lofter
+        .set_vertex_positions(0, &[(vertex, Vec3::new(2., 0., 0.))])
+        .unwrap();
+    lofter
+        .vertices_mut(1, |(_, position)| *position *= 2.)
+        .unwrap();
+    assert!(lofter.remove_vertex(5, vertex).is_err());
+
+    // Non-finite vertices fail the pair.
+    lofter
+        .set_vertex_positions(0, &[(vertex, Vec3::NAN)])
+        .unwrap();
+    lofter.loft(&LoftOptions::default());
+
+    assert_eq!(
+        lofter.session_stats(),
+        SessionStats {
+            lofts: 2,
+            failed_pairs: 1,
+            vertex_edits: 3,
+            failed_vertex_edits: 1,
+        }
+    );
+}
+
+#[test]
+fn export_number_format() {
+    let numbers = NumberFormat {
+        precision: Some(2),
+        decimal_separator: ',',
+    };
+    assert_eq!(numbers.format(1.5), "1,50");
+    assert_eq!(numbers.parse(" 2,5 "), Some(2.5));
+    assert_eq!(numbers.parse("2.5"), Some(2.5));
+    assert_eq!(NumberFormat::default().format(0.1), "0.1");
+    assert_eq!(NumberFormat::with_precision(3).format(-0.25), "-0.250");
+
+    let mesh = IndexedMesh::from_lofter(&Lofter::demo());
+
+    let mut obj = Vec::new();
+    export::write_obj(&mesh, numbers, false, &mut obj).unwrap();
+    let obj = String::from_utf8(obj).unwrap();
+    assert_eq!(obj.lines().next(), Some("v 0,00 -1,00 3,00"));
+
+    // XML always uses a point.
+    let mut amf = Vec::new();
+    export::write_amf(&mesh, Unit::Millimeter, numbers, &mut amf).unwrap();
+    assert!(String::from_utf8(amf).unwrap().contains("<x>1.00</x>"));
+}
+
+/// Compares `snapshot` with `tests/snapshots/<name>.snap`, so changes to the
+/// algorithm show up as reviewable diffs. Missing snapshots are written, and
+/// all are when `UPDATE_SNAPSHOTS` is set.
+fn assert_snapshot(name: &str, snapshot: &str) {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/snapshots")
+        .join(format!("{name}.snap"));
+
+    match std::fs::read_to_string(&path) {
+        Ok(expected) if std::env::var_os("UPDATE_SNAPSHOTS").is_none() => {
+            assert!(
+                expected == snapshot,
+                "snapshot {name} changed; rerun with UPDATE_SNAPSHOTS=1 to accept:\n{snapshot}"
+            );
+        }
+        _ => {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, snapshot).unwrap();
+        }
+    }
+}
+
+/// Describes the ruling edges matched in each pair, and the sections they
+/// were decomposed into.
+fn topology_snapshot(lofter: &mut Lofter) -> String {
+    let format = |position: Vec3| {
+        // Rounded, so the snapshots aren't sensitive to float noise.
+        let [x, y, z] = position.to_array().map(|coordinate| coordinate + 0.);
+        format!("({x:.4}, {y:.4}, {z:.4})")
+    };
+
+    let (steps, result) = lofter.explain_loft(&LoftOptions::default());
+    assert!(result.is_ok());
+
+    let mut snapshot = String::new();
+    let mut section = 0;
+
+    for step in steps {
+        let line = match step {
+            LoftStep::CandidateAccepted {
+                sketch_pair,
+                vertices: (lower, upper),
+                ..
+            } => format!("pair {sketch_pair}: accepted {lower} -> {upper}"),
+            LoftStep::CandidateRejected { .. } => continue,
+            LoftStep::SectionBuilt {
+                sketch_pair,
+                ruling_edges,
+            } => {
+                let edges: Vec<String> = ruling_edges
+                    .into_iter()
+                    .map(|[lower, upper]| format!("  {} -> {}", format(lower), format(upper)))
+                    .collect();
+                section += 1;
+
+                format!(
+                    "pair {sketch_pair}: section {}, {} ruling edges\n{}",
+                    section - 1,
+                    edges.len(),
+                    edges.join("\n")
+                )
+            }
+        };
+
+        snapshot += &line;
+        snapshot.push('\n');
+    }
+
+    snapshot
+}
+
+#[test]
+fn topology_snapshots() {
+    assert_snapshot("demo", &topology_snapshot(&mut Lofter::demo()));
+
+    for (template, name) in [
+        (Template::BoatHull, "boat_hull"),
+        (Template::Duct, "duct"),
+        (Template::WingPanel, "wing_panel"),
+    ] {
+        assert_snapshot(
+            name,
+            &topology_snapshot(&mut Lofter::from_template(template)),
+        );
+    }
+}
+
+#[test]
+fn max_triangles_per_section() {
+    let square = Superellipse {
+        a: 1.,
+        b: 1.,
+        exponent: 10.,
+    }
+    .sketch(8);
+    let circle = SketchDescriptor {
+        relative_position: Vec3::new(0., 0., 2.),
+        rotation: Vec3::new(0., 0., 30.),
+        ..Superellipse {
+            a: 1.,
+            b: 1.,
+            exponent: 2.,
+        }
+        .sketch(24)
+    };
+    let mut lofter = Lofter::from_sketches(&[square, circle]).unwrap();
+
+    let mut section_triangles = |max_triangles_per_section| {
+        let result = lofter.loft(&LoftOptions {
+            max_radial_edge_angle: 5.,
+            max_triangles_per_section,
+            ..Default::default()
+        });
+        assert!(result.is_ok());
+
+        let sections = lofter.triangle_sections();
+        let mut counts = vec![0; sections.iter().max().unwrap() + 1];
+        for section in sections {
+            counts[section] += 1;
+        }
+
+        counts
+    };
+
+    let unlimited = section_triangles(None);
+    let limited = section_triangles(Some(4));
+    // Sections can't be coarsened below one triangle per sketch vertex.
+    let minimal = section_triangles(Some(0));
+
+    assert_eq!(limited.len(), unlimited.len());
+    assert!(limited.iter().sum::<usize>() < unlimited.iter().sum::<usize>());
+    for (section, &limited) in limited.iter().enumerate() {
+        assert!(limited <= unlimited[section]);
+        assert!(limited <= minimal[section].max(4));
+    }
+    assert!(minimal.iter().sum::<usize>() >= 8 + 24);
+}
+
+#[test]
+fn max_candidates_per_vertex() {
+    let mut lofter = Lofter::from_template(Template::Duct);
+    let options = LoftOptions {
+        // Every candidate is tried, so the steps show them all.
+        max_radial_edge_angle: 1e6,
+        max_candidates_per_vertex: Some(2),
+        ..Default::default()
+    };
+
+    let (steps, result) = lofter.explain_loft(&options);
+    assert!(result.is_ok());
+
+    let mut candidates = HashMap::new();
+    for step in steps {
+        if let LoftStep::CandidateAccepted {
+            sketch_pair,
+            vertices,
+            ..
+        }
+        | LoftStep::CandidateRejected {
+            sketch_pair,
+            vertices,
+            ..
+        } = step
+        {
+            *candidates.entry((sketch_pair, vertices.0)).or_insert(0) += 1;
+        }
+    }
+    assert!(!candidates.is_empty());
+    assert!(candidates.values().all(|&count| count == 2));
+
+    // A limit above the vertex count changes nothing.
+    lofter.loft(&LoftOptions::default());
+    let edges = lofter.ruling_edges();
+    lofter.loft(&LoftOptions {
+        max_candidates_per_vertex: Some(1000),
+        ..Default::default()
+    });
+    assert_eq!(lofter.ruling_edges(), edges);
+}
+
+#[test]
+fn fewer_than_two_sketches() {
+    let assert_empty = |lofter: &mut Lofter| {
+        let result = lofter.loft(&LoftOptions::default());
+        assert!(result.is_empty());
+        assert!(result.is_ok());
+
+        assert!(lofter.explain_loft(&LoftOptions::default()).0.is_empty());
+        assert!(lofter.vertex_buffer().is_empty());
+        assert!(lofter.local_vertex_buffer().is_empty());
+        assert!(lofter.ruling_edges().is_empty());
+        assert!(lofter.triangle_sections().is_empty());
+        assert_eq!(lofter.triangle_count(), 0);
+        assert_eq!(lofter.triangles_iter().count(), 0);
+
+        let mesh = IndexedMesh::from_lofter(lofter);
+        assert!(mesh.positions.is_empty());
+        assert!(mesh.chunks(MAX_CHUNK_VERTICES).is_empty());
+
+        let mut obj = Vec::new();
+        export::stream_obj(lofter, NumberFormat::default(), &mut obj).unwrap();
+        assert!(obj.is_empty());
+    };
+
+    assert_empty(&mut Lofter::new());
+    assert_empty(&mut Lofter::default());
+
+    let mut lofter = Lofter::from_sketches(&[]).unwrap();
+    assert_empty(&mut lofter);
+    assert_eq!(lofter.remove_sketch(0), Err(LoftError::SketchNotFound(0)));
+
+    // Removing a sketch drops the lofts it was part of.
+    let mut lofter = Lofter::demo();
+    assert!(lofter.triangle_count() > 0);
+    lofter.remove_sketch(1).unwrap();
+    assert_eq!(lofter.triangle_count(), 0);
+    assert_empty(&mut lofter);
+
+    lofter.remove_sketch(0).unwrap();
+    assert_empty(&mut lofter);
+}
+
+#[test]
+fn remove_sketch_keeps_positions() {
+    let stack = || {
+        let mut lofter = Lofter::demo();
+        lofter
+            .push_sketch(&lofter.sketch_descriptor(1).unwrap())
+            .unwrap();
+        lofter.loft(&LoftOptions::default());
+        lofter
+    };
+
+    // The sketch above the removed one stays in place, even when locked.
+    let mut lofter = stack();
+    lofter.set_sketch_locked(2, true).unwrap();
+    lofter.remove_sketch(1).unwrap();
+    assert_eq!(lofter.sketch_world_position(1), Some(Vec3::new(0., 0., 6.)));
+    assert_eq!(
+        lofter.sketch_relative_position(1),
+        Some(&Vec3::new(0., 0., 6.))
+    );
+    // The joined pair has no surface until lofted.
+    assert_eq!(lofter.triangle_count(), 0);
+    assert!(lofter.loft(&LoftOptions::default()).is_ok());
+
+    let mut lofter = stack();
+    lofter.remove_sketch_with(1, false).unwrap();
+    assert_eq!(lofter.sketch_world_position(1), Some(Vec3::new(0., 0., 3.)));
+
+    // Removing the first sketch keeps the others' lofts.
+    let mut lofter = stack();
+    let ruling_edges = lofter
+        .ruling_edges()
+        .split_off(lofter.ruling_edges().len() / 2);
+    lofter.remove_sketch(0).unwrap();
+    assert_eq!(lofter.sketch_world_position(0), Some(Vec3::new(0., 0., 3.)));
+    assert_eq!(lofter.ruling_edges(), ruling_edges);
+}
+
+/// Each pair of adjacent sketches has a loft, even before it's lofted.
+#[cfg(feature = "serde")]
+#[test]
+fn loft_per_sketch_pair() {
+    let loft_count = |lofter: &Lofter| {
+        serde_json::to_value(lofter).unwrap()["loft_maps"]
+            .as_array()
+            .unwrap()
+            .len()
+    };
+
+    let mut lofter = Lofter::new();
+    assert_eq!(loft_count(&lofter), 0);
+
+    let square = Lofter::demo().sketch_descriptor(0).unwrap();
+
+    for (sketch_index, expected) in [(0, 0), (1, 1), (2, 2), (0, 3), (2, 4)] {
+        lofter.insert_sketch(sketch_index, &square).unwrap();
+        assert_eq!(loft_count(&lofter), expected);
+    }
+    lofter.loft(&Default::default());
+    assert_eq!(loft_count(&lofter), 4);
+
+    for (sketch_index, expected) in [(4, 3), (0, 2), (1, 1), (1, 0), (0, 0)] {
+        lofter.remove_sketch(sketch_index).unwrap();
+        assert_eq!(loft_count(&lofter), expected);
+    }
+}
+
+/// Sections without edges are rejected when lofting, but a lofter restored
+/// with one still meshes without them.
+#[cfg(feature = "serde")]
+#[test]
+fn empty_section() {
+    let lofter = Lofter::from_template(Template::Duct);
+    let mut state = serde_json::to_value(&lofter).unwrap();
+    let sections = state["loft_maps"][0]["sections"].as_array_mut().unwrap();
+    assert!(sections.len() > 1);
+
+    let removed_edges = sections[0]["loft_edges"].as_array().unwrap().len();
+    sections[0]["loft_edges"] = serde_json::json!([]);
+    let lofter: Lofter = serde_json::from_value(state).unwrap();
+
+    assert_eq!(lofter.triangles_iter().count(), lofter.triangle_count());
+    assert_eq!(
+        lofter.vertex_buffer().len(),
+        lofter.triangle_sections().len()
+    );
+    assert!(
+        lofter
+            .triangle_sections()
+            .iter()
+            .all(|&section| section != 0)
+    );
+    assert_eq!(
+        lofter.ruling_edges().len(),
+        Lofter::from_template(Template::Duct).ruling_edges().len() - removed_edges
+    );
+}
+
+#[test]
+fn sketch_descriptor_round_trip() {
+    let mut lofter = Lofter::from_template(Template::BoatHull);
+    lofter.remove_vertex(1, 2).unwrap();
+    lofter
+        .set_sketch_rotation(2, &Vec3::new(0., 0., 15.))
+        .unwrap();
+    lofter.loft(&LoftOptions::default());
+
+    let descriptors: Vec<_> = (0..lofter.sketch_count())
+        .map(|sketch_index| lofter.sketch_descriptor(sketch_index).unwrap())
+        .collect();
+    assert_eq!(lofter.sketch_descriptor(lofter.sketch_count()), None);
+    assert_eq!(descriptors[2].rotation, Vec3::new(0., 0., 15.));
+
+    let mut copy = Lofter::from_sketches(&descriptors).unwrap();
+    copy.loft(&LoftOptions::default());
+    assert_eq!(copy.ruling_edges(), lofter.ruling_edges());
+    assert_eq!(copy.sketch_descriptor(1), lofter.sketch_descriptor(1));
+}
+
+#[test]
+fn document_merge() {
+    let mut server = LoftDocument::new(Lofter::demo(), 0);
+    let mut a = server.fork(1);
+    let mut b = server.fork(2);
+
+    let moved = Vec3::new(2., 0., 0.);
+    a.apply(Command::SetVertexPositions {
+        sketch_index: 0,
+        positions: vec![(0, moved)],
+    })
+    .unwrap();
+    b.apply(Command::InsertVertex {
+        sketch_index: 1,
+        between_vertices: (0, 1),
+    })
+    .unwrap();
+    // Failed commands aren't logged.
+    assert!(
+        b.apply(Command::RemoveSketch {
+            sketch_index: 5,
+            keep_positions: true,
+        })
+        .is_err()
+    );
+    assert_eq!(b.ops().len(), 1);
+
+    // Both clients sync through the server, in either order.
+    assert!(server.merge(a.ops()).is_empty());
+    assert!(server.merge(b.ops()).is_empty());
+    assert!(a.merge(server.ops()).is_empty());
+    assert!(b.merge(server.ops()).is_empty());
+
+    assert_eq!(a.ops(), server.ops());
+    assert_eq!(b.ops(), server.ops());
+    for document in [&a, &b] {
+        assert_eq!(document.lofter().get_vertex(0, 0), Some(&moved));
+        assert_eq!(document.lofter().vertices(1).unwrap().count(), 5);
+    }
+    assert_eq!(
+        server.base().sketch_descriptor(0),
+        Lofter::demo().sketch_descriptor(0)
+    );
+
+    // Ops which conflict with the merged log are dropped.
+    a.apply(Command::RemoveVertex {
+        sketch_index: 1,
+        vertex_id: 4,
+    })
+    .unwrap();
+    b.apply(Command::SetVertexPositions {
+        sketch_index: 1,
+        positions: vec![(4, Vec3::ZERO)],
+    })
+    .unwrap();
+    server.merge(a.ops());
+    let dropped = b.merge(server.ops());
+    assert_eq!(dropped.len(), 1);
+    assert_eq!(dropped[0].id.client, 2);
+    assert_eq!(b.ops(), server.ops());
+
+    // Undoing takes back a's own ops, latest first, keeping b's.
+    let undone = a.undo().unwrap();
+    assert_eq!(undone.len(), 1);
+    assert!(matches!(undone[0].command, Command::RemoveVertex { .. }));
+    let undone = a.undo().unwrap();
+    assert!(matches!(
+        undone[0].command,
+        Command::SetVertexPositions { .. }
+    ));
+    assert_eq!(a.lofter().get_vertex(0, 0), Lofter::demo().get_vertex(0, 0));
+    assert_eq!(a.lofter().vertices(1).unwrap().count(), 5);
+    assert_eq!(a.undo(), None);
+}
+
+#[test]
+fn document_append_and_discard() {
+    let mut lofter = Lofter::demo();
+    lofter.start_capturing();
+    lofter.set_vertex_positions(0, &[(0, Vec3::X)]).unwrap();
+    assert!(lofter.remove_sketch(5).is_err());
+
+    // Failed commands are captured too, but aren't logged by the document.
+    let captured = lofter.take_captured();
+    assert_eq!(captured.len(), 2);
+    assert!(lofter.take_captured().is_empty());
+
+    let mut client = LoftDocument::new(Lofter::demo(), 1);
+    for command in captured {
+        let _ = client.apply(command);
+    }
+    assert_eq!(client.ops().len(), 1);
+
+    // The server skips ops it already has.
+    let mut server = LoftDocument::new(Lofter::demo(), 0);
+    assert!(server.append(client.ops()).is_empty());
+    assert!(server.append(client.ops()).is_empty());
+    assert_eq!(server.ops(), client.ops());
+    assert_eq!(server.lofter().get_vertex(0, 0), Some(&Vec3::X));
+
+    client
+        .apply(Command::RemoveVertex {
+            sketch_index: 0,
+            vertex_id: 0,
+        })
+        .unwrap();
+    client
+        .apply(Command::SetVertexPositions {
+            sketch_index: 0,
+            positions: vec![(1, Vec3::Y)],
+        })
+        .unwrap();
+
+    // Discarding the removal leaves the later move.
+    let removal = client.ops()[1].id;
+    assert!(client.discard(&[removal]).is_empty());
+    assert_eq!(client.ops().len(), 2);
+    assert_eq!(client.lofter().get_vertex(0, 0), Some(&Vec3::X));
+    assert_eq!(client.lofter().get_vertex(0, 1), Some(&Vec3::Y));
+}
+
+#[test]
+fn sketch_and_mesh_issues() {
+    for template in Template::ALL {
+        let mut lofter = Lofter::from_template(template);
+        for sketch_index in 0..lofter.sketch_count() {
+            assert_eq!(lofter.sketch_issues(sketch_index), Some(vec![]));
+        }
+
+        lofter.loft(&LoftOptions::default());
+        assert!(IndexedMesh::from_lofter(&lofter).issues().is_empty());
+    }
+
+    let mut lofter = Lofter::new();
+    let square = [
+        Vec3::new(1., 1., 0.),
+        Vec3::new(-1., 1., 0.),
+        Vec3::new(-1., -1., 0.),
+        Vec3::new(1., -1., 0.),
+    ];
+    for vertices in [
+        // A bow tie.
+        vec![square[0], square[1], square[3], square[2]],
+        square.into_iter().rev().collect(),
+        vec![Vec3::X, Vec3::Y, Vec3::new(2., -1., 0.)],
+    ] {
+        lofter
+            .push_sketch(&SketchDescriptor {
+                vertices,
+                relative_position: Vec3::ZERO,
+                rotation: Vec3::ZERO,
+            })
+            .unwrap();
+    }
+
+    assert_eq!(
+        lofter.sketch_issues(0),
+        Some(vec![SketchIssue::SelfIntersection {
+            edges: [(1, 2), (3, 0)]
+        }])
+    );
+    assert_eq!(lofter.sketch_issues(1), Some(vec![SketchIssue::Clockwise]));
+    assert_eq!(lofter.sketch_issues(2), Some(vec![SketchIssue::ZeroArea]));
+    assert_eq!(lofter.sketch_issues(3), None);
+
+    let mesh = IndexedMesh {
+        positions: vec![Vec3::ZERO, Vec3::X, Vec3::X * 2., Vec3::NAN],
+        triangles: vec![[0, 1, 2], [0, 1, 3]],
+        triangle_sections: vec![0, 0],
+        section_count: 1,
+        section_pairs: vec![0],
+    };
+    assert_eq!(
+        mesh.issues(),
+        [
+            MeshIssue::NonFinitePosition(3),
+            MeshIssue::DegenerateTriangle(0)
+        ]
+    );
+}
+
+#[test]
+fn station_changes() {
+    let square = |size: f32, z: f32| SketchDescriptor {
+        vertices: vec![
+            Vec3::new(size, size, 0.),
+            Vec3::new(-size, size, 0.),
+            Vec3::new(-size, -size, 0.),
+            Vec3::new(size, -size, 0.),
+        ],
+        relative_position: Vec3::new(0., 0., z),
+        rotation: Vec3::ZERO,
+    };
+    let mut lofter = Lofter::new();
+    for sketch in [square(1., 0.), square(1.1, 1.), square(3., 1.)] {
+        lofter.push_sketch(&sketch).unwrap();
+    }
+
+    assert!(
+        lofter
+            .loft(&LoftOptions::default())
+            .station_changes
+            .is_empty()
+    );
+
+    for options in [
+        LoftOptions {
+            max_station_change_distance: Some(0.5),
+            ..Default::default()
+        },
+        LoftOptions {
+            max_station_change_angle: Some(30.),
+            ..Default::default()
+        },
+    ] {
+        let changes = lofter.loft(&options).station_changes;
+        assert!(!changes.is_empty());
+
+        // Only the outlier's edges change too much.
+        let ruling_edges = lofter.ruling_edges();
+        for StationChange {
+            sketch_pair,
+            ruling_edge,
+            distance,
+            angle,
+        } in changes
+        {
+            assert_eq!(sketch_pair, 1);
+            let [lower, upper] = ruling_edges[ruling_edge];
+            assert_eq!(lower.z, 1.);
+            assert_eq!(upper.z, 2.);
+            assert!(distance > 0.5 || angle > 30.);
+        }
+    }
+}
+
+#[test]
+fn infer_sketch_at() {
+    let square = |size: f32, relative_position: Vec3| SketchDescriptor {
+        vertices: vec![
+            Vec3::new(size, size, 0.),
+            Vec3::new(-size, size, 0.),
+            Vec3::new(-size, -size, 0.),
+            Vec3::new(size, -size, 0.),
+        ],
+        relative_position,
+        rotation: Vec3::ZERO,
+    };
+    let mut lofter =
+        Lofter::from_sketches(&[square(1., Vec3::ZERO), square(3., Vec3::new(1., 0., 2.))])
+            .unwrap();
+
+    // Only lofted pairs have a cross-section.
+    assert_eq!(
+        lofter.infer_sketch_at(1.),
+        Err(LoftError::NoLoftAtHeight(1.))
+    );
+    lofter.loft(&LoftOptions::default());
+    for z in [-1., 0., 2., 3.] {
+        assert_eq!(lofter.infer_sketch_at(z), Err(LoftError::NoLoftAtHeight(z)));
+    }
+
+    assert_eq!(lofter.infer_sketch_at(1.), Ok(1));
+    assert_eq!(lofter.sketch_count(), 3);
+    assert_eq!(
+        lofter.sketch_world_position(1),
+        Some(Vec3::new(0.5, 0., 1.))
+    );
+    assert_eq!(lofter.sketch_world_position(2), Some(Vec3::new(1., 0., 2.)));
+    assert_eq!(lofter.sketch_issues(1), Some(vec![]));
+    assert!(lofter.ruling_edges().is_empty());
+
+    // The cross-section halfway up is a square of the average size.
+    let corner = Vec3::new(2., 2., 0.);
+    assert!(
+        lofter
+            .vertices(1)
+            .unwrap()
+            .any(|(_, position)| position.distance(corner) < 1e-5)
+    );
+    assert!(
+        lofter
+            .vertices(1)
+            .unwrap()
+            .all(|(_, position)| position.abs().max_element() <= 2. + 1e-5)
+    );
+
+    assert!(lofter.loft(&LoftOptions::default()).is_ok());
+}
+
+#[test]
+fn correspondences() {
+    let polygon = |count: usize, z: f32| SketchDescriptor {
+        vertices: (0..count)
+            .map(|i| {
+                let angle = i as f32 / count as f32 * std::f32::consts::TAU;
+                Vec3::new(angle.cos(), angle.sin(), 0.)
+            })
+            .collect(),
+        relative_position: Vec3::new(0., 0., z),
+        rotation: Vec3::ZERO,
+    };
+    let mut lofter = Lofter::new();
+    for sketch in [polygon(4, 0.), polygon(7, 1.)] {
+        lofter.push_sketch(&sketch).unwrap();
+    }
+    lofter.loft(&LoftOptions::default());
+
+    // Each correspondence gives the ends of its ruling edge.
+    let correspondences = lofter.correspondences();
+    let ruling_edges = lofter.ruling_edges();
+    assert_eq!(correspondences.len(), ruling_edges.len());
+
+    for (correspondence, ruling_edge) in zip(&correspondences, &ruling_edges) {
+        let sketches = [correspondence.sketch_pair, correspondence.sketch_pair + 1];
+        let points = [correspondence.lower, correspondence.upper];
+
+        for ((sketch_index, point), end) in zip(zip(sketches, points), ruling_edge) {
+            assert!((0.0..1.).contains(&point.param));
+
+            let count = lofter
+                .sketch_descriptor(sketch_index)
+                .unwrap()
+                .vertices
+                .len();
+            let next = (point.vertex_id + 1) % count as VertexId;
+            let a = lofter
+                .vertex_world_position(sketch_index, point.vertex_id)
+                .unwrap();
+            let b = lofter.vertex_world_position(sketch_index, next).unwrap();
+            assert!(a.lerp(b, point.param).distance(*end) < 1e-5);
+        }
+    }
+
+    let mut csv = Vec::new();
+    CorrespondenceFormat::Csv
+        .write(&correspondences, NumberFormat::default(), &mut csv)
+        .unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let mut lines = csv.lines();
+    assert_eq!(
+        lines.next(),
+        Some("sketch_pair,section,lower_id,lower_param,upper_id,upper_param")
+    );
+    assert_eq!(lines.count(), correspondences.len());
+
+    #[cfg(feature = "serde")]
+    {
+        let mut json = Vec::new();
+        CorrespondenceFormat::Json
+            .write(&correspondences, NumberFormat::default(), &mut json)
+            .unwrap();
+        let read: Vec<Correspondence> = serde_json::from_slice(&json).unwrap();
+        assert_eq!(read, correspondences);
+    }
+}
+
+#[test]
+fn pinned_correspondences() {
+    let square = |z: f32| SketchDescriptor {
+        vertices: vec![
+            Vec3::new(1., 1., 0.),
+            Vec3::new(-1., 1., 0.),
+            Vec3::new(-1., -1., 0.),
+            Vec3::new(1., -1., 0.),
+        ],
+        relative_position: Vec3::new(0., 0., z),
+        rotation: Vec3::ZERO,
+    };
+    let mut lofter = Lofter::new();
+    for sketch in [square(0.), square(1.)] {
+        lofter.push_sketch(&sketch).unwrap();
+    }
+
+    let point = |vertex_id| SketchPoint {
+        vertex_id,
+        param: 0.,
+    };
+    let joins = |correspondences: &[Correspondence], lower, upper| {
+        correspondences
+            .iter()
+            .any(|c| c.lower == point(lower) && c.upper == point(upper))
+    };
+
+    assert!(lofter.loft(&LoftOptions::default()).is_ok());
+    assert!(!joins(&lofter.correspondences(), 0, 1));
+
+    // A pin twists the loft, which matching wouldn't.
+    let pin = Correspondence {
+        sketch_pair: 0,
+        section: 0,
+        lower: point(0),
+        upper: point(1),
+    };
+    let options = LoftOptions {
+        pinned_correspondences: vec![pin],
+        ..Default::default()
+    };
+    assert!(lofter.loft(&options).is_ok());
+    assert!(joins(&lofter.correspondences(), 0, 1));
+
+    // Pinning the exported correspondences reproduces the loft.
+    let mut csv = Vec::new();
+    export::write_correspondences_csv(&lofter.correspondences(), NumberFormat::default(), &mut csv)
+        .unwrap();
+    let pins =
+        export::read_correspondences_csv(&String::from_utf8(csv).unwrap(), NumberFormat::default())
+            .unwrap();
+    assert_eq!(pins, lofter.correspondences());
+
+    let ruling_edges = lofter.ruling_edges();
+    let options = LoftOptions {
+        pinned_correspondences: pins,
+        ..Default::default()
+    };
+    assert!(lofter.loft(&options).is_ok());
+    assert_eq!(lofter.ruling_edges(), ruling_edges);
+
+    assert_eq!(
+        export::read_correspondences_csv("0,0,1,0,1\n", NumberFormat::default()),
+        Err(InvalidCorrespondenceLine(1))
+    );
+
+    let options = LoftOptions {
+        pinned_correspondences: vec![Correspondence {
+            lower: point(99),
+            ..pin
+        }],
+        ..Default::default()
+    };
+    assert_eq!(
+        lofter.loft(&options).pairs,
+        vec![Err(PairLoftError::InvalidPin(0))]
+    );
+}
+
+#[test]
+fn excluded_vertices() {
+    let mut lofter = Lofter::from_template(Template::Duct);
+    let accepted_upper = |lofter: &mut Lofter| {
+        let (steps, result) = lofter.explain_loft(&LoftOptions::default());
+        assert!(result.is_ok());
+
+        steps
+            .into_iter()
+            .filter_map(|step| match step {
+                LoftStep::CandidateAccepted {
+                    sketch_pair: 0,
+                    vertices,
+                    ..
+                } => Some(vertices.1),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let upper = accepted_upper(&mut lofter)[0];
+    lofter.set_vertex_excluded(1, upper, true).unwrap();
+    assert_eq!(lofter.vertex_excluded(1, upper), Some(true));
+
+    // The vertex no longer starts a section, but is still lofted.
+    assert!(!accepted_upper(&mut lofter).contains(&upper));
+    assert!(
+        lofter
+            .correspondences()
+            .iter()
+            .any(|c| c.sketch_pair == 0 && c.upper.vertex_id == upper)
+    );
+
+    // Excluding every vertex excludes none.
+    let vertex_ids: Vec<VertexId> = lofter.vertices(1).unwrap().map(|(id, _)| id).collect();
+    for &vertex_id in &vertex_ids {
+        lofter.set_vertex_excluded(1, vertex_id, true).unwrap();
+    }
+    assert!(accepted_upper(&mut lofter).contains(&upper));
+
+    assert_eq!(
+        lofter.set_vertex_excluded(1, 999, true),
+        Err(LoftError::VertexNotFound {
+            sketch_index: 1,
+            vertex_id: 999
+        })
+    );
+}
+
+#[test]
+fn feature_matching() {
+    let square = SketchDescriptor {
+        vertices: vec![
+            Vec3::new(1., 1., 0.),
+            Vec3::new(-1., 1., 0.),
+            Vec3::new(-1., -1., 0.),
+            Vec3::new(1., -1., 0.),
+        ],
+        relative_position: Vec3::ZERO,
+        rotation: Vec3::ZERO,
+    };
+    // Each corner is rounded by five vertices, the middle one on the diagonal.
+    let rounded_square = SketchDescriptor {
+        vertices: (0..4)
+            .flat_map(|corner| {
+                let start = corner as f32 * FRAC_PI_2;
+                let center = Vec3::new(start.cos() - start.sin(), start.sin() + start.cos(), 0.);
+
+                (0..5).map(move |i| {
+                    let angle = start + i as f32 / 4. * FRAC_PI_2;
+                    center * 0.7 + Vec3::new(angle.cos(), angle.sin(), 0.) * 0.3
+                })
+            })
+            .collect(),
+        relative_position: Vec3::new(0., 0., 1.),
+        rotation: Vec3::ZERO,
+    };
+    let mut lofter = Lofter::from_sketches(&[square, rounded_square]).unwrap();
+
+    assert_eq!(lofter.feature_vertices(0, 60.), Some(vec![0, 1, 2, 3]));
+    assert_eq!(lofter.feature_vertices(1, 60.), Some(vec![2, 7, 12, 17]));
+    assert_eq!(lofter.feature_vertices(1, 100.), Some(vec![]));
+
+    // Corners are joined to corners first.
+    let options = LoftOptions {
+        feature_angle: Some(60.),
+        ..Default::default()
+    };
+    let (steps, result) = lofter.explain_loft(&options);
+    assert!(result.is_ok());
+
+    let mut accepted: Vec<(VertexId, VertexId)> = steps
+        .iter()
+        .filter_map(|step| match step {
+            LoftStep::CandidateAccepted { vertices, .. } => Some(*vertices),
+            _ => None,
+        })
+        .take(4)
+        .collect();
+    accepted.sort_unstable();
+    assert_eq!(accepted, [(0, 2), (1, 7), (2, 12), (3, 17)]);
+}
+
+#[test]
+fn fillet_and_chamfer() {
+    let square = SketchDescriptor {
+        vertices: vec![
+            Vec3::new(1., 1., 0.),
+            Vec3::new(-1., 1., 0.),
+            Vec3::new(-1., -1., 0.),
+            Vec3::new(1., -1., 0.),
+        ],
+        relative_position: Vec3::ZERO,
+        rotation: Vec3::ZERO,
+    };
+    let mut lofter = Lofter::from_sketches(&[square.clone(), square]).unwrap();
+
+    let arc = lofter.fillet_vertex(0, 0, 0.5, 4).unwrap();
+    assert_eq!(arc, vec![0, 4, 5, 6, 7]);
+
+    let vertices: Vec<(VertexId, Vec3)> = lofter
+        .vertices(0)
+        .unwrap()
+        .map(|(id, &position)| (id, position))
+        .collect();
+    let ids: Vec<VertexId> = vertices.iter().map(|&(id, _)| id).collect();
+    assert_eq!(ids, vec![0, 4, 5, 6, 7, 1, 2, 3]);
+
+    // The arc is tangent to both edges, about the corner's inset center.
+    assert!(vertices[0].1.abs_diff_eq(Vec3::new(1., 0.5, 0.), 1e-5));
+    assert!(vertices[4].1.abs_diff_eq(Vec3::new(0.5, 1., 0.), 1e-5));
+    for &(_, position) in &vertices[..5] {
+        let radius = position.distance(Vec3::new(0.5, 0.5, 0.));
+        assert!((radius - 0.5).abs() < 1e-5);
+    }
+
+    assert_eq!(
+        lofter.fillet_vertex(0, 1, 2., 4),
+        Err(LoftError::CornerTooSmall {
+            sketch_index: 0,
+            vertex_id: 1
+        })
+    );
+
+    assert_eq!(lofter.chamfer_vertex(0, 2, 0.5), Ok(vec![2, 8]));
+    let position = |id| {
+        *lofter
+            .vertices(0)
+            .unwrap()
+            .find(|&(vertex_id, _)| vertex_id == id)
+            .unwrap()
+            .1
+    };
+    assert!(position(2).abs_diff_eq(Vec3::new(-1., -0.5, 0.), 1e-5));
+    assert!(position(8).abs_diff_eq(Vec3::new(-0.5, -1., 0.), 1e-5));
+
+    // Arcs have at most `MAX_FILLET_SEGMENTS` edges.
+    let arc = lofter.fillet_vertex(0, 3, 0.5, usize::MAX).unwrap();
+    assert_eq!(arc.len(), MAX_FILLET_SEGMENTS + 1);
+}
+
+#[test]
+fn mesh_chunks() {
+    let mesh = IndexedMesh::from_lofter(&Lofter::from_template(Template::Duct));
+    assert_eq!(mesh.chunks(export::MAX_CHUNK_VERTICES).len(), 1);
+
+    let chunks = mesh.chunks(50);
+    assert!(chunks.len() > 1);
+
+    let triangles: Vec<[Vec3; 3]> = chunks
+        .iter()
+        .flat_map(|chunk| {
+            assert!(chunk.positions.len() <= 50);
+
+            for position in &chunk.positions {
+                assert!(position.cmpge(chunk.min).all() && position.cmple(chunk.max).all());
+            }
+
+            chunk
+                .triangles
+                .iter()
+                .map(|triangle| triangle.map(|index| chunk.positions[index as usize]))
+        })
+        .collect();
+    let sections: Vec<usize> = chunks
+        .iter()
+        .flat_map(|chunk| chunk.triangle_sections.iter().copied())
+        .collect();
+
+    // The chunks hold the mesh's triangles, in order.
+    let expected: Vec<[Vec3; 3]> = mesh
+        .triangles
+        .iter()
+        .map(|triangle| triangle.map(|index| mesh.positions[index as usize]))
+        .collect();
+    assert_eq!(triangles, expected);
+    assert_eq!(sections, mesh.triangle_sections);
+}
+
+#[test]
+fn streaming_export() {
+    let lofter = Lofter::from_template(Template::BoatHull);
+    let mesh = IndexedMesh::from_lofter(&lofter);
+    assert_eq!(lofter.triangle_count(), mesh.triangles.len());
+
+    let (mut streamed, mut written) = (Vec::new(), Vec::new());
+    export::stream_stl(&lofter, &mut streamed).unwrap();
+    export::write_stl(&mesh, &mut written).unwrap();
+    assert_eq!(streamed, written);
+
+    let mut obj = Vec::new();
+    export::stream_obj(&lofter, NumberFormat::default(), &mut obj).unwrap();
+    let obj = String::from_utf8(obj).unwrap();
+
+    let count = |prefix| obj.lines().filter(|line| line.starts_with(prefix)).count();
+    assert_eq!(count("f -3 -2 -1"), mesh.triangles.len());
+    assert_eq!(count("v "), mesh.triangles.len() * 3);
+    // Each pair's last triangle closes its first section, starting its
+    // group again.
+    assert!(count("g ") >= mesh.section_count);
+}
+
+#[test]
+fn triangles_iter() {
+    let mut lofter = Lofter::from_template(Template::Duct);
+    lofter
+        .set_sketch_rotation(2, &Vec3::new(0., 0., 15.))
+        .unwrap();
+    lofter.loft(&LoftOptions::default());
+
+    let triangles: Vec<_> = lofter.triangles_iter().collect();
+    assert_eq!(triangles, lofter.vertex_buffer());
+
+    // Consumers can stop early, or filter, without building the buffer.
+    assert_eq!(lofter.triangles_iter().take(3).count(), 3);
+    let above = lofter
+        .triangles_iter()
+        .filter(|triangle| triangle.iter().all(|[position, _]| position.z > 2.))
+        .count();
+    assert!(above > 0 && above < triangles.len());
+}
+
+#[cfg(feature = "profiling")]
+#[test]
+fn last_profile() {
+    let lofter = Lofter::from_template(Template::Duct);
+    let profile = lofter.last_profile();
+    assert_eq!(profile.pairs.len(), lofter.sketch_count() - 1);
+    assert!(
+        profile
+            .pairs
+            .iter()
+            .all(|pair| pair.buffer_assembly.is_zero())
+    );
+
+    lofter.vertex_buffer();
+    let pair = lofter.last_profile().pairs[0];
+    assert!(!pair.candidate_generation.is_zero());
+    assert!(!pair.edge_building.is_zero());
+    assert!(!pair.buffer_assembly.is_zero());
+}
+
+#[cfg(feature = "mint")]
+#[test]
+fn mint_interop() {
+    use lofter::mint::Vector3;
+
+    let square = [[0., 0., 0.], [1., 0., 0.], [1., 1., 0.], [0., 1., 0.]];
+    let descriptor = |z: f32| {
+        SketchDescriptor::new(
+            square.map(Vector3::from),
+            Vector3::from([0., 0., z]),
+            Vector3::from([0.; 3]),
+        )
+    };
+
+    assert_eq!(
+        descriptor(0.).vertices,
+        square.map(Vec3::from_array).to_vec()
+    );
+
+    let mut lofter = Lofter::from_sketches(&[descriptor(0.), descriptor(1.)]).unwrap();
+    lofter.loft(&LoftOptions::default());
+
+    let positions: Vec<Vector3<f32>> = lofter
+        .vertex_buffer()
+        .iter()
+        .flatten()
+        .map(|&[position, _]| position.into())
+        .collect();
+    assert!(!positions.is_empty());
+    assert!(
+        positions
+            .iter()
+            .all(|position| position.z == 0. || position.z == 1.)
+    );
+
+    let position: Vector3<f32> = lofter.sketch_world_position(1).unwrap().into();
+    assert_eq!(position, Vector3::from([0., 0., 1.]));
+}